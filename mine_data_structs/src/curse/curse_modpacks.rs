@@ -9,9 +9,23 @@ pub struct CursePackFiles {
     project_id: usize,
     #[serde(rename = "fileID")]
     file_id: usize,
+    #[serde(default = "default_true")]
+    required: bool,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 impl CursePackFiles {
+    pub fn new(project_id: usize, file_id: usize) -> CursePackFiles {
+        CursePackFiles {
+            project_id,
+            file_id,
+            required: true,
+        }
+    }
+
     pub fn get_project_id(&self) -> usize {
         self.project_id
     }
@@ -19,19 +33,128 @@ impl CursePackFiles {
     pub fn get_file_id(&self) -> usize {
         self.file_id
     }
+
+    /// Optional files are marked as not required in the manifest.
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+}
+
+/// Minecraft version and loaders of a Curse modpack.
+///
+/// ```json
+/// "minecraft": {
+///     "version": "1.20.1",
+///     "modLoaders": [{ "id": "forge-47.2.0", "primary": true }]
+/// }
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CurseMinecraft {
+    pub version: String,
+    #[serde(default)]
+    pub mod_loaders: Vec<CurseModLoader>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CurseModLoader {
+    pub id: String,
+    #[serde(default)]
+    pub primary: bool,
+}
+
+impl CurseModLoader {
+    pub fn new(id: &str, primary: bool) -> CurseModLoader {
+        CurseModLoader {
+            id: id.to_owned(),
+            primary,
+        }
+    }
+
+    /// Splits the loader id into the loader name and its version, e.g.
+    /// `"forge-47.2.0"` into `("forge", "47.2.0")`.
+    ///
+    /// Returns `None` if the id has no version.
+    pub fn name_and_version(&self) -> Option<(&str, &str)> {
+        self.id
+            .split_once('-')
+            .filter(|(name, version)| !name.is_empty() && !version.is_empty())
+    }
+}
+
+/// The `manifest.json` of a Curse modpack.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct CursePack {
+    #[serde(default)]
+    pub minecraft: CurseMinecraft,
+    #[serde(default = "default_manifest_type")]
+    pub manifest_type: String,
+    #[serde(default = "default_manifest_version")]
+    pub manifest_version: u32,
     pub name: String,
+    #[serde(default)]
+    pub version: String,
     pub author: String,
     files: Vec<CursePackFiles>,
+    #[serde(default = "default_overrides")]
+    pub overrides: String,
+}
+
+fn default_manifest_type() -> String {
+    "minecraftModpack".to_owned()
+}
+
+fn default_manifest_version() -> u32 {
+    1
+}
+
+fn default_overrides() -> String {
+    "overrides".to_owned()
 }
 
 impl CursePack {
+    /// Makes an empty modpack for `minecraft_version`, `loader` is the Curse
+    /// loader id, e.g. `"fabric-0.15.11"` or `"forge-47.2.0"`.
+    pub fn new(name: &str, author: &str, minecraft_version: &str, loader: &str) -> CursePack {
+        CursePack {
+            minecraft: CurseMinecraft {
+                version: minecraft_version.to_owned(),
+                mod_loaders: vec![CurseModLoader {
+                    id: loader.to_owned(),
+                    primary: true,
+                }],
+            },
+            manifest_type: default_manifest_type(),
+            manifest_version: default_manifest_version(),
+            name: name.to_owned(),
+            version: "1.0.0".to_owned(),
+            author: author.to_owned(),
+            files: vec![],
+            overrides: default_overrides(),
+        }
+    }
+
+    pub fn minecraft_version(&self) -> &str {
+        &self.minecraft.version
+    }
+
+    /// Returns the loader marked as primary, or the first one if none is.
+    pub fn primary_loader(&self) -> Option<&CurseModLoader> {
+        let loaders = &self.minecraft.mod_loaders;
+        loaders
+            .iter()
+            .find(|l| l.primary)
+            .or(loaders.first())
+    }
+
     pub fn get_files(&self) -> &Vec<CursePackFiles> {
         &self.files
     }
+
+    pub fn add_file(&mut self, file: CursePackFiles) {
+        self.files.push(file);
+    }
 }
 
 fn deserializ_pack(path: &str) -> Result<CursePack, Error> {
@@ -56,3 +179,60 @@ pub fn load_curse_pack(pack_path: &str) -> Option<CursePack> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_roundtrip() {
+        let mut pack = CursePack::new("My pack", "Steve", "1.20.1", "forge-47.2.0");
+        pack.add_file(CursePackFiles::new(238222, 4712953));
+
+        let json = serde_json::to_value(&pack).unwrap();
+        assert_eq!(json["manifestType"], "minecraftModpack");
+        assert_eq!(json["minecraft"]["modLoaders"][0]["id"], "forge-47.2.0");
+        assert_eq!(json["files"][0]["projectID"], 238222);
+        assert_eq!(json["overrides"], "overrides");
+
+        // Old manifests only have the fields Uranium used to read.
+        let old: CursePack = serde_json::from_str(
+            r#"{"name": "a", "author": "b", "files": [{"projectID": 1, "fileID": 2}]}"#,
+        )
+        .unwrap();
+        assert_eq!(old.get_files()[0].get_file_id(), 2);
+        assert!(old.get_files()[0].is_required());
+        assert!(old.primary_loader().is_none());
+    }
+
+    #[test]
+    fn primary_loader() {
+        let pack: CursePack = serde_json::from_str(
+            r#"{
+                "minecraft": {
+                    "version": "1.20.1",
+                    "modLoaders": [
+                        {"id": "fabric-0.15.11", "primary": false},
+                        {"id": "neoforge-47.1.106", "primary": true}
+                    ]
+                },
+                "manifestType": "minecraftModpack",
+                "manifestVersion": 1,
+                "name": "a",
+                "author": "b",
+                "files": [{"projectID": 1, "fileID": 2, "required": false}],
+                "overrides": "overrides"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(pack.minecraft_version(), "1.20.1");
+        assert_eq!(
+            pack.primary_loader()
+                .and_then(CurseModLoader::name_and_version),
+            Some(("neoforge", "47.1.106"))
+        );
+        assert!(!pack.get_files()[0].is_required());
+        assert_eq!(CurseModLoader::new("forge", true).name_and_version(), None);
+    }
+}
@@ -2,6 +2,8 @@ use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 
+use super::curse_search::CursePagination;
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 /// This struct only contains data about the mod logo.
 pub struct Logo {
@@ -31,6 +33,8 @@ pub struct CurseFile {
     file_length: usize,
     #[serde(rename = "gameVersions")]
     game_versions: Vec<String>,
+    #[serde(rename = "fileFingerprint", default)]
+    file_fingerprint: u32,
 }
 
 impl CurseFile {
@@ -59,6 +63,16 @@ impl CurseFile {
         &self.file_name
     }
 
+    /// Returns the size of the file in bytes.
+    pub fn get_file_length(&self) -> usize {
+        self.file_length
+    }
+
+    /// Returns the murmur2 fingerprint of the file.
+    pub fn get_file_fingerprint(&self) -> u32 {
+        self.file_fingerprint
+    }
+
     pub fn get_download_url(&self) -> &str {
         self.download_url
             .as_ref()
@@ -91,6 +105,13 @@ impl CurseFingerPrint {
     pub fn get_file(&self) -> &CurseFile {
         &self.exact_matches[0].file
     }
+
+    /// Returns the files matching any of the requested fingerprints.
+    pub fn get_files(&self) -> impl Iterator<Item = &CurseFile> {
+        self.exact_matches
+            .iter()
+            .map(|m| &m.file)
+    }
 }
 
 /// This struct contains the data about a single version of a mod
@@ -111,6 +132,19 @@ pub struct CurseVersion {
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct CurseVersions {
     data: Vec<CurseVersion>,
+    #[serde(default)]
+    pub pagination: CursePagination,
+}
+
+impl CurseVersions {
+    pub fn get_versions(&self) -> &[CurseVersion] {
+        &self.data
+    }
+
+    /// Returns `true` if there are more versions after this page.
+    pub fn has_more(&self) -> bool {
+        self.pagination.has_more()
+    }
 }
 
 /// Because the standard response from Curse API is:
@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+
+use super::curse_mods::{CurseFile, Logo};
+
+/// Response of the `/v1/mods/search` endpoint.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CurseSearchResponse {
+    pub data: Vec<CurseSearchHit>,
+    pub pagination: CursePagination,
+}
+
+impl CurseSearchResponse {
+    /// Returns `true` if there are more results after this page.
+    pub fn has_more(&self) -> bool {
+        self.pagination.has_more()
+    }
+}
+
+/// Pagination data of a Curse search.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CursePagination {
+    /// Index of the first result of this page.
+    pub index: usize,
+    pub page_size: usize,
+    /// Number of results in this page.
+    pub result_count: usize,
+    /// Number of results of the whole search.
+    pub total_count: usize,
+}
+
+impl CursePagination {
+    /// Returns `true` if there are more results after this page.
+    pub fn has_more(&self) -> bool {
+        self.index + self.result_count < self.total_count
+    }
+
+    /// Returns the index of the first result of the next page.
+    pub fn next_index(&self) -> usize {
+        self.index + self.result_count
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CurseLinks {
+    pub website_url: Option<String>,
+    pub wiki_url: Option<String>,
+    pub issues_url: Option<String>,
+    pub source_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CurseAuthor {
+    pub id: usize,
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CurseCategory {
+    pub id: usize,
+    pub name: String,
+    pub slug: String,
+    pub class_id: Option<usize>,
+}
+
+/// A mod (or modpack, resource pack...) returned by a search.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CurseSearchHit {
+    id: usize,
+    game_id: usize,
+    name: String,
+    slug: String,
+    #[serde(default)]
+    summary: String,
+    #[serde(default)]
+    links: CurseLinks,
+    download_count: u64,
+    class_id: Option<usize>,
+    #[serde(default)]
+    categories: Vec<CurseCategory>,
+    #[serde(default)]
+    authors: Vec<CurseAuthor>,
+    logo: Option<Logo>,
+    #[serde(default)]
+    latest_files: Vec<CurseFile>,
+}
+
+impl CurseSearchHit {
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+
+    pub fn get_game_id(&self) -> usize {
+        self.game_id
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_slug(&self) -> &str {
+        &self.slug
+    }
+
+    pub fn get_summary(&self) -> &str {
+        &self.summary
+    }
+
+    pub fn get_links(&self) -> &CurseLinks {
+        &self.links
+    }
+
+    pub fn get_download_count(&self) -> u64 {
+        self.download_count
+    }
+
+    pub fn get_class_id(&self) -> Option<usize> {
+        self.class_id
+    }
+
+    pub fn get_categories(&self) -> &[CurseCategory] {
+        &self.categories
+    }
+
+    pub fn get_authors(&self) -> &[CurseAuthor] {
+        &self.authors
+    }
+
+    pub fn get_logo(&self) -> Option<&Logo> {
+        self.logo.as_ref()
+    }
+
+    pub fn get_latest_files(&self) -> &[CurseFile] {
+        &self.latest_files
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_response() {
+        let response: CurseSearchResponse = serde_json::from_str(
+            r#"{
+                "data": [{
+                    "id": 238222, "gameId": 432, "name": "Just Enough Items (JEI)", "slug": "jei",
+                    "links": {"websiteUrl": "https://www.curseforge.com/minecraft/mc-mods/jei",
+                              "wikiUrl": "", "issuesUrl": null, "sourceUrl": null},
+                    "summary": "View Items and Recipes", "status": 4, "downloadCount": 1000,
+                    "isFeatured": false, "primaryCategoryId": 421,
+                    "categories": [{"id": 421, "gameId": 432, "name": "API and Library",
+                                    "slug": "library-api", "classId": 6}],
+                    "classId": 6,
+                    "authors": [{"id": 1, "name": "mezz", "url": "https://www.curseforge.com/members/mezz"}],
+                    "logo": null, "latestFiles": []
+                }],
+                "pagination": {"index": 0, "pageSize": 1, "resultCount": 1, "totalCount": 2}
+            }"#,
+        )
+        .unwrap();
+
+        let hit = &response.data[0];
+        assert_eq!(hit.get_slug(), "jei");
+        assert_eq!(hit.get_class_id(), Some(6));
+        assert_eq!(hit.get_authors()[0].name, "mezz");
+        assert!(response.has_more());
+        assert_eq!(
+            response
+                .pagination
+                .next_index(),
+            1
+        );
+    }
+}
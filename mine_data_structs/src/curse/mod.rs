@@ -1,2 +1,3 @@
 pub mod curse_modpacks;
 pub mod curse_mods;
+pub mod curse_search;
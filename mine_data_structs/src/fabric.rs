@@ -0,0 +1,210 @@
+//! Fabric and Quilt meta servers data structures.
+//!
+//! Both servers share the same format, Quilt only adds the `hashed` mappings
+//! and doesn't send `stable`. The urls are made with
+//! [`Fabric`](crate::url_maker::maker::Fabric) and
+//! [`Quilt`](crate::url_maker::maker::Quilt).
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+fn stable_default() -> bool {
+    true
+}
+
+/// Entry of `versions/game`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaGameVersion {
+    pub version: String,
+    #[serde(default = "stable_default")]
+    pub stable: bool,
+}
+
+/// A loader release, from `versions/loader`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaLoader {
+    pub separator: String,
+    pub build: u32,
+    /// Maven coordinates, like `net.fabricmc:fabric-loader:0.15.11`.
+    pub maven: String,
+    pub version: String,
+    /// Quilt doesn't send it, its versions are taken as stable.
+    #[serde(default = "stable_default")]
+    pub stable: bool,
+}
+
+/// Mappings artifact, from `versions/intermediary` or inside a
+/// [`LoaderListing`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaMappings {
+    pub maven: String,
+    pub version: String,
+    #[serde(default = "stable_default")]
+    pub stable: bool,
+}
+
+/// Entry of `versions/installer`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaInstaller {
+    pub url: String,
+    pub maven: String,
+    pub version: String,
+    #[serde(default = "stable_default")]
+    pub stable: bool,
+}
+
+/// Entry of `versions/loader/{game version}`, a loader version usable with
+/// that game version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoaderListing {
+    pub loader: MetaLoader,
+    pub intermediary: MetaMappings,
+    /// Quilt only.
+    pub hashed: Option<MetaMappings>,
+}
+
+/// Version json of `versions/loader/{game}/{loader}/profile/json`, it
+/// inherits from the vanilla version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoaderProfile {
+    pub id: String,
+    pub inherits_from: String,
+    pub release_time: String,
+    pub time: String,
+    #[serde(rename = "type")]
+    pub version_type: String,
+    pub main_class: String,
+    #[serde(default)]
+    pub arguments: ProfileArguments,
+    pub libraries: Vec<MavenLibrary>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileArguments {
+    #[serde(default)]
+    pub game: Vec<String>,
+    #[serde(default)]
+    pub jvm: Vec<String>,
+}
+
+/// A library given by its maven coordinates and repository.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MavenLibrary {
+    /// `group:artifact:version`, with an optional `:classifier`.
+    pub name: String,
+    /// Base url of the maven repository.
+    pub url: String,
+    pub sha1: Option<String>,
+    pub size: Option<u64>,
+}
+
+impl MavenLibrary {
+    /// Returns the path of the library inside a maven repository (and inside
+    /// `.minecraft/libraries`), `None` if the name is not valid.
+    pub fn get_path(&self) -> Option<PathBuf> {
+        maven_path(&self.name)
+    }
+
+    /// Returns the download url of the library.
+    pub fn get_url(&self) -> Option<String> {
+        let path = self.get_path()?;
+        let path: Vec<_> = path
+            .iter()
+            .map(|p| p.to_string_lossy())
+            .collect();
+        Some(format!(
+            "{}/{}",
+            self.url.trim_end_matches('/'),
+            path.join("/")
+        ))
+    }
+}
+
+/// Returns the path of a maven artifact from its `group:artifact:version`
+/// name (an optional `:classifier` is supported).
+pub fn maven_path(name: &str) -> Option<PathBuf> {
+    let mut parts = name.split(':');
+    let group = parts.next()?;
+    let artifact = parts.next()?;
+    let version = parts.next()?;
+    let file = match parts.next() {
+        Some(classifier) => format!("{artifact}-{version}-{classifier}.jar"),
+        None => format!("{artifact}-{version}.jar"),
+    };
+
+    let mut path: PathBuf = group.split('.').collect();
+    path.push(artifact);
+    path.push(version);
+    path.push(file);
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maven_paths() {
+        assert_eq!(
+            maven_path("net.fabricmc:fabric-loader:0.15.11"),
+            Some(PathBuf::from(
+                "net/fabricmc/fabric-loader/0.15.11/fabric-loader-0.15.11.jar"
+            ))
+        );
+        assert_eq!(
+            maven_path("org.lwjgl:lwjgl:3.3.1:natives-linux"),
+            Some(PathBuf::from(
+                "org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-linux.jar"
+            ))
+        );
+        assert_eq!(maven_path("fabric-loader"), None);
+    }
+
+    #[test]
+    fn fabric_and_quilt_listings() {
+        let fabric: Vec<LoaderListing> = serde_json::from_str(
+            r#"[{
+                "loader": {"separator": ".", "build": 11, "maven": "net.fabricmc:fabric-loader:0.15.11",
+                           "version": "0.15.11", "stable": true},
+                "intermediary": {"maven": "net.fabricmc:intermediary:1.20.1",
+                                 "version": "1.20.1", "stable": true},
+                "launcherMeta": {"version": 2}
+            }]"#,
+        )
+        .unwrap();
+        assert!(fabric[0].loader.stable);
+        assert!(fabric[0].hashed.is_none());
+
+        let quilt: Vec<LoaderListing> = serde_json::from_str(
+            r#"[{
+                "loader": {"separator": ".", "build": 0, "maven": "org.quiltmc:quilt-loader:0.26.0",
+                           "version": "0.26.0"},
+                "hashed": {"maven": "org.quiltmc:hashed:1.20.1", "version": "1.20.1"},
+                "intermediary": {"maven": "net.fabricmc:intermediary:1.20.1", "version": "1.20.1"}
+            }]"#,
+        )
+        .unwrap();
+        assert!(quilt[0].loader.stable);
+        assert_eq!(
+            quilt[0]
+                .hashed
+                .as_ref()
+                .unwrap()
+                .version,
+            "1.20.1"
+        );
+
+        let library = MavenLibrary {
+            name: "net.fabricmc:sponge-mixin:0.13.3+mixin.0.8.5".to_owned(),
+            url: "https://maven.fabricmc.net/".to_owned(),
+            sha1: None,
+            size: None,
+        };
+        assert_eq!(
+            library.get_url().as_deref(),
+            Some("https://maven.fabricmc.net/net/fabricmc/sponge-mixin/0.13.3+mixin.0.8.5/sponge-mixin-0.13.3+mixin.0.8.5.jar")
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! Forge and NeoForge version metadata.
+//!
+//! Forge publishes the recommended and latest version for each Minecraft
+//! version in `promotions_slim.json`, NeoForge only has the list of versions
+//! in its maven. The urls are made with
+//! [`Forge`](crate::url_maker::maker::Forge) and
+//! [`NeoForge`](crate::url_maker::maker::NeoForge).
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Forge `promotions_slim.json`.
+///
+/// The `promos` keys are `{minecraft version}-recommended` and
+/// `{minecraft version}-latest`, their values the Forge version.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ForgePromotions {
+    #[serde(default)]
+    pub homepage: String,
+    pub promos: HashMap<String, String>,
+}
+
+impl ForgePromotions {
+    /// Returns the recommended Forge version for `minecraft_version`.
+    pub fn recommended(&self, minecraft_version: &str) -> Option<&str> {
+        self.promos
+            .get(&format!("{minecraft_version}-recommended"))
+            .map(String::as_str)
+    }
+
+    /// Returns the latest Forge version for `minecraft_version`.
+    pub fn latest(&self, minecraft_version: &str) -> Option<&str> {
+        self.promos
+            .get(&format!("{minecraft_version}-latest"))
+            .map(String::as_str)
+    }
+
+    /// Returns the recommended version for `minecraft_version`, or the latest
+    /// one if there is no recommended version yet.
+    pub fn best(&self, minecraft_version: &str) -> Option<&str> {
+        self.recommended(minecraft_version)
+            .or_else(|| self.latest(minecraft_version))
+    }
+}
+
+/// Versions listing of the NeoForge maven api, oldest first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NeoForgeVersions {
+    #[serde(default)]
+    pub is_snapshot: bool,
+    pub versions: Vec<String>,
+}
+
+impl NeoForgeVersions {
+    /// Returns the versions for `minecraft_version`, oldest first.
+    pub fn for_minecraft<'a>(
+        &'a self,
+        minecraft_version: &str,
+    ) -> impl DoubleEndedIterator<Item = &'a str> {
+        let prefix = neoforge_prefix(minecraft_version);
+        self.versions
+            .iter()
+            .map(String::as_str)
+            .filter(move |v| {
+                prefix
+                    .as_ref()
+                    .is_some_and(|p| v.starts_with(p.as_str()))
+            })
+    }
+
+    /// Returns the latest non beta version for `minecraft_version`, the
+    /// NeoForge counterpart of [`ForgePromotions::recommended`].
+    pub fn recommended(&self, minecraft_version: &str) -> Option<&str> {
+        self.for_minecraft(minecraft_version)
+            .rfind(|v| !v.contains("beta"))
+    }
+
+    /// Returns the latest version for `minecraft_version`, betas included.
+    pub fn latest(&self, minecraft_version: &str) -> Option<&str> {
+        self.for_minecraft(minecraft_version)
+            .next_back()
+    }
+}
+
+/// The only Minecraft version whose NeoForge was published with the Forge
+/// artifact and versions, like `1.20.1-47.1.106`.
+pub const LEGACY_NEOFORGE_MINECRAFT: &str = "1.20.1";
+
+/// Returns whether `neoforge_version` is one of the Minecraft 1.20.1 versions,
+/// which are published as `net.neoforged:forge`.
+pub fn is_legacy_neoforge(neoforge_version: &str) -> bool {
+    neoforge_version.starts_with(LEGACY_NEOFORGE_MINECRAFT)
+}
+
+/// Returns the id of the version installed by the NeoForge installer of
+/// `neoforge_version`.
+pub fn neoforge_version_id(neoforge_version: &str) -> String {
+    match neoforge_version.split_once('-') {
+        Some((minecraft, forge)) if is_legacy_neoforge(neoforge_version) => {
+            format!("{minecraft}-forge-{forge}")
+        }
+        _ => format!("neoforge-{neoforge_version}"),
+    }
+}
+
+/// Returns the prefix of the NeoForge versions for `minecraft_version`.
+///
+/// NeoForge versions drop the leading `1.` of the Minecraft version, so
+/// `1.20.4` is `20.4.x` and `1.21` is `21.0.x`. The 1.20.1 versions keep
+/// the Forge scheme, `1.20.1-47.1.x`.
+pub fn neoforge_prefix(minecraft_version: &str) -> Option<String> {
+    if minecraft_version == LEGACY_NEOFORGE_MINECRAFT {
+        return Some(format!("{minecraft_version}-"));
+    }
+    let rest = minecraft_version.strip_prefix("1.")?;
+    let mut parts = rest.split('.');
+    let major = parts.next()?;
+    let minor = parts.next().unwrap_or("0");
+    Some(format!("{major}.{minor}."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::url_maker::maker::NeoForge;
+
+    #[test]
+    fn neoforge_prefixes() {
+        assert_eq!(neoforge_prefix("1.20.4").as_deref(), Some("20.4."));
+        assert_eq!(neoforge_prefix("1.21").as_deref(), Some("21.0."));
+        assert_eq!(neoforge_prefix("1.20.1").as_deref(), Some("1.20.1-"));
+        assert_eq!(neoforge_prefix("24w14a"), None);
+    }
+
+    #[test]
+    fn legacy_neoforge() {
+        let versions: NeoForgeVersions = serde_json::from_str(
+            r#"{"versions": ["1.20.1-47.1.3", "1.20.1-47.1.106", "47.1.79"]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(versions.recommended("1.20.1"), Some("1.20.1-47.1.106"));
+        assert_eq!(
+            neoforge_version_id("1.20.1-47.1.106"),
+            "1.20.1-forge-47.1.106"
+        );
+        assert_eq!(neoforge_version_id("21.0.167"), "neoforge-21.0.167");
+        assert!(NeoForge::installer("1.20.1-47.1.106")
+            .ends_with("/net/neoforged/forge/1.20.1-47.1.106/forge-1.20.1-47.1.106-installer.jar"));
+        assert!(NeoForge::installer("21.0.167")
+            .ends_with("/net/neoforged/neoforge/21.0.167/neoforge-21.0.167-installer.jar"));
+        assert!(NeoForge::versions_for("1.20.1").ends_with("/net/neoforged/forge"));
+    }
+
+    #[test]
+    fn forge_promotions() {
+        let promotions: ForgePromotions = serde_json::from_str(
+            r#"{
+                "homepage": "https://files.minecraftforge.net/net/minecraftforge/forge/",
+                "promos": {"1.20.1-recommended": "47.3.0", "1.20.1-latest": "47.3.12",
+                           "1.21.1-latest": "52.0.16"}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(promotions.best("1.20.1"), Some("47.3.0"));
+        assert_eq!(promotions.best("1.21.1"), Some("52.0.16"));
+        assert_eq!(promotions.best("1.8"), None);
+    }
+
+    #[test]
+    fn neoforge_versions() {
+        let versions: NeoForgeVersions = serde_json::from_str(
+            r#"{"isSnapshot": false,
+                "versions": ["20.4.237", "21.0.1-beta", "21.0.167", "21.0.168-beta"]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(versions.recommended("1.21"), Some("21.0.167"));
+        assert_eq!(versions.latest("1.21"), Some("21.0.168-beta"));
+        assert_eq!(versions.latest("1.20.4"), Some("20.4.237"));
+        assert_eq!(versions.latest("1.19.2"), None);
+    }
+}
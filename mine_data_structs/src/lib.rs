@@ -1,5 +1,8 @@
 pub mod curse;
+pub mod fabric;
+pub mod forge;
 pub mod minecraft;
 pub mod rinth;
+pub mod timestamp;
 pub mod url_maker;
 //pub mod uranium_modpack;
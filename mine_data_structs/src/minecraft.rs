@@ -4,8 +4,11 @@ use std::{
     path::{Path, PathBuf},
 };
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::timestamp::parse_timestamp;
+
 const BASE: &str = "https://resources.download.minecraft.net/";
 
 /*
@@ -51,7 +54,7 @@ pub struct Resources {
 */
 
 /// # Example:
-/// ```json 
+/// ```json
 /// {
 ///  "id": "24w39a",
 ///  "type": "snapshot",
@@ -60,17 +63,44 @@ pub struct Resources {
 ///  "releaseTime": "2024-09-25T13:08:41+00:00"
 /// }
 /// ```
+///
+/// `type` is kept as a raw string so new version types don't break the
+/// parsing, use [`MinecraftVersion::get_version_type`] to match it.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct MinecraftVersion {
     pub id: String,
     #[serde(rename = "type")]
     pub instance_type: String,
     pub url: String,
+    #[serde(default)]
     pub time: String,
-    #[serde(rename = "releaseTime")]
+    #[serde(rename = "releaseTime", default)]
     pub release_time: String,
 }
 
+/// Known values of the `type` field of a Minecraft version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionType {
+    Release,
+    Snapshot,
+    OldBeta,
+    OldAlpha,
+    /// Any type Mojang may add in the future.
+    Other,
+}
+
+impl From<&str> for VersionType {
+    fn from(value: &str) -> Self {
+        match value {
+            "release" => VersionType::Release,
+            "snapshot" => VersionType::Snapshot,
+            "old_beta" => VersionType::OldBeta,
+            "old_alpha" => VersionType::OldAlpha,
+            _ => VersionType::Other,
+        }
+    }
+}
+
 impl MinecraftVersion {
     pub fn get_id_raw(&self) -> &str {
         &self.id
@@ -79,6 +109,20 @@ impl MinecraftVersion {
     pub fn get_link_raw(&self) -> &str {
         &self.url
     }
+
+    pub fn get_version_type(&self) -> VersionType {
+        VersionType::from(self.instance_type.as_str())
+    }
+
+    /// Returns `time` parsed, `None` if Mojang sent an invalid date.
+    pub fn get_time(&self) -> Option<DateTime<Utc>> {
+        parse_timestamp(&self.time)
+    }
+
+    /// Returns `release_time` parsed, `None` if Mojang sent an invalid date.
+    pub fn get_release_time(&self) -> Option<DateTime<Utc>> {
+        parse_timestamp(&self.release_time)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -118,28 +162,69 @@ pub struct Library {
     pub downloads: Option<LibraryDownloads>,
     pub name: String,
     pub rules: Option<Vec<Rule>>,
+    /// OS name -> classifier with the natives for that OS, e.g.
+    /// `"windows" -> "natives-windows-${arch}"`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub natives: HashMap<String, String>,
 }
 
 impl Library {
+    /// Returns the OS of the first rule naming one.
+    ///
+    /// A rule may be a `disallow` one, use [`Library::is_allowed`] to know if
+    /// the library is needed in this OS.
     pub fn get_os(&self) -> Option<Os> {
         self.rules
             .as_ref()
-            .map(|r| {
+            .and_then(|r| {
                 r.iter()
-                    .find(|x| x.os.is_some())
-                    .unwrap()
-                    .os
+                    .find_map(|x| x.os.as_ref()?.name)
             })
-            .flatten()
     }
 
-    pub fn get_url(&self) -> &str {
+    /// Returns `true` if the rules of the library allow it in the current OS
+    /// and architecture. Libraries without rules are always allowed.
+    pub fn is_allowed(&self) -> bool {
+        self.rules
+            .as_ref()
+            .is_none_or(|rules| rules_allow(rules, &HashMap::new()))
+    }
+
+    /// Returns the natives of the library for the current OS, if any.
+    pub fn get_natives(&self) -> Option<&Artifact> {
+        let classifier = self
+            .natives
+            .get(Os::current().as_str()?)?
+            .replace("${arch}", Arch::current().bits());
+        self.downloads
+            .as_ref()?
+            .classifiers
+            .get(&classifier)
+    }
+
+    /// Returns the files of the library needed in the current OS, the
+    /// artifact and the natives. Empty if the library is not allowed.
+    pub fn get_os_artifacts(&self) -> Vec<&Artifact> {
+        if !self.is_allowed() {
+            return vec![];
+        }
+        self.get_artifact()
+            .into_iter()
+            .chain(self.get_natives())
+            .collect()
+    }
+
+    /// Returns the artifact of the library, `None` for libraries which only
+    /// have natives (`classifiers`).
+    pub fn get_artifact(&self) -> Option<&Artifact> {
         self.downloads
             .as_ref()
-            .unwrap()
-            .artifact
-            .url
-            .as_str()
+            .and_then(|d| d.artifact.as_ref())
+    }
+
+    pub fn get_url(&self) -> Option<&str> {
+        self.get_artifact()
+            .map(|a| a.url.as_str())
     }
 
     pub fn get_name(&self) -> &str {
@@ -170,25 +255,28 @@ pub type Libraries = Vec<Library>;
 pub trait Lib {
     fn get_paths(&self) -> Vec<PathBuf>;
     fn get_urls(&self) -> Vec<&str>;
+    /// Returns the files needed in the current OS, see
+    /// [`Library::get_os_artifacts`].
+    fn get_os_artifacts(&self) -> Vec<&Artifact>;
 }
 
 impl Lib for Libraries {
     fn get_paths(&self) -> Vec<PathBuf> {
         self.iter()
-            .map(|l| {
-                l.downloads
-                    .as_ref()
-                    .unwrap()
-                    .artifact
-                    .path
-                    .clone()
-            })
+            .filter_map(Library::get_artifact)
+            .map(|a| a.path.clone())
             .collect()
     }
 
     fn get_urls(&self) -> Vec<&str> {
         self.iter()
-            .map(Library::get_url)
+            .filter_map(Library::get_url)
+            .collect()
+    }
+
+    fn get_os_artifacts(&self) -> Vec<&Artifact> {
+        self.iter()
+            .flat_map(Library::get_os_artifacts)
             .collect()
     }
 }
@@ -260,15 +348,10 @@ impl Profile {
 
         let r: Root = serde_json::from_reader(file).ok()?;
 
-        if r.inherits_from.is_some() {
-            Some(
-                r.inherits_from
-                    .unwrap()
-                    .clone(),
-            )
-        } else {
-            Some(r.id.clone())
-        }
+        Some(
+            r.inherits_from
+                .unwrap_or(r.id),
+        )
     }
 }
 
@@ -408,16 +491,24 @@ impl ProfilesJson {
         &self.profiles
     }
 
-    /// Saves the profiles into a file.
+    /// Saves the profiles into the `launcher_profiles.json` of the default
+    /// `.minecraft`, see [`get_minecraft_path`].
     pub fn save(&self) -> std::io::Result<()> {
+        let minecraft_path = get_minecraft_path().ok_or(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            ".minecraft not found",
+        ))?;
+        self.save_to(minecraft_path.join("launcher_profiles.json"))
+    }
+
+    /// Saves the profiles into `path`, for a `.minecraft` which is not in the
+    /// user home.
+    pub fn save_to<I: AsRef<Path>>(&self, path: I) -> std::io::Result<()> {
         let mut file = std::fs::OpenOptions::new()
             .write(true)
             .create(true)
             .truncate(true)
-            .open(get_minecraft_path().ok_or(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                ".minecraft not found",
-            ))?)?;
+            .open(path)?;
 
         file.write_all(serde_json::to_string_pretty(&self)?.as_bytes())?;
         Ok(())
@@ -431,9 +522,13 @@ impl ProfilesJson {
 /// field which indicates that it should **inherit** the values from other
 /// version. (The values must be **added** and not **overwrite** the current
 /// data)
+///
+/// Old versions (< 1.13) have no `arguments` nor `javaVersion`, in that case
+/// their default values are used.
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Root {
+    #[serde(default)]
     pub arguments: Arguments,
 
     pub asset_index: AssetIndex,
@@ -441,9 +536,11 @@ pub struct Root {
     #[serde(default = "Default::default")]
     pub assets: String,
 
+    #[serde(default)]
     pub downloads: HashMap<String, DownloadData>,
     pub id: String,
 
+    #[serde(default)]
     pub java_version: JavaVersion,
     pub libraries: Vec<Library>,
     pub inherits_from: Option<String>,
@@ -472,43 +569,383 @@ pub struct JavaVersion {
     #[serde(rename = "majorVersion")]
     pub major_version: usize,
 }
-#[derive(Serialize, Deserialize, Debug)]
+
+impl Default for JavaVersion {
+    /// Versions without `javaVersion` run on the legacy Java 8 runtime.
+    fn default() -> Self {
+        JavaVersion {
+            component: "jre-legacy".to_owned(),
+            major_version: 8,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Arguments {
+    #[serde(default)]
     game: Vec<GameArgument>,
     //jvm: HashMap<String, String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl Arguments {
+    pub fn get_game(&self) -> &[GameArgument] {
+        &self.game
+    }
+
+    /// Resolves every game argument, see [`GameArgument::resolve`].
+    pub fn resolve_game(&self, placeholders: &PlaceholderMap) -> Vec<String> {
+        self.game
+            .iter()
+            .flat_map(|arg| arg.resolve(placeholders))
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(untagged)]
 pub enum GameArgument {
     String(String),
     Object(GameObject),
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl GameArgument {
+    /// Returns the argument with its `${...}` placeholders substituted.
+    ///
+    /// Conditional arguments (e.g. `--demo` or `--width ${resolution_width}`)
+    /// are only returned if their rules allow them for the current OS and the
+    /// features set in `placeholders`.
+    pub fn resolve(&self, placeholders: &PlaceholderMap) -> Vec<String> {
+        match self {
+            GameArgument::String(arg) => vec![placeholders.substitute(arg)],
+            GameArgument::Object(obj) if rules_allow(&obj.rules, &placeholders.features) => obj
+                .value
+                .values()
+                .iter()
+                .map(|arg| placeholders.substitute(arg))
+                .collect(),
+            GameArgument::Object(_) => vec![],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GameObject {
     pub rules: Vec<Rule>,
+    #[serde(default)]
+    pub value: ArgumentValue,
 }
-#[derive(Serialize, Deserialize, Debug)]
+
+/// The value of a conditional argument can be a single argument or a list.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum ArgumentValue {
+    Single(String),
+    Many(Vec<String>),
+}
+
+impl Default for ArgumentValue {
+    fn default() -> Self {
+        ArgumentValue::Many(vec![])
+    }
+}
+
+impl ArgumentValue {
+    pub fn values(&self) -> &[String] {
+        match self {
+            ArgumentValue::Single(value) => std::slice::from_ref(value),
+            ArgumentValue::Many(values) => values,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Rule {
     pub action: String,
-    pub os: Option<Os>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os: Option<OsRule>,
+    /// Launcher features the rule depends on, e.g. `is_demo_user`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub features: HashMap<String, bool>,
+}
+
+impl Rule {
+    /// Returns `true` if the rule applies to the current OS and `features`.
+    pub fn matches(&self, features: &HashMap<String, bool>) -> bool {
+        self.os
+            .as_ref()
+            .is_none_or(OsRule::matches)
+            && self
+                .features
+                .iter()
+                .all(|(name, value)| {
+                    features
+                        .get(name)
+                        .copied()
+                        .unwrap_or(false)
+                        == *value
+                })
+    }
+}
+
+/// Mojang rules start disallowing and the last matching rule decides.
+fn rules_allow(rules: &[Rule], features: &HashMap<String, bool>) -> bool {
+    rules
+        .iter()
+        .rev()
+        .find(|r| r.matches(features))
+        .is_some_and(|r| r.action == "allow")
+}
+
+/// Values for the `${...}` placeholders of the game arguments.
+///
+/// ```
+/// use mine_data_structs::minecraft::{GameArgument, PlaceholderMap};
+///
+/// let placeholders = PlaceholderMap::new()
+///     .auth("Steve", "uuid", "token", "msa")
+///     .version_name("1.21");
+///
+/// let arg = GameArgument::String("${auth_player_name}".to_owned());
+/// assert_eq!(arg.resolve(&placeholders), ["Steve"]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PlaceholderMap {
+    values: HashMap<String, String>,
+    features: HashMap<String, bool>,
+}
+
+impl PlaceholderMap {
+    pub fn new() -> PlaceholderMap {
+        PlaceholderMap::default()
+    }
+
+    /// Sets the value of `${key}`.
+    pub fn set(mut self, key: &str, value: &str) -> Self {
+        self.values
+            .insert(key.to_owned(), value.to_owned());
+        self
+    }
+
+    /// Enables or disables a launcher feature used by the argument rules,
+    /// e.g. `is_demo_user` or `has_quick_plays_support`.
+    pub fn feature(mut self, name: &str, enabled: bool) -> Self {
+        self.features
+            .insert(name.to_owned(), enabled);
+        self
+    }
+
+    /// Sets `${auth_player_name}`, `${auth_uuid}`, `${auth_access_token}` and
+    /// `${user_type}`.
+    pub fn auth(self, player_name: &str, uuid: &str, access_token: &str, user_type: &str) -> Self {
+        self.set("auth_player_name", player_name)
+            .set("auth_uuid", uuid)
+            .set("auth_access_token", access_token)
+            .set("user_type", user_type)
+    }
+
+    pub fn version_name(self, version: &str) -> Self {
+        self.set("version_name", version)
+    }
+
+    pub fn version_type(self, version_type: &str) -> Self {
+        self.set("version_type", version_type)
+    }
+
+    pub fn game_directory<I: AsRef<Path>>(self, path: I) -> Self {
+        self.set(
+            "game_directory",
+            &path
+                .as_ref()
+                .display()
+                .to_string(),
+        )
+    }
+
+    pub fn assets_root<I: AsRef<Path>>(self, path: I) -> Self {
+        self.set(
+            "assets_root",
+            &path
+                .as_ref()
+                .display()
+                .to_string(),
+        )
+    }
+
+    pub fn assets_index_name(self, name: &str) -> Self {
+        self.set("assets_index_name", name)
+    }
+
+    /// Sets the window size, also enables the `has_custom_resolution` feature.
+    pub fn resolution(self, width: u32, height: u32) -> Self {
+        self.set("resolution_width", &width.to_string())
+            .set("resolution_height", &height.to_string())
+            .feature("has_custom_resolution", true)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values
+            .get(key)
+            .map(String::as_str)
+    }
+
+    /// Replaces every known `${key}` in `arg`, unknown placeholders are kept
+    /// as they are.
+    pub fn substitute(&self, arg: &str) -> String {
+        let mut result = String::with_capacity(arg.len());
+        let mut rest = arg;
+
+        while let Some(start) = rest.find("${") {
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            let placeholder = &rest[start..start + len + 1];
+
+            result.push_str(&rest[..start]);
+            match self.get(&placeholder[2..placeholder.len() - 1]) {
+                Some(value) => result.push_str(value),
+                None => result.push_str(placeholder),
+            }
+            rest = &rest[start + len + 1..];
+        }
+
+        result.push_str(rest);
+        result
+    }
+}
+
+/// The `os` condition of a rule, every field set must match.
+///
+/// The `version` regex of some old rules is ignored.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct OsRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<Os>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arch: Option<Arch>,
+}
+
+impl OsRule {
+    /// Returns `true` if the current OS and architecture match.
+    pub fn matches(&self) -> bool {
+        self.matches_platform(current_platform())
+    }
+
+    /// Returns `true` if `platform` matches.
+    pub fn matches_platform(&self, platform: Platform) -> bool {
+        self.name
+            .is_none_or(|os| os == platform.os)
+            && self
+                .arch
+                .is_none_or(|arch| arch == platform.arch)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
-#[serde(tag = "name")]
 pub enum Os {
     #[serde(rename = "linux")]
     Linux,
     #[serde(rename = "windows")]
     Windows,
+    #[serde(rename = "osx")]
+    MacOs,
     #[serde(other)]
     Other,
 }
 
+impl Os {
+    /// Returns the OS this program is running on.
+    pub fn current() -> Os {
+        match std::env::consts::OS {
+            "linux" => Os::Linux,
+            "windows" => Os::Windows,
+            "macos" => Os::MacOs,
+            _ => Os::Other,
+        }
+    }
+
+    /// Returns the name used by Mojang for the OS, `None` for `Os::Other`.
+    pub fn as_str(self) -> Option<&'static str> {
+        match self {
+            Os::Linux => Some("linux"),
+            Os::Windows => Some("windows"),
+            Os::MacOs => Some("osx"),
+            Os::Other => None,
+        }
+    }
+}
+
+/// CPU architectures, as named in the rules.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Arch {
+    /// 32 bits x86.
+    #[serde(rename = "x86")]
+    X86,
+    #[serde(rename = "x86_64", alias = "amd64")]
+    X86_64,
+    #[serde(rename = "arm64", alias = "aarch64")]
+    Arm64,
+    #[serde(other)]
+    Other,
+}
+
+impl Arch {
+    /// Returns the architecture this program is running on.
+    pub fn current() -> Arch {
+        match std::env::consts::ARCH {
+            "x86" => Arch::X86,
+            "x86_64" => Arch::X86_64,
+            "aarch64" => Arch::Arm64,
+            _ => Arch::Other,
+        }
+    }
+
+    /// Value of `${arch}` in the natives classifiers.
+    pub fn bits(self) -> &'static str {
+        match self {
+            Arch::X86 => "32",
+            Arch::Other if cfg!(target_pointer_width = "32") => "32",
+            _ => "64",
+        }
+    }
+}
+
+/// An OS and an architecture.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Platform {
+    pub os: Os,
+    pub arch: Arch,
+}
+
+impl Platform {
+    /// Returns the platform of the Java runtimes manifest for this one,
+    /// `None` if Mojang has no runtimes for it.
+    pub fn runtime_platform(self) -> Option<RuntimePlatform> {
+        match (self.os, self.arch) {
+            (Os::Linux, Arch::X86_64) => Some(RuntimePlatform::Linux),
+            (Os::Linux, Arch::X86) => Some(RuntimePlatform::LinuxI386),
+            (Os::MacOs, Arch::Arm64) => Some(RuntimePlatform::MacOsArm64),
+            (Os::MacOs, Arch::X86_64) => Some(RuntimePlatform::MacOs),
+            (Os::Windows, Arch::X86_64) => Some(RuntimePlatform::WindowsX64),
+            (Os::Windows, Arch::X86) => Some(RuntimePlatform::WindowsX86),
+            (Os::Windows, Arch::Arm64) => Some(RuntimePlatform::WindowsArm64),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the platform this program is running on.
+pub fn current_platform() -> Platform {
+    Platform {
+        os: Os::current(),
+        arch: Arch::current(),
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct LibraryDownloads {
-    pub artifact: Artifact,
+    pub artifact: Option<Artifact>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub classifiers: HashMap<String, Artifact>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -519,6 +956,121 @@ pub struct Artifact {
     pub url: String,
 }
 
+/*
+
+    https://launchermeta.mojang.com/v1/products/java-runtime/2ec0cc96c44e5a76b9c8b7c39df7210883d12871/all.json
+                    JAVA RUNTIMES DATA STRUCTURE
+
+*/
+
+/// Platforms listed in the Java runtimes manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimePlatform {
+    Gamecore,
+    Linux,
+    LinuxI386,
+    MacOs,
+    MacOsArm64,
+    WindowsArm64,
+    WindowsX64,
+    WindowsX86,
+}
+
+impl RuntimePlatform {
+    /// Returns the key used by Mojang for this platform.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RuntimePlatform::Gamecore => "gamecore",
+            RuntimePlatform::Linux => "linux",
+            RuntimePlatform::LinuxI386 => "linux-i386",
+            RuntimePlatform::MacOs => "mac-os",
+            RuntimePlatform::MacOsArm64 => "mac-os-arm64",
+            RuntimePlatform::WindowsArm64 => "windows-arm64",
+            RuntimePlatform::WindowsX64 => "windows-x64",
+            RuntimePlatform::WindowsX86 => "windows-x86",
+        }
+    }
+}
+
+/// Java runtimes manifest.
+///
+/// Platforms and components are kept in maps so new ones added by Mojang
+/// don't break the parsing, [`Runtimes::get_platform`] gives access to the
+/// known ones.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(transparent)]
+pub struct Runtimes {
+    /// platform -> component (`java-runtime-gamma`, `jre-legacy`...) ->
+    /// runtimes
+    pub platforms: HashMap<String, HashMap<String, Vec<Runtime>>>,
+}
+
+impl Runtimes {
+    pub fn get_platform(
+        &self,
+        platform: RuntimePlatform,
+    ) -> Option<&HashMap<String, Vec<Runtime>>> {
+        self.platforms
+            .get(platform.as_str())
+    }
+
+    /// Returns the first runtime of `component` for `platform`, `None` if
+    /// there is none.
+    pub fn get_runtime(&self, platform: RuntimePlatform, component: &str) -> Option<&Runtime> {
+        self.get_platform(platform)?
+            .get(component)?
+            .first()
+    }
+
+    /// Returns the runtime of `component` for the current platform, see
+    /// [`current_platform`].
+    pub fn get_current_runtime(&self, component: &str) -> Option<&Runtime> {
+        self.get_runtime(current_platform().runtime_platform()?, component)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Runtime {
+    pub manifest: DownloadData,
+    pub version: RuntimeVersion,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RuntimeVersion {
+    pub name: String,
+    #[serde(default)]
+    pub released: String,
+}
+
+/// Files of a Java runtime, downloaded from the `manifest` of a [`Runtime`].
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct RuntimeManifest {
+    /// Path inside the runtime -> file.
+    pub files: HashMap<String, RuntimeFile>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum RuntimeFile {
+    File {
+        #[serde(default)]
+        executable: bool,
+        downloads: RuntimeFileDownloads,
+    },
+    Directory,
+    /// A symlink, `target` is relative to the link.
+    Link {
+        target: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RuntimeFileDownloads {
+    pub raw: DownloadData,
+    /// The same file compressed, uranium uses the raw one.
+    pub lzma: Option<DownloadData>,
+}
+
 /// Returns `Some(.minecraft path)` on success, otherwise `None`.
 ///
 /// MacOS not supported.
@@ -541,3 +1093,201 @@ pub fn get_minecraft_path() -> Option<PathBuf> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_root() {
+        // Trimmed 1.8.9.json, no `arguments`, no `javaVersion` and natives
+        // only libraries.
+        let root: Root = serde_json::from_str(
+            r#"{
+                "assetIndex": {
+                    "id": "1.8",
+                    "sha1": "f6ad102bcaa53b1a58358f16e376d548d44933ec",
+                    "size": 78494,
+                    "totalSize": 114885064,
+                    "url": "https://launchermeta.mojang.com/v1/packages/f6ad/1.8.json"
+                },
+                "assets": "1.8",
+                "id": "1.8.9",
+                "libraries": [
+                    {
+                        "downloads": {
+                            "classifiers": {
+                                "natives-linux": {
+                                    "path": "lwjgl-platform-2.9.4-natives-linux.jar",
+                                    "sha1": "931074f46c795d2f7b30ed6395df5715cfd7675b",
+                                    "size": 578680,
+                                    "url": "https://libraries.minecraft.net/lwjgl-platform-2.9.4-natives-linux.jar"
+                                }
+                            }
+                        },
+                        "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4",
+                        "rules": [
+                            { "action": "allow" },
+                            { "action": "disallow", "os": { "name": "osx" } },
+                            { "action": "allow", "os": { "arch": "x86" } }
+                        ]
+                    }
+                ],
+                "mainClass": "net.minecraft.client.main.Main",
+                "type": "release",
+                "newField": true
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            root.java_version
+                .major_version,
+            8
+        );
+        assert!(root
+            .libraries
+            .get_paths()
+            .is_empty());
+        assert_eq!(root.libraries[0].get_os(), Some(Os::MacOs));
+    }
+
+    #[test]
+    fn library_rules() {
+        let library: Library = serde_json::from_str(
+            r#"{
+                "downloads": {
+                    "artifact": {
+                        "path": "lwjgl-2.9.4.jar",
+                        "sha1": "a", "size": 1,
+                        "url": "https://libraries.minecraft.net/lwjgl-2.9.4.jar"
+                    },
+                    "classifiers": {
+                        "natives-linux": {
+                            "path": "lwjgl-2.9.4-natives-linux.jar",
+                            "sha1": "b", "size": 2,
+                            "url": "https://libraries.minecraft.net/lwjgl-2.9.4-natives-linux.jar"
+                        },
+                        "natives-windows-64": {
+                            "path": "lwjgl-2.9.4-natives-windows-64.jar",
+                            "sha1": "c", "size": 3,
+                            "url": "https://libraries.minecraft.net/lwjgl-2.9.4-natives-windows-64.jar"
+                        }
+                    }
+                },
+                "name": "org.lwjgl:lwjgl:2.9.4",
+                "natives": { "linux": "natives-linux", "windows": "natives-windows-${arch}" },
+                "rules": [
+                    { "action": "allow" },
+                    { "action": "disallow", "os": { "name": "osx" } }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let paths: Vec<&Path> = library
+            .get_os_artifacts()
+            .iter()
+            .map(|a| a.path.as_path())
+            .collect();
+        match Os::current() {
+            Os::MacOs => assert!(paths.is_empty()),
+            Os::Linux => assert_eq!(
+                paths,
+                [
+                    Path::new("lwjgl-2.9.4.jar"),
+                    Path::new("lwjgl-2.9.4-natives-linux.jar")
+                ]
+            ),
+            _ => assert_eq!(paths[0], Path::new("lwjgl-2.9.4.jar")),
+        }
+
+        let x86_only: OsRule = serde_json::from_str(r#"{ "arch": "x86" }"#).unwrap();
+        let apple_silicon: OsRule =
+            serde_json::from_str(r#"{ "name": "osx", "arch": "arm64" }"#).unwrap();
+        let mac_arm = Platform {
+            os: Os::MacOs,
+            arch: Arch::Arm64,
+        };
+        assert!(!x86_only.matches_platform(mac_arm));
+        assert!(apple_silicon.matches_platform(mac_arm));
+        assert!(!apple_silicon.matches_platform(Platform {
+            os: Os::Linux,
+            arch: Arch::Arm64,
+        }));
+        assert_eq!(
+            mac_arm.runtime_platform(),
+            Some(RuntimePlatform::MacOsArm64)
+        );
+    }
+
+    #[test]
+    fn runtimes_unknown_platform() {
+        let runtimes: Runtimes = serde_json::from_str(
+            r#"{
+                "linux": {
+                    "java-runtime-gamma": [{
+                        "availability": { "group": 1, "progress": 100 },
+                        "manifest": { "sha1": "aaa", "size": 1, "url": "https://a.b/manifest.json" },
+                        "version": { "name": "17.0.8", "released": "2023-07-18T00:00:00+00:00" }
+                    }],
+                    "jre-legacy": []
+                },
+                "beos-riscv": {}
+            }"#,
+        )
+        .unwrap();
+
+        let gamma = runtimes
+            .get_runtime(RuntimePlatform::Linux, "java-runtime-gamma")
+            .unwrap();
+        assert_eq!(gamma.version.name, "17.0.8");
+        assert!(runtimes
+            .get_runtime(RuntimePlatform::Linux, "jre-legacy")
+            .is_none());
+        assert!(runtimes
+            .get_platform(RuntimePlatform::MacOs)
+            .is_none());
+    }
+
+    #[test]
+    fn game_arguments_resolution() {
+        let arguments: Arguments = serde_json::from_str(
+            r#"{
+                "game": [
+                    "--username", "${auth_player_name}",
+                    "--gameDir", "${game_directory}",
+                    "--clientId", "${clientid}",
+                    { "rules": [{ "action": "allow", "features": { "is_demo_user": true } }],
+                      "value": "--demo" },
+                    { "rules": [{ "action": "allow", "features": { "has_custom_resolution": true } }],
+                      "value": ["--width", "${resolution_width}", "--height", "${resolution_height}"] }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let placeholders = PlaceholderMap::new()
+            .auth("Steve", "uuid", "token", "msa")
+            .game_directory("/games/mc");
+        assert_eq!(
+            arguments.resolve_game(&placeholders),
+            [
+                "--username",
+                "Steve",
+                "--gameDir",
+                "/games/mc",
+                "--clientId",
+                "${clientid}"
+            ]
+        );
+
+        let placeholders = placeholders
+            .resolution(854, 480)
+            .feature("is_demo_user", true);
+        assert_eq!(
+            arguments.resolve_game(&placeholders)[6..],
+            ["--demo", "--width", "854", "--height", "480"]
+        );
+    }
+}
@@ -1,15 +1,31 @@
 use core::fmt;
+use std::collections::HashMap;
 use std::path::Path;
 use std::{fs::read_to_string, path::PathBuf};
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::timestamp::parse_timestamp;
+
 pub enum Attributes {
     Loader,
     Name,
     VersionType,
 }
 
+/// Body of the Modrinth responses with an error status.
+///
+/// ```json
+/// { "error": "invalid_input", "description": "Error while validating input: ..." }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RinthError {
+    pub error: String,
+    #[serde(default)]
+    pub description: String,
+}
+
 /// `RinthMod` pretends to be the structure for the response of
 /// `https://api.modrinth.com/v2/project/{id | slug}`
 /// This type is also usable when requesting searches for rinth api
@@ -33,7 +49,133 @@ pub struct RinthProject {
     pub versions: Vec<String>,
     pub icon_url: String,
     // Optional fields
-    //TODO!
+    #[serde(default)]
+    pub gallery: Vec<GalleryImage>,
+    #[serde(default)]
+    pub license: Option<RinthLicense>,
+    #[serde(default)]
+    pub donation_urls: Vec<DonationUrl>,
+}
+
+/// An image of the project gallery.
+///
+/// Search results only list the urls of the images, in that case the other
+/// fields are empty.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(from = "RawGalleryImage")]
+pub struct GalleryImage {
+    pub url: String,
+    pub featured: bool,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub created: Option<String>,
+    pub ordering: i64,
+}
+
+impl GalleryImage {
+    /// Returns `created` parsed, `None` if it is missing or not a valid date.
+    pub fn get_created(&self) -> Option<DateTime<Utc>> {
+        self.created
+            .as_deref()
+            .and_then(parse_timestamp)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawGalleryImage {
+    Url(String),
+    Full {
+        url: String,
+        #[serde(default)]
+        featured: bool,
+        title: Option<String>,
+        description: Option<String>,
+        created: Option<String>,
+        #[serde(default)]
+        ordering: i64,
+    },
+}
+
+impl From<RawGalleryImage> for GalleryImage {
+    fn from(raw: RawGalleryImage) -> Self {
+        match raw {
+            RawGalleryImage::Url(url) => GalleryImage {
+                url,
+                featured: false,
+                title: None,
+                description: None,
+                created: None,
+                ordering: 0,
+            },
+            RawGalleryImage::Full {
+                url,
+                featured,
+                title,
+                description,
+                created,
+                ordering,
+            } => GalleryImage {
+                url,
+                featured,
+                title,
+                description,
+                created,
+                ordering,
+            },
+        }
+    }
+}
+
+/// The license of a project, `id` is a SPDX identifier like `"LGPL-3.0-only"`.
+///
+/// Search results only have the id, then `name` is the id too.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(from = "RawLicense")]
+pub struct RinthLicense {
+    pub id: String,
+    pub name: String,
+    pub url: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawLicense {
+    Id(String),
+    Full {
+        id: String,
+        name: String,
+        url: Option<String>,
+    },
+}
+
+impl From<RawLicense> for RinthLicense {
+    fn from(raw: RawLicense) -> Self {
+        match raw {
+            RawLicense::Id(id) => RinthLicense {
+                name: id.clone(),
+                id,
+                url: None,
+            },
+            RawLicense::Full { id, name, url } => RinthLicense { id, name, url },
+        }
+    }
+}
+
+/// A donation link of a project, `platform` is the display name, like
+/// `"Ko-fi"`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DonationUrl {
+    pub id: String,
+    pub platform: String,
+    pub url: String,
+}
+
+impl RinthProject {
+    /// Returns `updated` parsed, `None` if it is not a valid date.
+    pub fn get_updated(&self) -> Option<DateTime<Utc>> {
+        parse_timestamp(&self.updated)
+    }
 }
 
 impl fmt::Display for RinthProject {
@@ -66,13 +208,27 @@ pub struct Dependency {
 }
 
 impl Dependency {
+    /// `dependency_type` is one of `required`, `optional`, `incompatible` or
+    /// `embedded`.
+    pub fn new(project_id: Option<&str>, version_id: Option<&str>, dependency_type: &str) -> Self {
+        Dependency {
+            version_id: version_id.map(str::to_owned),
+            project_id: project_id.map(str::to_owned),
+            dependency_type: dependency_type.to_owned(),
+        }
+    }
+
     pub fn get_project_id(&self) -> &str {
         self.project_id
-            .as_ref()
-            .map(|s| s.as_str())
+            .as_deref()
             .unwrap_or_default()
     }
 
+    /// Returns `true` if the dependency must be installed too.
+    pub fn is_required(&self) -> bool {
+        self.dependency_type == "required"
+    }
+
     pub fn get_version_id(&self) -> &str {
         match self.version_id {
             Some(ref id) => id,
@@ -101,6 +257,20 @@ pub struct RinthVersion {
 }
 
 impl RinthVersion {
+    /// Returns `date_published` parsed, `None` if it is not a valid date.
+    pub fn get_date_published(&self) -> Option<DateTime<Utc>> {
+        parse_timestamp(&self.date_published)
+    }
+
+    /// Returns the primary file of the version, the first one if none is
+    /// marked as primary.
+    pub fn get_primary_file(&self) -> Option<&RinthFile> {
+        self.files
+            .iter()
+            .find(|f| f.primary)
+            .or(self.files.first())
+    }
+
     pub fn get_file_url(&self) -> &str {
         &self.files[0].url
     }
@@ -132,6 +302,75 @@ impl RinthVersion {
     }
 }
 
+/// `RinthCollection` pretends to be the response for:
+/// `https://api.modrinth.com/v3/collection/{id}`
+///
+/// Only the fields Uranium needs are parsed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RinthCollection {
+    pub id: String,
+    pub name: String,
+    /// Ids of the projects in the collection.
+    pub projects: Vec<String>,
+}
+
+/// The user that owns a Modrinth PAT, from `https://api.modrinth.com/v2/user`.
+///
+/// Only the public fields are parsed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RinthUser {
+    pub id: String,
+    pub username: String,
+    pub name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub bio: Option<String>,
+    pub created: String,
+    pub role: String,
+}
+
+/// A notification of `https://api.modrinth.com/v2/user/{id}/notifications`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RinthNotification {
+    pub id: String,
+    pub user_id: String,
+    /// `project_update`, `team_invite`, `status_change`... `None` for
+    /// legacy notifications.
+    #[serde(rename = "type")]
+    pub notification_type: Option<String>,
+    pub title: String,
+    pub text: String,
+    pub link: String,
+    pub read: bool,
+    pub created: String,
+}
+
+impl RinthNotification {
+    /// Returns `created` parsed, `None` if it is not a valid date.
+    pub fn get_created(&self) -> Option<DateTime<Utc>> {
+        parse_timestamp(&self.created)
+    }
+}
+
+/// The `data` part of a `POST https://api.modrinth.com/v2/version` request,
+/// which creates a new version of a project.
+///
+/// `file_parts` are the names of the multipart parts with the files.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RinthVersionDraft {
+    pub project_id: String,
+    pub name: String,
+    pub version_number: String,
+    pub changelog: Option<String>,
+    pub dependencies: Vec<Dependency>,
+    pub game_versions: Vec<String>,
+    /// `release`, `beta` or `alpha`.
+    pub version_type: String,
+    pub loaders: Vec<String>,
+    pub featured: bool,
+    pub file_parts: Vec<String>,
+    pub primary_file: Option<String>,
+}
+
 /// RinthVersions pretends to parse the response of:
 /// `https://api.modrinth.com/v2/project/{id | slug}/version`
 /// This type is commonly use.
@@ -262,6 +501,16 @@ pub struct RinthModpack {
     pub version_id: String,
     pub name: PathBuf,
     pub files: Vec<RinthMdFiles>,
+    /// Minecraft and loader versions, like `"minecraft": "1.20.1"` and
+    /// `"fabric-loader": "0.15.11"`.
+    #[serde(default)]
+    pub dependencies: HashMap<String, String>,
+}
+
+impl Default for RinthModpack {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl RinthModpack {
@@ -272,9 +521,34 @@ impl RinthModpack {
             version_id: "0.0.0".to_owned(),
             name: "example".into(),
             files: Vec::new(),
+            dependencies: HashMap::new(),
         }
     }
 
+    /// Returns the Minecraft version of the modpack.
+    pub fn get_game_version(&self) -> Option<&str> {
+        self.dependencies
+            .get("minecraft")
+            .map(String::as_str)
+    }
+
+    /// Returns the loader of the modpack as Modrinth names it (`fabric`,
+    /// `quilt`, `forge` or `neoforge`), `None` for vanilla modpacks.
+    pub fn get_loader(&self) -> Option<&'static str> {
+        [
+            ("fabric-loader", "fabric"),
+            ("quilt-loader", "quilt"),
+            ("neoforge", "neoforge"),
+            ("forge", "forge"),
+        ]
+        .into_iter()
+        .find(|(key, _)| {
+            self.dependencies
+                .contains_key(*key)
+        })
+        .map(|(_, loader)| loader)
+    }
+
     pub fn get_mods(&self) -> &[RinthMdFiles] {
         &self.files
     }
@@ -297,7 +571,7 @@ impl RinthModpack {
         self.files.push(new_mod);
     }
 
-    pub fn write_mod_pack_with_name(&self) -> std::io::Result<()>{
+    pub fn write_mod_pack_with_name(&self) -> std::io::Result<()> {
         let j = serde_json::to_string_pretty(self)?;
         std::fs::write("modrinth.index.json", j)?;
         Ok(())
@@ -328,16 +602,46 @@ impl RinthModpack {
 pub struct RinthMdFiles {
     path: PathBuf,
     hashes: Hashes,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    env: Option<Env>,
     downloads: Vec<String>,
     #[serde(rename = "fileSize")]
     file_size: usize,
 }
 
+/// Where a file of the modpack is needed, each side is one of `required`,
+/// `optional` or `unsupported`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Env {
+    pub client: String,
+    pub server: String,
+}
+
+impl Env {
+    pub fn new(client: &str, server: &str) -> Env {
+        Env {
+            client: client.to_owned(),
+            server: server.to_owned(),
+        }
+    }
+
+    pub fn supports_client(&self) -> bool {
+        self.client != "unsupported"
+    }
+
+    pub fn supports_server(&self) -> bool {
+        self.server != "unsupported"
+    }
+}
+
+/// Versions don't know their sides, those are set by the project, so `env`
+/// is `None` unless set with [`RinthMdFiles::with_env`].
 impl From<RinthVersion> for RinthMdFiles {
     fn from(version: RinthVersion) -> RinthMdFiles {
         RinthMdFiles {
             path: ("mods/".to_owned() + version.get_file_name()).into(),
             hashes: version.get_hashes().clone(),
+            env: None,
             downloads: vec![version
                 .get_file_url()
                 .to_string()],
@@ -353,6 +657,7 @@ impl From<RinthVersionFile> for RinthMdFiles {
             hashes: version.files[0]
                 .hashes
                 .clone(),
+            env: None,
             downloads: vec![version.files[0]
                 .url
                 .to_string()],
@@ -366,23 +671,31 @@ impl RinthMdFiles {
         &self.downloads[0]
     }
 
+    /// Returns the Modrinth project id of this file.
+    ///
+    /// Same as [`RinthMdFiles::project_id`], kept for compatibility.
     pub fn get_id(&self) -> Option<&str> {
-        /*
-            So the download link should be something like this:
-            https://cdn.modrinth.com/data/DOUdJVEm/versions/QiCZiPOr/Controlify-2.0.0-beta.14%2B1.21-fabric.jar
-                                          ^^^^^^^^
-            And this code just extract     this     part and return it.
-        */
-
-        for download_link in &self.downloads {
-            if download_link.contains("modrinth") {
-                return download_link
-                    .split("data/")
-                    .nth(1)
-                    .map(|f| &f[0..8]);
-            }
-        }
-        None
+        self.project_id()
+    }
+
+    /// Returns the Modrinth project id taken from the download link, `None`
+    /// if the file is not hosted in Modrinth's CDN.
+    pub fn project_id(&self) -> Option<&str> {
+        self.modrinth_ids()
+            .map(|(project, _)| project)
+    }
+
+    /// Returns the Modrinth version id taken from the download link, `None`
+    /// if the file is not hosted in Modrinth's CDN.
+    pub fn version_id(&self) -> Option<&str> {
+        self.modrinth_ids()
+            .map(|(_, version)| version)
+    }
+
+    fn modrinth_ids(&self) -> Option<(&str, &str)> {
+        self.downloads
+            .iter()
+            .find_map(|link| parse_cdn_ids(link))
     }
 
     pub fn get_name(&self) -> &str {
@@ -397,6 +710,62 @@ impl RinthMdFiles {
     pub fn get_path(&self) -> &Path {
         &self.path
     }
+
+    /// Returns the size of the file in bytes.
+    pub fn get_size(&self) -> usize {
+        self.file_size
+    }
+
+    pub fn get_hashes(&self) -> &Hashes {
+        &self.hashes
+    }
+
+    /// Returns where the file is needed, `None` if the modpack doesn't say.
+    pub fn get_env(&self) -> Option<&Env> {
+        self.env.as_ref()
+    }
+
+    #[must_use]
+    pub fn with_env(mut self, env: Env) -> Self {
+        self.env = Some(env);
+        self
+    }
+}
+
+/// Extracts the project and version ids from a Modrinth CDN link.
+///
+/// The download link should be something like this:
+/// `https://cdn.modrinth.com/data/DOUdJVEm/versions/QiCZiPOr/Controlify.jar`
+/// where `DOUdJVEm` is the project id and `QiCZiPOr` the version id. Ids are
+/// not assumed to have any fixed length.
+fn parse_cdn_ids(link: &str) -> Option<(&str, &str)> {
+    let without_scheme = link
+        .split_once("://")
+        .map_or(link, |(_, rest)| rest);
+    let (host, path) = without_scheme.split_once('/')?;
+
+    if !host.ends_with("modrinth.com") {
+        return None;
+    }
+
+    let mut segments = path
+        .split(['?', '#'])
+        .next()?
+        .split('/');
+
+    match (
+        segments.next(),
+        segments.next(),
+        segments.next(),
+        segments.next(),
+    ) {
+        (Some("data"), Some(project), Some("versions"), Some(version))
+            if !project.is_empty() && !version.is_empty() =>
+        {
+            Some((project, version))
+        }
+        _ => None,
+    }
 }
 
 /// Represents a version file in the Modrinth API.
@@ -438,9 +807,159 @@ pub struct RinthVersionFile {
     pub dependency: Vec<Dependency>,
 }
 
+impl RinthVersionFile {
+    /// Returns `date_published` parsed, `None` if it is not a valid date.
+    pub fn get_date_published(&self) -> Option<DateTime<Utc>> {
+        parse_timestamp(&self.date_published)
+    }
+}
+
 pub fn load_rinth_pack<I: AsRef<Path>>(pack_path: I) -> Option<RinthModpack> {
     read_to_string(&pack_path)
         .map(|s| serde_json::from_str(&s).ok())
         .ok()
         .flatten()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn md_file(link: &str) -> RinthMdFiles {
+        serde_json::from_str(&format!(
+            r#"{{
+                "path": "mods/controlify.jar",
+                "hashes": {{ "sha1": "", "sha512": "" }},
+                "downloads": ["{link}"],
+                "fileSize": 0
+            }}"#
+        ))
+        .unwrap()
+    }
+
+    const SODIUM_PROJECT: &str = r#"{
+        "slug": "sodium", "title": "Sodium",
+        "description": "The fastest and most compatible rendering optimization mod for Minecraft.",
+        "categories": ["optimization"], "client_side": "required", "server_side": "unsupported",
+        "body": "", "status": "approved", "project_type": "mod", "downloads": 51237861,
+        "id": "AANobbMI", "team": "4reLOAKe", "updated": "2024-08-05T18:02:43.531185Z",
+        "versions": ["yaoBL9D9"], "icon_url": "https://cdn.modrinth.com/data/AANobbMI/icon.png",
+        "license": {
+            "id": "LicenseRef-Polyform-Shield-License-1.0.0",
+            "name": "Polyform Shield License 1.0.0",
+            "url": "https://polyformproject.org/licenses/shield/1.0.0/"
+        },
+        "donation_urls": [
+            {"id": "ko-fi", "platform": "Ko-fi", "url": "https://ko-fi.com/jellysquid_"}
+        ],
+        "gallery": [
+            {
+                "url": "https://cdn.modrinth.com/data/AANobbMI/images/sodium.png",
+                "featured": true, "title": "Comparison", "description": null,
+                "created": "2023-05-07T01:13:40.470563Z", "ordering": 0
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn project_page_fields() {
+        let project: RinthProject = serde_json::from_str(SODIUM_PROJECT).unwrap();
+
+        let license = project
+            .license
+            .clone()
+            .unwrap();
+        assert_eq!(license.name, "Polyform Shield License 1.0.0");
+        assert!(license.url.is_some());
+        assert_eq!(project.donation_urls[0].platform, "Ko-fi");
+        let image = &project.gallery[0];
+        assert!(image.featured);
+        assert_eq!(image.title.as_deref(), Some("Comparison"));
+        assert!(image.get_created().is_some());
+
+        // Serializing and reading back must keep every field.
+        let project: RinthProject =
+            serde_json::from_str(&serde_json::to_string(&project).unwrap()).unwrap();
+        assert_eq!(project.gallery[0].ordering, 0);
+        assert_eq!(project.license.unwrap().id, license.id);
+    }
+
+    #[test]
+    fn search_hit_fields() {
+        let mut hit: serde_json::Value = serde_json::from_str(SODIUM_PROJECT).unwrap();
+        hit["license"] = "MIT".into();
+        hit["gallery"] = serde_json::json!(["https://cdn.modrinth.com/data/AANobbMI/images/a.png"]);
+        hit.as_object_mut()
+            .unwrap()
+            .remove("donation_urls");
+
+        let hit: RinthProject = serde_json::from_value(hit).unwrap();
+        assert_eq!(hit.license.unwrap().name, "MIT");
+        assert!(hit.gallery[0].title.is_none());
+        assert!(hit.donation_urls.is_empty());
+    }
+
+    #[test]
+    fn notifications_and_drafts() {
+        let notification: RinthNotification = serde_json::from_str(
+            r#"{
+                "id": "UJLe7LW2", "user_id": "EEFwvyGf", "type": "project_update",
+                "title": "**Sodium** has been updated!",
+                "text": "The project Sodium has released a new version: 0.5.11",
+                "link": "/mod/AANobbMI/version/yaoBL9D9", "read": false,
+                "created": "2024-07-03T01:02:03.123Z", "actions": []
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            notification
+                .notification_type
+                .as_deref(),
+            Some("project_update")
+        );
+        assert!(notification
+            .get_created()
+            .is_some());
+
+        let draft = RinthVersionDraft {
+            project_id: "AANobbMI".to_owned(),
+            name: "Sodium 0.5.11".to_owned(),
+            version_number: "0.5.11".to_owned(),
+            changelog: None,
+            dependencies: vec![Dependency::new(Some("P7dR8mSH"), None, "required")],
+            game_versions: vec!["1.21".to_owned()],
+            version_type: "release".to_owned(),
+            loaders: vec!["fabric".to_owned()],
+            featured: true,
+            file_parts: vec!["file".to_owned()],
+            primary_file: Some("file".to_owned()),
+        };
+        let json = serde_json::to_value(&draft).unwrap();
+        assert_eq!(json["dependencies"][0]["dependency_type"], "required");
+        assert_eq!(json["file_parts"][0], "file");
+    }
+
+    #[test]
+    fn cdn_ids() {
+        let file = md_file(
+            "https://cdn.modrinth.com/data/DOUdJVEm/versions/QiCZiPOr/Controlify-2.0.0.jar",
+        );
+        assert_eq!(file.project_id(), Some("DOUdJVEm"));
+        assert_eq!(file.version_id(), Some("QiCZiPOr"));
+
+        let file = md_file("https://cdn.modrinth.com/data/AANobbMI123/versions/1.2.3/sodium.jar");
+        assert_eq!(file.project_id(), Some("AANobbMI123"));
+        assert_eq!(file.version_id(), Some("1.2.3"));
+    }
+
+    #[test]
+    fn not_cdn_ids() {
+        assert_eq!(md_file("https://cdn.modrinth.com/data/").project_id(), None);
+        assert_eq!(md_file("https://cdn.modrinth.com/data/ab").get_id(), None);
+        assert_eq!(
+            md_file("https://github.com/data/DOUdJVEm/versions/QiCZiPOr/a.jar").project_id(),
+            None
+        );
+        assert_eq!(md_file("modrinth").version_id(), None);
+    }
+}
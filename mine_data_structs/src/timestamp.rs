@@ -0,0 +1,38 @@
+//! Helpers to turn the raw dates returned by Mojang and Modrinth into
+//! [`DateTime<Utc>`].
+//!
+//! The structs keep the raw strings so they serialize back exactly as they were
+//! received, the parsed values are only exposed through accessors.
+
+use chrono::{DateTime, Utc};
+
+/// Parses an RFC 3339 / ISO 8601 date such as `2024-09-25T13:08:41+00:00` or
+/// `2023-01-14T16:26:53.114576Z`.
+///
+/// Returns `None` if `raw` is not a valid date.
+pub fn parse_timestamp(raw: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(raw)
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Datelike, Timelike};
+
+    use super::*;
+
+    #[test]
+    fn parse_dates() {
+        let mojang = parse_timestamp("2024-09-25T13:08:41+00:00").unwrap();
+        assert_eq!((mojang.year(), mojang.month(), mojang.day()), (2024, 9, 25));
+
+        let offset = parse_timestamp("2024-09-25T15:08:41+02:00").unwrap();
+        assert_eq!(offset, mojang);
+
+        let rinth = parse_timestamp("2023-01-14T16:26:53.114576Z").unwrap();
+        assert_eq!(rinth.hour(), 16);
+
+        assert!(parse_timestamp("yesterday").is_none());
+    }
+}
@@ -1,4 +1,10 @@
+use crate::forge::{is_legacy_neoforge, LEGACY_NEOFORGE_MINECRAFT};
+
 const BASE_CUR_URL: &str = "https://api.curseforge.com";
+const BASE_FABRIC_URL: &str = "https://meta.fabricmc.net/v2";
+const BASE_QUILT_URL: &str = "https://meta.quiltmc.org/v3";
+const BASE_FORGE_URL: &str = "https://maven.minecraftforge.net/net/minecraftforge/forge";
+const BASE_NEOFORGE_URL: &str = "https://maven.neoforged.net";
 
 pub struct Curse;
 
@@ -10,4 +16,129 @@ impl Curse {
     pub fn hash() -> String {
         format!("{}/v1/fingerprints", BASE_CUR_URL)
     }
+
+    pub fn search() -> String {
+        format!("{}/v1/mods/search", BASE_CUR_URL)
+    }
+
+    /// The Minecraft game, a cheap request to check the API key.
+    pub fn minecraft() -> String {
+        format!("{}/v1/games/432", BASE_CUR_URL)
+    }
+}
+
+/// Urls of the Fabric meta server, see [`crate::fabric`].
+pub struct Fabric;
+
+/// Urls of the Quilt meta server, same format as [`Fabric`].
+pub struct Quilt;
+
+macro_rules! loader_meta_urls {
+    ($loader:ident, $base:ident) => {
+        impl $loader {
+            pub fn game_versions() -> String {
+                format!("{}/versions/game", $base)
+            }
+
+            pub fn loaders(game_version: &str) -> String {
+                format!("{}/versions/loader/{}", $base, game_version)
+            }
+
+            pub fn profile(game_version: &str, loader_version: &str) -> String {
+                format!(
+                    "{}/versions/loader/{}/{}/profile/json",
+                    $base, game_version, loader_version
+                )
+            }
+
+            pub fn intermediary(game_version: &str) -> String {
+                format!("{}/versions/intermediary/{}", $base, game_version)
+            }
+
+            pub fn installers() -> String {
+                format!("{}/versions/installer", $base)
+            }
+        }
+    };
+}
+
+loader_meta_urls!(Fabric, BASE_FABRIC_URL);
+loader_meta_urls!(Quilt, BASE_QUILT_URL);
+
+/// Urls of the Forge files, see [`crate::forge`].
+pub struct Forge;
+
+impl Forge {
+    pub fn promotions() -> String {
+        "https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json".to_owned()
+    }
+
+    /// Url of the installer jar of `forge_version` (like `47.3.0`) for
+    /// `minecraft_version`.
+    pub fn installer(minecraft_version: &str, forge_version: &str) -> String {
+        Self::artifact(minecraft_version, forge_version, "installer")
+    }
+
+    pub fn universal(minecraft_version: &str, forge_version: &str) -> String {
+        Self::artifact(minecraft_version, forge_version, "universal")
+    }
+
+    fn artifact(minecraft_version: &str, forge_version: &str, classifier: &str) -> String {
+        let full = format!("{minecraft_version}-{forge_version}");
+        format!("{BASE_FORGE_URL}/{full}/forge-{full}-{classifier}.jar")
+    }
+}
+
+/// Urls of the NeoForge maven, see [`crate::forge`].
+///
+/// The NeoForge versions for Minecraft 1.20.1 are published as
+/// `net.neoforged:forge` (like `1.20.1-47.1.106`), the later ones as
+/// `net.neoforged:neoforge`.
+pub struct NeoForge;
+
+impl NeoForge {
+    pub fn versions() -> String {
+        Self::versions_of("neoforge")
+    }
+
+    /// Versions of `net.neoforged:forge`, the NeoForge versions for
+    /// Minecraft 1.20.1.
+    pub fn legacy_versions() -> String {
+        Self::versions_of("forge")
+    }
+
+    /// The versions listing with the NeoForge versions for
+    /// `minecraft_version`.
+    pub fn versions_for(minecraft_version: &str) -> String {
+        if minecraft_version == LEGACY_NEOFORGE_MINECRAFT {
+            Self::legacy_versions()
+        } else {
+            Self::versions()
+        }
+    }
+
+    fn versions_of(artifact: &str) -> String {
+        format!("{BASE_NEOFORGE_URL}/api/maven/versions/releases/net/neoforged/{artifact}")
+    }
+
+    /// Url of the installer jar of `neoforge_version` (like `21.0.167` or
+    /// `1.20.1-47.1.106`).
+    pub fn installer(neoforge_version: &str) -> String {
+        Self::artifact(neoforge_version, "installer")
+    }
+
+    pub fn universal(neoforge_version: &str) -> String {
+        Self::artifact(neoforge_version, "universal")
+    }
+
+    fn artifact(neoforge_version: &str, classifier: &str) -> String {
+        let artifact = if is_legacy_neoforge(neoforge_version) {
+            "forge"
+        } else {
+            "neoforge"
+        };
+        format!(
+            "{BASE_NEOFORGE_URL}/releases/net/neoforged/{artifact}/{neoforge_version}/{artifact}-{neoforge_version}-{classifier}.jar"
+        )
+    }
 }
@@ -0,0 +1,61 @@
+//! Uses the public API from outside the crate, the way other crates have to.
+//!
+//! The crate docs check with `compile_fail` examples that the
+//! `#[non_exhaustive]` enums and reports can't be matched exhaustively nor
+//! built, this checks that the input types can still be built.
+
+use uranium::downloaders::{DownloadState, RetryPolicy};
+use uranium::instance::{InstalledFile, JavaRuntime, LaunchOverrides, Provider};
+use uranium::verifier::PackVerificationReport;
+
+#[test]
+fn launch_overrides_literal_and_builder() {
+    let mut built = LaunchOverrides::default()
+        .jvm_args(vec!["-Xmx6G".to_owned()])
+        .env("MESA_GL_VERSION_OVERRIDE", "4.6")
+        .wrapper(vec!["gamemoderun".to_owned()])
+        .pre_launch(vec!["backup.sh".to_owned()])
+        .post_exit(vec!["notify.sh".to_owned()]);
+    built.register_runtime(JavaRuntime::new("/opt/graalvm-21/bin/java", 21));
+
+    let literal = LaunchOverrides {
+        jvm_args: vec!["-Xmx6G".to_owned()],
+        env: [("MESA_GL_VERSION_OVERRIDE".to_owned(), "4.6".to_owned())].into(),
+        wrapper: vec!["gamemoderun".to_owned()],
+        pre_launch: vec!["backup.sh".to_owned()],
+        post_exit: vec!["notify.sh".to_owned()],
+        java_runtimes: vec![JavaRuntime::new("/opt/graalvm-21/bin/java", 21)],
+    };
+
+    assert_eq!(built, literal);
+    assert_eq!(
+        built
+            .runtime_for(21)
+            .map(|r| r.major_version),
+        Some(21)
+    );
+}
+
+#[test]
+fn constructors() {
+    let source = InstalledFile::curse(238222, 4_000_000);
+    assert_eq!(source.provider, Provider::Curse);
+    assert_eq!(source.version_id, "4000000");
+
+    let policy = RetryPolicy::default()
+        .max_attempts(1)
+        .retry_on(&[503]);
+    assert!(policy.is_retryable(reqwest::StatusCode::SERVICE_UNAVAILABLE));
+    assert!(!policy.is_retryable(reqwest::StatusCode::NOT_FOUND));
+}
+
+#[test]
+fn read_only_types() {
+    let report = PackVerificationReport::default();
+    assert!(report.is_ok());
+    assert_eq!(report.files_checked, 0);
+    assert!(!matches!(
+        DownloadState::MakingRequests,
+        DownloadState::Completed
+    ));
+}
@@ -0,0 +1,235 @@
+//! Authenticated Modrinth API.
+//!
+//! [`searcher::rinth`](crate::searcher::rinth) builds urls for the public
+//! endpoints, [`RinthClient`] sends a personal access token (PAT) for the ones
+//! which need a user: followed projects, notifications, collections and
+//! uploading versions.
+//!
+//! ```no_run
+//! # async fn foo() -> uranium::error::Result<()> {
+//! use uranium::api::RinthClient;
+//!
+//! // Reads MODRINTH_TOKEN from the keyring or the env.
+//! let client = RinthClient::from_secrets()?;
+//! let me = client.current_user().await?;
+//! for project in client.followed_projects(&me.id).await? {
+//!     println!("{project}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::Path;
+
+use mine_data_structs::rinth::{
+    RinthCollection, RinthNotification, RinthProject, RinthUser, RinthVersion, RinthVersionDraft,
+};
+use reqwest::multipart::{Form, Part};
+use reqwest::RequestBuilder;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    error::{Result, UraniumError},
+    http::{default_client, rinth_json_limited},
+    rate_limit::rinth_limiter,
+    secrets::{default_store, SecretStore, MODRINTH_TOKEN},
+};
+
+const API: &str = "https://api.modrinth.com";
+
+/// Modrinth client authenticated with a PAT.
+///
+/// Every request goes through [`rinth_limiter()`], like the public ones.
+pub struct RinthClient {
+    client: reqwest::Client,
+    token: String,
+    /// Replaces [`API`], used by the tests.
+    api: Option<String>,
+}
+
+impl RinthClient {
+    #[must_use]
+    pub fn new(token: &str) -> RinthClient {
+        RinthClient {
+            client: default_client().unwrap_or_default(),
+            token: token.to_owned(),
+            api: None,
+        }
+    }
+
+    /// Makes a client with the `MODRINTH_TOKEN` secret, see
+    /// [`secrets`](crate::secrets).
+    ///
+    /// # Errors
+    /// Returns an error if there is no token.
+    pub fn from_secrets() -> Result<RinthClient> {
+        let token = default_store()
+            .get(MODRINTH_TOKEN)?
+            .ok_or(UraniumError::OtherWithReason(format!(
+                "{MODRINTH_TOKEN} is not set"
+            )))?;
+        Ok(RinthClient::new(&token))
+    }
+
+    /// Returns the owner of the token.
+    ///
+    /// # Errors
+    /// Returns an error if the request fails or the token is not valid.
+    pub async fn current_user(&self) -> Result<RinthUser> {
+        self.fetch(
+            self.client
+                .get(self.url("v2/user")),
+        )
+        .await
+    }
+
+    /// Returns the projects followed by the user `id` (or username).
+    ///
+    /// # Errors
+    /// Returns an error if the request fails or the token can't see them.
+    pub async fn followed_projects(&self, id: &str) -> Result<Vec<RinthProject>> {
+        self.fetch(
+            self.client
+                .get(self.url(&format!("v2/user/{id}/follows"))),
+        )
+        .await
+    }
+
+    /// Returns the notifications of the user `id` (or username).
+    ///
+    /// # Errors
+    /// Returns an error if the request fails or the token can't see them.
+    pub async fn notifications(&self, id: &str) -> Result<Vec<RinthNotification>> {
+        self.fetch(
+            self.client
+                .get(self.url(&format!("v2/user/{id}/notifications"))),
+        )
+        .await
+    }
+
+    /// Returns the collections of the user `id` (or username), private ones
+    /// included if the token belongs to that user.
+    ///
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub async fn collections(&self, id: &str) -> Result<Vec<RinthCollection>> {
+        self.fetch(
+            self.client
+                .get(self.url(&format!("v3/user/{id}/collections"))),
+        )
+        .await
+    }
+
+    /// Uploads `file` (a mod jar or a `.mrpack`) as a new version of
+    /// `draft.project_id`.
+    ///
+    /// The file is sent as the only part, so `draft.file_parts` and
+    /// `draft.primary_file` are set to it.
+    ///
+    /// # Errors
+    /// Returns an error if `file` can't be read, or if Modrinth rejects the
+    /// version.
+    pub async fn create_version<I: AsRef<Path>>(
+        &self,
+        mut draft: RinthVersionDraft,
+        file: I,
+    ) -> Result<RinthVersion> {
+        let file = file.as_ref();
+        let file_name = file
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let part_name = "file".to_owned();
+        draft.file_parts = vec![part_name.clone()];
+        draft.primary_file = Some(part_name.clone());
+
+        let data = serde_json::to_string(&draft).map_err(|_| UraniumError::WrongFileFormat)?;
+        let form = Form::new()
+            .text("data", data)
+            .part(
+                part_name,
+                Part::bytes(std::fs::read(file)?).file_name(file_name),
+            );
+
+        self.fetch(
+            self.client
+                .post(self.url("v2/version"))
+                .multipart(form),
+        )
+        .await
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!(
+            "{}/{path}",
+            self.api
+                .as_deref()
+                .unwrap_or(API)
+        )
+    }
+
+    async fn fetch<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T> {
+        let request = request.header(reqwest::header::AUTHORIZATION, &self.token);
+        let response = rinth_limiter()
+            .send(request)
+            .await?;
+        rinth_json_limited(response).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+
+    use super::*;
+    use crate::test_utils::FixtureServer;
+
+    #[tokio::test]
+    async fn user_requests() {
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let user = serde_json::json!({
+            "id": "EJSfhGfb",
+            "username": "steve",
+            "name": null,
+            "avatar_url": null,
+            "bio": null,
+            "created": "2023-01-01T00:00:00Z",
+            "role": "developer"
+        });
+        server.add("/v2/user", user.to_string().into_bytes());
+        server.add("/v2/user/EJSfhGfb/notifications", b"[]".to_vec());
+
+        let mut client = RinthClient::new("mrp_token");
+        client.api = Some(server.url(""));
+
+        let me = client
+            .current_user()
+            .await
+            .unwrap();
+        assert_eq!(me.username, "steve");
+        assert!(client
+            .notifications(&me.id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Not in the server.
+        assert!(matches!(
+            client.collections(&me.id).await,
+            Err(UraniumError::NotFound(url)) if url.ends_with("/v3/user/EJSfhGfb/collections")
+        ));
+
+        // A rejected token without a Modrinth error body.
+        server.fail("/v2/user", 401, 1);
+        assert!(matches!(
+            client.current_user().await,
+            Err(UraniumError::HttpStatus {
+                status: StatusCode::UNAUTHORIZED,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,296 @@
+//! Cache of downloaded files shared by every instance, keyed by their hash.
+//!
+//! Many instances use the same libraries, assets and mods. With a cache set
+//! through [`set_artifact_cache`](crate::set_artifact_cache) the
+//! [`Downloader`](crate::downloaders::Downloader) looks for each file in the
+//! cache before requesting it, and stores there the files it downloads. Files
+//! are copied into the destination, never linked, so changing a file of an
+//! instance can't break the cache or the other instances.
+//!
+//! The cache never removes anything by itself, use [`ArtifactCache::gc`].
+//!
+//! ```no_run
+//! # fn foo() -> uranium::error::Result<()> {
+//! use std::time::Duration;
+//!
+//! use uranium::artifact_cache::ArtifactCache;
+//!
+//! let cache = ArtifactCache::open_default()?;
+//! uranium::set_artifact_cache(Some(cache.clone()));
+//!
+//! // Later, keep the cache under 2 GiB and drop what wasn't used in a month.
+//! cache.gc(Some(Duration::from_secs(30 * 24 * 60 * 60)), Some(2 << 30))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    cmp::Reverse,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use log::warn;
+
+use crate::{
+    downloaders::HashType,
+    error::{Result, UraniumError},
+    paths::cache_dir,
+};
+
+/// Directory of files named by their hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtifactCache {
+    dir: PathBuf,
+}
+
+/// What [`ArtifactCache::gc`] removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CacheGc {
+    pub removed: usize,
+    /// Bytes freed.
+    pub freed: u64,
+}
+
+impl ArtifactCache {
+    /// Uses `dir` as cache, it is created when the first file is stored.
+    pub fn new<I: AsRef<Path>>(dir: I) -> ArtifactCache {
+        ArtifactCache {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Opens the cache in `artifacts/` inside [`cache_dir`],
+    /// `~/.uranium/cache` by default.
+    ///
+    /// # Errors
+    /// Returns an error if the cache dir can't be found.
+    pub fn open_default() -> Result<ArtifactCache> {
+        Ok(Self::new(cache_dir()?.join("artifacts")))
+    }
+
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns where the file with `hash` is stored, like
+    /// `sha1/ab/abcdef...`.
+    ///
+    /// The hashes come from modpacks and manifests, `None` is returned unless
+    /// the hash is hex of the right length, so it can never point outside
+    /// the cache.
+    fn path_of(&self, hash: &HashType) -> Option<PathBuf> {
+        let (algorithm, len) = match hash {
+            HashType::Sha1(_) => ("sha1", 40),
+            HashType::Sha512(_) => ("sha512", 128),
+            HashType::Md5(_) => ("md5", 32),
+        };
+        let value = hash
+            .value()
+            .to_ascii_lowercase();
+        let is_hex = value
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+        if value.len() != len || !is_hex {
+            warn!("Not caching invalid {algorithm} hash {:?}", hash.value());
+            return None;
+        }
+
+        Some(
+            self.dir
+                .join(algorithm)
+                .join(&value[..2])
+                .join(&value),
+        )
+    }
+
+    /// Returns the path of the cached file with `hash`, `None` if it isn't
+    /// cached.
+    ///
+    /// The file is hashed first, so this may block for a while with big
+    /// files. Broken files are removed.
+    #[must_use]
+    pub fn get(&self, hash: &HashType) -> Option<PathBuf> {
+        let path = self.path_of(hash)?;
+        let matches = fs::File::open(&path)
+            .and_then(|file| hash.matches_reader(file))
+            .ok()?;
+        if !matches {
+            warn!("Removing broken cached file {}", path.display());
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+
+        // The modification time tells `gc` when the file was last used.
+        if let Ok(file) = fs::File::options()
+            .write(true)
+            .open(&path)
+        {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(path)
+    }
+
+    /// Stores everything read from `content`, which must have `hash`.
+    ///
+    /// # Errors
+    /// Returns an error if `hash` is not a valid hash, or if the content
+    /// can't be read or the file written.
+    pub fn store<R: Read>(&self, hash: &HashType, mut content: R) -> Result<()> {
+        let path = self
+            .path_of(hash)
+            .ok_or_else(|| {
+                UraniumError::OtherWithReason(format!("Invalid hash {:?}", hash.value()))
+            })?;
+        if path.exists() {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Other downloads may be reading the cache at the same time, they
+        // must never see a half written file.
+        let part = path.with_added_extension(format!("{}.part", std::process::id()));
+        let copied = fs::File::create(&part).and_then(|mut file| io::copy(&mut content, &mut file));
+        if let Err(e) = copied {
+            let _ = fs::remove_file(&part);
+            return Err(e.into());
+        }
+        fs::rename(part, path)?;
+        Ok(())
+    }
+
+    /// Returns the size in bytes of every cached file.
+    ///
+    /// # Errors
+    /// Returns an error if the cache can't be read.
+    pub fn size(&self) -> Result<u64> {
+        Ok(self
+            .files()?
+            .iter()
+            .map(|(_, size, _)| size)
+            .sum())
+    }
+
+    /// Removes the files not used in `max_age`, then the least recently used
+    /// ones until the cache takes at most `max_size` bytes.
+    ///
+    /// # Errors
+    /// Returns an error if the cache can't be read or a file can't be removed.
+    pub fn gc(&self, max_age: Option<Duration>, max_size: Option<u64>) -> Result<CacheGc> {
+        let mut files = self.files()?;
+        // Most recently used first.
+        files.sort_by_key(|(_, _, used)| Reverse(*used));
+
+        let now = SystemTime::now();
+        let mut gc = CacheGc::default();
+        let mut kept = 0;
+        for (path, size, used) in files {
+            let expired = max_age.is_some_and(|age| {
+                now.duration_since(used)
+                    .is_ok_and(|elapsed| elapsed > age)
+            });
+            let too_big = max_size.is_some_and(|max| kept + size > max);
+            if expired || too_big {
+                fs::remove_file(path)?;
+                gc.removed += 1;
+                gc.freed += size;
+            } else {
+                kept += size;
+            }
+        }
+        Ok(gc)
+    }
+
+    /// Returns every cached file with its size and last use.
+    fn files(&self) -> Result<Vec<(PathBuf, u64, SystemTime)>> {
+        let mut files = Vec::new();
+        if self.dir.is_dir() {
+            list_files(&self.dir, &mut files)?;
+        }
+        Ok(files)
+    }
+}
+
+fn list_files(dir: &Path, files: &mut Vec<(PathBuf, u64, SystemTime)>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            list_files(&entry.path(), files)?;
+        } else {
+            files.push((entry.path(), metadata.len(), metadata.modified()?));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashes::sha1_bytes;
+
+    #[test]
+    fn store_get_and_gc() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ArtifactCache::new(dir.path());
+
+        let jar = HashType::Sha1(sha1_bytes(b"jar"));
+        let asset = HashType::Sha1(sha1_bytes(b"asset"));
+        assert_eq!(cache.get(&jar), None);
+        cache
+            .store(&jar, &b"jar"[..])
+            .unwrap();
+        cache
+            .store(&asset, &b"asset"[..])
+            .unwrap();
+        let cached = cache.get(&jar).unwrap();
+        assert_eq!(fs::read(&cached).unwrap(), b"jar");
+
+        // A cached file changed by hand is not used.
+        fs::write(&cached, b"changed").unwrap();
+        assert_eq!(cache.get(&jar), None);
+        assert_eq!(cache.size().unwrap(), 5);
+
+        let gc = cache
+            .gc(None, Some(1))
+            .unwrap();
+        let left = cache.size().unwrap();
+
+        assert_eq!(
+            gc,
+            CacheGc {
+                removed: 1,
+                freed: 5
+            }
+        );
+        assert_eq!(left, 0);
+    }
+
+    #[test]
+    fn invalid_hashes() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ArtifactCache::new(dir.path().join("cache"));
+        let victim = dir.path().join("victim");
+        fs::write(&victim, b"precious").unwrap();
+
+        // `cache/sha1/<prefix>/<hash>` would be `cache/sha1/../../victim`.
+        let traversal = HashType::Sha1("../victim".to_owned());
+        assert_eq!(cache.get(&traversal), None);
+        assert!(cache
+            .store(&traversal, &b"evil"[..])
+            .is_err());
+        assert_eq!(fs::read(&victim).unwrap(), b"precious");
+
+        let short = HashType::Sha1("abcdef".to_owned());
+        assert!(cache
+            .store(&short, &b"abc"[..])
+            .is_err());
+        let not_hex = HashType::Md5("z".repeat(32));
+        assert_eq!(cache.get(&not_hex), None);
+    }
+}
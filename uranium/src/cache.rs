@@ -0,0 +1,144 @@
+//! Persistent cache of Modrinth versions keyed by file hash.
+//!
+//! Building a modpack asks Modrinth about every mod in the `mods/` folder,
+//! most of them are the same between runs so the answers are cached in
+//! `~/.uranium/cache/rinth_versions.json` for a while, see
+//! [`paths`](crate::paths) to use other directory.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::Utc;
+use log::warn;
+use mine_data_structs::rinth::RinthVersion;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Result, UraniumError},
+    paths::cache_dir,
+};
+
+/// How long an entry is valid by default, 7 days.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+const CACHE_FILE: &str = "rinth_versions.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    /// Unix timestamp (seconds) of when the version was fetched.
+    fetched_at: i64,
+    version: RinthVersion,
+}
+
+/// Hash (sha1) -> `RinthVersion` cache stored as JSON.
+#[derive(Debug, Clone)]
+pub struct VersionCache {
+    path: PathBuf,
+    ttl: Duration,
+    entries: HashMap<String, CacheEntry>,
+}
+
+impl VersionCache {
+    /// Opens the cache in [`cache_dir`], `~/.uranium/cache` by default, with
+    /// [`DEFAULT_TTL`].
+    ///
+    /// # Errors
+    /// Returns an error if the cache dir can't be found.
+    pub fn open_default() -> Result<VersionCache> {
+        // Unit tests must not read nor write the user cache.
+        if cfg!(test) {
+            return Err(UraniumError::OtherWithReason(
+                "The default cache is disabled in tests".to_owned(),
+            ));
+        }
+        Ok(Self::open(cache_dir()?.join(CACHE_FILE), DEFAULT_TTL))
+    }
+
+    /// Opens the cache stored in `path`.
+    ///
+    /// If the file doesn't exist or is broken the cache starts empty, a cache
+    /// should never stop the user from working.
+    pub fn open<I: AsRef<Path>>(path: I, ttl: Duration) -> VersionCache {
+        let path = path.as_ref().to_path_buf();
+        let entries = match std::fs::read(&path) {
+            Ok(content) => serde_json::from_slice(&content).unwrap_or_else(|e| {
+                warn!("Ignoring broken cache {}: {e}", path.display());
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+
+        VersionCache { path, ttl, entries }
+    }
+
+    /// Returns the cached version of the file with `hash`, `None` if it is not
+    /// cached or has expired.
+    pub fn get(&self, hash: &str) -> Option<&RinthVersion> {
+        let max_age = i64::try_from(self.ttl.as_secs()).unwrap_or(i64::MAX);
+        self.entries
+            .get(hash)
+            .filter(|e| Utc::now().timestamp() - e.fetched_at <= max_age)
+            .map(|e| &e.version)
+    }
+
+    pub fn insert(&mut self, hash: String, version: RinthVersion) {
+        self.entries.insert(
+            hash,
+            CacheEntry {
+                fetched_at: Utc::now().timestamp(),
+                version,
+            },
+        );
+    }
+
+    /// Writes the cache into its file, expired entries are dropped.
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be written.
+    pub fn save(&mut self) -> Result<()> {
+        let max_age = i64::try_from(self.ttl.as_secs()).unwrap_or(i64::MAX);
+        let now = Utc::now().timestamp();
+        self.entries
+            .retain(|_, e| now - e.fetched_at <= max_age);
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content =
+            serde_json::to_vec(&self.entries).map_err(|_| UraniumError::WrongFileFormat)?;
+        std::fs::write(&self.path, content)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::rinth_version;
+
+    #[test]
+    fn cache_roundtrip_and_ttl() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir
+            .path()
+            .join("rinth_versions.json");
+
+        let mut cache = VersionCache::open(&path, DEFAULT_TTL);
+        assert!(cache.get("abc").is_none());
+        cache.insert("abc".to_owned(), rinth_version("yaoBL9D9"));
+        cache.save().unwrap();
+
+        let cache = VersionCache::open(&path, DEFAULT_TTL);
+        assert_eq!(cache.get("abc").unwrap().id, "yaoBL9D9");
+
+        let mut expired = VersionCache::open(&path, DEFAULT_TTL);
+        expired
+            .entries
+            .get_mut("abc")
+            .unwrap()
+            .fetched_at -= DEFAULT_TTL.as_secs() as i64 + 1;
+        assert!(expired.get("abc").is_none());
+    }
+}
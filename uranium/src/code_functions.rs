@@ -1,4 +1,6 @@
-use crate::variables::constants::{DEFAULT_NTHREADS, NTHREADS};
+use crate::variables::constants::{
+    DEFAULT_MAX_METADATA_SIZE, DEFAULT_NTHREADS, MAX_METADATA_SIZE as MAX_SIZE, NTHREADS,
+};
 
 #[allow(non_snake_case)]
 #[allow(unused)]
@@ -9,3 +11,12 @@ pub fn N_THREADS() -> usize {
         Err(_) => DEFAULT_NTHREADS,
     }
 }
+
+#[allow(non_snake_case)]
+/// Returns the max size in bytes allowed for metadata responses.
+pub fn MAX_METADATA_SIZE() -> u64 {
+    match MAX_SIZE.read() {
+        Ok(e) => *e,
+        Err(_) => DEFAULT_MAX_METADATA_SIZE,
+    }
+}
@@ -0,0 +1,124 @@
+//! Detection of paths which only differ in case.
+//!
+//! On case-insensitive filesystems (Windows and macOS by default) two paths
+//! like `mods/Sodium.jar` and `mods/sodium.jar` end up being the same file, so
+//! one silently overwrites the other.
+
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+};
+
+use log::warn;
+
+use crate::error::{Result, UraniumError};
+
+/// A group of paths which are the same file on a case-insensitive filesystem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseCollision {
+    pub paths: Vec<PathBuf>,
+}
+
+impl Display for CaseCollision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let paths: Vec<String> = self
+            .paths
+            .iter()
+            .map(|p| format!("{}", p.display()))
+            .collect();
+        write!(f, "[{}]", paths.join(", "))
+    }
+}
+
+/// Returns every group of `paths` which only differ in case, in the order they
+/// were first seen.
+pub fn find_case_collisions<I, P>(paths: I) -> Vec<CaseCollision>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    let mut groups: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    let mut order = Vec::new();
+
+    for path in paths {
+        let path = path.as_ref();
+        let key = path
+            .to_string_lossy()
+            .to_lowercase();
+
+        let group = groups
+            .entry(key.clone())
+            .or_insert_with(|| {
+                order.push(key);
+                Vec::new()
+            });
+        if !group
+            .iter()
+            .any(|p| p == path)
+        {
+            group.push(path.to_path_buf());
+        }
+    }
+
+    order
+        .into_iter()
+        .filter_map(|key| groups.remove(&key))
+        .filter(|paths| paths.len() > 1)
+        .map(|paths| CaseCollision { paths })
+        .collect()
+}
+
+/// Logs a warning for each collision in `paths`.
+///
+/// # Errors
+/// On case-insensitive platforms (Windows and macOS) collisions would corrupt
+/// the files, so `Err(UraniumError::CaseCollisions)` is returned instead.
+pub(crate) fn check_case_collisions<I, P>(paths: I) -> Result<()>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    let collisions = find_case_collisions(paths);
+    if collisions.is_empty() {
+        return Ok(());
+    }
+
+    for collision in &collisions {
+        warn!("Paths only differing in case: {collision}");
+    }
+
+    if cfg!(any(windows, target_os = "macos")) {
+        return Err(UraniumError::CaseCollisions(collisions));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn case_collisions() {
+        let collisions = find_case_collisions([
+            "mods/Sodium.jar",
+            "mods/lithium.jar",
+            "mods/sodium.jar",
+            "config/a.toml",
+            "mods/SODIUM.jar",
+            "mods/lithium.jar",
+        ]);
+
+        assert_eq!(
+            collisions,
+            vec![CaseCollision {
+                paths: vec![
+                    "mods/Sodium.jar".into(),
+                    "mods/sodium.jar".into(),
+                    "mods/SODIUM.jar".into()
+                ]
+            }]
+        );
+        assert!(find_case_collisions(["a", "b"]).is_empty());
+    }
+}
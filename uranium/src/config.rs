@@ -0,0 +1,429 @@
+//! Settings kept in `~/.uranium/config.toml`.
+//!
+//! [`UraniumConfig::load`] reads the settings in layers, each one overriding
+//! the previous:
+//! 1. The config file, `config.toml` or `config.json` in [`uranium_dir`].
+//! 2. The `URANIUM_*` env vars.
+//! 3. Whatever the program changes in the returned config.
+//!
+//! ```no_run
+//! # fn foo() -> uranium::error::Result<()> {
+//! use uranium::config::UraniumConfig;
+//!
+//! let mut config = UraniumConfig::load()?;
+//! config.threads = Some(8);
+//! config.apply()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A config file with every setting:
+//!
+//! ```toml
+//! threads = 16
+//! max_metadata_size = 67108864
+//! uranium_dir = "/srv/uranium"
+//! cache_dir = "/var/cache/uranium"
+//! minecraft_dir = "/srv/minecraft"
+//! proxy = "http://127.0.0.1:8080"
+//! # Bytes per second.
+//! max_bandwidth = 5000000
+//!
+//! # Env var each secret is read from.
+//! [api_key_vars]
+//! CURSE_API_KEY = "MY_CURSEFORGE_KEY"
+//! ```
+
+use std::{collections::BTreeMap, fmt::Display, path::Path, path::PathBuf, str::FromStr};
+
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::{
+    error::{Result, UraniumError},
+    paths::{
+        current as current_dirs, set_dirs, uranium_dir, Dirs, CACHE_DIR_VAR, DIRS,
+        MINECRAFT_DIR_VAR, URANIUM_HOME_VAR,
+    },
+    rate_limit::{set_max_bandwidth, BANDWIDTH},
+    secrets::{set_env_var, CURSE_API_KEY, ENV_VARS, MODRINTH_TOKEN},
+    set_max_metadata_size, set_proxy, set_threads,
+    variables::constants::{CONFIG_FILE, CONFIG_JSON_FILE, MAX_METADATA_SIZE, NTHREADS, PROXY},
+};
+
+/// Env var overriding [`UraniumConfig::threads`].
+pub const THREADS_VAR: &str = "URANIUM_THREADS";
+/// Env var overriding [`UraniumConfig::max_metadata_size`].
+pub const MAX_METADATA_SIZE_VAR: &str = "URANIUM_MAX_METADATA_SIZE";
+/// Env var overriding [`UraniumConfig::proxy`].
+pub const PROXY_VAR: &str = "URANIUM_PROXY";
+/// Env var overriding [`UraniumConfig::max_bandwidth`].
+pub const MAX_BANDWIDTH_VAR: &str = "URANIUM_MAX_BANDWIDTH";
+
+/// The settings of Uranium, `None` keeps the default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct UraniumConfig {
+    /// See [`set_threads`](crate::set_threads).
+    pub threads: Option<usize>,
+    /// See [`set_max_metadata_size`](crate::set_max_metadata_size).
+    pub max_metadata_size: Option<u64>,
+    /// Where the logs and the config file are, see [`crate::paths`].
+    pub uranium_dir: Option<PathBuf>,
+    /// Where the caches are written, `cache/` inside the uranium dir by
+    /// default.
+    pub cache_dir: Option<PathBuf>,
+    /// The `.minecraft` directory, see [`crate::paths`].
+    pub minecraft_dir: Option<PathBuf>,
+    /// See [`set_proxy`](crate::set_proxy).
+    pub proxy: Option<String>,
+    /// Download speed limit in bytes per second.
+    pub max_bandwidth: Option<u64>,
+    /// Env var each secret is read from, by secret name, so the keys
+    /// themselves are never written in the config file.
+    pub api_key_vars: BTreeMap<String, String>,
+}
+
+impl UraniumConfig {
+    /// Reads the config file, if there is one, and the env vars on top of it.
+    ///
+    /// # Errors
+    /// Returns `Err(UraniumError::InvalidConfig)` with the offending setting
+    /// if the file or an env var has a wrong value.
+    pub fn load() -> Result<Self> {
+        let file = match config_file() {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        Ok(file.merge(Self::from_env()?))
+    }
+
+    /// Reads the settings of a TOML file, or a JSON one if its extension is
+    /// `.json`.
+    ///
+    /// # Errors
+    /// Returns `Err(UraniumError::FileNotFound)` if the file can't be read
+    /// and `Err(UraniumError::InvalidConfig)` if it is not valid.
+    pub fn from_file<I: AsRef<Path>>(path: I) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|_| UraniumError::FileNotFound(path.display().to_string()))?;
+
+        let parsed = if path.extension() == Some("json".as_ref()) {
+            serde_json::from_str::<Value>(&content).map_err(|e| invalid(path.display(), e))
+        } else {
+            toml::from_str::<toml::Table>(&content)
+                .map_err(|e| invalid(path.display(), e.message()))
+                .and_then(|t| serde_json::to_value(t).map_err(|e| invalid(path.display(), e)))
+        };
+        match parsed? {
+            Value::Object(settings) => Self::from_settings(settings),
+            _ => Err(invalid(path.display(), "expected a table of settings")),
+        }
+    }
+
+    /// Reads the settings set in env vars.
+    ///
+    /// # Errors
+    /// Returns `Err(UraniumError::InvalidConfig)` with the name of the env
+    /// var if one has a wrong value.
+    pub fn from_env() -> Result<Self> {
+        let config = UraniumConfig {
+            threads: env_var(THREADS_VAR)?,
+            max_metadata_size: env_var(MAX_METADATA_SIZE_VAR)?,
+            uranium_dir: std::env::var_os(URANIUM_HOME_VAR).map(PathBuf::from),
+            cache_dir: std::env::var_os(CACHE_DIR_VAR).map(PathBuf::from),
+            minecraft_dir: std::env::var_os(MINECRAFT_DIR_VAR).map(PathBuf::from),
+            proxy: env_var(PROXY_VAR)?,
+            max_bandwidth: env_var(MAX_BANDWIDTH_VAR)?,
+            api_key_vars: BTreeMap::new(),
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Returns `self` with the settings of `overrides` which are set.
+    #[must_use]
+    pub fn merge(mut self, overrides: UraniumConfig) -> Self {
+        self.threads = overrides
+            .threads
+            .or(self.threads);
+        self.max_metadata_size = overrides
+            .max_metadata_size
+            .or(self.max_metadata_size);
+        self.uranium_dir = overrides
+            .uranium_dir
+            .or(self.uranium_dir);
+        self.cache_dir = overrides
+            .cache_dir
+            .or(self.cache_dir);
+        self.minecraft_dir = overrides
+            .minecraft_dir
+            .or(self.minecraft_dir);
+        self.proxy = overrides.proxy.or(self.proxy);
+        self.max_bandwidth = overrides
+            .max_bandwidth
+            .or(self.max_bandwidth);
+        self.api_key_vars
+            .extend(overrides.api_key_vars);
+        self
+    }
+
+    /// Checks that every setting has a usable value.
+    ///
+    /// # Errors
+    /// Returns `Err(UraniumError::InvalidConfig)` with the first wrong
+    /// setting.
+    pub fn validate(&self) -> Result<()> {
+        if self.threads == Some(0) {
+            return Err(invalid("threads", "must be at least 1"));
+        }
+        if self.max_metadata_size == Some(0) {
+            return Err(invalid("max_metadata_size", "must be at least 1 byte"));
+        }
+        if self.max_bandwidth == Some(0) {
+            return Err(invalid(
+                "max_bandwidth",
+                "must be at least 1 byte per second",
+            ));
+        }
+        if let Some(proxy) = &self.proxy {
+            reqwest::Proxy::all(proxy).map_err(|e| invalid("proxy", e))?;
+        }
+        for (name, var) in &self.api_key_vars {
+            if ![CURSE_API_KEY, MODRINTH_TOKEN].contains(&name.as_str()) {
+                return Err(invalid(format!("api_key_vars.{name}"), "unknown secret"));
+            }
+            if var.trim().is_empty() {
+                return Err(invalid(
+                    format!("api_key_vars.{name}"),
+                    "the env var name is empty",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Uses the settings which are set from now on.
+    ///
+    /// # Errors
+    /// Returns `Err(UraniumError::InvalidConfig)` if a setting is not valid
+    /// and `Err(UraniumError::Other)` if the settings can't be updated,
+    /// nothing is changed in both cases.
+    pub fn apply(&self) -> Result<()> {
+        self.validate()?;
+        // The setters only fail if their lock is poisoned, so checking them
+        // first changes either every setting or none.
+        let poisoned = NTHREADS.is_poisoned()
+            || MAX_METADATA_SIZE.is_poisoned()
+            || PROXY.is_poisoned()
+            || DIRS.is_poisoned()
+            || BANDWIDTH.is_poisoned()
+            || ENV_VARS.is_poisoned();
+        if poisoned {
+            return Err(UraniumError::Other);
+        }
+
+        if let Some(threads) = self.threads {
+            set_threads(threads).ok_or(UraniumError::Other)?;
+        }
+        if let Some(bytes) = self.max_metadata_size {
+            set_max_metadata_size(bytes).ok_or(UraniumError::Other)?;
+        }
+        if let Some(dirs) = self.dirs(current_dirs()) {
+            set_dirs(dirs).ok_or(UraniumError::Other)?;
+        }
+        if let Some(proxy) = &self.proxy {
+            set_proxy(Some(proxy))?;
+        }
+        if let Some(bytes) = self.max_bandwidth {
+            set_max_bandwidth(Some(bytes))?;
+        }
+        for (name, var) in &self.api_key_vars {
+            set_env_var(name, var).ok_or(UraniumError::Other)?;
+        }
+        Ok(())
+    }
+
+    /// Returns `current` with the dirs of this config, `None` if it has none.
+    fn dirs(&self, current: Dirs) -> Option<Dirs> {
+        if self.uranium_dir.is_none() && self.cache_dir.is_none() && self.minecraft_dir.is_none() {
+            return None;
+        }
+        let mut dirs = current;
+        if let Some(dir) = &self.uranium_dir {
+            dirs = dirs.with_uranium_dir(dir);
+        }
+        if let Some(dir) = &self.cache_dir {
+            dirs = dirs.with_cache_dir(dir);
+        }
+        if let Some(dir) = &self.minecraft_dir {
+            dirs = dirs.with_minecraft_dir(dir);
+        }
+        Some(dirs)
+    }
+
+    fn from_settings(settings: Map<String, Value>) -> Result<Self> {
+        let mut config = UraniumConfig::default();
+        for (key, value) in settings {
+            match key.as_str() {
+                "threads" => config.threads = setting(&key, value)?,
+                "max_metadata_size" => config.max_metadata_size = setting(&key, value)?,
+                "uranium_dir" => config.uranium_dir = setting(&key, value)?,
+                "cache_dir" => config.cache_dir = setting(&key, value)?,
+                "minecraft_dir" => config.minecraft_dir = setting(&key, value)?,
+                "proxy" => config.proxy = setting(&key, value)?,
+                "max_bandwidth" => config.max_bandwidth = setting(&key, value)?,
+                "api_key_vars" => config.api_key_vars = setting(&key, value)?,
+                _ => return Err(invalid(key, "unknown setting")),
+            }
+        }
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Returns the config file in the uranium dir, the TOML one first.
+fn config_file() -> Option<PathBuf> {
+    let dir = uranium_dir().ok()?;
+    [CONFIG_FILE, CONFIG_JSON_FILE]
+        .into_iter()
+        .map(|file| dir.join(file))
+        .find(|path| path.is_file())
+}
+
+fn setting<T: DeserializeOwned>(key: &str, value: Value) -> Result<T> {
+    serde_json::from_value(value).map_err(|e| invalid(key, e))
+}
+
+fn env_var<T: FromStr>(var: &str) -> Result<Option<T>>
+where
+    T::Err: Display,
+{
+    std::env::var(var)
+        .ok()
+        .map(|value| {
+            value
+                .trim()
+                .parse()
+                .map_err(|e| invalid(var, e))
+        })
+        .transpose()
+}
+
+fn invalid(key: impl Display, reason: impl Display) -> UraniumError {
+    UraniumError::InvalidConfig {
+        key: key.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let toml_file = dir.join(CONFIG_FILE);
+        std::fs::write(
+            &toml_file,
+            "threads = 8\nproxy = \"http://127.0.0.1:8080\"\ncache_dir = \"/var/cache/uranium\"\n\n[api_key_vars]\nCURSE_API_KEY = \"CF_KEY\"\n",
+        )
+        .unwrap();
+        let json_file = dir.join(CONFIG_JSON_FILE);
+        std::fs::write(&json_file, r#"{"threads": 4, "max_bandwidth": 1000}"#).unwrap();
+
+        let config = UraniumConfig::from_file(&toml_file)
+            .unwrap()
+            .merge(UraniumConfig::from_file(&json_file).unwrap());
+        assert_eq!(config.threads, Some(4));
+        assert_eq!(config.proxy.as_deref(), Some("http://127.0.0.1:8080"));
+        assert_eq!(config.max_bandwidth, Some(1000));
+        assert_eq!(config.cache_dir, Some(PathBuf::from("/var/cache/uranium")));
+        assert_eq!(config.api_key_vars[CURSE_API_KEY], "CF_KEY");
+
+        std::fs::write(&toml_file, "threads = \"many\"").unwrap();
+        let wrong_type = UraniumConfig::from_file(&toml_file);
+        std::fs::write(&toml_file, "thread = 8").unwrap();
+        let unknown = UraniumConfig::from_file(&toml_file);
+        std::fs::write(&toml_file, "threads = 0").unwrap();
+        let zero = UraniumConfig::from_file(&toml_file);
+
+        for result in [wrong_type, unknown, zero] {
+            assert!(matches!(
+                result,
+                Err(UraniumError::InvalidConfig { key, .. }) if key.starts_with("thread")
+            ));
+        }
+    }
+
+    #[test]
+    fn env_settings() {
+        std::env::set_var(THREADS_VAR, " 6 ");
+        std::env::set_var(MAX_BANDWIDTH_VAR, "1000");
+        let config = UraniumConfig::from_env();
+        std::env::set_var(THREADS_VAR, "many");
+        let wrong = UraniumConfig::from_env();
+        std::env::remove_var(THREADS_VAR);
+        std::env::remove_var(MAX_BANDWIDTH_VAR);
+
+        let config = config.unwrap();
+        assert_eq!(config.threads, Some(6));
+        assert_eq!(config.max_bandwidth, Some(1000));
+        assert_eq!(config.proxy, None);
+        assert!(matches!(
+            wrong,
+            Err(UraniumError::InvalidConfig { key, .. }) if key == THREADS_VAR
+        ));
+    }
+
+    #[test]
+    fn apply_settings() {
+        let only_uranium = UraniumConfig {
+            uranium_dir: Some("/srv/uranium".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            only_uranium.dirs(Dirs::new().with_minecraft_dir("/srv/minecraft")),
+            Some(
+                Dirs::new()
+                    .with_uranium_dir("/srv/uranium")
+                    .with_minecraft_dir("/srv/minecraft")
+            )
+        );
+        assert_eq!(UraniumConfig::default().dirs(Dirs::new()), None);
+        let only_cache = UraniumConfig {
+            cache_dir: Some("/var/cache/uranium".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            only_cache.dirs(Dirs::new()),
+            Some(Dirs::new().with_cache_dir("/var/cache/uranium"))
+        );
+
+        let env_var = || {
+            ENV_VARS
+                .read()
+                .unwrap()
+                .get(MODRINTH_TOKEN)
+                .cloned()
+        };
+        let mut config = UraniumConfig::default();
+        config
+            .api_key_vars
+            .insert(MODRINTH_TOKEN.to_owned(), "URANIUM_TEST_TOKEN".to_owned());
+
+        // Nothing is changed if a setting is wrong.
+        let wrong = UraniumConfig {
+            threads: Some(0),
+            ..config.clone()
+        };
+        assert!(wrong.apply().is_err());
+        assert_eq!(env_var(), None);
+
+        config.apply().unwrap();
+        assert_eq!(env_var().as_deref(), Some("URANIUM_TEST_TOKEN"));
+    }
+}
@@ -0,0 +1,319 @@
+//! Checks to know if the external APIs used by Uranium are reachable.
+//!
+//! Useful to tell if a failing install is a network problem or an API one
+//! before starting it.
+
+use std::{
+    fmt::Write as _,
+    io::{Cursor, Read, Seek, SeekFrom},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use futures::future::join_all;
+use reqwest::Method;
+
+use crate::{
+    downloaders::DownloadConfig,
+    error::{Result, UraniumError},
+    hashes::sha1_file,
+    instance::InstanceMetadata,
+    paths::uranium_dir,
+    variables::constants::{GAME_LOG_FILE, LATEST_LOG_FILE, RINTH_JSON},
+    zipper::zip_files,
+};
+
+/// Time given to each endpoint before considering it unreachable.
+pub const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+/// Only the end of the log files is added to the reports.
+const MAX_LOG_SIZE: u64 = 2 * 1024 * 1024;
+const REDACTED: &str = "<redacted>";
+const SECRET_WORDS: &[&str] = &["token", "password", "secret", "key"];
+
+const ENDPOINTS: &[(&str, &str, Method)] = &[
+    (
+        "Mojang version manifest",
+        "https://launchermeta.mojang.com/mc/game/version_manifest.json",
+        Method::HEAD,
+    ),
+    (
+        "Piston meta",
+        "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json",
+        Method::HEAD,
+    ),
+    (
+        "Minecraft resources",
+        "https://resources.download.minecraft.net/",
+        Method::HEAD,
+    ),
+    ("Modrinth", "https://api.modrinth.com/v2/", Method::GET),
+    ("CurseForge", "https://api.curseforge.com/", Method::GET),
+];
+
+/// Result of checking a single endpoint.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct EndpointStatus {
+    pub name: &'static str,
+    pub url: &'static str,
+    /// HTTP status code, `None` if no response was received.
+    pub status: Option<u16>,
+    /// Time until the response (or the error) arrived.
+    pub latency: Duration,
+    pub error: Option<String>,
+}
+
+impl EndpointStatus {
+    /// An endpoint is reachable if it answered, whatever the status code is.
+    /// E.g. CurseForge answers 403 without an API key but it is up.
+    #[must_use]
+    pub fn is_reachable(&self) -> bool {
+        self.status.is_some()
+    }
+}
+
+/// Pings the Mojang version manifest, piston-meta, the resources CDN,
+/// Modrinth and CurseForge concurrently and reports their reachability and
+/// latency.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn foo() {
+/// use uranium::diagnostics::check_endpoints;
+///
+/// for endpoint in check_endpoints().await {
+///     match endpoint.status {
+///         Some(status) => println!("{}: {status} in {:?}", endpoint.name, endpoint.latency),
+///         None => println!("{}: unreachable ({:?})", endpoint.name, endpoint.error),
+///     }
+/// }
+/// # }
+/// ```
+pub async fn check_endpoints() -> Vec<EndpointStatus> {
+    let client = DownloadConfig::default()
+        .builder()
+        .and_then(|builder| {
+            Ok(builder
+                .timeout(CHECK_TIMEOUT)
+                .build()?)
+        })
+        .unwrap_or_default();
+
+    join_all(
+        ENDPOINTS
+            .iter()
+            .map(|(name, url, method)| check_endpoint(&client, name, url, method.clone())),
+    )
+    .await
+}
+
+async fn check_endpoint(
+    client: &reqwest::Client,
+    name: &'static str,
+    url: &'static str,
+    method: Method,
+) -> EndpointStatus {
+    let start = Instant::now();
+    let response = client
+        .request(method, url)
+        .send()
+        .await;
+    let latency = start.elapsed();
+
+    match response {
+        Ok(response) => EndpointStatus {
+            name,
+            url,
+            status: Some(response.status().as_u16()),
+            latency,
+            error: None,
+        },
+        Err(e) => EndpointStatus {
+            name,
+            url,
+            status: None,
+            latency,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// Builds a zip with everything needed in a bug report about `instance`:
+///
+/// - `report.txt`: uranium version, OS and endpoints health.
+/// - `uranium_instance.json`: the instance metadata with secrets redacted.
+/// - `modrinth.index.json`: the instance manifest, if any.
+/// - `mods.txt`: size and sha1 of every file in `mods/`.
+/// - `logs/`: the end of the game log and the latest uranium log.
+///
+/// The zip is returned so it can be written or uploaded.
+///
+/// # Errors
+/// Returns an error if `instance` doesn't exist or the zip can't be built.
+/// Missing logs or manifests are not an error, they are just not included.
+pub async fn collect_report<I: AsRef<Path>>(instance: I) -> Result<Vec<u8>> {
+    let instance = instance.as_ref();
+    if !instance.is_dir() {
+        return Err(UraniumError::FileNotFound(instance.display().to_string()));
+    }
+
+    let report = summary(&check_endpoints().await);
+    let instance = instance.to_path_buf();
+    // Reading and hashing the files blocks.
+    tokio::task::spawn_blocking(move || report_zip(&instance, report)).await?
+}
+
+/// Zips `report` with the files of `instance`, see [`collect_report`].
+fn report_zip(instance: &Path, report: String) -> Result<Vec<u8>> {
+    let mut files = vec![("report.txt".to_owned(), report.into_bytes())];
+
+    let mut metadata = InstanceMetadata::load(instance).unwrap_or_default();
+    redact(&mut metadata);
+    files.push((
+        "uranium_instance.json".to_owned(),
+        serde_json::to_vec_pretty(&metadata).map_err(|_| UraniumError::WrongFileFormat)?,
+    ));
+
+    if let Ok(manifest) = std::fs::read(instance.join(RINTH_JSON)) {
+        files.push((RINTH_JSON.to_owned(), manifest));
+    }
+
+    files.push((
+        "mods.txt".to_owned(),
+        mods_summary(&instance.join("mods")).into_bytes(),
+    ));
+
+    if let Ok(log) = read_tail(&instance.join(GAME_LOG_FILE)) {
+        files.push(("logs/game.log".to_owned(), log));
+    }
+    if let Some(log) = uranium_dir()
+        .ok()
+        .and_then(|dir| read_tail(&dir.join(LATEST_LOG_FILE)).ok())
+    {
+        files.push(("logs/uranium.log".to_owned(), log));
+    }
+
+    Ok(zip_files(Cursor::new(Vec::new()), &files)?.into_inner())
+}
+
+fn summary(endpoints: &[EndpointStatus]) -> String {
+    let mut report = format!(
+        "uranium {}\nos: {} {}\n\nendpoints:\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+
+    for endpoint in endpoints {
+        let _ = match (endpoint.status, &endpoint.error) {
+            (Some(status), _) => writeln!(
+                report,
+                "  {}: {status} in {:?}",
+                endpoint.name, endpoint.latency
+            ),
+            (None, error) => writeln!(
+                report,
+                "  {}: unreachable ({})",
+                endpoint.name,
+                error
+                    .as_deref()
+                    .unwrap_or_default()
+            ),
+        };
+    }
+
+    report
+}
+
+/// Env values may contain tokens and JVM args things like
+/// `-Dapi.key=...`, neither belongs in a bug report.
+fn redact(metadata: &mut InstanceMetadata) {
+    for value in metadata
+        .launch
+        .env
+        .values_mut()
+    {
+        *value = REDACTED.to_owned();
+    }
+
+    for arg in &mut metadata.launch.jvm_args {
+        let lower = arg.to_lowercase();
+        if SECRET_WORDS
+            .iter()
+            .any(|w| lower.contains(w))
+        {
+            *arg = REDACTED.to_owned();
+        }
+    }
+}
+
+fn mods_summary(mods_dir: &Path) -> String {
+    let Ok(entries) = std::fs::read_dir(mods_dir) else {
+        return "No mods folder\n".to_owned();
+    };
+
+    let mut mods: Vec<_> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file())
+        .collect();
+    mods.sort();
+
+    let mut summary = String::new();
+    for path in mods {
+        let size = path
+            .metadata()
+            .map(|m| m.len())
+            .unwrap_or_default();
+        let hash = sha1_file(&path).unwrap_or_else(|e| e.to_string());
+        let _ = writeln!(
+            summary,
+            "{} {size} {hash}",
+            path.file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+        );
+    }
+    summary
+}
+
+/// Reads the last [`MAX_LOG_SIZE`] bytes of `path`.
+fn read_tail(path: &Path) -> std::io::Result<Vec<u8>> {
+    let mut file = std::fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(MAX_LOG_SIZE)))?;
+
+    let mut content = Vec::new();
+    file.read_to_end(&mut content)?;
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::default_client;
+
+    #[test]
+    fn redacted_metadata() {
+        let mut metadata = InstanceMetadata::default();
+        metadata
+            .launch
+            .env
+            .insert("GITHUB_TOKEN".to_owned(), "ghp_123".to_owned());
+        metadata.launch.jvm_args = vec!["-Xmx4G".to_owned(), "-Dapi.key=abc".to_owned()];
+
+        redact(&mut metadata);
+
+        assert_eq!(metadata.launch.env["GITHUB_TOKEN"], REDACTED);
+        assert_eq!(metadata.launch.jvm_args, ["-Xmx4G", REDACTED]);
+    }
+
+    #[tokio::test]
+    async fn unreachable_endpoint() {
+        let client = default_client().unwrap();
+        let status = check_endpoint(&client, "closed", "http://127.0.0.1:1/", Method::HEAD).await;
+
+        assert!(!status.is_reachable());
+        assert!(status.error.is_some());
+    }
+}
@@ -0,0 +1,96 @@
+use glob::Pattern;
+
+use crate::error::{Result, UraniumError};
+
+/// Which assets objects are installed, for devices with little storage.
+///
+/// Assets are matched by their name in the assets index, like
+/// `minecraft/sounds/ambient/cave/cave1.ogg` or `minecraft/lang/es_es.json`.
+/// Everything is installed by default.
+///
+/// ```
+/// use uranium::downloaders::AssetFilter;
+///
+/// let filter = AssetFilter::default()
+///     .exclude("minecraft/sounds/**")
+///     .unwrap()
+///     .languages(&["es_es"]);
+///
+/// assert!(!filter.includes("minecraft/sounds/ambient/cave/cave1.ogg"));
+/// assert!(!filter.includes("minecraft/lang/fr_fr.json"));
+/// assert!(filter.includes("minecraft/lang/es_es.json"));
+/// assert!(filter.includes("icons/icon_16x16.png"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AssetFilter {
+    exclude: Vec<Pattern>,
+    languages: Option<Vec<String>>,
+}
+
+impl AssetFilter {
+    /// Skips the sounds, music included, and every language but English.
+    #[must_use]
+    pub fn low_storage() -> AssetFilter {
+        AssetFilter {
+            exclude: vec![Pattern::new("minecraft/sounds/**").expect("valid glob")],
+            languages: Some(vec![]),
+        }
+    }
+
+    /// Skips the assets matching `glob`.
+    ///
+    /// # Errors
+    /// Returns `Err(UraniumError::OtherWithReason)` if `glob` is not a valid
+    /// pattern.
+    pub fn exclude(mut self, glob: &str) -> Result<Self> {
+        let pattern = Pattern::new(glob)
+            .map_err(|e| UraniumError::OtherWithReason(format!("Invalid glob {glob}: {e}")))?;
+        self.exclude.push(pattern);
+        Ok(self)
+    }
+
+    /// Only installs the language files of `languages`, like `"es_es"`.
+    ///
+    /// English is always available since it is inside the client jar.
+    #[must_use]
+    pub fn languages(mut self, languages: &[&str]) -> Self {
+        self.languages = Some(
+            languages
+                .iter()
+                .map(|l| l.to_lowercase())
+                .collect(),
+        );
+        self
+    }
+
+    /// Returns `true` if the asset called `name` must be installed.
+    pub fn includes(&self, name: &str) -> bool {
+        if self
+            .exclude
+            .iter()
+            .any(|p| p.matches(name))
+        {
+            return false;
+        }
+
+        match (&self.languages, language_of(name)) {
+            (Some(languages), Some(language)) => languages
+                .iter()
+                .any(|l| l == language),
+            _ => true,
+        }
+    }
+
+    /// Returns `true` if every asset is installed.
+    pub fn is_empty(&self) -> bool {
+        self.exclude.is_empty() && self.languages.is_none()
+    }
+}
+
+/// Returns the language of a `minecraft/lang/` file, `.json` for modern
+/// versions and `.lang` for the old ones.
+fn language_of(name: &str) -> Option<&str> {
+    let file = name.strip_prefix("minecraft/lang/")?;
+    file.strip_suffix(".json")
+        .or_else(|| file.strip_suffix(".lang"))
+}
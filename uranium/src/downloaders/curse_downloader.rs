@@ -1,18 +1,36 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
 
-use futures::future::join_all;
+use futures::{future::join_all, Stream};
+use log::warn;
 use mine_data_structs::{
     curse::{curse_modpacks::*, curse_mods::*},
     url_maker::maker::Curse,
 };
 use reqwest::Response;
+use tempfile::TempDir;
+use tokio::sync::mpsc::unbounded_channel;
 
-use super::{gen_downloader::DownloadState, DownloadableObject};
+use super::functions::overrides;
+use super::journal::{InstallJournal, JournalReporter};
+use super::{
+    gen_downloader::{DownloadReport, DownloadState},
+    DownloadConfig, DownloadableObject, RetryPolicy,
+};
 use crate::{
     code_functions::N_THREADS,
+    collisions::check_case_collisions,
     error::{Result, UraniumError},
-    variables::constants::{CURSE_JSON, TEMP_DIR},
-    zipper::pack_unzipper::unzip_temp_pack,
+    events::{emit, emit_result, EventSender, Phase, UraniumEvent},
+    hooks::{HookPhase, Hooks},
+    http::json_limited,
+    instance::{InstalledFile, InstanceMetadata},
+    modpack_maker::ModLoaders,
+    progress::{completion_stream, CompletedFile, ProgressReporter, Reporter},
+    secrets::ping_curse,
+    variables::constants::CURSE_JSON,
+    zipper::{pack_unzipper::unzip_temp_pack, verify_overrides},
     FileDownloader,
 };
 
@@ -27,25 +45,86 @@ use crate::{
 /// # async fn foo() {
 /// CurseDownloader::<Downloader>::new("modpack_path", "installation_path").await;
 /// # }
+/// ```
+///
+/// Like `RinthDownloader` the installed files are journaled, so an
+/// interrupted download can be resumed.
 pub struct CurseDownloader<T: FileDownloader> {
     gen_downloader: T,
     modpack: CursePack,
+    download_size: u64,
+    destination: PathBuf,
+    /// The extracted modpack, removed once the download ends.
+    temp_pack: Option<TempDir>,
+    /// Source of each file, by its path relative to the destination.
+    sources: Vec<(PathBuf, InstalledFile)>,
+    /// Overrides removed from the extracted modpack, see [`verify_overrides`].
+    corrupted_overrides: Vec<PathBuf>,
+    journal: Arc<InstallJournal>,
+    reporter: Reporter,
+    events: Option<EventSender>,
+    hooks: Hooks,
+    /// The before install hooks already ran.
+    hooks_started: bool,
+    /// Set by `set_pack_source`.
+    pack: Option<InstalledFile>,
 }
 
 impl<T: FileDownloader> CurseDownloader<T> {
+    /// Makes a new `CurseDownloader`, the API key is read from the
+    /// `CURSE_API_KEY` env var or the keyring, see [`secrets`](crate::secrets).
+    /// Use [`CurseDownloader::with_api_key`] to give it directly.
+    ///
+    /// The key is checked with CurseForge before anything is downloaded.
+    ///
+    /// # Errors
+    /// Returns `Err(UraniumError::MissingApiKey)` if there is no key and
+    /// `Err(UraniumError::InvalidApiKey)` if CurseForge rejects it.
     pub async fn new<I: AsRef<Path>, J: AsRef<Path>>(
         modpack_path: I,
         destination: J,
+    ) -> Result<Self> {
+        Self::with_download_config(modpack_path, destination, &DownloadConfig::default()).await
+    }
+
+    /// Same as `CurseDownloader::new` but using the CurseForge API key `key`.
+    ///
+    /// # Errors
+    /// Returns `Err(UraniumError::MissingApiKey)` if `key` is blank and
+    /// `Err(UraniumError::InvalidApiKey)` if it is not valid or CurseForge
+    /// rejects it.
+    pub async fn with_api_key<I: AsRef<Path>, J: AsRef<Path>>(
+        modpack_path: I,
+        destination: J,
+        key: &str,
+    ) -> Result<Self> {
+        let config = DownloadConfig::new().curse_api_key(key);
+        Self::with_download_config(modpack_path, destination, &config).await
+    }
+
+    /// Same as `CurseDownloader::new` but the Curse API and the mods are
+    /// requested with `config`, see [`DownloadConfig`].
+    pub async fn with_download_config<I: AsRef<Path>, J: AsRef<Path>>(
+        modpack_path: I,
+        destination: J,
+        config: &DownloadConfig,
     ) -> Result<Self> {
         let destination = destination.as_ref();
         Self::check_mods_dir(destination)?;
         Self::check_rp_dir(destination)?;
         Self::check_config_dir(destination)?;
+        let api_key = config.resolve_curse_api_key()?;
 
-        unzip_temp_pack(modpack_path)?;
+        let temp_pack = unzip_temp_pack(modpack_path)?;
 
-        let curse_pack = load_curse_pack((TEMP_DIR.to_owned() + CURSE_JSON).as_ref())
-            .expect("Couldnt load the pack");
+        let curse_pack = load_curse_pack(
+            &temp_pack
+                .path()
+                .join(CURSE_JSON)
+                .to_string_lossy(),
+        )
+        .expect("Couldnt load the pack");
+        let corrupted_overrides = verify_overrides(temp_pack.path(), &curse_pack.overrides)?;
 
         let files_ids: Vec<String> = curse_pack
             .get_files()
@@ -58,61 +137,295 @@ impl<T: FileDownloader> CurseDownloader<T> {
             })
             .collect();
 
-        let mut header_map = reqwest::header::HeaderMap::new();
-        let (_, curse_api_key) = std::env::vars()
-            .find(|(v, _)| v == "CURSE_API_KEY")
-            .unwrap_or_default();
-
-        /* TODO!: This should be other Error kind since the problem isn't coming from
-           reqwest but from http InvalidHeaderValue error kind
-        */
-        header_map.insert("x-api-key", curse_api_key.parse()?);
-        header_map.insert("Content-Type", "application/json".parse()?);
-        header_map.insert("Accept", "application/json".parse()?);
-
-        let client = reqwest::ClientBuilder::new()
-            .default_headers(header_map)
-            .build()?;
+        let client = config
+            .clone()
+            .header("x-api-key", &api_key)
+            .header("Content-Type", "application/json")
+            .header("Accept", "application/json")
+            .client()?;
+        // A rejected key would make every file request fail with a 403.
+        ping_curse(client.get(Curse::minecraft())).await?;
 
         let responses: Vec<Response> = Self::get_mod_responses(&client, &files_ids).await;
         let mut files = Vec::with_capacity(responses.len());
+        let mut sources = Vec::with_capacity(responses.len());
         let mods_path = destination.join("mods/");
 
         for response in responses {
-            let cf = response
-                .json::<CurseResponse<CurseFile>>()
-                .await?;
-            files.push(DownloadableObject::new(
-                &cf.data.get_download_url(),
-                cf.data
-                    .get_file_name()
-                    .to_str()
-                    .unwrap_or_default(),
-                &mods_path,
-                None,
+            let cf = json_limited::<CurseResponse<CurseFile>>(response).await?;
+            sources.push((
+                Path::new("mods").join(cf.data.get_file_name()),
+                InstalledFile::curse(cf.data.get_mod_id(), cf.data.get_id()),
             ));
+            files.push(
+                DownloadableObject::new(
+                    cf.data.get_download_url(),
+                    cf.data
+                        .get_file_name()
+                        .to_str()
+                        .unwrap_or_default(),
+                    &mods_path,
+                    None,
+                )
+                .with_size(cf.data.get_file_length() as u64),
+            );
         }
 
-        Ok(CurseDownloader {
-            gen_downloader: T::new(files),
+        check_case_collisions(
+            files
+                .iter()
+                .map(|f| f.path.join(&f.name)),
+        )?;
+
+        let download_size = files
+            .iter()
+            .filter_map(|f| f.size)
+            .sum();
+
+        let journal = Arc::new(InstallJournal::open(destination));
+        let files = journal.pending(files);
+
+        let mut gen_downloader = T::new(files);
+        gen_downloader.set_download_config(config)?;
+
+        let mut curse = CurseDownloader {
+            gen_downloader,
             modpack: curse_pack,
-        })
+            download_size,
+            destination: destination.to_path_buf(),
+            temp_pack: Some(temp_pack),
+            sources,
+            corrupted_overrides,
+            journal,
+            reporter: None,
+            events: None,
+            hooks: Hooks::default(),
+            hooks_started: false,
+            pack: None,
+        };
+        curse.set_journal_reporter();
+        Ok(curse)
+    }
+
+    /// Gives `T` a reporter which journals the installed files and forwards
+    /// the events to the user reporter, `T` sends them to the event sender.
+    fn set_journal_reporter(&mut self) {
+        let mut inner: Vec<Arc<dyn ProgressReporter>> = Vec::new();
+        if let Some(reporter) = &self.reporter {
+            inner.push(reporter.clone());
+        }
+        self.gen_downloader
+            .set_progress_reporter(Arc::new(JournalReporter {
+                journal: self.journal.clone(),
+                inner,
+            }));
+    }
+
+    /// Returns the Minecraft version the modpack is made for.
+    #[must_use]
+    pub fn minecraft_version(&self) -> &str {
+        self.modpack
+            .minecraft_version()
+    }
+
+    /// Returns the primary mod loader of the modpack and its version, which
+    /// can be installed with [`LoaderInstaller`](super::LoaderInstaller).
+    ///
+    /// Returns `None` if the manifest has no loader or Uranium doesn't know
+    /// it.
+    #[must_use]
+    pub fn mod_loader(&self) -> Option<(ModLoaders, &str)> {
+        let (name, version) = self
+            .modpack
+            .primary_loader()?
+            .name_and_version()?;
+        Some((ModLoaders::from_name(name)?, version))
+    }
+
+    /// Returns how many bytes will be downloaded, as reported by the Curse
+    /// API.
+    #[must_use]
+    pub fn estimated_download_size(&self) -> u64 {
+        self.download_size
     }
 
     /// This function will call `FileDownloader::progress()` and returns it's
     /// output.
+    ///
+    /// Once the mods are downloaded the overrides folder of the modpack is
+    /// copied into the destination and the extracted modpack is removed.
     pub async fn progress(&mut self) -> Result<DownloadState> {
-        self.gen_downloader
+        self.run_before_hooks()
+            .await?;
+        self.emit_started();
+        let mut r = self
+            .gen_downloader
             .progress()
-            .await
+            .await;
+        if let Ok(DownloadState::Completed) = r {
+            if let Some(temp_pack) = self.temp_pack.take() {
+                r = overrides(&self.destination, temp_pack.path(), &self.modpack.overrides)
+                    .map(|()| DownloadState::Completed);
+            }
+        }
+        if let Err(e) = self.journal.save() {
+            warn!("Can't write the download journal: {e}");
+        }
+        if let Ok(DownloadState::Completed) = r {
+            self.journal.clear();
+            let result = InstanceMetadata::update(&self.destination, |metadata| {
+                if let Some(pack) = &self.pack {
+                    metadata.pack = Some(pack.clone());
+                }
+                for (path, source) in &self.sources {
+                    metadata.record_file(path, source.clone());
+                }
+            });
+            if let Err(e) = result {
+                warn!("Can't record the installed files: {e}");
+            }
+            r = self
+                .hooks
+                .run_reporting(
+                    HookPhase::AfterInstall,
+                    &self.destination,
+                    self.events.as_ref(),
+                )
+                .await
+                .map(|()| DownloadState::Completed);
+        }
+        emit_result(
+            self.events.as_ref(),
+            r.as_ref()
+                .map(|s| matches!(s, DownloadState::Completed)),
+        );
+        r
     }
 
     /// This function will call `FileDownloader::complete' and returns it's
     /// output.
     pub async fn complete(&mut self) -> Result<()> {
+        loop {
+            match self.progress().await {
+                Ok(DownloadState::Completed) => return Ok(()),
+                Ok(_) => {}
+                Err(e) => {
+                    self.temp_pack = None;
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// Same as [`CurseDownloader::complete`] but returns a summary of the
+    /// download.
+    ///
+    /// # Errors
+    /// Same as [`CurseDownloader::complete`].
+    pub async fn complete_with_report(&mut self) -> Result<DownloadReport> {
+        let start = Instant::now();
+        self.complete().await?;
+        let mut report = self.report();
+        report.duration = start.elapsed();
+        Ok(report)
+    }
+
+    /// Returns what has been downloaded so far.
+    #[must_use]
+    pub fn report(&self) -> DownloadReport {
+        let mut report = self.gen_downloader.report();
+        report
+            .corrupted_overrides
+            .clone_from(&self.corrupted_overrides);
+        report
+    }
+
+    async fn run_before_hooks(&mut self) -> Result<()> {
+        if self.hooks_started {
+            return Ok(());
+        }
+        self.hooks_started = true;
+        let result = self
+            .hooks
+            .run_reporting(
+                HookPhase::BeforeInstall,
+                &self.destination,
+                self.events.as_ref(),
+            )
+            .await;
+        if let Err(e) = &result {
+            emit(self.events.as_ref(), UraniumEvent::Failed(e.to_string()));
+        }
+        result
+    }
+
+    fn emit_started(&self) {
+        if self
+            .gen_downloader
+            .requests_left()
+            == self.gen_downloader.len()
+        {
+            emit(
+                self.events.as_ref(),
+                UraniumEvent::PhaseChanged(Phase::DownloadingMods),
+            );
+        }
+    }
+
+    /// Sets where the [`ProgressEvent`](crate::progress::ProgressEvent)s of
+    /// the mods download are sent.
+    pub fn set_progress_reporter(&mut self, reporter: Arc<dyn ProgressReporter>) {
+        self.reporter = Some(reporter);
+        self.set_journal_reporter();
+    }
+
+    /// Sets where the [`UraniumEvent`]s of the download are sent.
+    pub fn set_event_sender(&mut self, sender: EventSender) {
+        self.gen_downloader
+            .set_event_sender(sender.clone());
+        self.events = Some(sender);
+        self.set_journal_reporter();
+    }
+
+    /// Returns a stream with a [`CompletedFile`] for each file done, see
+    /// [`FileDownloader::completions`].
+    ///
+    /// The stream ends once the downloader is dropped.
+    pub fn completions(&mut self) -> impl Stream<Item = CompletedFile> + Send + Unpin + 'static {
+        let (sender, receiver) = unbounded_channel();
+        self.gen_downloader
+            .set_completion_sender(sender);
+        completion_stream(receiver)
+    }
+
+    /// Sets the modpack project and version being installed, recorded as
+    /// [`InstanceMetadata::pack`] so a [`Watcher`](crate::watcher::Watcher)
+    /// can check it for updates. The modpack file doesn't say it.
+    pub fn set_pack_source(&mut self, source: InstalledFile) {
+        self.pack = Some(source);
+    }
+
+    /// Sets the [`Hooks`] run before the first download and after the
+    /// install.
+    pub fn set_hooks(&mut self, hooks: Hooks) {
+        self.hooks = hooks;
+    }
+
+    /// Sets how failed mod downloads are retried.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
         self.gen_downloader
-            .complete()
-            .await
+            .set_retry_policy(policy);
+    }
+
+    /// Sets the user agent, headers, proxy, timeouts... of the mods download,
+    /// see [`DownloadConfig`]. Use [`CurseDownloader::with_download_config`]
+    /// for the Curse API requests too.
+    ///
+    /// # Errors
+    /// Returns an error if the client can't be built with `config`.
+    pub fn set_download_config(&mut self, config: &DownloadConfig) -> Result<()> {
+        self.gen_downloader
+            .set_download_config(config)
     }
 
     /// Returns the number of mods to download.
@@ -121,6 +434,12 @@ impl<T: FileDownloader> CurseDownloader<T> {
         self.gen_downloader.len()
     }
 
+    /// Returns `true` if the modpack has no mods to download.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.gen_downloader.is_empty()
+    }
+
     /// Returns `true` if there are no mods to download.
     #[must_use]
     pub fn finished(&self) -> bool {
@@ -148,7 +467,7 @@ impl<T: FileDownloader> CurseDownloader<T> {
             .gen_downloader
             .requests_left();
 
-        if left % N_THREADS() == 0 {
+        if left.is_multiple_of(N_THREADS()) {
             left / N_THREADS()
         } else {
             left / N_THREADS() + 1
@@ -181,7 +500,7 @@ impl<T: FileDownloader> CurseDownloader<T> {
         for chunk in files_ids.chunks(threads) {
             let mut requests = Vec::with_capacity(chunk.len());
             for url in chunk {
-                let task = async move { curse_req.get(url).send() }.await;
+                let task = curse_req.get(url).send();
                 requests.push(task);
             }
             let res: Vec<Response> = join_all(requests)
@@ -231,3 +550,25 @@ impl<T: FileDownloader> CurseDownloader<T> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::downloaders::Downloader;
+    use crate::test_utils::{curse_pack, write_curse_zip};
+
+    #[tokio::test]
+    async fn explicit_api_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let pack = dir.join("pack.zip");
+        write_curse_zip(&pack, &curse_pack(&[]), &[]).unwrap();
+
+        // The pack can be read, only the keys are wrong.
+        let empty = CurseDownloader::<Downloader>::with_api_key(&pack, dir, "  ").await;
+        let broken = CurseDownloader::<Downloader>::with_api_key(&pack, dir, "bad\nkey").await;
+
+        assert!(matches!(empty, Err(UraniumError::MissingApiKey("curse"))));
+        assert!(matches!(broken, Err(UraniumError::InvalidApiKey("curse"))));
+    }
+}
@@ -3,14 +3,18 @@ use std::{fs, path::Path};
 use log::error;
 
 use crate::error::{Result, UraniumError};
-use crate::variables::constants::TEMP_DIR;
 
-pub fn overrides(destination_path: &Path, overrides_folder: &str) -> Result<()> {
-    // Copy all the content of overrides into the minecraft root folder
-    let options = fs_extra::dir::CopyOptions::new();
-    // let mut file_options = fs_extra::file::CopyOptions::new();
-    // file_options.overwrite = true;
-    let overrides_folder = TEMP_DIR.to_owned() + overrides_folder;
+/// Copies the content of `overrides_folder`, inside the extracted modpack
+/// `pack_dir`, into `destination_path`. Modpacks without overrides are fine.
+pub fn overrides(destination_path: &Path, pack_dir: &Path, overrides_folder: &str) -> Result<()> {
+    // Copy all the content of overrides into the minecraft root folder,
+    // `config/` and `resourcepacks/` already exist so they are merged.
+    let mut options = fs_extra::dir::CopyOptions::new();
+    options.overwrite = true;
+    let overrides_folder = pack_dir.join(overrides_folder);
+    if !overrides_folder.exists() {
+        return Ok(());
+    }
 
     let entries = match fs::read_dir(&overrides_folder) {
         Ok(e) => e,
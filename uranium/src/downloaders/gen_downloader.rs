@@ -1,18 +1,40 @@
+use std::hash::{BuildHasher, RandomState};
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::{
-    collections::VecDeque,
+    collections::{HashMap, HashSet, VecDeque},
     net::{IpAddr, Ipv4Addr, SocketAddr},
     path::{Path, PathBuf},
 };
 
-use futures::{future::join_all, StreamExt};
+use bytes::Bytes;
+use futures::{future::join_all, Stream, StreamExt};
 use log::{error, info, warn};
+use mine_data_structs::rinth::Hashes;
 use reqwest::Response;
-use sha1::Digest;
-use tokio::sync::{OwnedSemaphorePermit, Semaphore};
-use tokio::{io::AsyncWriteExt, task::JoinHandle};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{self, unbounded_channel, UnboundedSender};
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
 
+use super::{DownloadConfig, RetryPolicy};
+use crate::artifact_cache::ArtifactCache;
 use crate::error::Result;
+use crate::events::{emit, EventSender, UraniumEvent};
+use crate::hashes::{
+    md5_bytes, md5_reader, sha1_bytes, sha1_reader, sha512_bytes, sha512_reader, ChunkHasher,
+    CHUNK_SIZE,
+};
+use crate::http::{check_status, client_builder};
+use crate::progress::{
+    completion_stream, report, CompletedFile, CompletionTracker, ProgressEvent, ProgressReporter,
+    Reporter,
+};
+use crate::rate_limit::throttle_download;
+use crate::storage::{LocalStorage, StorageBackend};
+use crate::variables::constants::ARTIFACT_CACHE;
 use crate::{code_functions::N_THREADS, error::UraniumError};
 
 /// Download files asynchronously.
@@ -77,6 +99,29 @@ pub trait FileDownloader {
         }
     }
 
+    /// Same as [`FileDownloader::complete`] but returns a summary of the
+    /// download.
+    ///
+    /// # Errors
+    /// Same as [`FileDownloader::complete`].
+    async fn complete_with_report(&mut self) -> Result<DownloadReport> {
+        let start = Instant::now();
+        self.complete().await?;
+        let mut report = self.report();
+        if report.duration.is_zero() {
+            report.duration = start.elapsed();
+        }
+        Ok(report)
+    }
+
+    /// Returns what has been downloaded so far.
+    ///
+    /// Downloaders which don't keep track of it can ignore it, that's the
+    /// default and returns an empty report.
+    fn report(&self) -> DownloadReport {
+        DownloadReport::default()
+    }
+
     /// Return how many requests are left.
     ///
     /// This method is important when it comes to know the % of the
@@ -85,21 +130,266 @@ pub trait FileDownloader {
 
     /// Return how many requests the downloader has.
     fn len(&self) -> usize;
+
+    /// Return `true` if the downloader has no requests at all.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Sets where the [`ProgressEvent`]s of the download are sent.
+    ///
+    /// Downloaders which don't report progress can ignore it, that's the
+    /// default.
+    fn set_progress_reporter(&mut self, _reporter: Arc<dyn ProgressReporter>) {}
+
+    /// Sets how failed requests are retried.
+    ///
+    /// Downloaders which don't retry can ignore it, that's the default.
+    fn set_retry_policy(&mut self, _policy: RetryPolicy) {}
+
+    /// Sets the user agent, headers, proxy, timeouts... of the requests.
+    ///
+    /// Downloaders which don't use `reqwest` can ignore it, that's the
+    /// default.
+    ///
+    /// # Errors
+    /// Returns an error if the client can't be built with `config`.
+    fn set_download_config(&mut self, _config: &DownloadConfig) -> Result<()> {
+        Ok(())
+    }
+
+    /// Sets where the [`UraniumEvent`](crate::events::UraniumEvent)s of the
+    /// download are sent.
+    ///
+    /// Completed and failed files should be sent as events too, without
+    /// replacing the reporter given to
+    /// [`FileDownloader::set_progress_reporter`]. Downloaders which don't send
+    /// events can ignore it, that's the default.
+    fn set_event_sender(&mut self, _sender: EventSender) {}
+
+    /// Sets where a [`CompletedFile`] is sent each time a file is done, see
+    /// [`FileDownloader::completions`].
+    ///
+    /// Downloaders which don't report progress can ignore it, that's the
+    /// default.
+    fn set_completion_sender(&mut self, _sender: UnboundedSender<CompletedFile>) {}
+
+    /// Returns a stream with a [`CompletedFile`] for each file done, including
+    /// the skipped ones, so a GUI can update a row per file without polling.
+    ///
+    /// The stream ends once the downloader is dropped.
+    ///
+    /// ```no_run
+    /// # async fn foo() -> uranium::error::Result<()> {
+    /// use futures::StreamExt;
+    /// use uranium::downloaders::{Downloader, FileDownloader};
+    ///
+    /// let mut downloader = Downloader::new(vec![]);
+    /// let mut completions = downloader.completions();
+    /// tokio::spawn(async move {
+    ///     while let Some(file) = completions.next().await {
+    ///         println!("{} done in {:?}", file.path.display(), file.duration);
+    ///     }
+    /// });
+    /// downloader.complete().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn completions(&mut self) -> impl Stream<Item = CompletedFile> + Send + Unpin + use<Self> {
+        let (sender, receiver) = unbounded_channel();
+        self.set_completion_sender(sender);
+        completion_stream(receiver)
+    }
+}
+
+/// Summary of a download, see [`FileDownloader::complete_with_report`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DownloadReport {
+    /// Files downloaded and written.
+    pub downloaded: usize,
+    /// Files already installed and identical, not downloaded.
+    pub skipped: usize,
+    /// Requests sent again after a network error or a retryable status, and
+    /// broken files downloaded again.
+    pub retried: usize,
+    /// Bytes downloaded.
+    pub bytes: u64,
+    pub duration: Duration,
+    /// Files which were broken after a download, they were downloaded again
+    /// up to the [`RetryPolicy`] attempts, and files which couldn't be
+    /// downloaded.
+    pub failures: Vec<PathBuf>,
+    /// Files taken from the [`ArtifactCache`] instead of downloaded.
+    pub from_cache: usize,
+    /// Files copied from the installation given to
+    /// [`MinecraftDownloader::seed_from`](super::MinecraftDownloader::seed_from).
+    pub seeded: usize,
+    /// Overrides of the modpack which didn't match the hashes recorded when
+    /// it was created, relative to the overrides folder. They were not
+    /// installed.
+    pub corrupted_overrides: Vec<PathBuf>,
+}
+
+impl DownloadReport {
+    /// Adds `other` to this report, for downloads made of many steps. The
+    /// steps may run at the same time, so the longest duration is kept.
+    pub fn merge(&mut self, other: DownloadReport) {
+        self.downloaded += other.downloaded;
+        self.skipped += other.skipped;
+        self.retried += other.retried;
+        self.bytes += other.bytes;
+        self.duration = self
+            .duration
+            .max(other.duration);
+        self.failures
+            .extend(other.failures);
+        self.from_cache += other.from_cache;
+        self.seeded += other.seeded;
+        self.corrupted_overrides
+            .extend(other.corrupted_overrides);
+    }
+}
+
+/// Counters updated by the download tasks of a [`Downloader`].
+#[derive(Default)]
+struct DownloadStats {
+    downloaded: AtomicUsize,
+    skipped: AtomicUsize,
+    retried: AtomicUsize,
+    cached: AtomicUsize,
+    bytes: AtomicU64,
+    /// Files which couldn't be downloaded.
+    failed: std::sync::Mutex<Vec<PathBuf>>,
+}
+
+impl DownloadStats {
+    fn fail(&self, path: PathBuf) {
+        if let Ok(mut failed) = self.failed.lock() {
+            failed.push(path);
+        }
+    }
 }
 
 /// Indicates the state of the downloader
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum DownloadState {
     MakingRequests,
     Downloading,
     Completed,
+    /// This many files were already installed and identical, they won't be
+    /// downloaded. Returned once, before the download starts.
+    Skipped(usize),
 }
 
-// TODO! : Add Sha5
 /// Indicates which hash the file uses for verification.
-#[derive(Debug, Clone)]
+///
+/// Serialized as `{"sha1": "..."}`, `{"sha512": "..."}` or `{"md5": "..."}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum HashType {
     Sha1(String),
+    Sha512(String),
+    /// Only for CDNs which don't give anything better.
+    Md5(String),
+}
+
+impl HashType {
+    /// Returns the strongest of the Modrinth `hashes`, sha512 unless it is
+    /// missing.
+    #[must_use]
+    pub fn strongest(hashes: &Hashes) -> HashType {
+        if hashes.sha512.is_empty() {
+            HashType::Sha1(hashes.sha1.clone())
+        } else {
+            HashType::Sha512(hashes.sha512.clone())
+        }
+    }
+
+    /// Returns the expected hash as a hex string.
+    #[must_use]
+    pub fn value(&self) -> &str {
+        match self {
+            HashType::Sha1(h) | HashType::Sha512(h) | HashType::Md5(h) => h,
+        }
+    }
+
+    /// Returns the hash if it is a sha1.
+    #[must_use]
+    pub fn sha1(&self) -> Option<&str> {
+        match self {
+            HashType::Sha1(h) => Some(h),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if `bytes` have this hash, the comparison ignores the
+    /// case of the hex string.
+    #[must_use]
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        let actual = match self {
+            HashType::Sha1(_) => sha1_bytes(bytes),
+            HashType::Sha512(_) => sha512_bytes(bytes),
+            HashType::Md5(_) => md5_bytes(bytes),
+        };
+        actual.eq_ignore_ascii_case(self.value())
+    }
+
+    /// Like [`HashType::matches`] but hashing `reader` in chunks.
+    ///
+    /// # Errors
+    /// Returns the `io::Error` if reading fails.
+    pub fn matches_reader<R: std::io::Read>(&self, reader: R) -> std::io::Result<bool> {
+        let actual = match self {
+            HashType::Sha1(_) => sha1_reader(reader)?,
+            HashType::Sha512(_) => sha512_reader(reader)?,
+            HashType::Md5(_) => md5_reader(reader)?,
+        };
+        Ok(actual.eq_ignore_ascii_case(self.value()))
+    }
+
+    /// Returns a hasher with the algorithm of this hash.
+    fn hasher(&self) -> ChunkHasher {
+        match self {
+            HashType::Sha1(_) => ChunkHasher::Sha1(Default::default()),
+            HashType::Sha512(_) => ChunkHasher::Sha512(Default::default()),
+            HashType::Md5(_) => ChunkHasher::Md5(Default::default()),
+        }
+    }
+}
+
+/// How much work is done to check that an already existing file doesn't need
+/// to be downloaded again.
+///
+/// Hashing every file of a big instance is slow, on constrained devices it
+/// may be worth trading some confidence for speed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VerificationLevel {
+    /// Every existing file is fully hashed.
+    #[default]
+    Full,
+    /// Only the size of the existing file is compared with the size of the
+    /// remote one.
+    SizeOnly,
+    /// Like `SizeOnly` but a random subset of the files (the given percentage,
+    /// from 0 to 100) is also fully hashed.
+    Sampled(u8),
+}
+
+impl VerificationLevel {
+    /// Returns `true` if the file in `path` must be fully hashed.
+    pub(crate) fn must_hash(self, path: &Path) -> bool {
+        match self {
+            VerificationLevel::Full => true,
+            VerificationLevel::SizeOnly => false,
+            VerificationLevel::Sampled(pct) => {
+                // `RandomState` is randomly seeded so each run picks a
+                // different subset.
+                RandomState::new().hash_one(path) % 100 < u64::from(pct.min(100))
+            }
+        }
+    }
 }
 
 /// Simple struct with the necessary data to download a file
@@ -113,12 +403,15 @@ pub enum HashType {
 ///
 /// `name`: MyMinecraftMod.jar <br>
 /// `path`: /home/sergio/.minecraft/Fabric1.18/mods/
-#[derive(Debug, Clone)]
+///
+/// `size` is the expected size in bytes, if known.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DownloadableObject {
     pub url: String,
     pub name: String,
     pub path: PathBuf,
     pub hash: Option<HashType>,
+    pub size: Option<u64>,
 }
 
 impl DownloadableObject {
@@ -128,46 +421,558 @@ impl DownloadableObject {
             name: name.to_owned(),
             path: path.to_owned(),
             hash,
+            size: None,
         }
     }
+
+    /// Sets the expected size of the file in bytes.
+    #[must_use]
+    pub fn with_size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+}
+
+/// Downloads avoided because another object is written to the same path.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DedupSavings {
+    pub files: usize,
+    /// Bytes not downloaded, only counts the objects with a known size.
+    pub bytes: u64,
+}
+
+/// Removes the objects written to the same path as a previous one, keeping
+/// the first of them.
+///
+/// E.g. many asset keys point to the same hash object, downloading it once is
+/// enough.
+pub fn dedup_objects(objects: &mut Vec<DownloadableObject>) -> DedupSavings {
+    let mut seen = HashSet::with_capacity(objects.len());
+    let mut savings = DedupSavings::default();
+
+    objects.retain(|obj| {
+        let unique = seen.insert(obj.path.join(&obj.name));
+        if !unique {
+            savings.files += 1;
+            savings.bytes += obj.size.unwrap_or_default();
+        }
+        unique
+    });
+
+    savings
+}
+
+/// Files at least this big are downloaded with many connections by
+/// [`download_single_file`].
+pub const RANGED_DOWNLOAD_THRESHOLD: u64 = 64 * 1024 * 1024;
+const RANGED_CONNECTIONS: u64 = 4;
+
+/// Downloads a single file and writes it through `storage`.
+///
+/// The file is streamed to `<name>.part`, so if a previous call was
+/// interrupted the download is resumed from where it was left with a `Range`
+/// request. If the server ignores the range the file is downloaded again from
+/// the start. If the range can't be satisfied the part file is kept when it
+/// already has the expected hash, otherwise it is downloaded again. Files of at
+/// least [`RANGED_DOWNLOAD_THRESHOLD`] bytes are split in many ranges which are
+/// downloaded in parallel, unless the server doesn't accept them. In any case
+/// the file is hashed while it is written and only moved to its final path if
+/// it matches `obj.hash`.
+///
+/// The expected size is `obj.size` or, if it is unknown, the `Content-Length`
+/// of the response.
+///
+/// Network errors and retryable statuses are retried following `retry`, an
+/// interrupted download is resumed by the next attempt.
+///
+/// # Errors
+/// Returns `Err(UraniumError::FileNotMatch)` if the size or the hash of the
+/// file are wrong, or the corresponding error if a request or the write fails.
+pub async fn download_single_file(
+    client: &reqwest::Client,
+    obj: &DownloadableObject,
+    storage: Arc<dyn StorageBackend>,
+    retry: &RetryPolicy,
+) -> Result<()> {
+    let mut attempt = 1;
+    loop {
+        match try_download_single_file(client, obj, storage.clone()).await {
+            Err(e) => match retry.retry_delay(&e, attempt) {
+                Some(delay) => {
+                    warn!("{}: {e}, retrying ({attempt})", obj.url);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                None => return Err(e),
+            },
+            ok => return ok,
+        }
+    }
+}
+
+async fn try_download_single_file(
+    client: &reqwest::Client,
+    obj: &DownloadableObject,
+    storage: Arc<dyn StorageBackend>,
+) -> Result<()> {
+    let file_path = obj.path.join(&obj.name);
+    let part_path = file_path.with_added_extension("part");
+    let resume_from = match obj.size {
+        Some(size) => {
+            let part_path = part_path.clone();
+            blocking(&storage, move |s| Ok(resume_offset(s, &part_path, size))).await?
+        }
+        None => None,
+    };
+
+    let (written, size) = match (resume_from, obj.size) {
+        (Some(offset), _) => download_resumed(client, obj, &storage, &part_path, offset).await?,
+        (None, Some(size)) if size >= RANGED_DOWNLOAD_THRESHOLD => (
+            download_ranges(client, obj, &storage, &part_path, size).await?,
+            Some(size),
+        ),
+        (None, size) => {
+            let response = check_status(
+                client
+                    .get(&obj.url)
+                    .send()
+                    .await?,
+            )?;
+            let size = size.or(response.content_length());
+            let part = PartWriter::open(storage.clone(), part_path.clone(), obj.hash.as_ref());
+            (write_part(response, part).await?, size)
+        }
+    };
+
+    if !size.is_none_or(|size| size == written.len) || !written.matches(obj.hash.as_ref()) {
+        error!("{} is broken", obj.name);
+        // Drop the part file so the next try doesn't resume a broken file.
+        blocking(&storage, move |s| Ok(remove_part(s, &part_path)?)).await?;
+        return Err(UraniumError::FileNotMatch(obj.clone()));
+    }
+
+    blocking(&storage, move |s| Ok(s.rename(&part_path, &file_path)?)).await
+}
+
+/// Runs `f` with `storage` in a blocking task, the backends do synchronous IO
+/// which must not stall the async runtime.
+async fn blocking<T, F>(storage: &Arc<dyn StorageBackend>, f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&dyn StorageBackend) -> Result<T> + Send + 'static,
+{
+    let storage = storage.clone();
+    tokio::task::spawn_blocking(move || f(storage.as_ref())).await?
+}
+
+/// Removes a broken `.part` file, it is fine if it was never written.
+fn remove_part(storage: &dyn StorageBackend, part_path: &Path) -> io::Result<()> {
+    match storage.remove(part_path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// How many chunks can be waiting to be written by a [`PartWriter`].
+const PART_WRITER_CHUNKS: usize = 16;
+
+/// Writes a download into its `.part` file from a blocking task.
+///
+/// The chunks are hashed as they are written, so the content doesn't have to
+/// be kept in memory to verify it.
+struct PartWriter {
+    chunks: mpsc::Sender<Bytes>,
+    task: JoinHandle<Result<WrittenPart>>,
+}
+
+/// What a [`PartWriter`] wrote.
+struct WrittenPart {
+    len: u64,
+    /// Hash of the content if the writer was given the expected one.
+    hash: Option<String>,
+}
+
+impl WrittenPart {
+    /// Returns `true` if the content has the `expected` hash, or there is no
+    /// hash to check.
+    fn matches(&self, expected: Option<&HashType>) -> bool {
+        match (expected, &self.hash) {
+            (Some(expected), Some(actual)) => actual.eq_ignore_ascii_case(expected.value()),
+            _ => true,
+        }
+    }
+}
+
+impl PartWriter {
+    /// Truncates `part_path` and starts writing into it, `hash` is the
+    /// expected hash of the content.
+    fn open(storage: Arc<dyn StorageBackend>, part_path: PathBuf, hash: Option<&HashType>) -> Self {
+        Self::spawn(storage, part_path, hash, false)
+    }
+
+    /// Starts writing at the end of `part_path`, the content already there is
+    /// hashed but not written again.
+    fn append(
+        storage: Arc<dyn StorageBackend>,
+        part_path: PathBuf,
+        hash: Option<&HashType>,
+    ) -> Self {
+        Self::spawn(storage, part_path, hash, true)
+    }
+
+    fn spawn(
+        storage: Arc<dyn StorageBackend>,
+        part_path: PathBuf,
+        hash: Option<&HashType>,
+        append: bool,
+    ) -> Self {
+        let (chunks, mut received) = mpsc::channel::<Bytes>(PART_WRITER_CHUNKS);
+        let mut hasher = hash.map(HashType::hasher);
+
+        let task = tokio::task::spawn_blocking(move || {
+            let (mut file, mut len) = if append {
+                let len = match hasher.as_mut() {
+                    Some(hasher) => hasher.update_reader(storage.open_read(&part_path)?)?,
+                    None => storage.len(&part_path)?,
+                };
+                (storage.open_append(&part_path)?, len)
+            } else {
+                (storage.open_write(&part_path)?, 0)
+            };
+            while let Some(chunk) = received.blocking_recv() {
+                file.write_all(&chunk)?;
+                if let Some(hasher) = hasher.as_mut() {
+                    hasher.update(&chunk);
+                }
+                len += chunk.len() as u64;
+            }
+            file.flush()?;
+            Ok(WrittenPart {
+                len,
+                hash: hasher.map(ChunkHasher::finalize),
+            })
+        });
+
+        Self { chunks, task }
+    }
+
+    async fn write(&mut self, chunk: Bytes) -> Result<()> {
+        if self
+            .chunks
+            .send(chunk)
+            .await
+            .is_ok()
+        {
+            return Ok(());
+        }
+        // The task only stops receiving before the end if a write failed.
+        match (&mut self.task).await? {
+            Err(e) => Err(e),
+            Ok(_) => Err(UraniumError::OtherWithReason(
+                "The part file writer stopped early".into(),
+            )),
+        }
+    }
+
+    /// Waits until everything is written.
+    async fn finish(self) -> Result<WrittenPart> {
+        drop(self.chunks);
+        self.task.await?
+    }
+}
+
+/// Streams the body of `response` into `part`.
+async fn write_part(response: Response, mut part: PartWriter) -> Result<WrittenPart> {
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        throttle_download(chunk.len()).await;
+        part.write(chunk).await?;
+    }
+    part.finish().await
+}
+
+/// Returns how many bytes of `part_path` can be kept, `None` if there is
+/// nothing to resume.
+fn resume_offset(storage: &dyn StorageBackend, part_path: &Path, size: u64) -> Option<u64> {
+    if !storage.exists(part_path) {
+        return None;
+    }
+    storage
+        .len(part_path)
+        .ok()
+        .filter(|&len| len > 0 && len < size)
+}
+
+/// Returns what is already in `part_path`, hashed if `hash` is given.
+fn read_part(
+    storage: &dyn StorageBackend,
+    part_path: &Path,
+    hash: Option<&HashType>,
+) -> Result<WrittenPart> {
+    let Some(mut hasher) = hash.map(HashType::hasher) else {
+        return Ok(WrittenPart {
+            len: storage.len(part_path)?,
+            hash: None,
+        });
+    };
+    let len = hasher.update_reader(storage.open_read(part_path)?)?;
+    Ok(WrittenPart {
+        len,
+        hash: Some(hasher.finalize()),
+    })
+}
+
+/// Downloads `obj` into `part_path` keeping its first `offset` bytes.
+///
+/// Returns what was written and the size the file must have, `None` if only
+/// the hash can tell.
+async fn download_resumed(
+    client: &reqwest::Client,
+    obj: &DownloadableObject,
+    storage: &Arc<dyn StorageBackend>,
+    part_path: &Path,
+    offset: u64,
+) -> Result<(WrittenPart, Option<u64>)> {
+    info!("Resuming {} from byte {offset}", part_path.display());
+    let response = client
+        .get(&obj.url)
+        .header(reqwest::header::RANGE, format!("bytes={offset}-"))
+        .send()
+        .await?;
+
+    // The file is smaller than expected, the part file may already be all of
+    // it.
+    if response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+        let existing = {
+            let (part_path, hash) = (part_path.to_path_buf(), obj.hash.clone());
+            blocking(storage, move |s| read_part(s, &part_path, hash.as_ref())).await?
+        };
+        if obj.hash.is_some() && existing.matches(obj.hash.as_ref()) {
+            return Ok((existing, None));
+        }
+
+        info!("Can't resume {}, downloading it again", obj.name);
+        let response = check_status(
+            client
+                .get(&obj.url)
+                .send()
+                .await?,
+        )?;
+        let size = response.content_length();
+        let part = PartWriter::open(storage.clone(), part_path.to_path_buf(), obj.hash.as_ref());
+        return Ok((write_part(response, part).await?, size));
+    }
+
+    let response = check_status(response)?;
+    let part = if response.status() == reqwest::StatusCode::PARTIAL_CONTENT {
+        PartWriter::append(storage.clone(), part_path.to_path_buf(), obj.hash.as_ref())
+    } else {
+        info!(
+            "The server ignored the range, downloading {} again",
+            obj.name
+        );
+        PartWriter::open(storage.clone(), part_path.to_path_buf(), obj.hash.as_ref())
+    };
+    Ok((write_part(response, part).await?, obj.size))
+}
+
+/// Downloads `obj` with [`RANGED_CONNECTIONS`] parallel range requests.
+///
+/// Every range is streamed into its own file, which are joined into
+/// `part_path` at the end. If the server answers the first range with the
+/// whole file it is streamed into `part_path` as a normal download.
+async fn download_ranges(
+    client: &reqwest::Client,
+    obj: &DownloadableObject,
+    storage: &Arc<dyn StorageBackend>,
+    part_path: &Path,
+    size: u64,
+) -> Result<WrittenPart> {
+    let ranges = split_ranges(size, RANGED_CONNECTIONS);
+    let range_paths: Vec<PathBuf> = (0..ranges.len())
+        .map(|i| part_path.with_added_extension(i.to_string()))
+        .collect();
+
+    let first = range_request(client, &obj.url, ranges[0]).await?;
+    if first.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+        info!("{} doesn't accept ranges", obj.url);
+        let part = PartWriter::open(storage.clone(), part_path.to_path_buf(), obj.hash.as_ref());
+        return write_part(first, part).await;
+    }
+
+    info!(
+        "Downloading {} with {RANGED_CONNECTIONS} connections",
+        obj.name
+    );
+    let first = write_part(
+        first,
+        PartWriter::open(storage.clone(), range_paths[0].clone(), None),
+    );
+    let rest = ranges
+        .iter()
+        .zip(&range_paths)
+        .skip(1)
+        .map(|(&range, path)| async move {
+            let response = range_request(client, &obj.url, range).await?;
+            // A 200 means the whole file was sent, which would break the
+            // assembled file.
+            if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+                return Err(UraniumError::OtherWithReason(format!(
+                    "Range request to {} returned {}",
+                    obj.url,
+                    response.status()
+                )));
+            }
+            write_part(
+                response,
+                PartWriter::open(storage.clone(), path.clone(), None),
+            )
+            .await
+        });
+    let downloaded = futures::future::try_join(first, futures::future::try_join_all(rest)).await;
+
+    let part_path = part_path.to_path_buf();
+    let hash = obj.hash.clone();
+    blocking(storage, move |s| {
+        let joined = match downloaded {
+            Ok(_) => join_ranges(s, &range_paths, &part_path, hash.as_ref()),
+            Err(e) => Err(e),
+        };
+        for path in &range_paths {
+            remove_part(s, path)?;
+        }
+        joined
+    })
+    .await
+}
+
+async fn range_request(
+    client: &reqwest::Client,
+    url: &str,
+    (start, end): (u64, u64),
+) -> Result<Response> {
+    let response = client
+        .get(url)
+        .header(reqwest::header::RANGE, format!("bytes={start}-{end}"))
+        .send()
+        .await?;
+    check_status(response)
+}
+
+/// Writes the content of `range_paths` into `part_path` in order, hashing it.
+fn join_ranges(
+    storage: &dyn StorageBackend,
+    range_paths: &[PathBuf],
+    part_path: &Path,
+    hash: Option<&HashType>,
+) -> Result<WrittenPart> {
+    let mut hasher = hash.map(HashType::hasher);
+    let mut file = storage.open_write(part_path)?;
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut len = 0;
+    for path in range_paths {
+        let mut range = storage.open_read(path)?;
+        loop {
+            let n = match range.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            file.write_all(&buffer[..n])?;
+            if let Some(hasher) = hasher.as_mut() {
+                hasher.update(&buffer[..n]);
+            }
+            len += n as u64;
+        }
+    }
+    file.flush()?;
+    Ok(WrittenPart {
+        len,
+        hash: hasher.map(ChunkHasher::finalize),
+    })
+}
+
+/// Splits `size` bytes in `parts` inclusive ranges.
+fn split_ranges(size: u64, parts: u64) -> Vec<(u64, u64)> {
+    let chunk = size.div_ceil(parts.max(1));
+    (0..size)
+        .step_by(chunk.max(1) as usize)
+        .map(|start| (start, (start + chunk).min(size) - 1))
+        .collect()
+}
+
+/// Returns the sum of the sizes of `files` in bytes.
+///
+/// Files without a known size are skipped unless `head_unknown` is `true`, in
+/// that case a HEAD request is made to ask for their `Content-Length`.
+///
+/// # Errors
+/// Returns `Err(UraniumError::RequestError)` if a HEAD request fails.
+pub async fn estimated_size(files: &[DownloadableObject], head_unknown: bool) -> Result<u64> {
+    let known: u64 = files
+        .iter()
+        .filter_map(|f| f.size)
+        .sum();
+
+    if !head_unknown {
+        return Ok(known);
+    }
+
+    let client = client_builder().build()?;
+    let requests = files
+        .iter()
+        .filter(|f| f.size.is_none())
+        .map(|f| client.head(&f.url).send());
+
+    let mut unknown = 0;
+    for response in join_all(requests).await {
+        unknown += response?
+            .content_length()
+            .unwrap_or_default();
+    }
+
+    Ok(known + unknown)
 }
 
 /// Basic downloader
 ///
 /// `Downloader` is a basic implementation of `FileDownloader` trait.
 ///
-/// It uses `reqwest::Client` for the HTTP requests.
+/// It uses `reqwest::Client` for the HTTP requests and writes the files
+/// through a [`StorageBackend`], which is the local filesystem unless
+/// [`Downloader::with_storage`] is used.
 pub struct Downloader {
     files: Vec<DownloadableObject>,
     requester: reqwest::Client,
     start: usize,
     s: Arc<Semaphore>,
     tasks: VecDeque<JoinHandle<Result<()>>>,
+    storage: Arc<dyn StorageBackend>,
+    verification: VerificationLevel,
+    reporter: Reporter,
+    completions: Option<Arc<CompletionTracker>>,
+    retry: RetryPolicy,
+    events: Option<EventSender>,
+    stats: Arc<DownloadStats>,
+    failures: Vec<PathBuf>,
+    /// How many times each file was broken after its download.
+    broken: HashMap<PathBuf, u32>,
+    cache: Option<ArtifactCache>,
+    started: Option<Instant>,
+    duration: Duration,
 }
 
 impl FileDownloader for Downloader {
     fn new(files: Vec<DownloadableObject>) -> Self {
-        let n_files = files.len();
-        info!("{n_files} files to download");
-
-        let client = reqwest::ClientBuilder::new()
-            .resolve(
-                "resources.download.minecraft.net",
-                SocketAddr::new(IpAddr::V4(Ipv4Addr::new(13, 107, 246, 43)), 80),
-            )
-            .build()
-            .expect("Error while creating the Downloader client, please report this error.");
-
-        Downloader {
-            files,
-            requester: client,
-            start: 0,
-            s: Arc::new(Semaphore::new(N_THREADS())),
-            tasks: VecDeque::with_capacity(n_files),
-        }
+        Self::with_storage(files, Arc::new(LocalStorage))
     }
 
     async fn progress(&mut self) -> Result<DownloadState> {
+        let started = *self
+            .started
+            .get_or_insert_with(Instant::now);
         let mut x = N_THREADS();
         while x > 0 && self.start != self.files.len() && self.s.available_permits() > 0 {
             self.make_requests().await?;
@@ -191,9 +996,7 @@ impl FileDownloader for Downloader {
                     let task = self.tasks.remove(i).unwrap();
                     guard = true;
                     match task.await? {
-                        Err(UraniumError::FilesDontMatch(objects)) => {
-                            self.files.extend(objects);
-                        }
+                        Err(UraniumError::FilesDontMatch(objects)) => self.requeue(objects)?,
                         Err(e) => Err(e)?,
                         Ok(_) => {}
                     }
@@ -217,89 +1020,384 @@ impl FileDownloader for Downloader {
                     .unwrap()
                     .await?
                 {
-                    Err(UraniumError::FilesDontMatch(objects)) => self.files.extend(objects),
+                    Err(UraniumError::FilesDontMatch(objects)) => self.requeue(objects)?,
                     Err(e) => Err(e)?,
                     _ => {}
                 };
                 return Ok(DownloadState::Downloading);
             }
         }
+        self.duration = started.elapsed();
         Ok(DownloadState::Completed)
     }
 
     /// Returns how many requests are left.
-    #[must_use]
     fn requests_left(&self) -> usize {
         self.files.len() - self.start + self.tasks.len()
     }
 
-    #[must_use]
     fn len(&self) -> usize {
         self.files.len()
     }
+
+    fn set_progress_reporter(&mut self, reporter: Arc<dyn ProgressReporter>) {
+        self.reporter = Some(reporter);
+    }
+
+    fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry = policy;
+    }
+
+    fn set_event_sender(&mut self, sender: EventSender) {
+        self.events = Some(sender);
+    }
+
+    fn set_download_config(&mut self, config: &DownloadConfig) -> Result<()> {
+        self.requester = Self::client(config)?;
+        Ok(())
+    }
+
+    fn set_completion_sender(&mut self, sender: UnboundedSender<CompletedFile>) {
+        self.completions = Some(Arc::new(CompletionTracker::new(sender)));
+    }
+
+    fn report(&self) -> DownloadReport {
+        DownloadReport {
+            downloaded: self
+                .stats
+                .downloaded
+                .load(Ordering::Relaxed),
+            skipped: self
+                .stats
+                .skipped
+                .load(Ordering::Relaxed),
+            retried: self
+                .stats
+                .retried
+                .load(Ordering::Relaxed),
+            bytes: self
+                .stats
+                .bytes
+                .load(Ordering::Relaxed),
+            duration: self.duration,
+            failures: self
+                .failures
+                .iter()
+                .cloned()
+                .chain(
+                    self.stats
+                        .failed
+                        .lock()
+                        .map(|failed| failed.clone())
+                        .unwrap_or_default(),
+                )
+                .collect(),
+            from_cache: self
+                .stats
+                .cached
+                .load(Ordering::Relaxed),
+            seeded: 0,
+            corrupted_overrides: Vec::new(),
+        }
+    }
 }
 
 impl Downloader {
+    /// Builds a new `Downloader` which writes the files through `storage`
+    /// instead of the local filesystem.
+    pub fn with_storage(files: Vec<DownloadableObject>, storage: Arc<dyn StorageBackend>) -> Self {
+        let n_files = files.len();
+        info!("{n_files} files to download");
+
+        let client = Self::client(&DownloadConfig::default())
+            .expect("Error while creating the Downloader client, please report this error.");
+
+        Downloader {
+            files,
+            requester: client,
+            start: 0,
+            s: Arc::new(Semaphore::new(N_THREADS())),
+            tasks: VecDeque::with_capacity(n_files),
+            storage,
+            verification: VerificationLevel::default(),
+            reporter: None,
+            completions: None,
+            retry: RetryPolicy::default(),
+            events: None,
+            stats: Arc::default(),
+            failures: vec![],
+            broken: HashMap::new(),
+            cache: ARTIFACT_CACHE
+                .read()
+                .ok()
+                .and_then(|dir| {
+                    dir.as_ref()
+                        .map(ArtifactCache::new)
+                }),
+            started: None,
+            duration: Duration::ZERO,
+        }
+    }
+
+    /// Sets how failed requests are retried, see [`RetryPolicy`].
+    #[must_use]
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Sets the user agent, headers, proxy, timeouts... of the requests, see
+    /// [`DownloadConfig`].
+    ///
+    /// # Errors
+    /// Returns an error if the client can't be built with `config`.
+    pub fn with_download_config(mut self, config: &DownloadConfig) -> Result<Self> {
+        self.set_download_config(config)?;
+        Ok(self)
+    }
+
+    fn client(config: &DownloadConfig) -> Result<reqwest::Client> {
+        Ok(config
+            .builder()?
+            .resolve(
+                "resources.download.minecraft.net",
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::new(13, 107, 246, 43)), 80),
+            )
+            .build()?)
+    }
+
+    /// Sets how already existing files are checked before skipping them, by
+    /// default they are fully hashed.
+    #[must_use]
+    pub fn with_verification_level(mut self, verification: VerificationLevel) -> Self {
+        self.verification = verification;
+        self
+    }
+
+    /// Sets the cache where files are looked up before downloading them and
+    /// stored after, by default the one given to
+    /// [`set_artifact_cache`](crate::set_artifact_cache).
+    #[must_use]
+    pub fn with_artifact_cache(mut self, cache: Option<ArtifactCache>) -> Self {
+        self.cache = cache;
+        self
+    }
 
     pub fn mi_static() -> i32 {
-        return -33;
+        -33
+    }
+
+    /// Queues again the files which were broken after their download.
+    ///
+    /// # Errors
+    /// Returns `Err(UraniumError::FileNotMatch)` if a file was already broken
+    /// as many times as the [`RetryPolicy`] attempts.
+    fn requeue(&mut self, objects: Vec<DownloadableObject>) -> Result<()> {
+        for obj in objects {
+            let path = obj.path.join(&obj.name);
+            let attempts = self
+                .broken
+                .entry(path.clone())
+                .or_default();
+            *attempts += 1;
+            if *attempts == 1 {
+                self.failures.push(path);
+            }
+            if *attempts >= self.retry.attempts() {
+                error!("{} is still broken, giving up", obj.name);
+                return Err(UraniumError::FileNotMatch(obj));
+            }
+
+            self.stats
+                .retried
+                .fetch_add(1, Ordering::Relaxed);
+            self.files.push(obj);
+        }
+        Ok(())
     }
 
     async fn make_requests(&mut self) -> Result<DownloadState> {
+        if self.start == 0 {
+            let bytes = self
+                .files
+                .iter()
+                .map(|f| f.size)
+                .sum();
+            emit(
+                self.events.as_ref(),
+                UraniumEvent::DownloadStarted {
+                    files: self.files.len(),
+                    bytes,
+                },
+            );
+        }
+
         let mut chunk_size = 32;
 
         if self.start + chunk_size > self.files.len() {
             chunk_size = self.files.len() - self.start;
         }
 
-        let files = &self.files[self.start..self.start + chunk_size];
+        let files = self.files[self.start..self.start + chunk_size].to_vec();
+        let files = self
+            .install_cached(files)
+            .await?;
+        if files.is_empty() {
+            self.start += chunk_size;
+            return Ok(DownloadState::MakingRequests);
+        }
 
         let mut requests_vec = Vec::new();
-        for file in files {
+        for file in &files {
             let rq = self.requester.clone();
             let file_url = file.url.to_owned();
+            let retry = &self.retry;
+            let events = self.events.as_ref();
+            let retried = &self.stats.retried;
 
-            requests_vec.push(async move { rq.get(&file_url).send().await });
+            requests_vec.push(async move {
+                retry
+                    .send(rq.get(&file_url), events, Some(retried))
+                    .await
+            });
         }
 
-        let responses: Vec<std::result::Result<Response, reqwest::Error>> = join_all(requests_vec)
+        let mut responses = Vec::with_capacity(files.len());
+        let mut failure = None;
+        for (response, file) in join_all(requests_vec)
             .await
             .into_iter()
-            .collect();
-
-        if let Some(i) = responses
-            .iter()
-            .position(|e| e.is_err())
+            .zip(&files)
         {
-            error!("{:?}", responses[i]);
-            return Err(UraniumError::Other);
+            match response
+                .map_err(UraniumError::from)
+                .and_then(check_status)
+            {
+                Ok(response) => responses.push(response),
+                Err(e) => {
+                    error!("Can't download {}: {e}", file.name);
+                    self.stats
+                        .fail(file.path.join(&file.name));
+                    failure.get_or_insert(e);
+                }
+            }
+        }
+        if let Some(e) = failure {
+            return Err(e);
         }
 
-        let responses = responses
-            .into_iter()
-            .flatten()
-            .collect();
-
-        let files = self.files[self.start..self.start + chunk_size].to_vec();
         let sem = self
             .s
             .clone()
             .acquire_owned()
             .await
             .unwrap();
-        let task = tokio::spawn(async move { download_and_write(files, responses, sem).await });
+        let storage = self.storage.clone();
+        let verification = self.verification;
+        let reporter = self.reporter();
+        let stats = self.stats.clone();
+        let cache = self.cache.clone();
+        let task = tokio::spawn(async move {
+            // The permit is released once the chunk is written.
+            let _sem = sem;
+            download_and_write(
+                files,
+                responses,
+                storage,
+                verification,
+                reporter,
+                stats,
+                cache,
+            )
+            .await
+        });
 
         info!("Pushing new task {}", self.start);
         self.tasks.push_back(task);
         self.start += chunk_size;
         Ok(DownloadState::MakingRequests)
     }
+
+    /// Returns the user reporter, the completions tracker and the event
+    /// sender, all of them get every event.
+    fn reporter(&self) -> Reporter {
+        let mut reporters: Vec<Arc<dyn ProgressReporter>> = Vec::with_capacity(3);
+        if let Some(completions) = &self.completions {
+            reporters.push(completions.clone());
+        }
+        reporters.extend(self.reporter.clone());
+        if let Some(events) = &self.events {
+            reporters.push(Arc::new(events.clone()));
+        }
+        match reporters.len() {
+            0 => None,
+            1 => reporters.pop(),
+            _ => Some(Arc::new(reporters)),
+        }
+    }
+
+    /// Installs the cached `files` from the cache and returns the ones left
+    /// to download.
+    ///
+    /// Installed files which are already right are kept as they are. Files
+    /// are hashed and copied in a blocking task.
+    async fn install_cached(
+        &self,
+        files: Vec<DownloadableObject>,
+    ) -> Result<Vec<DownloadableObject>> {
+        let Some(cache) = self.cache.clone() else {
+            return Ok(files);
+        };
+        let storage = self.storage.clone();
+        let verification = self.verification;
+        let reporter = self.reporter();
+        let stats = self.stats.clone();
+
+        let install = move || {
+            let mut left = Vec::with_capacity(files.len());
+            for obj in files {
+                let file_path = obj.path.join(&obj.name);
+                let Some(cached) = obj
+                    .hash
+                    .as_ref()
+                    .and_then(|hash| cache.get(hash))
+                else {
+                    left.push(obj);
+                    continue;
+                };
+
+                let up_to_date = storage.exists(&file_path)
+                    && is_up_to_date(storage.as_ref(), &file_path, &obj, obj.size, verification)
+                        .unwrap_or(false);
+                if up_to_date {
+                    stats
+                        .skipped
+                        .fetch_add(1, Ordering::Relaxed);
+                } else if let Err(e) = storage.copy_from(&cached, &file_path) {
+                    warn!("Can't install {} from the cache: {e}", file_path.display());
+                    left.push(obj);
+                    continue;
+                } else {
+                    stats
+                        .cached
+                        .fetch_add(1, Ordering::Relaxed);
+                }
+                report(&reporter, ProgressEvent::FileVerified { path: file_path });
+            }
+            left
+        };
+        Ok(tokio::task::spawn_blocking(install).await?)
+    }
 }
 
 async fn download_and_write(
     files: Vec<DownloadableObject>,
     responses: Vec<Response>,
-    _sem: OwnedSemaphorePermit,
+    storage: Arc<dyn StorageBackend>,
+    verification: VerificationLevel,
+    reporter: Reporter,
+    stats: Arc<DownloadStats>,
+    cache: Option<ArtifactCache>,
 ) -> Result<()> {
     debug_assert_eq!(responses.len(), files.len());
 
@@ -311,103 +1409,734 @@ async fn download_and_write(
 
     info!("Downloading data");
     let mut bytes_from_res = Vec::with_capacity(responses.len());
+    let mut paths = Vec::with_capacity(responses.len());
 
     for (response, obj) in responses
         .into_iter()
-        .zip(files.into_iter())
+        .zip(files)
     {
         let file_path = obj.path.join(&obj.name);
 
-        // If the file already exits check if its hash match, if so go for
-        // the next file.
-        if file_path.exists() {
-            let content = tokio::fs::read(&file_path).await?;
-            let good_hash = match obj.hash {
-                Some(HashType::Sha1(ref expected)) => {
-                    let mut hasher = sha1::Sha1::new();
-                    hasher.update(&content);
-                    let actual = hex::encode(hasher.finalize());
-                    &actual == expected
-                }
-                None => false,
-            };
-
-            if good_hash {
-                continue;
-            }
+        // If the file already exits check if it is the same file, if so go
+        // for the next file.
+        let up_to_date = {
+            let (file_path, obj) = (file_path.clone(), obj.clone());
+            let remote_size = response.content_length();
+            blocking(&storage, move |s| {
+                Ok(s.exists(&file_path)
+                    && is_up_to_date(s, &file_path, &obj, remote_size, verification)?)
+            })
+            .await?
+        };
+        if up_to_date {
+            stats
+                .skipped
+                .fetch_add(1, Ordering::Relaxed);
+            report(&reporter, ProgressEvent::FileVerified { path: file_path });
+            continue;
         }
 
+        paths.push(file_path.clone());
+        let storage = storage.clone();
+        let reporter = reporter.clone();
+        let stats = stats.clone();
+        let cache = cache.clone();
         bytes_from_res.push(async move {
-            let content_length = response
-                .content_length()
-                .map(|e| e as usize)
-                .unwrap_or_default();
+            let content_length = response.content_length();
 
-            let mut bytes_stream = response.bytes_stream();
+            report(
+                &reporter,
+                ProgressEvent::FileStarted {
+                    path: file_path.clone(),
+                    size: obj
+                        .size
+                        .or(response.content_length()),
+                },
+            );
 
-            let mut file = tokio::fs::OpenOptions::new()
-                .create(true)
-                .write(true)
-                .truncate(true)
-                .open(&file_path)
-                .await?;
+            let mut bytes_stream = response.bytes_stream();
 
-            let mut total = 0;
-            let mut buffer = Vec::with_capacity(content_length);
+            // The content is written into a `.part` file and moved to its final
+            // place once it has been verified, that way a broken download never
+            // looks like a good file.
+            let part_path = file_path.with_added_extension("part");
+            let mut file = PartWriter::open(storage.clone(), part_path.clone(), obj.hash.as_ref());
 
             while let Some(item) = bytes_stream.next().await {
                 let chunk = item?;
-                match file.write(&chunk).await {
-                    Err(e) => {
-                        error!("Can not write in {:?}: {}", file_path, e);
-                        return Err(e.into());
-                    }
-                    Ok(n) => total += n,
-                };
-                buffer.extend(chunk);
+                throttle_download(chunk.len()).await;
+                let bytes = chunk.len() as u64;
+                if let Err(e) = file.write(chunk).await {
+                    error!("Can not write in {:?}: {}", file_path, e);
+                    return Err(e);
+                }
+                report(
+                    &reporter,
+                    ProgressEvent::BytesDownloaded {
+                        path: file_path.clone(),
+                        bytes,
+                    },
+                );
             }
+            let written = file.finish().await?;
 
-            let good_hash = match obj.hash {
-                Some(HashType::Sha1(ref expected)) if total == content_length => {
-                    let mut hasher = sha1::Sha1::new();
-                    hasher.update(&buffer);
-                    let actual = hex::encode(hasher.finalize());
-                    &actual == expected
+            let good_size = content_length.is_none_or(|len| len == written.len);
+            if good_size && written.matches(obj.hash.as_ref()) {
+                {
+                    let (part_path, file_path) = (part_path.clone(), file_path.clone());
+                    blocking(&storage, move |s| Ok(s.rename(&part_path, &file_path)?)).await?;
                 }
-
-                // If a hash is available but the download size doesn't match
-                // the content length then something is wrong.
-                Some(_) => false,
-
-                None => true,
-            };
-
-            if total == content_length && good_hash {
+                if let (Some(cache), Some(hash)) = (cache, obj.hash.clone()) {
+                    store_in_cache(cache, hash, storage.clone(), file_path.clone()).await;
+                }
+                stats
+                    .downloaded
+                    .fetch_add(1, Ordering::Relaxed);
+                stats
+                    .bytes
+                    .fetch_add(written.len, Ordering::Relaxed);
+                report(&reporter, ProgressEvent::FileVerified { path: file_path });
                 Ok(())
             } else {
+                // A broken part file must not be mistaken for a resumable one.
+                if let Err(e) = blocking(&storage, move |s| Ok(remove_part(s, &part_path)?)).await {
+                    warn!("Can't remove the broken {}: {e}", file_path.display());
+                }
+                report(
+                    &reporter,
+                    ProgressEvent::FileFailed {
+                        path: file_path,
+                        reason: "Wrong size or hash".to_owned(),
+                    },
+                );
                 Err(UraniumError::FileNotMatch(obj))
             }
         });
     }
 
-    let errors: Vec<_> = join_all(bytes_from_res)
+    let mut broken = Vec::new();
+    let mut failure = None;
+    for (result, path) in join_all(bytes_from_res)
         .await
         .into_iter()
-        .filter_map(|e| e.err())
-        .collect();
+        .zip(paths)
+    {
+        match result {
+            Ok(()) => {}
+            Err(UraniumError::FileNotMatch(obj)) => broken.push(obj),
+            Err(e) => {
+                error!("Can't download {}: {e}", path.display());
+                stats.fail(path);
+                failure.get_or_insert(e);
+            }
+        }
+    }
 
-    if !errors.is_empty() {
+    if let Some(e) = failure {
+        return Err(e);
+    }
+    if !broken.is_empty() {
         warn!("Some files are broken");
-        let objects: Vec<DownloadableObject> = errors
-            .into_iter()
-            .filter_map(|e| match e {
-                UraniumError::FileNotMatch(obj) => Some(obj),
-                _ => None,
-            })
-            .collect();
-        return Err(UraniumError::FilesDontMatch(objects));
+        return Err(UraniumError::FilesDontMatch(broken));
     }
 
     info!("Chunk wrote successfully!");
     Ok(())
 }
+
+/// Copies the downloaded `file_path` into `cache` in a blocking task.
+async fn store_in_cache(
+    cache: ArtifactCache,
+    hash: HashType,
+    storage: Arc<dyn StorageBackend>,
+    file_path: PathBuf,
+) {
+    let path = file_path.clone();
+    let stored = tokio::task::spawn_blocking(move || -> Result<()> {
+        cache.store(&hash, storage.open_read(&path)?)
+    })
+    .await
+    .map_err(UraniumError::from)
+    .and_then(|r| r);
+    if let Err(e) = stored {
+        warn!("Can't cache {}: {e}", file_path.display());
+    }
+}
+
+/// Checks if the existing file in `file_path` matches `obj` according to
+/// `verification`.
+fn is_up_to_date(
+    storage: &dyn StorageBackend,
+    file_path: &Path,
+    obj: &DownloadableObject,
+    remote_size: Option<u64>,
+    verification: VerificationLevel,
+) -> Result<bool> {
+    let good_size = match remote_size {
+        Some(size) => storage.len(file_path)? == size,
+        None => false,
+    };
+
+    // Without the remote size the only way to know is hashing the file.
+    if !good_size && remote_size.is_some() {
+        return Ok(false);
+    }
+    if good_size && !verification.must_hash(file_path) {
+        return Ok(true);
+    }
+
+    match &obj.hash {
+        Some(hash) => Ok(hash.matches_reader(storage.open_read(file_path)?)?),
+        None => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    fn sodium(hash: &str) -> DownloadableObject {
+        DownloadableObject::new(
+            "https://cdn.modrinth.com/sodium.jar",
+            "sodium.jar",
+            Path::new("mods"),
+            Some(HashType::Sha1(hash.to_owned())),
+        )
+    }
+
+    #[test]
+    fn hash_types() {
+        let content = b"sodium";
+        assert!(HashType::Sha1(sha1_bytes(content)).matches(content));
+        assert!(HashType::Sha512(sha512_bytes(content).to_uppercase()).matches(content));
+        assert!(HashType::Md5(md5_bytes(content)).matches(content));
+        assert!(!HashType::Md5(sha1_bytes(content)).matches(content));
+
+        let hashes = Hashes {
+            sha512: String::new(),
+            sha1: "a".to_owned(),
+        };
+        assert_eq!(HashType::strongest(&hashes), HashType::Sha1("a".to_owned()));
+    }
+
+    #[test]
+    fn resume_only_partial_files() {
+        let storage = MemoryStorage::new();
+        let part = Path::new("mods/sodium.jar.part");
+        assert_eq!(resume_offset(&storage, part, 10), None);
+
+        storage
+            .open_write(part)
+            .unwrap()
+            .write_all(b"sod")
+            .unwrap();
+        assert_eq!(resume_offset(&storage, part, 10), Some(3));
+        assert_eq!(resume_offset(&storage, part, 3), None);
+
+        storage
+            .open_write(part)
+            .unwrap();
+        assert_eq!(resume_offset(&storage, part, 10), None);
+    }
+
+    #[tokio::test]
+    async fn single_file_throttled() {
+        use crate::rate_limit::set_max_bandwidth;
+        use crate::test_utils::FixtureServer;
+
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let url = server.add("/client.jar", vec![7; 30_000]);
+        let jar = DownloadableObject::new(&url, "client.jar", Path::new("versions"), None);
+        let client = reqwest::Client::new();
+        let storage: Arc<dyn StorageBackend> = Arc::new(MemoryStorage::new());
+
+        // The first download can take the whole burst at once, the second one
+        // waits for the 10 000 bytes over it.
+        set_max_bandwidth(Some(20_000)).unwrap();
+        let start = std::time::Instant::now();
+        for _ in 0..2 {
+            download_single_file(&client, &jar, storage.clone(), &RetryPolicy::none())
+                .await
+                .unwrap();
+        }
+        let elapsed = start.elapsed();
+        set_max_bandwidth(None).unwrap();
+
+        assert!(elapsed >= Duration::from_millis(500), "{elapsed:?}");
+    }
+
+    #[test]
+    fn dedup_same_path() {
+        let path = Path::new("assets/objects");
+        let mut objects = vec![
+            DownloadableObject::new("a", "ab/abcd", path, None).with_size(10),
+            DownloadableObject::new("b", "ab/abcd", path, None).with_size(10),
+            DownloadableObject::new("c", "cd/cdef", path, None),
+        ];
+
+        let savings = dedup_objects(&mut objects);
+
+        assert_eq!(
+            savings,
+            DedupSavings {
+                files: 1,
+                bytes: 10
+            }
+        );
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[0].url, "a");
+    }
+
+    #[test]
+    fn verification_levels() {
+        let storage = MemoryStorage::new();
+        let path = Path::new("mods/sodium.jar");
+        storage
+            .open_write(path)
+            .unwrap()
+            .write_all(b"sodium")
+            .unwrap();
+
+        let good = sodium("6f07dc1d0a287f925d7672e6884d85ceb3f339ed");
+        let bad = sodium("0000000000000000000000000000000000000000");
+        let check = |obj: &DownloadableObject, size, level| {
+            is_up_to_date(&storage, path, obj, size, level).unwrap()
+        };
+
+        assert!(check(&good, Some(6), VerificationLevel::Full));
+        assert!(check(&good, None, VerificationLevel::SizeOnly));
+        assert!(!check(&bad, Some(6), VerificationLevel::Full));
+        assert!(check(&bad, Some(6), VerificationLevel::SizeOnly));
+        assert!(!check(&good, Some(7), VerificationLevel::SizeOnly));
+        assert!(check(&bad, Some(6), VerificationLevel::Sampled(0)));
+        assert!(!check(&bad, Some(6), VerificationLevel::Sampled(100)));
+    }
+
+    #[tokio::test]
+    async fn download_reports() {
+        use crate::test_utils::FixtureServer;
+
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let good = server.add("/good.jar", b"good jar".to_vec());
+        let installed = server.add("/installed.jar", b"installed jar".to_vec());
+        let broken = server.add("/broken.jar", b"broken jar".to_vec());
+        std::fs::write(dir.join("installed.jar"), b"installed jar").unwrap();
+        let jar = |url: &str, name: &str, content: &[u8]| {
+            DownloadableObject::new(url, name, dir, Some(HashType::Sha1(sha1_bytes(content))))
+        };
+
+        let mut downloader = Downloader::new(vec![
+            jar(&good, "good.jar", b"good jar"),
+            jar(&installed, "installed.jar", b"installed jar"),
+            jar(&broken, "broken.jar", b"another jar"),
+        ])
+        .with_artifact_cache(None)
+        .with_retry_policy(RetryPolicy::none().max_attempts(2));
+        let result = downloader
+            .complete_with_report()
+            .await;
+        let report = downloader.report();
+
+        assert!(matches!(result, Err(UraniumError::FileNotMatch(_))));
+        assert_eq!((report.downloaded, report.skipped), (1, 1));
+        assert_eq!(report.retried, 1);
+        assert_eq!(report.bytes, 8);
+        assert_eq!(report.failures, [dir.join("broken.jar")]);
+        assert_eq!(server.requests(), 4);
+
+        let mut downloader =
+            Downloader::new(vec![jar(&server.url("/missing.jar"), "missing.jar", b"")])
+                .with_artifact_cache(None)
+                .with_retry_policy(RetryPolicy::none());
+        let result = downloader
+            .complete_with_report()
+            .await;
+        assert!(matches!(result, Err(UraniumError::NotFound(_))));
+        assert_eq!(downloader.report().failures, [dir.join("missing.jar")]);
+
+        let mut total = DownloadReport {
+            downloaded: 2,
+            bytes: 10,
+            duration: Duration::from_secs(3),
+            ..Default::default()
+        };
+        total.merge(DownloadReport {
+            downloaded: 1,
+            bytes: 5,
+            duration: Duration::from_secs(2),
+            failures: vec![PathBuf::from("mods/sodium.jar")],
+            ..Default::default()
+        });
+        assert_eq!(total.downloaded, 3);
+        assert_eq!(total.bytes, 15);
+        assert_eq!(total.duration, Duration::from_secs(3));
+        assert_eq!(total.failures.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn shared_artifact_cache() {
+        use crate::test_utils::FixtureServer;
+
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let cache = ArtifactCache::new(dir.join("cache"));
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let url = server.add("/sodium.jar", b"fake jar".to_vec());
+
+        let hash = HashType::Sha1(sha1_bytes(b"fake jar"));
+        let mut reports = Vec::new();
+        for instance in ["a", "b", "a"] {
+            let mods = dir.join(instance);
+            std::fs::create_dir_all(&mods).unwrap();
+            let jar = DownloadableObject::new(&url, "sodium.jar", &mods, Some(hash.clone()));
+            let report = Downloader::new(vec![jar])
+                .with_artifact_cache(Some(cache.clone()))
+                .complete_with_report()
+                .await
+                .unwrap();
+            reports.push(report);
+        }
+        let installed = std::fs::read(dir.join("b/sodium.jar")).unwrap();
+
+        assert_eq!(server.requests(), 1);
+        assert_eq!((reports[0].downloaded, reports[0].from_cache), (1, 0));
+        assert_eq!((reports[1].downloaded, reports[1].from_cache), (0, 1));
+        // Already installed, it isn't replaced.
+        assert_eq!((reports[2].skipped, reports[2].from_cache), (1, 0));
+        assert_eq!(installed, b"fake jar");
+
+        // Instances don't share the cached file.
+        std::fs::write(dir.join("b/sodium.jar"), b"changed").unwrap();
+        let cached = cache.get(&hash).unwrap();
+        assert_eq!(std::fs::read(cached).unwrap(), b"fake jar");
+        assert_eq!(
+            std::fs::read(dir.join("a/sodium.jar")).unwrap(),
+            b"fake jar"
+        );
+    }
+
+    #[tokio::test]
+    async fn broken_part_files_removed() {
+        use crate::test_utils::FixtureServer;
+
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let good_url = server.add("/good.jar", b"good jar".to_vec());
+        let broken_url = server.add("/broken.jar", b"broken jar".to_vec());
+        let good = DownloadableObject::new(
+            &good_url,
+            "good.jar",
+            Path::new("mods"),
+            Some(HashType::Sha1(sha1_bytes(b"good jar"))),
+        );
+        let broken = DownloadableObject::new(
+            &broken_url,
+            "broken.jar",
+            Path::new("mods"),
+            Some(HashType::Sha1(sha1_bytes(b"another jar"))),
+        );
+        let client = reqwest::Client::new();
+        let responses = vec![
+            client
+                .get(&good_url)
+                .send()
+                .await
+                .unwrap(),
+            client
+                .get(&broken_url)
+                .send()
+                .await
+                .unwrap(),
+        ];
+        let storage = MemoryStorage::new();
+
+        let result = download_and_write(
+            vec![good, broken.clone()],
+            responses,
+            Arc::new(storage.clone()),
+            VerificationLevel::default(),
+            None,
+            Arc::default(),
+            None,
+        )
+        .await;
+
+        assert!(
+            matches!(result, Err(UraniumError::FilesDontMatch(objects)) if objects == [broken])
+        );
+        assert_eq!(storage.paths(), vec![PathBuf::from("mods/good.jar")]);
+        assert_eq!(
+            storage
+                .read(Path::new("mods/good.jar"))
+                .unwrap(),
+            b"good jar"
+        );
+    }
+
+    #[tokio::test]
+    async fn completions_stream() {
+        use crate::test_utils::FixtureServer;
+
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        std::fs::write(dir.join("old.jar"), b"old").unwrap();
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let url = server.add("/new.jar", b"new jar".to_vec());
+        let old = DownloadableObject::new(
+            &server.add("/old.jar", b"old".to_vec()),
+            "old.jar",
+            dir,
+            Some(HashType::Sha1(sha1_bytes(b"old"))),
+        );
+        let new = DownloadableObject::new(&url, "new.jar", dir, None);
+
+        let mut downloader = Downloader::new(vec![old, new]);
+        let completions = downloader.completions();
+        downloader
+            .complete()
+            .await
+            .unwrap();
+        drop(downloader);
+        let mut files: Vec<CompletedFile> = completions.collect().await;
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        assert_eq!(files.len(), 2);
+        assert_eq!((files[0].bytes, files[0].was_skipped), (7, false));
+        assert_eq!((files[1].bytes, files[1].was_skipped), (0, true));
+    }
+
+    #[tokio::test]
+    async fn reporter_and_events() {
+        use std::sync::Mutex;
+
+        use crate::test_utils::FixtureServer;
+
+        let dir = tempfile::tempdir().unwrap();
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let url = server.add("/sodium.jar", b"sodium".to_vec());
+        let jar = DownloadableObject::new(&url, "sodium.jar", dir.path(), None);
+
+        let verified = Arc::new(Mutex::new(Vec::new()));
+        let (events, mut rx) = tokio::sync::mpsc::channel(16);
+        let mut downloader = Downloader::new(vec![jar]);
+        downloader.set_progress_reporter(Arc::new({
+            let verified = verified.clone();
+            move |event| {
+                if let ProgressEvent::FileVerified { path } = event {
+                    verified
+                        .lock()
+                        .unwrap()
+                        .push(path);
+                }
+            }
+        }));
+        downloader.set_event_sender(events);
+        downloader
+            .complete()
+            .await
+            .unwrap();
+        drop(downloader);
+
+        let path = dir.path().join("sodium.jar");
+        assert_eq!(
+            verified
+                .lock()
+                .unwrap()
+                .as_slice(),
+            std::slice::from_ref(&path)
+        );
+        let mut completed = Vec::new();
+        while let Some(event) = rx.recv().await {
+            if let UraniumEvent::FileCompleted { path } = event {
+                completed.push(path);
+            }
+        }
+        assert_eq!(completed, [path]);
+    }
+
+    #[tokio::test]
+    async fn estimated_size_known() {
+        let files = vec![
+            sodium("").with_size(10),
+            sodium(""),
+            sodium("").with_size(5),
+        ];
+        assert_eq!(
+            estimated_size(&files, false)
+                .await
+                .unwrap(),
+            15
+        );
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(split_ranges(10, 4), vec![(0, 2), (3, 5), (6, 8), (9, 9)]);
+        assert_eq!(split_ranges(8, 4), vec![(0, 1), (2, 3), (4, 5), (6, 7)]);
+        assert_eq!(split_ranges(3, 4), vec![(0, 0), (1, 1), (2, 2)]);
+        assert!(split_ranges(0, 4).is_empty());
+    }
+
+    #[tokio::test]
+    async fn single_file_streamed() {
+        use crate::test_utils::FixtureServer;
+
+        let content: Vec<u8> = (0..1000)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let url = server.add("/java.zip", content.clone());
+        let client = reqwest::Client::new();
+        let jar = |hash: &[u8]| {
+            DownloadableObject::new(
+                &url,
+                "java.zip",
+                Path::new("runtime"),
+                Some(HashType::Sha1(sha1_bytes(hash))),
+            )
+        };
+        let path = Path::new("runtime/java.zip");
+        let part = Path::new("runtime/java.zip.part");
+        let storage = MemoryStorage::new();
+        let shared: Arc<dyn StorageBackend> = Arc::new(storage.clone());
+
+        // The size comes from the GET, no HEAD is needed.
+        download_single_file(
+            &client,
+            &jar(&content),
+            shared.clone(),
+            &RetryPolicy::none(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(storage.read(path).unwrap(), content);
+        assert_eq!(server.requests(), 1);
+
+        // Resumed from the part file.
+        storage
+            .open_write(part)
+            .unwrap()
+            .write_all(&content[..300])
+            .unwrap();
+        download_single_file(
+            &client,
+            &jar(&content).with_size(1000),
+            shared.clone(),
+            &RetryPolicy::none(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(storage.read(path).unwrap(), content);
+        assert_eq!(server.requests(), 2);
+
+        let ranged = download_ranges(&client, &jar(&content), &shared, part, 1000)
+            .await
+            .unwrap();
+        assert!(ranged.matches(jar(&content).hash.as_ref()));
+        assert_eq!(ranged.len, 1000);
+        assert_eq!(storage.read(part).unwrap(), content);
+        assert_eq!(server.requests(), 2 + RANGED_CONNECTIONS as usize);
+        storage.remove(part).unwrap();
+
+        let broken =
+            download_single_file(&client, &jar(b"other"), shared, &RetryPolicy::none()).await;
+        assert!(matches!(broken, Err(UraniumError::FileNotMatch(_))));
+        assert_eq!(storage.paths(), vec![path.to_path_buf()]);
+    }
+
+    #[tokio::test]
+    async fn single_file_retried() {
+        use crate::test_utils::FixtureServer;
+
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let url = server.add("/sodium.jar", b"fake jar".to_vec());
+        let jar = DownloadableObject::new(&url, "sodium.jar", Path::new("mods"), None);
+        let client = reqwest::Client::new();
+        let storage: Arc<dyn StorageBackend> = Arc::new(MemoryStorage::new());
+        let retry = RetryPolicy::default()
+            .base_delay(Duration::from_millis(1))
+            .jitter(false);
+
+        server.fail("/sodium.jar", 503, 2);
+        download_single_file(&client, &jar, storage.clone(), &retry)
+            .await
+            .unwrap();
+        assert_eq!(server.requests(), 1);
+
+        server.fail("/sodium.jar", 503, 1);
+        let failed = download_single_file(&client, &jar, storage, &RetryPolicy::none()).await;
+        assert!(matches!(
+            failed,
+            Err(UraniumError::HttpStatus { status, .. }) if status.as_u16() == 503
+        ));
+    }
+
+    #[tokio::test]
+    async fn unsatisfiable_resume() {
+        use crate::test_utils::FixtureServer;
+
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let url = server.add("/sodium.jar", b"fake jar".to_vec());
+        // The expected size is wrong, so resuming asks for bytes after the end.
+        let jar = DownloadableObject::new(
+            &url,
+            "sodium.jar",
+            Path::new("mods"),
+            Some(HashType::Sha1(sha1_bytes(b"fake jar"))),
+        )
+        .with_size(100);
+        let client = reqwest::Client::new();
+        let part = Path::new("mods/sodium.jar.part");
+        let storage = MemoryStorage::new();
+        let shared: Arc<dyn StorageBackend> = Arc::new(storage.clone());
+
+        // The part file is the whole file.
+        storage
+            .open_write(part)
+            .unwrap()
+            .write_all(b"fake jar")
+            .unwrap();
+        download_single_file(&client, &jar, shared.clone(), &RetryPolicy::none())
+            .await
+            .unwrap();
+        assert_eq!(server.requests(), 0);
+
+        // A broken part file is downloaded again.
+        storage
+            .open_write(part)
+            .unwrap()
+            .write_all(b"broken jar")
+            .unwrap();
+        download_single_file(&client, &jar, shared, &RetryPolicy::none())
+            .await
+            .unwrap();
+        assert_eq!(server.requests(), 1);
+        assert_eq!(
+            storage
+                .read(Path::new("mods/sodium.jar"))
+                .unwrap(),
+            b"fake jar"
+        );
+        assert!(!storage.exists(part));
+    }
+}
@@ -0,0 +1,239 @@
+//! Journal of the files already installed by a modpack download.
+//!
+//! While a modpack is downloaded every verified file is written into
+//! `.uranium/progress.json` inside the destination, so if the process dies the
+//! next run only downloads what is left. The journal is removed once the
+//! download is completed.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use super::gen_downloader::DownloadableObject;
+use crate::error::{Result, UraniumError};
+use crate::progress::{ProgressEvent, ProgressReporter};
+use crate::variables::constants::JOURNAL_FILE;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct JournalEntry {
+    hash: Option<String>,
+    size: Option<u64>,
+}
+
+impl From<&DownloadableObject> for JournalEntry {
+    fn from(obj: &DownloadableObject) -> Self {
+        JournalEntry {
+            hash: obj
+                .hash
+                .as_ref()
+                .map(|h| h.value().to_owned()),
+            size: obj.size,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct JournalFile {
+    /// Installed files, by their path relative to the destination.
+    files: HashMap<PathBuf, JournalEntry>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Entries of the files being downloaded, by their full path.
+    expected: HashMap<PathBuf, JournalEntry>,
+    journal: JournalFile,
+}
+
+pub(crate) struct InstallJournal {
+    root: PathBuf,
+    state: Mutex<State>,
+    dirty: AtomicBool,
+}
+
+impl InstallJournal {
+    /// Opens the journal of `destination`, empty if there is none or it
+    /// can't be read.
+    pub(crate) fn open(destination: &Path) -> Self {
+        let journal = std::fs::read_to_string(destination.join(JOURNAL_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+
+        InstallJournal {
+            root: destination.to_path_buf(),
+            state: Mutex::new(State {
+                expected: HashMap::new(),
+                journal,
+            }),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns the `files` which are not installed yet.
+    ///
+    /// A file is skipped if a previous run journaled it with the same hash
+    /// and size and it is still on disk with that size.
+    pub(crate) fn pending(&self, files: Vec<DownloadableObject>) -> Vec<DownloadableObject> {
+        let Ok(mut state) = self.state.lock() else {
+            return files;
+        };
+
+        let total = files.len();
+        let pending: Vec<_> = files
+            .into_iter()
+            .filter(|obj| {
+                let path = obj.path.join(&obj.name);
+                let entry = JournalEntry::from(obj);
+                let installed = state
+                    .journal
+                    .files
+                    .get(self.relative(&path))
+                    .is_some_and(|e| *e == entry)
+                    && std::fs::metadata(&path).is_ok_and(|m| {
+                        entry
+                            .size
+                            .is_none_or(|size| m.len() == size)
+                    });
+                state
+                    .expected
+                    .insert(path, entry);
+                !installed
+            })
+            .collect();
+
+        if pending.len() < total {
+            info!(
+                "Resuming download, {} files already installed",
+                total - pending.len()
+            );
+        }
+        pending
+    }
+
+    fn record(&self, path: &Path) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if let Some(entry) = state
+            .expected
+            .get(path)
+            .cloned()
+        {
+            let relative = self
+                .relative(path)
+                .to_path_buf();
+            state
+                .journal
+                .files
+                .insert(relative, entry);
+            self.dirty
+                .store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.root)
+            .unwrap_or(path)
+    }
+
+    /// Writes the journal if files were installed since the last save.
+    pub(crate) fn save(&self) -> Result<()> {
+        if !self
+            .dirty
+            .swap(false, Ordering::Relaxed)
+        {
+            return Ok(());
+        }
+
+        let content = {
+            let state = self
+                .state
+                .lock()
+                .map_err(|_| UraniumError::Other)?;
+            serde_json::to_string(&state.journal).map_err(|_| UraniumError::WrongFileFormat)?
+        };
+        let path = self.root.join(JOURNAL_FILE);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Removes the journal, called once the download is completed.
+    pub(crate) fn clear(&self) {
+        let path = self.root.join(JOURNAL_FILE);
+        if path.exists() {
+            if let Err(e) = std::fs::remove_file(&path) {
+                warn!("Can't remove {}: {e}", path.display());
+            }
+        }
+    }
+}
+
+/// Records the verified files into the journal and forwards the events to
+/// the user reporters.
+pub(crate) struct JournalReporter {
+    pub(crate) journal: Arc<InstallJournal>,
+    pub(crate) inner: Vec<Arc<dyn ProgressReporter>>,
+}
+
+impl ProgressReporter for JournalReporter {
+    fn report(&self, event: ProgressEvent) {
+        if let ProgressEvent::FileVerified { path } = &event {
+            self.journal.record(path);
+        }
+        for reporter in &self.inner {
+            reporter.report(event.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_journaled_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        std::fs::create_dir_all(dir.join("mods")).unwrap();
+        std::fs::write(dir.join("mods/a.jar"), b"abc").unwrap();
+
+        let objects = || {
+            vec![
+                DownloadableObject::new("https://a.b/a.jar", "a.jar", &dir.join("mods"), None)
+                    .with_size(3),
+                DownloadableObject::new("https://a.b/b.jar", "b.jar", &dir.join("mods"), None)
+                    .with_size(3),
+            ]
+        };
+
+        let journal = InstallJournal::open(dir);
+        assert_eq!(
+            journal
+                .pending(objects())
+                .len(),
+            2
+        );
+        journal.record(&dir.join("mods/a.jar"));
+        journal.save().unwrap();
+
+        let resumed = InstallJournal::open(dir).pending(objects());
+        assert_eq!(resumed.len(), 1);
+        assert_eq!(resumed[0].name, "b.jar");
+
+        journal.clear();
+        assert_eq!(
+            InstallJournal::open(dir)
+                .pending(objects())
+                .len(),
+            2
+        );
+    }
+}
@@ -0,0 +1,469 @@
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use log::info;
+use mine_data_structs::{
+    fabric::{LoaderListing, LoaderProfile},
+    forge::{
+        is_legacy_neoforge, neoforge_version_id, ForgePromotions, NeoForgeVersions,
+        LEGACY_NEOFORGE_MINECRAFT,
+    },
+    minecraft::{Profile, ProfilesJson},
+    url_maker::maker::{Fabric, Forge, NeoForge, Quilt},
+};
+
+use super::gen_downloader::{download_single_file, DownloadableObject, FileDownloader, HashType};
+use super::{Downloader, RetryPolicy};
+use crate::{
+    code_functions::MAX_METADATA_SIZE,
+    error::{Result, UraniumError},
+    http::{client_builder, fetch_json, read_limited},
+    modpack_maker::ModLoaders,
+    storage::LocalStorage,
+    variables::constants::PROFILES_FILE,
+};
+
+/// Installs mod loaders on top of an already installed vanilla version.
+///
+/// Fabric and Quilt are installed from their meta servers: the version json
+/// is written into `versions/` and the loader libraries are downloaded with
+/// `T`. Forge and NeoForge need to run their own installer, so the installer
+/// jar is downloaded and run with `java` in headless mode.
+///
+/// ```no_run
+/// # async fn foo() -> uranium::error::Result<()> {
+/// use uranium::downloaders::LoaderInstaller;
+/// use uranium::modpack_maker::ModLoaders;
+///
+/// let installer: LoaderInstaller = LoaderInstaller::new(".minecraft");
+/// let version_id = installer
+///     .install(&ModLoaders::Fabric, "1.20.1", None)
+///     .await?;
+/// installer.add_profile(&version_id, "Fabric 1.20.1")?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct LoaderInstaller<T: FileDownloader = Downloader> {
+    client: reqwest::Client,
+    dot_minecraft: PathBuf,
+    java: PathBuf,
+    downloader: PhantomData<T>,
+    /// Server every url is requested from instead of its own host, which
+    /// becomes the first segment of the path.
+    mirror: Option<String>,
+}
+
+impl<T: FileDownloader> LoaderInstaller<T> {
+    pub fn new<I: AsRef<Path>>(dot_minecraft: I) -> Self {
+        LoaderInstaller {
+            client: client_builder()
+                .user_agent("uranium-rs/loader-installer contact: sergious234@gmail.com")
+                .build()
+                .unwrap_or_default(),
+            dot_minecraft: dot_minecraft
+                .as_ref()
+                .to_path_buf(),
+            java: PathBuf::from("java"),
+            downloader: PhantomData,
+            mirror: None,
+        }
+    }
+
+    /// Sets the java used to run the Forge and NeoForge installers, `java`
+    /// from the `PATH` by default.
+    #[must_use]
+    pub fn with_java<I: Into<PathBuf>>(mut self, java: I) -> Self {
+        self.java = java.into();
+        self
+    }
+
+    /// Returns the latest stable version of `loader` for `minecraft_version`.
+    ///
+    /// # Errors
+    /// Returns an error if the request fails or if there is no version of the
+    /// loader for `minecraft_version`.
+    pub async fn latest_version(
+        &self,
+        loader: &ModLoaders,
+        minecraft_version: &str,
+    ) -> Result<String> {
+        let not_found = || {
+            UraniumError::OtherWithReason(format!(
+                "There is no {loader} version for Minecraft {minecraft_version}"
+            ))
+        };
+
+        match loader {
+            ModLoaders::Fabric | ModLoaders::Quilt => {
+                let url = match loader {
+                    ModLoaders::Quilt => Quilt::loaders(minecraft_version),
+                    _ => Fabric::loaders(minecraft_version),
+                };
+                let entries: Vec<LoaderListing> =
+                    fetch_json(self.client.get(self.url(url))).await?;
+                entries
+                    .into_iter()
+                    .find(|e| e.loader.stable)
+                    .map(|e| e.loader.version)
+                    .ok_or_else(not_found)
+            }
+            ModLoaders::Forge => {
+                let promotions: ForgePromotions = fetch_json(
+                    self.client
+                        .get(self.url(Forge::promotions())),
+                )
+                .await?;
+                promotions
+                    .best(minecraft_version)
+                    .map(str::to_owned)
+                    .ok_or_else(not_found)
+            }
+            ModLoaders::NeoForge => {
+                let versions: NeoForgeVersions = fetch_json(
+                    self.client
+                        .get(self.url(NeoForge::versions_for(minecraft_version))),
+                )
+                .await?;
+                versions
+                    .recommended(minecraft_version)
+                    .map(str::to_owned)
+                    .ok_or_else(not_found)
+            }
+        }
+    }
+
+    /// Installs `loader` for `minecraft_version`, the latest stable loader
+    /// version is used if `loader_version` is `None`.
+    ///
+    /// Returns the id of the new version, the name of its folder in
+    /// `versions/`.
+    ///
+    /// # Errors
+    /// Returns an error if the loader metadata or any library can't be
+    /// downloaded, or if the Forge/NeoForge installer fails.
+    pub async fn install(
+        &self,
+        loader: &ModLoaders,
+        minecraft_version: &str,
+        loader_version: Option<&str>,
+    ) -> Result<String> {
+        let loader_version = match loader_version {
+            Some(v) => v.to_owned(),
+            None => {
+                self.latest_version(loader, minecraft_version)
+                    .await?
+            }
+        };
+        info!("Installing {loader} {loader_version} for {minecraft_version}");
+
+        match loader {
+            ModLoaders::Fabric | ModLoaders::Quilt => {
+                self.install_from_meta(loader, minecraft_version, &loader_version)
+                    .await
+            }
+            ModLoaders::Forge => {
+                let url = Forge::installer(minecraft_version, &loader_version);
+                self.run_installer(&url, "--installClient")
+                    .await?;
+                Ok(format!("{minecraft_version}-forge-{loader_version}"))
+            }
+            ModLoaders::NeoForge => {
+                // The 1.20.1 versions are also known without the Minecraft
+                // version, like `47.1.106`.
+                let loader_version = if minecraft_version == LEGACY_NEOFORGE_MINECRAFT
+                    && !is_legacy_neoforge(&loader_version)
+                {
+                    format!("{minecraft_version}-{loader_version}")
+                } else {
+                    loader_version
+                };
+                // The 1.20.1 installer is still the Forge one.
+                let install_flag = if is_legacy_neoforge(&loader_version) {
+                    "--installClient"
+                } else {
+                    "--install-client"
+                };
+                let url = NeoForge::installer(&loader_version);
+                self.run_installer(&url, install_flag)
+                    .await?;
+                Ok(neoforge_version_id(&loader_version))
+            }
+        }
+    }
+
+    /// Adds a launcher profile called `name` which plays `version_id`.
+    ///
+    /// # Errors
+    /// Returns an error if `launcher_profiles.json` can't be read or written.
+    pub fn add_profile(&self, version_id: &str, name: &str) -> Result<()> {
+        self.ensure_profiles_file()?;
+        let profiles_path = self
+            .dot_minecraft
+            .join(PROFILES_FILE);
+        let mut profiles = ProfilesJson::read_json_from(&profiles_path)?;
+
+        profiles.insert(
+            name,
+            Profile::new(
+                "Furnace",
+                version_id,
+                name,
+                "custom",
+                Some(&self.dot_minecraft),
+            ),
+        );
+
+        let content =
+            serde_json::to_string_pretty(&profiles).map_err(|_| UraniumError::WrongFileFormat)?;
+        std::fs::write(profiles_path, content)?;
+        Ok(())
+    }
+
+    async fn install_from_meta(
+        &self,
+        loader: &ModLoaders,
+        minecraft_version: &str,
+        loader_version: &str,
+    ) -> Result<String> {
+        let url = match loader {
+            ModLoaders::Quilt => Quilt::profile(minecraft_version, loader_version),
+            _ => Fabric::profile(minecraft_version, loader_version),
+        };
+        let response = self
+            .client
+            .get(self.url(url))
+            .send()
+            .await?;
+        // Written as received, the launcher reads fields uranium doesn't model.
+        let raw = read_limited(response, MAX_METADATA_SIZE()).await?;
+        let profile: LoaderProfile =
+            serde_json::from_slice(&raw).map_err(|_| UraniumError::WrongFileFormat)?;
+
+        let version_dir = self
+            .dot_minecraft
+            .join("versions")
+            .join(&profile.id);
+        std::fs::create_dir_all(&version_dir)?;
+        std::fs::write(version_dir.join(format!("{}.json", profile.id)), &raw)?;
+
+        let libraries_path = self
+            .dot_minecraft
+            .join("libraries");
+        let mut files = Vec::with_capacity(profile.libraries.len());
+        for lib in &profile.libraries {
+            let (Some(path), Some(url)) = (lib.get_path(), lib.get_url()) else {
+                return Err(UraniumError::OtherWithReason(format!(
+                    "Wrong library name {}",
+                    lib.name
+                )));
+            };
+            let path = libraries_path.join(path);
+            let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+                continue;
+            };
+            std::fs::create_dir_all(dir)?;
+
+            let mut obj = DownloadableObject::new(
+                &url,
+                &name.to_string_lossy(),
+                dir,
+                lib.sha1
+                    .clone()
+                    .map(HashType::Sha1),
+            );
+            if let Some(size) = lib.size {
+                obj = obj.with_size(size);
+            }
+            files.push(obj);
+        }
+
+        T::new(files)
+            .complete()
+            .await?;
+
+        Ok(profile.id)
+    }
+
+    async fn run_installer(&self, url: &str, install_flag: &str) -> Result<()> {
+        // The installers refuse to run without a launcher profiles file.
+        self.ensure_profiles_file()?;
+
+        let temp = tempfile::tempdir()?;
+        let temp = temp.path();
+        let installer =
+            DownloadableObject::new(&self.url(url.to_owned()), "installer.jar", temp, None);
+        download_single_file(
+            &self.client,
+            &installer,
+            Arc::new(LocalStorage),
+            &RetryPolicy::default(),
+        )
+        .await?;
+
+        let status = tokio::process::Command::new(&self.java)
+            .arg("-jar")
+            .arg(temp.join("installer.jar"))
+            .arg(install_flag)
+            .arg(&self.dot_minecraft)
+            .current_dir(temp)
+            .status()
+            .await?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(UraniumError::OtherWithReason(format!(
+                "The loader installer exited with {status}"
+            )))
+        }
+    }
+
+    fn url(&self, url: String) -> String {
+        match &self.mirror {
+            Some(mirror) => format!(
+                "{}/{}",
+                mirror.trim_end_matches('/'),
+                url.split_once("://")
+                    .map_or(url.as_str(), |(_, rest)| rest)
+            ),
+            None => url,
+        }
+    }
+
+    fn ensure_profiles_file(&self) -> Result<()> {
+        let profiles_path = self
+            .dot_minecraft
+            .join(PROFILES_FILE);
+        if !profiles_path.exists() {
+            std::fs::create_dir_all(&self.dot_minecraft)?;
+            let content = serde_json::to_string_pretty(&ProfilesJson::default())
+                .map_err(|_| UraniumError::WrongFileFormat)?;
+            std::fs::write(profiles_path, content)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hashes::sha1_bytes, test_utils::FixtureServer};
+
+    #[tokio::test]
+    async fn fabric_install() {
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let loader = b"fake loader".to_vec();
+        server.add(
+            "/meta.fabricmc.net/v2/versions/loader/1.20.1",
+            serde_json::to_vec(&serde_json::json!([
+                {
+                    "loader": {"separator": ".", "build": 2, "maven": "net.fabricmc:fabric-loader:0.16.0-beta.1",
+                               "version": "0.16.0-beta.1", "stable": false},
+                    "intermediary": {"maven": "net.fabricmc:intermediary:1.20.1", "version": "1.20.1"}
+                },
+                {
+                    "loader": {"separator": ".", "build": 1, "maven": "net.fabricmc:fabric-loader:0.15.11",
+                               "version": "0.15.11", "stable": true},
+                    "intermediary": {"maven": "net.fabricmc:intermediary:1.20.1", "version": "1.20.1"}
+                }
+            ]))
+            .unwrap(),
+        );
+        server.add(
+            "/meta.fabricmc.net/v2/versions/loader/1.20.1/0.15.11/profile/json",
+            serde_json::to_vec(&serde_json::json!({
+                "id": "fabric-loader-0.15.11-1.20.1",
+                "inheritsFrom": "1.20.1",
+                "releaseTime": "2024-05-01T00:00:00+0000",
+                "time": "2024-05-01T00:00:00+0000",
+                "type": "release",
+                "mainClass": "net.fabricmc.loader.impl.launch.knot.KnotClient",
+                "libraries": [{
+                    "name": "net.fabricmc:fabric-loader:0.15.11",
+                    "url": server.url("/maven/"),
+                    "sha1": sha1_bytes(&loader),
+                    "size": loader.len()
+                }]
+            }))
+            .unwrap(),
+        );
+        server.add(
+            "/maven/net/fabricmc/fabric-loader/0.15.11/fabric-loader-0.15.11.jar",
+            loader.clone(),
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut installer: LoaderInstaller = LoaderInstaller::new(dir.path());
+        installer.mirror = Some(server.url("/"));
+        let id = installer
+            .install(&ModLoaders::Fabric, "1.20.1", None)
+            .await
+            .unwrap();
+        installer
+            .add_profile(&id, "Fabric 1.20.1")
+            .unwrap();
+
+        assert_eq!(id, "fabric-loader-0.15.11-1.20.1");
+        assert!(dir
+            .path()
+            .join("versions/fabric-loader-0.15.11-1.20.1/fabric-loader-0.15.11-1.20.1.json")
+            .exists());
+        assert_eq!(
+            std::fs::read(
+                dir.path()
+                    .join("libraries/net/fabricmc/fabric-loader/0.15.11/fabric-loader-0.15.11.jar")
+            )
+            .unwrap(),
+            loader
+        );
+        let profiles = ProfilesJson::read_json_from(dir.path().join(PROFILES_FILE)).unwrap();
+        assert!(serde_json::to_string(&profiles)
+            .unwrap()
+            .contains("Fabric 1.20.1"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn legacy_neoforge_install() {
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        server.add(
+            "/maven.neoforged.net/api/maven/versions/releases/net/neoforged/forge",
+            serde_json::to_vec(&serde_json::json!({
+                "isSnapshot": false,
+                "versions": ["1.20.1-47.1.3", "1.20.1-47.1.106"]
+            }))
+            .unwrap(),
+        );
+        server.add(
+            "/maven.neoforged.net/releases/net/neoforged/forge/1.20.1-47.1.106/forge-1.20.1-47.1.106-installer.jar",
+            b"fake installer".to_vec(),
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        // `true` stands for java, the installer itself can't run here.
+        let mut installer: LoaderInstaller = LoaderInstaller::new(dir.path()).with_java("true");
+        installer.mirror = Some(server.url("/"));
+
+        let id = installer
+            .install(&ModLoaders::NeoForge, "1.20.1", None)
+            .await
+            .unwrap();
+        assert_eq!(id, "1.20.1-forge-47.1.106");
+
+        let id = installer
+            .install(&ModLoaders::NeoForge, "1.20.1", Some("47.1.106"))
+            .await
+            .unwrap();
+        assert_eq!(id, "1.20.1-forge-47.1.106");
+        assert_eq!(server.requests(), 3);
+        assert!(dir
+            .path()
+            .join(PROFILES_FILE)
+            .exists());
+    }
+}
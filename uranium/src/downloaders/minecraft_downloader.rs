@@ -2,9 +2,11 @@ use std::io::Write;
 use std::{
     fs::File,
     path::{Path, PathBuf},
-    sync::RwLock,
+    sync::{Arc, RwLock},
+    time::Instant,
 };
 
+use futures::{Stream, StreamExt};
 use log::{error, info, warn};
 use mine_data_structs::minecraft::{
     Lib, Libraries, MinecraftVersions, ObjectData, Profile, ProfilesJson, Resources, Root,
@@ -12,12 +14,25 @@ use mine_data_structs::minecraft::{
 use reqwest;
 use serde::{Deserialize, Serialize};
 use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
 
-use super::gen_downloader::{DownloadState, DownloadableObject, FileDownloader, HashType};
+use super::gen_downloader::{
+    dedup_objects, download_single_file, DedupSavings, DownloadReport, DownloadState,
+    DownloadableObject, FileDownloader, HashType,
+};
+use super::{AssetFilter, DownloadConfig, RetryPolicy, RuntimeDownloader};
 use crate::{
-    code_functions::N_THREADS,
+    code_functions::{MAX_METADATA_SIZE, N_THREADS},
+    collisions::check_case_collisions,
     error::{Result, UraniumError},
-    variables::constants::PROFILES_FILE,
+    events::{emit, EventSender, Phase, UraniumEvent},
+    hashes::sha1_bytes,
+    http::{client_builder, default_client, fetch_json, read_limited},
+    progress::{
+        completion_stream, report, CompletedFile, ProgressEvent, ProgressReporter, Reporter,
+    },
+    storage::{LocalStorage, StorageBackend},
+    variables::constants::{PROFILES_FILE, RUNTIMES_DIR},
 };
 
 const ASSETS_PATH: &str = "assets/";
@@ -40,14 +55,9 @@ const INSTANCES_LIST: &str = "https://launchermeta.mojang.com/mc/game/version_ma
 /// page. In that case this function will return an
 /// `Err(UraniumError::RequestError)`
 pub async fn list_instances() -> Result<MinecraftVersions> {
-    let requester = reqwest::Client::new();
+    let requester = default_client()?;
 
-    let instances = requester
-        .get(INSTANCES_LIST)
-        .send()
-        .await?
-        .json::<MinecraftVersions>()
-        .await?;
+    let instances = fetch_json::<MinecraftVersions>(requester.get(INSTANCES_LIST)).await?;
 
     Ok(instances)
 }
@@ -64,15 +74,13 @@ pub async fn list_instances() -> Result<MinecraftVersions> {
 /// Microsoft page. In such a case, this function will return an
 /// `Err(UraniumError::RequestError)`.
 pub async fn get_last_snapshot() -> Result<String> {
-    let requester = reqwest::Client::new();
-    Ok(requester
-        .get(INSTANCES_LIST)
-        .send()
-        .await?
-        .json::<MinecraftVersions>()
-        .await?
-        .latest
-        .snapshot)
+    let requester = default_client()?;
+    Ok(
+        fetch_json::<MinecraftVersions>(requester.get(INSTANCES_LIST))
+            .await?
+            .latest
+            .snapshot,
+    )
 }
 
 /// Function that returns the latest Minecraft release version as a
@@ -87,15 +95,13 @@ pub async fn get_last_snapshot() -> Result<String> {
 /// Microsoft page. In such a case, this function will return an
 /// `Err(UraniumError::RequestError)`.
 pub async fn get_last_release() -> Result<String> {
-    let requester = reqwest::Client::new();
-    Ok(requester
-        .get(INSTANCES_LIST)
-        .send()
-        .await?
-        .json::<MinecraftVersions>()
-        .await?
-        .latest
-        .release)
+    let requester = default_client()?;
+    Ok(
+        fetch_json::<MinecraftVersions>(requester.get(INSTANCES_LIST))
+            .await?
+            .latest
+            .release,
+    )
 }
 
 /*
@@ -105,7 +111,8 @@ pub async fn get_last_release() -> Result<String> {
 */
 
 /// Indicates the download state of a Minecraft instance.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[non_exhaustive]
 pub enum MinecraftDownloadState {
     GettingSources,
     DownloadingIndexes,
@@ -159,6 +166,17 @@ pub struct MinecraftDownloader<T: FileDownloader + Send> {
     minecraft_instance: Root,
     download_state: MinecraftDownloadState,
     downloader: Option<T>,
+    dedup_savings: DedupSavings,
+    /// Report of the steps already downloaded.
+    finished: DownloadReport,
+    reporter: Reporter,
+    retry: Option<RetryPolicy>,
+    config: Option<DownloadConfig>,
+    events: Option<EventSender>,
+    completions: Option<UnboundedSender<CompletedFile>>,
+    asset_filter: AssetFilter,
+    /// Installation whose files are copied instead of downloaded.
+    seed: Option<PathBuf>,
 
     #[allow(unused)]
     bad_files: RwLock<Vec<ObjectData>>,
@@ -191,7 +209,7 @@ impl<T: FileDownloader + Send + Sync> MinecraftDownloader<T> {
         destination_path: I,
         minecraft_version: &str,
     ) -> Result<Self> {
-        let requester = reqwest::Client::new();
+        let requester = client_builder().build()?;
         let instances = list_instances().await?;
 
         let instance_url = instances
@@ -200,32 +218,53 @@ impl<T: FileDownloader + Send + Sync> MinecraftDownloader<T> {
                 "Version {minecraft_version} doesn't exist"
             )))?;
 
-        let minecraft_instance: Root = requester
-            .get(instance_url)
-            .send()
-            .await?
-            .json()
-            .await?;
-
-        let destination_path = destination_path
-            .as_ref()
-            .to_path_buf();
+        let minecraft_instance: Root = fetch_json(requester.get(instance_url)).await?;
 
-        Ok(MinecraftDownloader::new(
-            destination_path,
+        Ok(MinecraftDownloader::from_root(
             minecraft_instance,
+            destination_path,
         ))
     }
 
-    /// WIP
-    fn new(destination_path: PathBuf, minecraft_instance: Root) -> Self {
+    /// Makes a new `MinecraftDownloader` from an already fetched version
+    /// `Root`, so the version manifest and the version JSON are not requested
+    /// again.
+    ///
+    /// Useful for launchers which already cache the version JSON.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use mine_data_structs::minecraft::Root;
+    /// use uranium::downloaders::{Downloader, MinecraftDownloader};
+    ///
+    /// # async fn foo(root: Root) -> uranium::error::Result<()> {
+    /// let mut minecraft_down = MinecraftDownloader::<Downloader>::from_root(root, "my/path");
+    /// minecraft_down.start().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_root<I: AsRef<Path>>(minecraft_instance: Root, destination_path: I) -> Self {
         MinecraftDownloader {
-            requester: reqwest::Client::new(),
-            dot_minecraft_path: destination_path,
+            requester: client_builder()
+                .build()
+                .unwrap_or_default(),
+            dot_minecraft_path: destination_path
+                .as_ref()
+                .to_path_buf(),
             resources: vec![],
             minecraft_instance,
             download_state: MinecraftDownloadState::GettingSources,
             downloader: None,
+            dedup_savings: DedupSavings::default(),
+            finished: DownloadReport::default(),
+            reporter: None,
+            retry: None,
+            config: None,
+            events: None,
+            completions: None,
+            asset_filter: AssetFilter::default(),
+            seed: None,
             bad_files: RwLock::new(vec![]),
         }
     }
@@ -250,6 +289,28 @@ impl<T: FileDownloader + Send + Sync> MinecraftDownloader<T> {
         Ok(MinecraftDownloadState::Completed)
     }
 
+    /// Same as [`MinecraftDownloader::start`] but returns a summary of the
+    /// assets, libraries and client downloads.
+    ///
+    /// # Errors
+    /// Same as [`MinecraftDownloader::start`].
+    pub async fn start_with_report(&mut self) -> Result<DownloadReport> {
+        let start = Instant::now();
+        self.start().await?;
+        let mut report = self.report();
+        report.duration = start.elapsed();
+        Ok(report)
+    }
+
+    /// Returns what has been downloaded so far by every step.
+    pub fn report(&self) -> DownloadReport {
+        let mut report = self.finished.clone();
+        if let Some(downloader) = &self.downloader {
+            report.merge(downloader.report());
+        }
+        report
+    }
+
     /// This function will make progress in the installation. It will go through
     /// all the installations steps (`MinecraftDownloadState`) so the user
     /// can know what is the downloader doing and can show a progress bar,
@@ -269,6 +330,39 @@ impl<T: FileDownloader + Send + Sync> MinecraftDownloader<T> {
     ///
     /// This function should not panic
     pub async fn progress(&mut self) -> Result<MinecraftDownloadState> {
+        let previous = self.download_state.clone();
+        let result = self.step().await;
+
+        match &result {
+            Ok(state) if *state != previous => {
+                let event = match state {
+                    MinecraftDownloadState::GettingSources => {
+                        UraniumEvent::PhaseChanged(Phase::GettingSources)
+                    }
+                    MinecraftDownloadState::DownloadingIndexes => {
+                        UraniumEvent::PhaseChanged(Phase::DownloadingIndexes)
+                    }
+                    MinecraftDownloadState::DownloadingAssests => {
+                        UraniumEvent::PhaseChanged(Phase::DownloadingAssets)
+                    }
+                    MinecraftDownloadState::DownloadingLibraries => {
+                        UraniumEvent::PhaseChanged(Phase::DownloadingLibraries)
+                    }
+                    MinecraftDownloadState::CheckingFiles => {
+                        UraniumEvent::PhaseChanged(Phase::CheckingFiles)
+                    }
+                    MinecraftDownloadState::Completed => UraniumEvent::Completed,
+                };
+                emit(self.events.as_ref(), event);
+            }
+            Ok(_) => {}
+            Err(e) => emit(self.events.as_ref(), UraniumEvent::Failed(e.to_string())),
+        }
+
+        result
+    }
+
+    async fn step(&mut self) -> Result<MinecraftDownloadState> {
         match self.download_state {
             MinecraftDownloadState::GettingSources => {
                 self.get_sources().await?;
@@ -286,7 +380,8 @@ impl<T: FileDownloader + Send + Sync> MinecraftDownloader<T> {
 
                 let mut files = vec![];
                 std::mem::swap(&mut files, self.resources.as_mut());
-                self.downloader = Some(T::new(files));
+                self.set_downloader(files)
+                    .await?;
 
                 self.download_state = MinecraftDownloadState::DownloadingAssests;
             }
@@ -316,23 +411,15 @@ impl<T: FileDownloader + Send + Sync> MinecraftDownloader<T> {
                                         | X.XX.X.json   < And despite what everyone might think, this too
 
                         */
-                        let url = self
+                        let client = self
                             .minecraft_instance
                             .downloads
                             .get("client")
-                            .map(|i| i.url.clone())
+                            .cloned()
                             .ok_or(UraniumError::OtherWithReason(
                                 "Client .jar not found in the minecraft instance".to_owned(),
                             ))?;
 
-                        let content = self
-                            .requester
-                            .get(url)
-                            .send()
-                            .await
-                            .unwrap()
-                            .bytes()
-                            .await?;
                         let instance_folder = self
                             .dot_minecraft_path
                             .join("versions")
@@ -342,18 +429,61 @@ impl<T: FileDownloader + Send + Sync> MinecraftDownloader<T> {
                             std::fs::create_dir_all(&instance_folder)?;
                         }
 
-                        let client_path = instance_folder
-                            .as_path()
-                            .join(
-                                self.minecraft_instance
-                                    .id
+                        let client_name = self
+                            .minecraft_instance
+                            .id
+                            .clone()
+                            + ".jar";
+                        let client_obj = DownloadableObject::new(
+                            &client.url,
+                            &client_name,
+                            &instance_folder,
+                            Some(HashType::Sha1(client.sha1.clone())),
+                        )
+                        .with_size(client.size as u64);
+                        if !instance_folder
+                            .join(&client_name)
+                            .exists()
+                            && !self
+                                .copy_from_seed(vec![client_obj.clone()])
+                                .await?
+                                .is_empty()
+                        {
+                            info!("Writing client!");
+                            let client_path = instance_folder.join(&client_name);
+                            report(
+                                &self.reporter,
+                                ProgressEvent::FileStarted {
+                                    path: client_path.clone(),
+                                    size: client_obj.size,
+                                },
+                            );
+                            if let Err(err) = download_single_file(
+                                &self.requester,
+                                &client_obj,
+                                Arc::new(LocalStorage),
+                                &self
+                                    .retry
                                     .clone()
-                                    + ".jar",
+                                    .unwrap_or_default(),
+                            )
+                            .await
+                            {
+                                report(
+                                    &self.reporter,
+                                    ProgressEvent::FileFailed {
+                                        path: client_path,
+                                        reason: err.to_string(),
+                                    },
+                                );
+                                return Err(err);
+                            }
+                            report(
+                                &self.reporter,
+                                ProgressEvent::FileVerified { path: client_path },
                             );
-                        if !client_path.exists() {
-                            info!("Writing client!");
-                            let mut client_file = File::create(client_path)?;
-                            client_file.write_all(&content)?;
+                            self.finished.downloaded += 1;
+                            self.finished.bytes += client.size as u64;
                         }
 
                         let manual_path = instance_folder.join(
@@ -371,7 +501,8 @@ impl<T: FileDownloader + Send + Sync> MinecraftDownloader<T> {
                                     .as_bytes(),
                             )?;
                         }
-                        self.prepare_libraries()?;
+                        self.prepare_libraries()
+                            .await?;
                         self.download_state = MinecraftDownloadState::DownloadingLibraries;
                     }
                     Err(e) => {
@@ -444,6 +575,127 @@ impl<T: FileDownloader + Send + Sync> MinecraftDownloader<T> {
             .unwrap_or_default()
     }
 
+    /// Returns how many bytes will be downloaded: the assets, the client and
+    /// the libraries for the current OS.
+    ///
+    /// Everything is taken from the version metadata, so no requests are
+    /// made.
+    pub fn estimated_download_size(&self) -> u64 {
+        let instance = &self.minecraft_instance;
+        let assets = u64::try_from(
+            instance
+                .asset_index
+                .total_size,
+        )
+        .unwrap_or(u64::MAX);
+        let client = instance
+            .downloads
+            .get("client")
+            .map_or(0, |c| c.size as u64);
+        let libraries: u64 = Self::get_os_libraries_sizes(&instance.libraries);
+
+        assets
+            .saturating_add(client)
+            .saturating_add(libraries)
+    }
+
+    /// Only downloads the assets included by `filter`, e.g.
+    /// [`AssetFilter::low_storage`] to skip sounds and languages.
+    ///
+    /// Must be set before the download starts. Check the installation with
+    /// the same filter, see [`InstallationVerifier::asset_filter`].
+    ///
+    /// [`InstallationVerifier::asset_filter`]: crate::verifier::InstallationVerifier::asset_filter
+    pub fn set_asset_filter(&mut self, filter: AssetFilter) {
+        self.asset_filter = filter;
+    }
+
+    /// Returns a [`RuntimeDownloader`] for the Java runtime this version
+    /// needs, installed in the `runtime` folder of the destination path
+    /// instead of the launcher `.minecraft`.
+    pub fn runtime_downloader<D: FileDownloader>(&self) -> RuntimeDownloader<D> {
+        RuntimeDownloader::for_version(
+            &self.minecraft_instance,
+            self.dot_minecraft_path
+                .join(RUNTIMES_DIR),
+        )
+    }
+
+    /// Returns the downloads skipped because many assets or libraries are
+    /// written to the same file.
+    pub fn dedup_savings(&self) -> DedupSavings {
+        self.dedup_savings
+    }
+
+    /// Sets where the [`ProgressEvent`]s of every step are sent.
+    pub fn set_progress_reporter(&mut self, reporter: Arc<dyn ProgressReporter>) {
+        if let Some(downloader) = self.downloader.as_mut() {
+            downloader.set_progress_reporter(reporter.clone());
+        }
+        self.reporter = Some(reporter);
+    }
+
+    /// Sets where the [`UraniumEvent`]s of every step are sent.
+    ///
+    /// A `PhaseChanged` event is sent each time the downloader moves to the
+    /// next [`MinecraftDownloadState`].
+    pub fn set_event_sender(&mut self, sender: EventSender) {
+        if let Some(downloader) = self.downloader.as_mut() {
+            downloader.set_event_sender(sender.clone());
+        }
+        self.events = Some(sender);
+    }
+
+    /// Returns a stream with a [`CompletedFile`] for each file done in every
+    /// step, see [`FileDownloader::completions`].
+    ///
+    /// The stream ends once the downloader is dropped.
+    pub fn completions(&mut self) -> impl Stream<Item = CompletedFile> + Send + Unpin + 'static {
+        let (sender, receiver) = unbounded_channel();
+        if let Some(downloader) = self.downloader.as_mut() {
+            downloader.set_completion_sender(sender.clone());
+        }
+        self.completions = Some(sender);
+        completion_stream(receiver)
+    }
+
+    /// Sets how failed requests of every step are retried.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        if let Some(downloader) = self.downloader.as_mut() {
+            downloader.set_retry_policy(policy.clone());
+        }
+        self.retry = Some(policy);
+    }
+
+    /// Sets the user agent, headers, proxy, timeouts... of the requests of
+    /// every step, see [`DownloadConfig`].
+    ///
+    /// # Errors
+    /// Returns an error if the client can't be built with `config`.
+    pub fn set_download_config(&mut self, config: &DownloadConfig) -> Result<()> {
+        self.requester = config.client()?;
+        if let Some(downloader) = self.downloader.as_mut() {
+            downloader.set_download_config(config)?;
+        }
+        self.config = Some(config.clone());
+        Ok(())
+    }
+
+    /// Copies the assets, libraries and client jar from the `.minecraft` in
+    /// `installation` when they are there, so only the missing ones are
+    /// downloaded.
+    ///
+    /// Each file is hashed before copying it, off the async runtime, changed
+    /// files are downloaded as usual. Files are always copied, never linked,
+    /// so both installations stay independent.
+    pub fn seed_from<I: AsRef<Path>>(&mut self, installation: I) {
+        self.seed = Some(
+            installation
+                .as_ref()
+                .to_path_buf(),
+        );
+    }
+
     /// Returns the number of chunks of libs to download: `libs.len() /
     /// N_THREADS()`
     pub fn lib_chunks(&self) -> usize {
@@ -471,7 +723,9 @@ impl<T: FileDownloader + Send + Sync> MinecraftDownloader<T> {
     ///
     /// If fails it will return the error in `Err()`.
     async fn get_sources(&mut self) -> Result<()> {
-        let resources: Resources = self
+        // The raw bytes are kept so the index is written exactly as Mojang
+        // sent it, re-serializing it would change the bytes and so its sha1.
+        let response = self
             .requester
             .get(
                 &self
@@ -480,9 +734,23 @@ impl<T: FileDownloader + Send + Sync> MinecraftDownloader<T> {
                     .url,
             )
             .send()
-            .await?
-            .json::<Resources>()
             .await?;
+        let raw_index = read_limited(response, MAX_METADATA_SIZE()).await?;
+
+        if sha1_bytes(&raw_index)
+            != self
+                .minecraft_instance
+                .asset_index
+                .sha1
+        {
+            error!("Assets index hash doesn't match");
+            return Err(UraniumError::OtherWithReason(
+                "Assets index hash doesn't match".to_owned(),
+            ));
+        }
+
+        let resources: Resources =
+            serde_json::from_slice(&raw_index).map_err(|_| UraniumError::WrongFileFormat)?;
 
         tokio::fs::create_dir_all(
             self.dot_minecraft_path
@@ -491,7 +759,7 @@ impl<T: FileDownloader + Send + Sync> MinecraftDownloader<T> {
         .await
         .map_err(|err| {
             error!("Cant create assets/indexes");
-            UraniumError::OtherWithReason(format!("assets/indexes: [{}]", err.to_string()))
+            UraniumError::OtherWithReason(format!("assets/indexes: [{}]", err))
         })?;
 
         if tokio::fs::create_dir_all(
@@ -505,31 +773,55 @@ impl<T: FileDownloader + Send + Sync> MinecraftDownloader<T> {
             return Err(UraniumError::CantCreateDir("assets/objects"));
         }
 
-        self.create_indexes(&resources)
+        self.create_indexes(&raw_index)
             .await?;
 
         let base = PathBuf::from(ASSETS_PATH).join(OBJECTS_PATH);
 
-        for obj in resources.objects.values() {
+        let mut objects: Vec<(&String, &ObjectData)> = resources
+            .objects
+            .iter()
+            .filter(|(name, _)| {
+                self.asset_filter
+                    .includes(name)
+            })
+            .collect();
+        objects.sort_by_key(|(name, _)| *name);
+
+        // The objects are stored by hash, but legacy versions copy them to
+        // their logical names.
+        check_case_collisions(
+            objects
+                .iter()
+                .map(|(name, _)| name),
+        )?;
+
+        for (_, obj) in objects {
             let url = obj.get_link();
             let path = base
                 .join(&obj.hash[..2])
                 .join(&obj.hash);
-            self.resources
-                .push(DownloadableObject::new(
+            self.resources.push(
+                DownloadableObject::new(
                     &url,
                     path.to_str()
                         .unwrap_or_default(),
                     &self.dot_minecraft_path,
                     Some(HashType::Sha1(obj.hash.to_owned())),
-                ));
+                )
+                .with_size(obj.size as u64),
+            );
         }
 
+        let savings = dedup_objects(&mut self.resources);
+        self.add_savings(savings);
+
         Ok(())
     }
 
-    /// Makes the minecraft index.json file
-    async fn create_indexes(&self, resources: &Resources) -> Result<()> {
+    /// Makes the minecraft index.json file with the exact bytes of
+    /// `raw_index`.
+    async fn create_indexes(&self, raw_index: &[u8]) -> Result<()> {
         let indexes_path = self
             .dot_minecraft_path
             .join(ASSETS_PATH)
@@ -542,16 +834,106 @@ impl<T: FileDownloader + Send + Sync> MinecraftDownloader<T> {
         let mut indexes = tokio::fs::File::create(indexes_path).await?;
 
         indexes
-            .write_all(
-                serde_json::to_string(resources)
-                    .unwrap_or_default()
-                    .as_bytes(),
-            )
+            .write_all(raw_index)
             .await?;
 
         Ok(())
     }
 
+    /// Makes the downloader for the next step, passing it the progress
+    /// reporter.
+    fn new_downloader(&self, files: Vec<DownloadableObject>) -> T {
+        let mut downloader = T::new(files);
+        if let Some(reporter) = &self.reporter {
+            downloader.set_progress_reporter(reporter.clone());
+        }
+        if let Some(retry) = &self.retry {
+            downloader.set_retry_policy(retry.clone());
+        }
+        if let Some(config) = &self.config {
+            // Already checked by `set_download_config`.
+            if let Err(e) = downloader.set_download_config(config) {
+                warn!("Can't use the download config: {e}");
+            }
+        }
+        if let Some(events) = &self.events {
+            downloader.set_event_sender(events.clone());
+        }
+        if let Some(completions) = &self.completions {
+            downloader.set_completion_sender(completions.clone());
+        }
+        downloader
+    }
+
+    /// Replaces the downloader of the previous step, keeping its report.
+    async fn set_downloader(&mut self, files: Vec<DownloadableObject>) -> Result<()> {
+        if let Some(previous) = &self.downloader {
+            self.finished
+                .merge(previous.report());
+        }
+        let files = self
+            .copy_from_seed(files)
+            .await?;
+        self.downloader = Some(self.new_downloader(files));
+        Ok(())
+    }
+
+    /// Copies `files` from the installation given to
+    /// [`MinecraftDownloader::seed_from`] and returns the ones which aren't
+    /// there or are different.
+    ///
+    /// The files are hashed in blocking tasks, [`N_THREADS`] at a time, and
+    /// each copied file is reported as verified.
+    async fn copy_from_seed(
+        &mut self,
+        files: Vec<DownloadableObject>,
+    ) -> Result<Vec<DownloadableObject>> {
+        let Some(seed) = self.seed.clone() else {
+            return Ok(files);
+        };
+        let root = self
+            .dot_minecraft_path
+            .clone();
+
+        let mut copies = futures::stream::iter(files)
+            .map(|file| {
+                let (seed, root) = (seed.clone(), root.clone());
+                tokio::task::spawn_blocking(move || {
+                    let copied = seed_file(&seed, &root, &file);
+                    (file, copied)
+                })
+            })
+            .buffered(N_THREADS());
+
+        let mut missing = Vec::new();
+        while let Some(copy) = copies.next().await {
+            match copy? {
+                (file, true) => {
+                    self.finished.seeded += 1;
+                    report(
+                        &self.reporter,
+                        ProgressEvent::FileVerified {
+                            path: file.path.join(&file.name),
+                        },
+                    );
+                }
+                (file, false) => missing.push(file),
+            }
+        }
+        Ok(missing)
+    }
+
+    fn add_savings(&mut self, savings: DedupSavings) {
+        if savings.files > 0 {
+            info!(
+                "Skipping {} duplicated files ({} bytes)",
+                savings.files, savings.bytes
+            );
+        }
+        self.dedup_savings.files += savings.files;
+        self.dedup_savings.bytes += savings.bytes;
+    }
+
     /// When success all the assets folder are created
     fn create_assess_folders(&self, names: &[DownloadableObject]) -> Result<()> {
         for p in names {
@@ -566,76 +948,57 @@ impl<T: FileDownloader + Send + Sync> MinecraftDownloader<T> {
         Ok(())
     }
 
-    /// Return a `Vec<String>` with the urls of the libraries for the current.
-    /// If the lib has no specified Os then it will be inside the vector too.
-    fn get_os_libraries(libraries: &Libraries) -> Vec<String> {
-        let current_os = match std::env::consts::OS {
-            "linux" => mine_data_structs::minecraft::Os::Linux,
-            "macos" => mine_data_structs::minecraft::Os::Other,
-            // "windows" => mine_data_structs::minecraft::Os::Windows,
-            _ => mine_data_structs::minecraft::Os::Windows,
-        };
-
+    /// Returns the sum of the sizes of the libraries for the current OS.
+    fn get_os_libraries_sizes(libraries: &Libraries) -> u64 {
         libraries
+            .get_os_artifacts()
             .iter()
-            .filter(|lib| {
-                lib.get_os().is_none()
-                    || lib
-                        .get_os()
-                        .is_some_and(|os| os == current_os)
-            })
-            .map(|lib| lib.get_url().to_owned())
-            .collect()
+            .map(|artifact| artifact.size)
+            .sum()
     }
 
     /// This function sets `self.downloader` with the urls and paths in order to
-    /// download minecraft libraries corresponding to the user OS.
+    /// download minecraft libraries corresponding to the user OS, their
+    /// natives included.
     ///
     /// This function **WILL NOT** start the download in any way.
-    fn prepare_libraries(&mut self) -> Result<()> {
-        let libraries = &self
-            .minecraft_instance
-            .libraries;
-        let raw_paths = libraries.get_paths();
-        let urls = Self::get_os_libraries(libraries);
-
-        let good_paths: Vec<PathBuf> = raw_paths
-            .iter()
-            .map(|p| {
-                self.dot_minecraft_path
-                    .join(PathBuf::from("libraries").join(p))
-            })
-            .collect();
+    async fn prepare_libraries(&mut self) -> Result<()> {
+        let libraries_path = self
+            .dot_minecraft_path
+            .join("libraries");
 
-        for p in &good_paths {
-            std::fs::create_dir_all(
-                p.parent()
-                    .ok_or(UraniumError::Other)?,
-            )?;
-        }
+        let mut files = Vec::new();
+        let mut paths = Vec::new();
+        for artifact in self
+            .minecraft_instance
+            .libraries
+            .get_os_artifacts()
+        {
+            let path = libraries_path.join(&artifact.path);
+            let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+                warn!("Wrong library path: {}", artifact.path.display());
+                continue;
+            };
+            std::fs::create_dir_all(dir)?;
 
-        // TODO!: Fix this unwraps
-        let files = good_paths
-            .iter()
-            .zip(&urls)
-            .zip(raw_paths)
-            .map(|((path, url), lib_path)| {
+            files.push(
                 DownloadableObject::new(
-                    url,
-                    lib_path
-                        .file_name()
-                        .unwrap()
-                        .to_str()
-                        .unwrap_or_default(),
-                    path.parent().unwrap(),
-                    None,
+                    &artifact.url,
+                    &name.to_string_lossy(),
+                    dir,
+                    Some(HashType::Sha1(artifact.sha1.clone())),
                 )
-            })
-            .collect();
+                .with_size(artifact.size),
+            );
+            paths.push(path);
+        }
 
-        self.downloader = Some(T::new(files));
+        check_case_collisions(&paths)?;
 
-        Ok(())
+        let savings = dedup_objects(&mut files);
+        self.add_savings(savings);
+        self.set_downloader(files)
+            .await
     }
 
     #[allow(clippy::await_holding_lock)]
@@ -758,11 +1121,95 @@ impl<T: FileDownloader + Send + Sync> MinecraftDownloader<T> {
     }
 }
 
+/// Copies `obj` from `seed`, the `.minecraft` of another installation, into
+/// `root`. Returns `false` if it isn't there or is different.
+fn seed_file(seed: &Path, root: &Path, obj: &DownloadableObject) -> bool {
+    let Some(hash) = &obj.hash else {
+        return false;
+    };
+    let path = obj.path.join(&obj.name);
+    let Ok(relative) = path.strip_prefix(root) else {
+        return false;
+    };
+    let source = seed.join(relative);
+    if path.exists() || source == path {
+        return false;
+    }
+
+    let same = std::fs::File::open(&source)
+        .and_then(|file| hash.matches_reader(file))
+        .unwrap_or(false);
+    if !same {
+        return false;
+    }
+    if let Err(e) = LocalStorage.copy_from(&source, &path) {
+        warn!("Can't copy {}: {e}", source.display());
+        return false;
+    }
+    true
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::downloaders::Downloader;
     use crate::error::Result;
+    use crate::test_utils::minecraft_root;
+
+    #[tokio::test]
+    async fn seed_from_installation() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let donor = dir.join("donor/libraries/lwjgl");
+        let instance = dir.join("instance");
+        let libraries = instance.join("libraries/lwjgl");
+        std::fs::create_dir_all(&donor).unwrap();
+        std::fs::create_dir_all(&libraries).unwrap();
+        std::fs::write(donor.join("lwjgl.jar"), b"lwjgl").unwrap();
+        std::fs::write(donor.join("glfw.jar"), b"changed").unwrap();
+
+        let library = |name: &str, content: &[u8]| {
+            DownloadableObject::new(
+                "http://127.0.0.1:1/",
+                name,
+                &libraries,
+                Some(HashType::Sha1(sha1_bytes(content))),
+            )
+        };
+        let mut downloader =
+            MinecraftDownloader::<Downloader>::from_root(minecraft_root("1.20.1"), &instance);
+        downloader.seed_from(dir.join("donor"));
+        let (tx, mut rx) = unbounded_channel();
+        downloader.set_progress_reporter(Arc::new(tx));
+        downloader
+            .set_downloader(vec![
+                library("lwjgl.jar", b"lwjgl"),
+                library("glfw.jar", b"glfw"),
+                library("missing.jar", b"missing"),
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            std::fs::read(libraries.join("lwjgl.jar")).unwrap(),
+            b"lwjgl"
+        );
+        assert!(!libraries
+            .join("glfw.jar")
+            .exists());
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(ProgressEvent::FileVerified { path }) if path == libraries.join("lwjgl.jar")
+        ));
+        assert_eq!(downloader.report().seeded, 1);
+        assert_eq!(
+            downloader
+                .downloader
+                .as_ref()
+                .map(FileDownloader::len),
+            Some(2)
+        );
+    }
 
     #[tokio::test(flavor = "multi_thread")]
     pub async fn download_minecraft() -> Result<()> {
@@ -1,12 +1,32 @@
+pub use asset_filter::AssetFilter;
 pub use curse_downloader::CurseDownloader;
+pub use download_config::DownloadConfig;
 pub use gen_downloader::*;
+pub use loader_installer::LoaderInstaller;
 pub use minecraft_downloader::*;
-pub use rinth_downloader::RinthDownloader;
-pub use updater::update_modpack;
+pub use plan::{DownloadPlan, ImportReport, PlannedFile};
+pub use resolver::{DependencyResolver, MissingDependency};
+pub use retry::RetryPolicy;
+pub use rinth_downloader::{DownloadSide, RinthDownloader};
+pub use runtime_downloader::{
+    repair_permissions, RuntimeDownloadState, RuntimeDownloader, RuntimesRepair,
+};
+pub use updater::{
+    check_updates, update_modpack, update_modpack_for, ContentType, FailedUpdate, UpdateCandidate,
+    UpdateReport, UpdatedMod,
+};
 
+mod asset_filter;
 mod curse_downloader;
+mod download_config;
 mod functions;
 mod gen_downloader;
+mod journal;
+mod loader_installer;
 mod minecraft_downloader;
+mod plan;
+mod resolver;
+mod retry;
 mod rinth_downloader;
+mod runtime_downloader;
 mod updater;
@@ -0,0 +1,356 @@
+use std::path::{Component, Path, PathBuf};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use super::gen_downloader::{DownloadableObject, FileDownloader, HashType};
+use crate::error::{Result, UraniumError};
+
+/// A file of a [`DownloadPlan`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedFile {
+    pub url: String,
+    /// Where the file goes, relative to the installation directory.
+    pub path: PathBuf,
+    pub hash: Option<HashType>,
+    pub size: Option<u64>,
+}
+
+impl PlannedFile {
+    /// Plans may come from another machine, a path which could end up
+    /// outside the installation directory is an error.
+    fn check_path(&self) -> Result<()> {
+        let inside = self
+            .path
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)));
+        if !inside
+            || self
+                .path
+                .as_os_str()
+                .is_empty()
+        {
+            return Err(UraniumError::OtherWithReason(format!(
+                "Invalid path in the download plan: {}",
+                self.path.display()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Every file an install needs, so it can be downloaded on a machine with
+/// internet and installed on one without it.
+///
+/// The plan is exported as json on the offline machine, the files are
+/// downloaded somewhere else with [`DownloadPlan::download_to`] (or by any
+/// other means) and brought back, then [`DownloadPlan::import_artifacts`]
+/// checks them and puts them in place without using the network.
+///
+/// ```no_run
+/// # async fn foo() -> uranium::error::Result<()> {
+/// use uranium::downloaders::{DownloadPlan, Downloader, RinthDownloader};
+///
+/// // Offline machine
+/// let rinth = RinthDownloader::<Downloader>::new("modpack.mrpack", "instance")?;
+/// rinth.download_plan().save("plan.json")?;
+///
+/// // Online machine
+/// let plan = DownloadPlan::load("plan.json")?;
+/// plan.download_to::<Downloader, _>("/media/usb/artifacts").await?;
+///
+/// // Offline machine again
+/// let report = plan.import_artifacts("/media/usb/artifacts", "instance")?;
+/// assert!(report.is_complete());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadPlan {
+    pub files: Vec<PlannedFile>,
+}
+
+/// Result of [`DownloadPlan::import_artifacts`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ImportReport {
+    pub imported: Vec<PathBuf>,
+    /// Files not found in the artifacts directory.
+    pub missing: Vec<PathBuf>,
+    /// Files found with a wrong size or hash, they are not copied.
+    pub corrupted: Vec<PathBuf>,
+}
+
+impl ImportReport {
+    /// Returns `true` if every file of the plan was imported.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty() && self.corrupted.is_empty()
+    }
+}
+
+impl DownloadPlan {
+    /// Makes a plan from `objects`, their paths are made relative to `base`.
+    ///
+    /// # Errors
+    /// Returns an error if an object is not inside `base`.
+    pub fn from_objects(objects: &[DownloadableObject], base: &Path) -> Result<DownloadPlan> {
+        let files = objects
+            .iter()
+            .map(|obj| {
+                let full = obj.path.join(&obj.name);
+                let path = full
+                    .strip_prefix(base)
+                    .map_err(|_| {
+                        UraniumError::OtherWithReason(format!(
+                            "{} is not inside {}",
+                            full.display(),
+                            base.display()
+                        ))
+                    })?
+                    .to_path_buf();
+                Ok(PlannedFile {
+                    url: obj.url.clone(),
+                    path,
+                    hash: obj.hash.clone(),
+                    size: obj.size,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(DownloadPlan { files })
+    }
+
+    /// Returns the objects to download the plan into `base`.
+    #[must_use]
+    pub fn to_objects(&self, base: &Path) -> Vec<DownloadableObject> {
+        self.files
+            .iter()
+            .map(|f| {
+                let full = base.join(&f.path);
+                let mut obj = DownloadableObject::new(
+                    &f.url,
+                    &full
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy(),
+                    full.parent().unwrap_or(base),
+                    f.hash.clone(),
+                );
+                obj.size = f.size;
+                obj
+            })
+            .collect()
+    }
+
+    /// Writes the plan as json into `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be written.
+    pub fn save<I: AsRef<Path>>(&self, path: I) -> Result<()> {
+        let content = serde_json::to_vec_pretty(self).map_err(|_| UraniumError::WrongFileFormat)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Reads a plan written by [`DownloadPlan::save`].
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be read, it's not a plan or a path
+    /// of the plan is absolute or has `..`.
+    pub fn load<I: AsRef<Path>>(path: I) -> Result<DownloadPlan> {
+        let content = std::fs::read(path)?;
+        let plan: DownloadPlan =
+            serde_json::from_slice(&content).map_err(|_| UraniumError::WrongFileFormat)?;
+        plan.check_paths()?;
+        Ok(plan)
+    }
+
+    fn check_paths(&self) -> Result<()> {
+        self.files
+            .iter()
+            .try_for_each(PlannedFile::check_path)
+    }
+
+    /// Downloads every file of the plan into `dir`, keeping their relative
+    /// paths.
+    ///
+    /// # Errors
+    /// Returns an error if a path of the plan is absolute or has `..`, a
+    /// directory can't be created or the download fails.
+    pub async fn download_to<T: FileDownloader, I: AsRef<Path>>(&self, dir: I) -> Result<()> {
+        self.check_paths()?;
+        let objects = self.to_objects(dir.as_ref());
+        for obj in &objects {
+            std::fs::create_dir_all(&obj.path)?;
+        }
+        T::new(objects)
+            .complete()
+            .await
+    }
+
+    /// Copies the files of the plan from `artifacts` into `destination`,
+    /// checking their size and hash first.
+    ///
+    /// Files are looked for at their relative path inside `artifacts` and, if
+    /// they are not there, by their file name at the top of `artifacts`. No
+    /// request is made.
+    ///
+    /// # Errors
+    /// Returns an error if a path of the plan is absolute or has `..`, before
+    /// anything is copied, or if a file can't be read or copied. Missing and
+    /// corrupted files are listed in the report instead.
+    pub fn import_artifacts<I: AsRef<Path>, J: AsRef<Path>>(
+        &self,
+        artifacts: I,
+        destination: J,
+    ) -> Result<ImportReport> {
+        let artifacts = artifacts.as_ref();
+        let destination = destination.as_ref();
+        let mut report = ImportReport::default();
+        self.check_paths()?;
+
+        for file in &self.files {
+            let candidates = [
+                Some(artifacts.join(&file.path)),
+                file.path
+                    .file_name()
+                    .map(|name| artifacts.join(name)),
+            ];
+            let Some(source) = candidates
+                .into_iter()
+                .flatten()
+                .find(|p| p.is_file())
+            else {
+                warn!("{} not found in the artifacts", file.path.display());
+                report
+                    .missing
+                    .push(file.path.clone());
+                continue;
+            };
+
+            let good_size = file
+                .size
+                .is_none_or(|size| std::fs::metadata(&source).is_ok_and(|m| m.len() == size));
+            let good_hash = match &file.hash {
+                Some(hash) => hash.matches_reader(std::fs::File::open(&source)?)?,
+                None => true,
+            };
+            if !good_size || !good_hash {
+                warn!("{} is corrupted", source.display());
+                report
+                    .corrupted
+                    .push(file.path.clone());
+                continue;
+            }
+
+            let target = destination.join(&file.path);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::copy(&source, &target)?;
+            report
+                .imported
+                .push(file.path.clone());
+        }
+
+        info!(
+            "{} files imported, {} missing, {} corrupted",
+            report.imported.len(),
+            report.missing.len(),
+            report.corrupted.len()
+        );
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashes::{sha1_bytes, sha512_bytes};
+
+    #[test]
+    fn import_checks_hashes() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        let artifacts = root.join("usb");
+        let instance = root.join("instance");
+        std::fs::create_dir_all(artifacts.join("mods")).unwrap();
+
+        let objects = [
+            DownloadableObject::new(
+                "https://a.b/sodium.jar",
+                "sodium.jar",
+                &instance.join("mods"),
+                Some(HashType::Sha1(sha1_bytes(b"sodium"))),
+            ),
+            DownloadableObject::new(
+                "https://a.b/iris.jar",
+                "iris.jar",
+                &instance.join("mods"),
+                Some(HashType::Sha512(sha512_bytes(b"iris"))),
+            ),
+            DownloadableObject::new(
+                "https://a.b/lithium.jar",
+                "lithium.jar",
+                &instance.join("mods"),
+                None,
+            ),
+        ];
+        let plan = DownloadPlan::from_objects(&objects, &instance).unwrap();
+        assert_eq!(plan.files[0].path, Path::new("mods/sodium.jar"));
+        assert_eq!(plan.to_objects(&instance), objects);
+
+        let saved = root.join("plan.json");
+        plan.save(&saved).unwrap();
+        let plan = DownloadPlan::load(&saved).unwrap();
+        // The sha512 of iris is kept.
+        assert_eq!(plan.to_objects(&instance), objects);
+
+        // Sodium at its relative path, iris flat and corrupted, no lithium.
+        std::fs::write(artifacts.join("mods/sodium.jar"), b"sodium").unwrap();
+        std::fs::write(artifacts.join("iris.jar"), b"not iris").unwrap();
+
+        let report = plan
+            .import_artifacts(&artifacts, &instance)
+            .unwrap();
+        assert_eq!(report.imported, [PathBuf::from("mods/sodium.jar")]);
+        assert_eq!(report.corrupted, [PathBuf::from("mods/iris.jar")]);
+        assert_eq!(report.missing, [PathBuf::from("mods/lithium.jar")]);
+        assert!(!report.is_complete());
+        assert!(instance
+            .join("mods/sodium.jar")
+            .exists());
+    }
+
+    #[test]
+    fn rejects_paths_outside() {
+        let root = tempfile::tempdir().unwrap();
+        let artifacts = root.path().join("usb");
+        std::fs::create_dir_all(&artifacts).unwrap();
+        std::fs::write(artifacts.join("evil.jar"), b"evil").unwrap();
+
+        for path in ["../evil.jar", "mods/../../evil.jar", "/tmp/evil.jar"] {
+            let plan = DownloadPlan {
+                files: vec![PlannedFile {
+                    url: "https://a.b/evil.jar".to_owned(),
+                    path: PathBuf::from(path),
+                    hash: None,
+                    size: None,
+                }],
+            };
+            let instance = root.path().join("instance");
+            assert!(plan
+                .import_artifacts(&artifacts, &instance)
+                .is_err());
+            assert!(!root
+                .path()
+                .join("evil.jar")
+                .exists());
+
+            let saved = root.path().join("plan.json");
+            plan.save(&saved).unwrap();
+            assert!(DownloadPlan::load(&saved).is_err());
+        }
+    }
+}
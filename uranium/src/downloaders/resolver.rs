@@ -0,0 +1,264 @@
+use std::collections::{HashMap, HashSet};
+
+use log::{info, warn};
+use mine_data_structs::rinth::{RinthModpack, RinthVersion};
+
+use crate::error::{Result, UraniumError};
+use crate::http::{default_client, fetch_rinth_json};
+use crate::searcher::rinth::{SearchBuilder, SearchType};
+
+/// A required dependency which is not in the modpack.
+#[derive(Debug, Clone)]
+pub struct MissingDependency {
+    pub project_id: String,
+    /// Project id of the mod which requires it.
+    pub required_by: String,
+    /// Version to add, `None` if there is no version of the dependency for
+    /// the modpack Minecraft version and loader.
+    pub version: Option<RinthVersion>,
+}
+
+/// Finds the required dependencies missing from a set of mods.
+///
+/// The mods are looked up by their sha1 in Modrinth, then the required
+/// dependencies of each one are walked: the pinned version of the dependency
+/// is used if there is one, else its latest version for the game version and
+/// loader. The dependencies of the added versions are walked too.
+///
+/// ```no_run
+/// # async fn foo() -> uranium::error::Result<()> {
+/// use uranium::downloaders::DependencyResolver;
+///
+/// let resolver = DependencyResolver::new("1.20.1", "fabric");
+/// let missing = resolver
+///     .resolve(vec!["619e250c133106bacc3e3b560839bd4b324dfda8".to_owned()])
+///     .await?;
+/// for dep in missing {
+///     println!("{} needs {}", dep.required_by, dep.project_id);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct DependencyResolver {
+    client: reqwest::Client,
+    game_version: String,
+    loader: String,
+    /// Modrinth API used instead of the public one.
+    api: Option<String>,
+}
+
+impl DependencyResolver {
+    pub fn new(game_version: &str, loader: &str) -> Self {
+        DependencyResolver {
+            client: default_client().unwrap_or_default(),
+            game_version: game_version.to_owned(),
+            loader: loader.to_owned(),
+            api: None,
+        }
+    }
+
+    /// Makes a resolver for the Minecraft version and loader of `modpack`.
+    ///
+    /// # Errors
+    /// Returns an error if the modpack doesn't declare its Minecraft version
+    /// or loader.
+    pub fn for_modpack(modpack: &RinthModpack) -> Result<Self> {
+        match (modpack.get_game_version(), modpack.get_loader()) {
+            (Some(game_version), Some(loader)) => Ok(Self::new(game_version, loader)),
+            _ => Err(UraniumError::OtherWithReason(
+                "The modpack has no Minecraft version or loader".to_owned(),
+            )),
+        }
+    }
+
+    /// Returns the required dependencies missing from the mods with
+    /// `sha1_hashes`. Files unknown to Modrinth are ignored, as well as
+    /// pinned versions which don't exist anymore.
+    ///
+    /// # Errors
+    /// Returns an error if Modrinth can't be reached.
+    pub async fn resolve(&self, sha1_hashes: Vec<String>) -> Result<Vec<MissingDependency>> {
+        let versions = self
+            .versions_of(sha1_hashes)
+            .await?;
+        let mut installed: HashSet<String> = versions
+            .values()
+            .map(|v| v.project_id.clone())
+            .collect();
+        let mut pending: Vec<RinthVersion> = versions
+            .into_values()
+            .collect();
+        let mut missing = Vec::new();
+
+        while let Some(version) = pending.pop() {
+            for (project_id, version_id) in missing_required(&version, &installed) {
+                let dependency = match version_id {
+                    Some(id) => self.version(&id).await?,
+                    None => {
+                        self.latest_version(&project_id)
+                            .await?
+                    }
+                };
+
+                // Dependencies given only by version have no project id.
+                let project_id = dependency
+                    .as_ref()
+                    .map_or(project_id, |d| d.project_id.clone());
+                if !installed.insert(project_id.clone()) {
+                    continue;
+                }
+
+                match &dependency {
+                    Some(d) => {
+                        info!("{} requires {}", version.project_id, d.name);
+                        pending.push(d.clone());
+                    }
+                    None => warn!(
+                        "{} requires {project_id} but it has no version for {} {}",
+                        version.project_id, self.game_version, self.loader
+                    ),
+                }
+                missing.push(MissingDependency {
+                    project_id,
+                    required_by: version.project_id.clone(),
+                    version: dependency,
+                });
+            }
+        }
+
+        Ok(missing)
+    }
+
+    fn search(&self) -> SearchBuilder<()> {
+        let search = SearchBuilder::new();
+        match &self.api {
+            Some(api) => search.api_url(api),
+            None => search,
+        }
+    }
+
+    async fn versions_of(&self, sha1_hashes: Vec<String>) -> Result<HashMap<String, RinthVersion>> {
+        let url = self
+            .search()
+            .search_type(SearchType::VersionFiles)
+            .build_url();
+        let body = serde_json::json!({ "hashes": sha1_hashes, "algorithm": "sha1" });
+        fetch_rinth_json(
+            self.client
+                .post(url)
+                .json(&body),
+        )
+        .await
+    }
+
+    async fn version(&self, id: &str) -> Result<Option<RinthVersion>> {
+        let url = self
+            .search()
+            .search_type(SearchType::Version { id: id.to_owned() })
+            .build_url();
+        match fetch_rinth_json(self.client.get(url)).await {
+            Ok(version) => Ok(Some(version)),
+            Err(UraniumError::NotFound(_)) => {
+                warn!("The version {id} doesn't exist anymore");
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn latest_version(&self, project_id: &str) -> Result<Option<RinthVersion>> {
+        let url = self
+            .search()
+            .project_versions(project_id)
+            .game_versions(vec![self.game_version.clone()])
+            .loaders(vec![self.loader.clone()])
+            .build_url();
+        let versions: Vec<RinthVersion> = fetch_rinth_json(self.client.get(url)).await?;
+        Ok(versions.into_iter().next())
+    }
+}
+
+/// Returns the project id (possibly empty) and pinned version id of the
+/// required dependencies of `version` which are not `installed`.
+fn missing_required(
+    version: &RinthVersion,
+    installed: &HashSet<String>,
+) -> Vec<(String, Option<String>)> {
+    version
+        .dependencies
+        .iter()
+        .filter(|d| d.is_required() && !installed.contains(d.get_project_id()))
+        .filter(|d| !d.get_project_id().is_empty() || !d.get_version_id().is_empty())
+        .map(|d| {
+            let version_id = Some(d.get_version_id())
+                .filter(|id| !id.is_empty())
+                .map(str::to_owned);
+            (d.get_project_id().to_owned(), version_id)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use mine_data_structs::rinth::Dependency;
+
+    use super::*;
+    use crate::test_utils::FixtureServer;
+
+    fn sodium_extra() -> RinthVersion {
+        serde_json::from_value(serde_json::json!({
+            "name": "Sodium Extra", "version_number": "0.5.0", "game_versions": ["1.20.1"],
+            "version_type": "release", "loaders": ["fabric"], "featured": false,
+            "id": "a", "project_id": "PtjYWJkn", "author_id": "a",
+            "date_published": "2024-07-03T01:02:03Z", "downloads": 1, "files": [],
+            "dependencies": []
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn only_missing_required_dependencies() {
+        let mut version = sodium_extra();
+        version.dependencies = vec![
+            Dependency::new(Some("AANobbMI"), None, "required"),
+            Dependency::new(Some("P7dR8mSH"), Some("abc"), "required"),
+            Dependency::new(Some("YL57xq9U"), None, "optional"),
+            Dependency::new(None, Some("def"), "required"),
+            Dependency::new(None, None, "required"),
+        ];
+        let installed = HashSet::from(["AANobbMI".to_owned()]);
+
+        assert_eq!(
+            missing_required(&version, &installed),
+            vec![
+                ("P7dR8mSH".to_owned(), Some("abc".to_owned())),
+                (String::new(), Some("def".to_owned())),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn deleted_pinned_dependency() {
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let mut version = sodium_extra();
+        version.dependencies = vec![Dependency::new(Some("P7dR8mSH"), Some("gone"), "required")];
+        server.add(
+            "/v2/version_files",
+            serde_json::to_vec(&HashMap::from([("sha1", version)])).unwrap(),
+        );
+
+        let mut resolver = DependencyResolver::new("1.20.1", "fabric");
+        resolver.api = Some(server.url("/v2/"));
+        let missing = resolver
+            .resolve(vec!["sha1".to_owned()])
+            .await
+            .unwrap();
+
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].project_id, "P7dR8mSH");
+        assert_eq!(missing[0].required_by, "PtjYWJkn");
+        assert!(missing[0].version.is_none());
+    }
+}
@@ -0,0 +1,293 @@
+use std::hash::{BuildHasher, RandomState};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use log::warn;
+use reqwest::{RequestBuilder, Response, StatusCode};
+
+use crate::error::UraniumError;
+use crate::events::{emit, EventSender, UraniumEvent};
+
+/// How many times and how often failed requests are retried.
+///
+/// A request is retried when it fails to connect or when the server answers
+/// with one of the `retry_on` status codes (429 and 5xx by default). The
+/// delay doubles after each attempt, starting at `base_delay` and never
+/// going over `max_delay`. If the server sends a `Retry-After` header with a
+/// number of seconds, that delay is used instead.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use uranium::downloaders::{Downloader, FileDownloader, RetryPolicy};
+///
+/// let policy = RetryPolicy::default()
+///     .max_attempts(5)
+///     .base_delay(Duration::from_secs(1));
+/// let downloader = Downloader::new(vec![]).with_retry_policy(policy);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+    retry_on: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+            retry_on: vec![408, 429, 500, 502, 503, 504],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy which never retries.
+    #[must_use]
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Sets how many times a request is sent at most, counting the first
+    /// one.
+    #[must_use]
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Sets the delay before the first retry.
+    #[must_use]
+    pub fn base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    /// Sets the longest delay between two attempts.
+    #[must_use]
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Enables or disables adding up to 50% of random time to each delay, so
+    /// many downloads don't retry all at once.
+    #[must_use]
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Sets which status codes are retried.
+    #[must_use]
+    pub fn retry_on(mut self, statuses: &[u16]) -> Self {
+        self.retry_on = statuses.to_vec();
+        self
+    }
+
+    /// Returns `true` if a response with `status` must be retried.
+    #[must_use]
+    pub fn is_retryable(&self, status: StatusCode) -> bool {
+        self.retry_on
+            .contains(&status.as_u16())
+    }
+
+    /// Returns how many times a request is sent at most.
+    pub(crate) fn attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Returns how long to wait after the failed `attempt` (starting at 1).
+    #[must_use]
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+
+        if self.jitter {
+            // `RandomState` is randomly seeded, good enough for a jitter.
+            let pct = RandomState::new().hash_one(attempt) % 50;
+            exp + exp.mul_f64(pct as f64 / 100.0)
+        } else {
+            exp
+        }
+    }
+
+    /// Returns how long to wait before retrying after the failed `attempt`
+    /// (starting at 1) ended with `error`, `None` if it must not be retried.
+    ///
+    /// For requests whose response is also parsed, where
+    /// [`RetryPolicy::send`] is not enough.
+    pub(crate) fn retry_delay(&self, error: &UraniumError, attempt: u32) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        let delay = match error {
+            UraniumError::RequestError(e) if e.is_connect() || e.is_timeout() || e.is_body() => {
+                self.delay(attempt)
+            }
+            UraniumError::HttpStatus { status, .. } if self.is_retryable(*status) => {
+                self.delay(attempt)
+            }
+            UraniumError::RateLimited { retry_after } => {
+                retry_after.unwrap_or_else(|| self.delay(attempt))
+            }
+            _ => return None,
+        };
+        Some(delay.min(self.max_delay))
+    }
+
+    /// Sends `request`, retrying it following this policy.
+    ///
+    /// The last response is returned even if its status is an error, so the
+    /// caller decides what to do with it. Each retry is reported to `events`
+    /// and counted in `retried`.
+    pub(crate) async fn send(
+        &self,
+        request: RequestBuilder,
+        events: Option<&EventSender>,
+        retried: Option<&AtomicUsize>,
+    ) -> reqwest::Result<Response> {
+        let mut attempt = 1;
+        loop {
+            // Requests with a streamed body can't be cloned, those are sent
+            // only once.
+            let Some(next) = request.try_clone() else {
+                return request.send().await;
+            };
+
+            let (url, wait) = match next.send().await {
+                Ok(response)
+                    if attempt < self.max_attempts && self.is_retryable(response.status()) =>
+                {
+                    warn!(
+                        "{} returned {}, retrying ({attempt}/{})",
+                        response.url(),
+                        response.status(),
+                        self.max_attempts
+                    );
+                    (
+                        response.url().to_string(),
+                        retry_after(&response).unwrap_or_else(|| self.delay(attempt)),
+                    )
+                }
+                Err(e) if attempt < self.max_attempts && (e.is_connect() || e.is_timeout()) => {
+                    warn!("{e}, retrying ({attempt}/{})", self.max_attempts);
+                    let url = e
+                        .url()
+                        .map(ToString::to_string)
+                        .unwrap_or_default();
+                    (url, self.delay(attempt))
+                }
+                result => return result,
+            };
+
+            let delay = wait.min(self.max_delay);
+            if let Some(retried) = retried {
+                retried.fetch_add(1, Ordering::Relaxed);
+            }
+            emit(
+                events,
+                UraniumEvent::RetryScheduled {
+                    url,
+                    attempt,
+                    delay,
+                },
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// Reads the `Retry-After` header when it is a number of seconds.
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exponential_delays() {
+        let policy = RetryPolicy::default()
+            .jitter(false)
+            .base_delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(5));
+
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+        assert_eq!(policy.delay(2), Duration::from_secs(2));
+        assert_eq!(policy.delay(3), Duration::from_secs(4));
+        assert_eq!(policy.delay(4), Duration::from_secs(5));
+        assert_eq!(policy.delay(40), Duration::from_secs(5));
+
+        let jittered = policy.jitter(true).delay(2);
+        assert!(jittered >= Duration::from_secs(2) && jittered < Duration::from_secs(3));
+    }
+
+    #[test]
+    fn retryable_statuses() {
+        let policy = RetryPolicy::default();
+        assert!(policy.is_retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(policy.is_retryable(StatusCode::BAD_GATEWAY));
+        assert!(!policy.is_retryable(StatusCode::NOT_FOUND));
+        assert!(!policy
+            .retry_on(&[])
+            .is_retryable(StatusCode::TOO_MANY_REQUESTS));
+    }
+
+    #[test]
+    fn retry_delays() {
+        let policy = RetryPolicy::default()
+            .jitter(false)
+            .max_attempts(3)
+            .base_delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(5));
+        let status = |status| UraniumError::HttpStatus {
+            url: "https://api.modrinth.com/v2/version_file/a".to_owned(),
+            status,
+        };
+        let limited = |secs: Option<u64>| UraniumError::RateLimited {
+            retry_after: secs.map(Duration::from_secs),
+        };
+
+        let secs = |secs| Some(Duration::from_secs(secs));
+        let cases = [
+            (status(StatusCode::SERVICE_UNAVAILABLE), 1, secs(1)),
+            (status(StatusCode::SERVICE_UNAVAILABLE), 2, secs(2)),
+            // No attempts left.
+            (status(StatusCode::SERVICE_UNAVAILABLE), 3, None),
+            (status(StatusCode::FORBIDDEN), 1, None),
+            (UraniumError::NotFound("a".to_owned()), 1, None),
+            (limited(Some(3)), 2, secs(3)),
+            (limited(Some(60)), 1, secs(5)),
+            (limited(None), 2, secs(2)),
+            (UraniumError::WrongFileFormat, 1, None),
+        ];
+        for (error, attempt, delay) in cases {
+            assert_eq!(
+                policy.retry_delay(&error, attempt),
+                delay,
+                "{error:?} {attempt}"
+            );
+        }
+    }
+}
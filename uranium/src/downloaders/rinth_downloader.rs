@@ -1,17 +1,62 @@
-use std::path::{Path, PathBuf};
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
 
-use log::info;
+use futures::Stream;
+use log::{info, warn};
 use mine_data_structs::rinth::{load_rinth_pack, RinthMdFiles, RinthModpack};
+use rayon::prelude::*;
+use tempfile::TempDir;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
 
-use super::gen_downloader::{DownloadState, DownloadableObject, FileDownloader};
-use crate::zipper::pack_unzipper::remove_temp_pack;
+use super::functions::overrides;
+use super::gen_downloader::{
+    DownloadReport, DownloadState, DownloadableObject, FileDownloader, HashType,
+};
+use super::journal::{InstallJournal, JournalReporter};
+use super::{
+    DependencyResolver, DownloadConfig, DownloadPlan, MissingDependency, PlannedFile, RetryPolicy,
+};
 use crate::{
     code_functions::N_THREADS,
+    collisions::check_case_collisions,
     error::{Result, UraniumError},
-    variables::constants::{RINTH_JSON, TEMP_DIR},
-    zipper::pack_unzipper::unzip_temp_pack,
+    events::{emit, emit_result, EventSender, Phase, UraniumEvent},
+    hooks::{HookPhase, Hooks},
+    instance::{InstalledFile, InstanceMetadata},
+    modpack_diff::PackDiff,
+    progress::{completion_stream, CompletedFile, ProgressReporter, Reporter},
+    variables::constants::{OVERRIDES_FOLDER, RINTH_JSON},
+    verifier::{verify_pack_files, VerificationProblem},
+    zipper::{pack_unzipper::unzip_temp_pack_with_progress, verify_overrides},
 };
 
+/// Which side of the game the modpack is installed for.
+///
+/// Files without an `env` in the modpack are always installed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadSide {
+    /// Skips the server only files.
+    Client,
+    /// Skips the client only files, for dedicated servers.
+    Server,
+    #[default]
+    Both,
+}
+
+impl DownloadSide {
+    /// Returns `true` if `file` is installed on this side.
+    #[must_use]
+    pub fn includes(self, file: &RinthMdFiles) -> bool {
+        match (self, file.get_env()) {
+            (DownloadSide::Client, Some(env)) => env.supports_client(),
+            (DownloadSide::Server, Some(env)) => env.supports_server(),
+            _ => true,
+        }
+    }
+}
+
 /// This struct is responsible for downloading
 /// the given modpack.
 ///
@@ -27,9 +72,40 @@ use crate::{
 /// # Ok(())
 /// # }
 /// ```
+///
+/// The installed files are journaled in `.uranium/progress.json` inside the
+/// destination, so an interrupted download only downloads the missing files
+/// when it is started again.
 pub struct RinthDownloader<T: FileDownloader> {
     gen_downloader: T,
     modpack: RinthModpack,
+    destination: PathBuf,
+    /// The extracted modpack, removed once the download ends.
+    temp_pack: Option<TempDir>,
+    side: DownloadSide,
+    /// Set by [`RinthDownloader::apply_diff`].
+    diff: Option<PackDiff>,
+    /// Files already installed by the previous version of the modpack.
+    unchanged: HashSet<PathBuf>,
+    /// Files found identical in the destination, see [`skip_installed`].
+    skipped: usize,
+    skip_reported: bool,
+    /// Files to download which weren't checked against the destination yet,
+    /// hashing them can take a while so it is done by the first `progress`.
+    unchecked: Option<Vec<DownloadableObject>>,
+    /// Overrides removed from the extracted modpack, see [`verify_overrides`].
+    corrupted_overrides: Vec<PathBuf>,
+    journal: Arc<InstallJournal>,
+    reporter: Reporter,
+    retry: Option<RetryPolicy>,
+    config: Option<DownloadConfig>,
+    events: Option<EventSender>,
+    completions: Option<UnboundedSender<CompletedFile>>,
+    hooks: Hooks,
+    /// The before install hooks already ran.
+    hooks_started: bool,
+    /// Set by `set_pack_source`.
+    pack: Option<InstalledFile>,
 }
 
 type Links = Vec<String>;
@@ -65,41 +141,392 @@ impl<T: FileDownloader> RinthDownloader<T> {
     /// mods dir, resourcepacks dir or config dir are missing and can't be
     /// created.
     pub fn new<I: AsRef<Path>, J: AsRef<Path>>(modpack_path: I, destination: J) -> Result<Self> {
-        let modpack = Self::load_pack(modpack_path)?;
-        let (links, names) = Self::get_data(&modpack);
+        Self::open(modpack_path, destination, None)
+    }
+
+    /// Same as `RinthDownloader::new` followed by
+    /// [`set_event_sender`](RinthDownloader::set_event_sender), but the
+    /// extraction of the modpack, which can take a while for big modpacks, is
+    /// reported too with `PhaseChanged(Phase::ExtractingPack)` and
+    /// `PackExtracting` events.
+    ///
+    /// # Errors
+    ///
+    /// Same as `RinthDownloader::new`.
+    pub fn with_event_sender<I: AsRef<Path>, J: AsRef<Path>>(
+        modpack_path: I,
+        destination: J,
+        sender: EventSender,
+    ) -> Result<Self> {
+        Self::open(modpack_path, destination, Some(sender))
+    }
 
+    fn open<I: AsRef<Path>, J: AsRef<Path>>(
+        modpack_path: I,
+        destination: J,
+        events: Option<EventSender>,
+    ) -> Result<Self> {
+        emit(
+            events.as_ref(),
+            UraniumEvent::PhaseChanged(Phase::ExtractingPack),
+        );
+        let (modpack, temp_pack) = Self::load_pack(modpack_path, &|done, total| {
+            emit(
+                events.as_ref(),
+                UraniumEvent::PackExtracting { done, total },
+            );
+        })?;
+        let corrupted_overrides = verify_overrides(temp_pack.path(), OVERRIDES_FOLDER)?;
         let destination = destination.as_ref();
 
         Self::check_mods_dir(destination)?;
         Self::check_rp_dir(destination)?;
         Self::check_config_dir(destination)?;
 
-        let files = links
+        let journal = Arc::new(InstallJournal::open(destination));
+        let files = journal.pending(Self::get_objects(
+            &modpack,
+            destination,
+            DownloadSide::Both,
+        )?);
+
+        let mut rinth = RinthDownloader {
+            gen_downloader: T::new(files.clone()),
+            skipped: 0,
+            skip_reported: false,
+            unchecked: Some(files),
+            corrupted_overrides,
+            modpack,
+            destination: destination.to_path_buf(),
+            temp_pack: Some(temp_pack),
+            side: DownloadSide::Both,
+            diff: None,
+            unchanged: HashSet::new(),
+            journal,
+            reporter: None,
+            retry: None,
+            config: None,
+            events: None,
+            completions: None,
+            hooks: Hooks::default(),
+            hooks_started: false,
+            pack: None,
+        };
+        match events {
+            Some(sender) => rinth.set_event_sender(sender),
+            None => rinth.set_journal_reporter(),
+        }
+        Ok(rinth)
+    }
+
+    fn get_objects(
+        modpack: &RinthModpack,
+        destination: &Path,
+        side: DownloadSide,
+    ) -> Result<Vec<DownloadableObject>> {
+        let (links, names) = Self::get_data(modpack);
+        check_case_collisions(&names)?;
+
+        Ok(links
             .iter()
             .zip(names.iter())
-            .map(|(url, name)| {
+            .zip(modpack.get_files())
+            .filter(|(_, file)| side.includes(file))
+            .map(|((url, name), file)| {
                 DownloadableObject::new(
                     url,
                     name.to_str()
                         .unwrap_or_default(),
                     destination,
-                    None,
+                    Some(HashType::strongest(file.get_hashes())),
                 )
+                .with_size(file.get_size() as u64)
             })
+            .collect())
+    }
+
+    /// Returns the required dependencies of the modpack mods which are not
+    /// in the modpack, see [`DependencyResolver`].
+    ///
+    /// # Errors
+    /// Returns an error if the modpack doesn't declare its Minecraft version
+    /// or loader, or if Modrinth can't be reached.
+    pub async fn missing_dependencies(&self) -> Result<Vec<MissingDependency>> {
+        let hashes = self
+            .modpack
+            .get_files()
+            .iter()
+            .map(|f| f.get_hashes().sha1.clone())
             .collect();
+        DependencyResolver::for_modpack(&self.modpack)?
+            .resolve(hashes)
+            .await
+    }
 
-        Ok(RinthDownloader {
-            gen_downloader: T::new(files),
-            modpack,
+    /// Adds the versions of `dependencies` to the mods to download, those
+    /// without a version are skipped. Returns how many were added.
+    ///
+    /// Must be called before the download starts.
+    ///
+    /// # Errors
+    /// Returns an error if a new file collides with another one.
+    pub fn add_dependencies(&mut self, dependencies: &[MissingDependency]) -> Result<usize> {
+        let mut modpack = self.modpack.clone();
+        let mut added = 0;
+        for version in dependencies
+            .iter()
+            .filter_map(|d| d.version.as_ref())
+            .filter(|v| !v.files.is_empty())
+        {
+            info!("Adding dependency {}", version.name);
+            modpack.add_mod(version.clone().into());
+            added += 1;
+        }
+
+        let files = self.pending_objects(&modpack, self.side)?;
+        self.modpack = modpack;
+        self.set_files(files);
+        Ok(added)
+    }
+
+    /// Only installs the files needed by `side`, all of them by default.
+    ///
+    /// Must be called before the download starts.
+    ///
+    /// # Errors
+    /// Returns an error if two files of the modpack collide.
+    pub fn set_download_side(&mut self, side: DownloadSide) -> Result<()> {
+        let files = self.pending_objects(&self.modpack, side)?;
+        self.side = side;
+        self.set_files(files);
+        Ok(())
+    }
+
+    /// Upgrades an installation of an older version of the modpack, `diff`
+    /// must go from that version to this one, see
+    /// [`modpack_diff`](crate::modpack_diff).
+    ///
+    /// Only the added and changed files are downloaded. Once the download is
+    /// completed the files no longer in the modpack are removed and the
+    /// added and changed overrides are copied.
+    ///
+    /// Must be called before the download starts.
+    ///
+    /// # Errors
+    /// Returns an error if two files of the modpack collide.
+    pub fn apply_diff(&mut self, diff: PackDiff) -> Result<()> {
+        let wanted: HashSet<&Path> = diff
+            .to_download()
+            .map(RinthMdFiles::get_path)
+            .collect();
+        self.unchanged = self
+            .modpack
+            .get_files()
+            .iter()
+            .map(RinthMdFiles::get_path)
+            .filter(|path| !wanted.contains(path))
+            .map(Path::to_path_buf)
+            .collect();
+        self.diff = Some(diff);
+
+        let files = self.pending_objects(&self.modpack, self.side)?;
+        self.set_files(files);
+        Ok(())
+    }
+
+    /// Returns the files of `modpack` for `side` which are not journaled as
+    /// installed yet.
+    fn pending_objects(
+        &self,
+        modpack: &RinthModpack,
+        side: DownloadSide,
+    ) -> Result<Vec<DownloadableObject>> {
+        let files = Self::get_objects(modpack, &self.destination, side)?
+            .into_iter()
+            .filter(|obj| {
+                !self
+                    .unchanged
+                    .contains(Path::new(&obj.name))
+            })
+            .collect();
+        Ok(self.journal.pending(files))
+    }
+
+    fn set_files(&mut self, files: Vec<DownloadableObject>) {
+        self.gen_downloader = self.new_downloader(files.clone());
+        self.unchecked = Some(files);
+        self.skipped = 0;
+    }
+
+    /// Leaves out of the download the files already installed, see
+    /// [`skip_installed`].
+    async fn check_installed(&mut self) -> Result<()> {
+        let Some(files) = self.unchecked.take() else {
+            return Ok(());
+        };
+        let (files, skipped) = tokio::task::spawn_blocking(move || skip_installed(files)).await?;
+        if skipped > 0 {
+            self.gen_downloader = self.new_downloader(files);
+        }
+        self.skipped = skipped;
+        Ok(())
+    }
+
+    /// Returns how many files were already installed and identical, those
+    /// are not downloaded again.
+    ///
+    /// The installed files are checked by the first call to
+    /// [`RinthDownloader::progress`], until then this is 0.
+    #[must_use]
+    pub fn skipped_files(&self) -> usize {
+        self.skipped
+    }
+
+    /// Returns what has been downloaded so far, the files skipped before
+    /// the download included.
+    #[must_use]
+    pub fn report(&self) -> DownloadReport {
+        let mut report = self.gen_downloader.report();
+        report.skipped += self.skipped;
+        report
+            .corrupted_overrides
+            .clone_from(&self.corrupted_overrides);
+        report
+    }
+
+    /// Returns the files of the modpack installed on the download side.
+    fn side_files(&self) -> Vec<&RinthMdFiles> {
+        self.modpack
+            .get_files()
+            .iter()
+            .filter(|f| self.side.includes(f))
+            .collect()
+    }
+
+    /// Makes a `T` with the reporter, retry policy and event sender set.
+    fn new_downloader(&self, files: Vec<DownloadableObject>) -> T {
+        let mut downloader = T::new(files);
+        if let Some(retry) = &self.retry {
+            downloader.set_retry_policy(retry.clone());
+        }
+        if let Some(config) = &self.config {
+            // Already checked by `set_download_config`.
+            if let Err(e) = downloader.set_download_config(config) {
+                warn!("Can't use the download config: {e}");
+            }
+        }
+        if let Some(events) = &self.events {
+            downloader.set_event_sender(events.clone());
+        }
+        if let Some(completions) = &self.completions {
+            downloader.set_completion_sender(completions.clone());
+        }
+        downloader.set_progress_reporter(self.journal_reporter());
+        downloader
+    }
+
+    /// Returns the reporter which journals the installed files and forwards
+    /// the events to the user reporter, `T` sends them to the event sender.
+    fn journal_reporter(&self) -> Arc<dyn ProgressReporter> {
+        let mut inner: Vec<Arc<dyn ProgressReporter>> = Vec::new();
+        if let Some(reporter) = &self.reporter {
+            inner.push(reporter.clone());
+        }
+        Arc::new(JournalReporter {
+            journal: self.journal.clone(),
+            inner,
         })
     }
 
+    fn set_journal_reporter(&mut self) {
+        let reporter = self.journal_reporter();
+        self.gen_downloader
+            .set_progress_reporter(reporter);
+    }
+
+    /// Sets where the [`ProgressEvent`](crate::progress::ProgressEvent)s of
+    /// the mods download are sent.
+    pub fn set_progress_reporter(&mut self, reporter: Arc<dyn ProgressReporter>) {
+        self.reporter = Some(reporter);
+        self.set_journal_reporter();
+    }
+
+    /// Sets where the [`UraniumEvent`]s of the download are sent.
+    pub fn set_event_sender(&mut self, sender: EventSender) {
+        self.gen_downloader
+            .set_event_sender(sender.clone());
+        self.events = Some(sender);
+        self.set_journal_reporter();
+    }
+
+    /// Returns a stream with a [`CompletedFile`] for each file done, see
+    /// [`FileDownloader::completions`].
+    ///
+    /// The stream ends once the downloader is dropped.
+    pub fn completions(&mut self) -> impl Stream<Item = CompletedFile> + Send + Unpin + 'static {
+        let (sender, receiver) = unbounded_channel();
+        self.gen_downloader
+            .set_completion_sender(sender.clone());
+        self.completions = Some(sender);
+        completion_stream(receiver)
+    }
+
+    /// Sets the modpack project and version being installed, recorded as
+    /// [`InstanceMetadata::pack`] so a [`Watcher`](crate::watcher::Watcher)
+    /// can check it for updates. The modpack file doesn't say it.
+    pub fn set_pack_source(&mut self, source: InstalledFile) {
+        self.pack = Some(source);
+    }
+
+    /// Sets the [`Hooks`] run before the first download and after the
+    /// install.
+    pub fn set_hooks(&mut self, hooks: Hooks) {
+        self.hooks = hooks;
+    }
+
+    /// Sets how failed mod downloads are retried.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.gen_downloader
+            .set_retry_policy(policy.clone());
+        self.retry = Some(policy);
+    }
+
+    /// Sets the user agent, headers, proxy, timeouts... of the mods download,
+    /// see [`DownloadConfig`].
+    ///
+    /// # Errors
+    /// Returns an error if the client can't be built with `config`.
+    pub fn set_download_config(&mut self, config: &DownloadConfig) -> Result<()> {
+        self.gen_downloader
+            .set_download_config(config)?;
+        self.config = Some(config.clone());
+        Ok(())
+    }
+
     /// Returns the number of mods to download.
+    ///
+    /// Until the first call to [`RinthDownloader::progress`] the files already
+    /// installed are counted too, see [`RinthDownloader::skipped_files`].
     #[must_use]
     pub fn len(&self) -> usize {
         self.gen_downloader.len()
     }
 
+    /// Returns `true` if the modpack has no mods to download.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.gen_downloader.is_empty()
+    }
+
+    /// Returns how many bytes will be downloaded, as listed in the modpack.
+    #[must_use]
+    pub fn estimated_download_size(&self) -> u64 {
+        self.side_files()
+            .iter()
+            .map(|f| f.get_size() as u64)
+            .sum()
+    }
+
     /// Returns `true` if there are no mods to download.
     #[must_use]
     pub fn finished(&self) -> bool {
@@ -127,7 +554,7 @@ impl<T: FileDownloader> RinthDownloader<T> {
             .gen_downloader
             .requests_left();
 
-        if left % N_THREADS() == 0 {
+        if left.is_multiple_of(N_THREADS()) {
             left / N_THREADS()
         } else {
             left / N_THREADS() + 1
@@ -168,19 +595,20 @@ impl<T: FileDownloader> RinthDownloader<T> {
         (file_links, file_names)
     }
 
-    fn load_pack<I: AsRef<Path>>(path: I) -> Result<RinthModpack> {
-        match unzip_temp_pack(&path) {
-            Err(UraniumError::CantCreateDir("temp_dir")) => {
-                // retry
-                unzip_temp_pack(path)?
-            }
-            Err(e) => Err(e)?,
-            Ok(_) => {}
-        }
+    fn load_pack<I, F>(path: I, progress: &F) -> Result<(RinthModpack, TempDir)>
+    where
+        I: AsRef<Path>,
+        F: Fn(usize, usize) + Sync,
+    {
+        let temp_pack = unzip_temp_pack_with_progress(path, progress)?;
 
-        if let Some(rinth_pack) = load_rinth_pack(&(TEMP_DIR.to_owned() + RINTH_JSON)) {
+        if let Some(rinth_pack) = load_rinth_pack(
+            temp_pack
+                .path()
+                .join(RINTH_JSON),
+        ) {
             info!("Pack loaded {}", rinth_pack.get_name());
-            Ok(rinth_pack)
+            Ok((rinth_pack, temp_pack))
         } else {
             Err(UraniumError::WrongFileFormat)
         }
@@ -192,12 +620,29 @@ impl<T: FileDownloader> RinthDownloader<T> {
     /// # Errors
     /// This function can return an `Err(UraniumError)` like `progress` can.
     pub async fn complete(&mut self) -> Result<()> {
-        let r = self
-            .gen_downloader
-            .complete()
-            .await;
-        remove_temp_pack();
-        r
+        loop {
+            match self.progress().await {
+                Ok(DownloadState::Completed) => return Ok(()),
+                Ok(_) => {}
+                Err(e) => {
+                    self.temp_pack = None;
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// Same as [`RinthDownloader::complete`] but returns a summary of the
+    /// download.
+    ///
+    /// # Errors
+    /// Same as [`RinthDownloader::complete`].
+    pub async fn complete_with_report(&mut self) -> Result<DownloadReport> {
+        let start = Instant::now();
+        self.complete().await?;
+        let mut report = self.report();
+        report.duration = start.elapsed();
+        Ok(report)
     }
 
     /// Make progress.
@@ -207,20 +652,196 @@ impl<T: FileDownloader> RinthDownloader<T> {
     ///
     /// Else return None.
     ///
+    /// If some files were already installed the first call returns
+    /// `DownloadState::Skipped` with how many.
+    ///
     /// # Errors
     /// In case the downloader fails to download or write the chunk this method
     /// will return an error with the corresponding variant.
     pub async fn progress(&mut self) -> Result<DownloadState> {
-        let r = self
+        self.run_before_hooks()
+            .await?;
+        self.check_installed().await?;
+        if !self.skip_reported {
+            self.skip_reported = true;
+            if self.skipped > 0 {
+                info!("{} files already installed", self.skipped);
+                return Ok(DownloadState::Skipped(self.skipped));
+            }
+        }
+        self.emit_started();
+        let mut r = self
             .gen_downloader
             .progress()
             .await;
+        if let Err(e) = self.journal.save() {
+            warn!("Can't write the download journal: {e}");
+        }
+        if let (Ok(DownloadState::Completed), None, Some(pack)) = (&r, &self.diff, &self.temp_pack)
+        {
+            // An upgrade only copies the overrides which changed.
+            r = overrides(&self.destination, pack.path(), OVERRIDES_FOLDER)
+                .map(|()| DownloadState::Completed);
+        }
         if let Ok(DownloadState::Completed) = r {
-            remove_temp_pack();
+            self.journal.clear();
+            self.finish_upgrade();
+            self.record_sources();
+            self.temp_pack = None;
+            r = self
+                .hooks
+                .run_reporting(
+                    HookPhase::AfterInstall,
+                    &self.destination,
+                    self.events.as_ref(),
+                )
+                .await
+                .map(|()| DownloadState::Completed);
         }
+        emit_result(
+            self.events.as_ref(),
+            r.as_ref()
+                .map(|s| matches!(s, DownloadState::Completed)),
+        );
         r
     }
 
+    /// Removes the obsolete files and copies the overrides of the applied
+    /// diff, if any.
+    fn finish_upgrade(&self) {
+        let Some(diff) = &self.diff else {
+            return;
+        };
+
+        let current: HashSet<&Path> = self
+            .modpack
+            .get_files()
+            .iter()
+            .map(RinthMdFiles::get_path)
+            .collect();
+        for path in diff
+            .obsolete_files()
+            .into_iter()
+            .filter(|p| !current.contains(p) && is_relative(p))
+        {
+            let path = self.destination.join(path);
+            if path.exists() {
+                info!("Removing {}", path.display());
+                if let Err(e) = std::fs::remove_file(&path) {
+                    warn!("Can't remove {}: {e}", path.display());
+                }
+            }
+        }
+
+        let Some(pack) = &self.temp_pack else {
+            return;
+        };
+        for path in diff
+            .overrides_added
+            .iter()
+            .chain(&diff.overrides_changed)
+            .filter(|p| is_relative(p))
+        {
+            let from = pack
+                .path()
+                .join(OVERRIDES_FOLDER)
+                .join(path);
+            let to = self.destination.join(path);
+            let copied = to
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|()| std::fs::copy(&from, &to));
+            if let Err(e) = copied {
+                warn!("Can't copy the override {}: {e}", path.display());
+            }
+        }
+    }
+
+    /// Records the Modrinth project and version of each file in the
+    /// instance metadata.
+    fn record_sources(&self) {
+        let result = InstanceMetadata::update(&self.destination, |metadata| {
+            if let Some(pack) = &self.pack {
+                metadata.pack = Some(pack.clone());
+            }
+            if let Some(diff) = &self.diff {
+                for path in diff.obsolete_files() {
+                    metadata.forget_file(path);
+                }
+            }
+            for file in self.side_files() {
+                if let (Some(project), Some(version)) = (file.project_id(), file.version_id()) {
+                    metadata
+                        .record_file(file.get_path(), InstalledFile::modrinth(project, version));
+                }
+            }
+        });
+        if let Err(e) = result {
+            warn!("Can't record the installed files: {e}");
+        }
+    }
+
+    async fn run_before_hooks(&mut self) -> Result<()> {
+        if self.hooks_started {
+            return Ok(());
+        }
+        self.hooks_started = true;
+        let result = self
+            .hooks
+            .run_reporting(
+                HookPhase::BeforeInstall,
+                &self.destination,
+                self.events.as_ref(),
+            )
+            .await;
+        if let Err(e) = &result {
+            emit(self.events.as_ref(), UraniumEvent::Failed(e.to_string()));
+        }
+        result
+    }
+
+    fn emit_started(&self) {
+        if self
+            .gen_downloader
+            .requests_left()
+            == self.gen_downloader.len()
+        {
+            emit(
+                self.events.as_ref(),
+                UraniumEvent::PhaseChanged(Phase::DownloadingMods),
+            );
+        }
+    }
+
+    /// Returns the files of the modpack as a [`DownloadPlan`], to install it
+    /// on a machine without internet.
+    #[must_use]
+    pub fn download_plan(&self) -> DownloadPlan {
+        let files = self
+            .side_files()
+            .iter()
+            .map(|f| PlannedFile {
+                url: f
+                    .get_download_link()
+                    .to_owned(),
+                path: f.get_path().to_owned(),
+                hash: Some(HashType::strongest(f.get_hashes())),
+                size: Some(f.get_size() as u64),
+            })
+            .collect();
+        DownloadPlan { files }
+    }
+
+    /// Checks the installed files against the sha1/sha512 and size of the
+    /// modpack index, returning the ones that don't match.
+    ///
+    /// Nothing is downloaded, a file with problems can be fixed by running the
+    /// downloader again.
+    #[must_use]
+    pub fn verify(&self) -> Vec<VerificationProblem> {
+        verify_pack_files(&self.destination, &self.side_files())
+    }
+
     pub fn get_modpack(&self) -> &RinthModpack {
         &self.modpack
     }
@@ -258,3 +879,243 @@ impl<T: FileDownloader> RinthDownloader<T> {
         Ok(())
     }
 }
+
+/// Splits off the files already in the destination with the expected size
+/// and hash, returning the rest and how many were skipped.
+///
+/// Reinstalling a modpack into an existing instance only downloads what
+/// changed this way.
+fn skip_installed(files: Vec<DownloadableObject>) -> (Vec<DownloadableObject>, usize) {
+    let total = files.len();
+    let pending: Vec<DownloadableObject> = files
+        .into_par_iter()
+        .filter(|obj| !is_installed(obj))
+        .collect();
+    let skipped = total - pending.len();
+    (pending, skipped)
+}
+
+fn is_installed(obj: &DownloadableObject) -> bool {
+    let path = obj.path.join(&obj.name);
+    let Some(hash) = &obj.hash else {
+        return false;
+    };
+    let Ok(metadata) = std::fs::metadata(&path) else {
+        return false;
+    };
+    if obj
+        .size
+        .is_some_and(|size| size != metadata.len())
+    {
+        return false;
+    }
+    std::fs::File::open(&path)
+        .and_then(|f| hash.matches_reader(std::io::BufReader::new(f)))
+        .unwrap_or(false)
+}
+
+/// Returns `true` if `path` stays inside the directory it is joined to.
+fn is_relative(path: &Path) -> bool {
+    path.components()
+        .all(|c| matches!(c, Component::Normal(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use mine_data_structs::rinth::Env;
+
+    use super::*;
+    use crate::downloaders::Downloader;
+    use crate::hashes::{sha1_bytes, sha512_bytes};
+    use crate::test_utils::{rinth_file, rinth_modpack, write_mrpack, FixtureServer};
+    use crate::verifier::ProblemKind;
+
+    fn md_file(path: &str, content: &[u8]) -> RinthMdFiles {
+        serde_json::from_value(serde_json::json!({
+            "path": path,
+            "hashes": { "sha1": sha1_bytes(content), "sha512": sha512_bytes(content) },
+            "downloads": [],
+            "fileSize": content.len()
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn skip_installed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        std::fs::create_dir_all(dir.join("mods")).unwrap();
+        std::fs::write(dir.join("mods/same.jar"), b"sodium").unwrap();
+        std::fs::write(dir.join("mods/old.jar"), b"iris 1").unwrap();
+
+        let object = |name: &str, content: &[u8]| {
+            DownloadableObject::new("", name, dir, Some(HashType::Sha1(sha1_bytes(content))))
+                .with_size(content.len() as u64)
+        };
+        let (pending, skipped) = skip_installed(vec![
+            object("mods/same.jar", b"sodium"),
+            object("mods/old.jar", b"iris 2"),
+            object("mods/new.jar", b"jei"),
+        ]);
+
+        assert_eq!(skipped, 1);
+        assert_eq!(
+            pending
+                .iter()
+                .map(|o| o.name.as_str())
+                .collect::<Vec<_>>(),
+            ["mods/old.jar", "mods/new.jar"]
+        );
+    }
+
+    #[test]
+    fn download_sides() {
+        let shaders = md_file("mods/iris.jar", b"").with_env(Env::new("required", "unsupported"));
+        let unknown = md_file("mods/lithium.jar", b"");
+
+        assert!(DownloadSide::Client.includes(&shaders));
+        assert!(!DownloadSide::Server.includes(&shaders));
+        assert!(DownloadSide::Both.includes(&shaders));
+        assert!(DownloadSide::Server.includes(&unknown));
+    }
+
+    #[test]
+    fn verify_installed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        std::fs::create_dir_all(dir.join("mods")).unwrap();
+        std::fs::write(dir.join("mods/ok.jar"), b"sodium").unwrap();
+        std::fs::write(dir.join("mods/corrupt.jar"), b"iris!!").unwrap();
+        std::fs::write(dir.join("mods/short.jar"), b"lit").unwrap();
+
+        let files = [
+            md_file("mods/ok.jar", b"sodium"),
+            md_file("mods/corrupt.jar", b"iris??"),
+            md_file("mods/short.jar", b"lithium"),
+            md_file("mods/missing.jar", b"jei"),
+        ];
+        let files: Vec<_> = files.iter().collect();
+        let mut problems: Vec<_> = verify_pack_files(dir, &files)
+            .into_iter()
+            .map(|p| {
+                (
+                    p.path
+                        .file_name()
+                        .unwrap()
+                        .to_owned(),
+                    p.kind,
+                )
+            })
+            .collect();
+        problems.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(
+            problems,
+            [
+                ("corrupt.jar".into(), ProblemKind::WrongHash),
+                ("missing.jar".into(), ProblemKind::Missing),
+                (
+                    "short.jar".into(),
+                    ProblemKind::WrongSize {
+                        expected: 7,
+                        found: 3
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn fresh_install_copies_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let destination = dir.path().join("instance");
+        std::fs::create_dir_all(&destination).unwrap();
+
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let url = server.add("/sodium.jar", b"fake jar".to_vec());
+        let modpack = rinth_modpack(vec![rinth_file("mods/sodium.jar", b"fake jar", &url)]);
+        write_mrpack(
+            dir.path().join("pack.mrpack"),
+            &modpack,
+            &[("config/sodium.json", b"{}")],
+        )
+        .unwrap();
+
+        RinthDownloader::<Downloader>::new(dir.path().join("pack.mrpack"), &destination)
+            .unwrap()
+            .complete()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            std::fs::read(destination.join("config/sodium.json")).unwrap(),
+            b"{}"
+        );
+
+        // Installed files are only hashed once the download starts.
+        let mut reinstall =
+            RinthDownloader::<Downloader>::new(dir.path().join("pack.mrpack"), &destination)
+                .unwrap();
+        assert_eq!(reinstall.skipped_files(), 0);
+        assert!(matches!(
+            reinstall.progress().await,
+            Ok(DownloadState::Skipped(1))
+        ));
+        assert!(reinstall.is_empty());
+        reinstall
+            .complete()
+            .await
+            .unwrap();
+        assert_eq!(server.requests(), 1);
+    }
+
+    #[test]
+    fn extraction_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let destination = dir.path().join("instance");
+        std::fs::create_dir_all(&destination).unwrap();
+        write_mrpack(
+            dir.path().join("pack.mrpack"),
+            &rinth_modpack(vec![]),
+            &[
+                ("config/sodium.json", b"{}"),
+                ("config/iris.properties", b"shaders=off"),
+            ],
+        )
+        .unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let rinth = RinthDownloader::<Downloader>::with_event_sender(
+            dir.path().join("pack.mrpack"),
+            &destination,
+            tx,
+        )
+        .unwrap();
+        assert_eq!(
+            rx.try_recv().ok(),
+            Some(UraniumEvent::PhaseChanged(Phase::ExtractingPack))
+        );
+        // With `parallel-unzip` the entries may be reported in any order.
+        let mut extracted = vec![];
+        while let Ok(UraniumEvent::PackExtracting { done, total }) = rx.try_recv() {
+            extracted.push((done, total));
+        }
+        extracted.sort_unstable();
+
+        // The index and the two overrides.
+        assert_eq!(extracted, [(1, 3), (2, 3), (3, 3)]);
+        let extracted = rinth
+            .temp_pack
+            .as_ref()
+            .unwrap()
+            .path()
+            .join(OVERRIDES_FOLDER);
+        assert_eq!(
+            std::fs::read(extracted.join("config/iris.properties")).unwrap(),
+            b"shaders=off"
+        );
+        assert!(rinth.events.is_some());
+    }
+}
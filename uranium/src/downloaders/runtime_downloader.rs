@@ -0,0 +1,472 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+use mine_data_structs::minecraft::{
+    current_platform, Os, Root, RuntimeFile, RuntimeManifest, Runtimes,
+};
+use serde::{Deserialize, Serialize};
+
+use super::gen_downloader::{DownloadState, DownloadableObject, FileDownloader, HashType};
+use super::Downloader;
+use crate::{
+    code_functions::N_THREADS,
+    error::{Result, UraniumError},
+    http::{client_builder, fetch_json},
+    paths,
+    variables::constants::{RUNTIMES_DIR, RUNTIME_MANIFEST},
+};
+
+const RUNTIMES_LIST: &str = "https://launchermeta.mojang.com/v1/products/java-runtime/2ec0cc96c44e5a76b9c8b7c39df7210883d12871/all.json";
+
+/// Indicates the download state of a Java runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[non_exhaustive]
+pub enum RuntimeDownloadState {
+    FetchingManifest,
+    DownloadingFiles,
+    SettingPermissions,
+    Completed,
+}
+
+/// Downloads the Java runtimes distributed by Mojang.
+///
+/// The runtime for the current OS and architecture is installed in
+/// `<runtimes_dir>/<component>`, e.g. `mac-os-arm64` on Apple Silicon or
+/// `windows-x64` on 64 bits Windows.
+///
+/// Like [`MinecraftDownloader`](super::MinecraftDownloader) it works as a
+/// state machine, each call to [`RuntimeDownloader::progress`] makes some
+/// progress so the installation can be shown:
+///
+/// ```no_run
+/// # async fn foo() -> uranium::error::Result<()> {
+/// use uranium::downloaders::{RuntimeDownloadState, RuntimeDownloader};
+///
+/// let mut runtime: RuntimeDownloader = RuntimeDownloader::new("java-runtime-gamma", "runtimes");
+/// while runtime.progress().await? != RuntimeDownloadState::Completed {
+///     println!("{} chunks left", runtime.requests_left());
+/// }
+/// println!("Java installed in {}", runtime.get_java().unwrap().display());
+/// # Ok(())
+/// # }
+/// ```
+pub struct RuntimeDownloader<T: FileDownloader = Downloader> {
+    client: reqwest::Client,
+    component: String,
+    destination: PathBuf,
+    state: RuntimeDownloadState,
+    downloader: Option<T>,
+    manifest: Option<RuntimeManifest>,
+    java: Option<PathBuf>,
+}
+
+impl<T: FileDownloader> RuntimeDownloader<T> {
+    /// `component` is the Mojang name of the runtime, like `jre-legacy` or
+    /// `java-runtime-gamma`.
+    pub fn new<I: AsRef<Path>>(component: &str, runtimes_dir: I) -> Self {
+        RuntimeDownloader {
+            client: client_builder()
+                .user_agent("uranium-rs/runtime-downloader contact: sergious234@gmail.com")
+                .build()
+                .unwrap_or_default(),
+            component: component.to_owned(),
+            destination: runtimes_dir
+                .as_ref()
+                .join(component),
+            state: RuntimeDownloadState::FetchingManifest,
+            downloader: None,
+            manifest: None,
+            java: None,
+        }
+    }
+
+    /// Installs the runtime in the `runtime` folder of the launcher
+    /// `.minecraft`, see [`paths::minecraft_dir`].
+    ///
+    /// Portable or isolated installations should use
+    /// [`RuntimeDownloader::new`] with a folder of the instance instead.
+    ///
+    /// # Errors
+    /// Returns an error if the `.minecraft` directory can't be found.
+    pub fn with_default_dir(component: &str) -> Result<Self> {
+        Ok(Self::new(
+            component,
+            paths::minecraft_dir()?.join(RUNTIMES_DIR),
+        ))
+    }
+
+    /// Downloads the runtime required by `version`.
+    pub fn for_version<I: AsRef<Path>>(version: &Root, runtimes_dir: I) -> Self {
+        Self::new(&version.java_version.component, runtimes_dir)
+    }
+
+    /// Returns where the runtime is installed.
+    pub fn get_destination(&self) -> &Path {
+        &self.destination
+    }
+
+    /// Returns the path of the java executable, available once the state is
+    /// [`RuntimeDownloadState::DownloadingFiles`].
+    pub fn get_java(&self) -> Option<&Path> {
+        self.java.as_deref()
+    }
+
+    /// Downloads the runtime and returns the path of its java executable.
+    ///
+    /// # Errors
+    /// Same as [`RuntimeDownloader::progress`].
+    pub async fn download(&mut self) -> Result<PathBuf> {
+        while self.progress().await? != RuntimeDownloadState::Completed {}
+        self.java
+            .clone()
+            .ok_or(UraniumError::Other)
+    }
+
+    /// Makes progress in the download and returns the new state.
+    ///
+    /// # Errors
+    /// Returns `Err(UraniumError::OtherWithReason)` if Mojang has no runtime
+    /// of the component for this platform, the request error if the
+    /// manifests can't be fetched and an io error if the files can't be
+    /// written.
+    pub async fn progress(&mut self) -> Result<RuntimeDownloadState> {
+        match self.state {
+            RuntimeDownloadState::FetchingManifest => {
+                let manifest = self.fetch_manifest().await?;
+                let files = self.prepare_files(&manifest)?;
+                self.downloader = Some(T::new(files));
+                self.manifest = Some(manifest);
+                self.state = RuntimeDownloadState::DownloadingFiles;
+            }
+
+            RuntimeDownloadState::DownloadingFiles => {
+                if let Some(downloader) = &mut self.downloader {
+                    if !matches!(downloader.progress().await?, DownloadState::Completed) {
+                        return Ok(self.state);
+                    }
+                }
+                self.state = RuntimeDownloadState::SettingPermissions;
+            }
+
+            RuntimeDownloadState::SettingPermissions => {
+                // Links and permissions once every file is in place.
+                if let Some(manifest) = self.manifest.take() {
+                    // Kept so the links and permissions can be repaired
+                    // later, only once the files exist.
+                    std::fs::create_dir_all(&self.destination)?;
+                    std::fs::write(
+                        self.destination
+                            .join(RUNTIME_MANIFEST),
+                        serde_json::to_vec(&manifest).map_err(|_| UraniumError::WrongFileFormat)?,
+                    )?;
+                    create_links(&self.destination, &manifest.files)?;
+                    set_permissions(&self.destination, &manifest.files)?;
+                }
+                self.downloader = None;
+                info!("{} runtime installed", self.component);
+                self.state = RuntimeDownloadState::Completed;
+            }
+
+            RuntimeDownloadState::Completed => {}
+        }
+
+        Ok(self.state)
+    }
+
+    /// Returns the number of requests left to be processed by the
+    /// downloader, in chunks of `N_THREADS()` files like
+    /// [`MinecraftDownloader::requests_left`](super::MinecraftDownloader::requests_left).
+    pub fn requests_left(&self) -> usize {
+        self.downloader
+            .as_ref()
+            .map(|d| {
+                d.requests_left()
+                    .div_ceil(N_THREADS())
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the total number of chunks of the download, see
+    /// [`RuntimeDownloader::requests_left`].
+    pub fn chunks(&self) -> usize {
+        self.downloader
+            .as_ref()
+            .map(|d| d.len().div_ceil(N_THREADS()))
+            .unwrap_or_default()
+    }
+
+    async fn fetch_manifest(&mut self) -> Result<RuntimeManifest> {
+        let runtimes: Runtimes = fetch_json(self.client.get(RUNTIMES_LIST)).await?;
+        let platform = current_platform();
+        let runtime = runtimes
+            .get_current_runtime(&self.component)
+            .ok_or_else(|| {
+                UraniumError::OtherWithReason(format!(
+                    "There is no {} runtime for {platform:?}",
+                    self.component
+                ))
+            })?;
+        info!(
+            "Downloading {} {} for {platform:?}",
+            self.component, runtime.version.name
+        );
+
+        let manifest: RuntimeManifest = fetch_json(
+            self.client
+                .get(&runtime.manifest.url),
+        )
+        .await?;
+        let java = java_path(&manifest, platform.os)
+            .ok_or_else(|| UraniumError::OtherWithReason("The runtime has no java".to_owned()))?;
+        self.java = Some(self.destination.join(java));
+
+        Ok(manifest)
+    }
+
+    /// Creates the directories of the runtime and returns its files.
+    fn prepare_files(&self, manifest: &RuntimeManifest) -> Result<Vec<DownloadableObject>> {
+        let mut files = Vec::new();
+        for (name, file) in &manifest.files {
+            let path = self.destination.join(name);
+            match file {
+                RuntimeFile::Directory => std::fs::create_dir_all(&path)?,
+                RuntimeFile::File { downloads, .. } => {
+                    let (Some(dir), Some(file_name)) = (path.parent(), path.file_name()) else {
+                        continue;
+                    };
+                    std::fs::create_dir_all(dir)?;
+                    files.push(
+                        DownloadableObject::new(
+                            &downloads.raw.url,
+                            &file_name.to_string_lossy(),
+                            dir,
+                            Some(HashType::Sha1(downloads.raw.sha1.clone())),
+                        )
+                        .with_size(downloads.raw.size as u64),
+                    );
+                }
+                RuntimeFile::Link { .. } => {}
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// The runtimes found by [`repair_permissions`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RuntimesRepair {
+    /// Runtimes whose links and permissions were repaired.
+    pub repaired: Vec<PathBuf>,
+    /// Runtimes skipped because their saved manifest is corrupted, they must
+    /// be downloaded again.
+    pub corrupted: Vec<PathBuf>,
+}
+
+/// Sets again the executable bits and recreates the links of the runtimes
+/// installed in `root`, lost when the files are synced with tools like
+/// Dropbox or rsync. Nothing is downloaded.
+///
+/// `root` can be a `.minecraft` or an instance with its own runtimes, only
+/// the runtimes installed by [`RuntimeDownloader`] in its `runtime` folder
+/// are repaired.
+///
+/// # Errors
+/// Returns an io error if the runtimes can't be read or the files can't be
+/// changed and `Err(UraniumError::OtherWithReason)` if there is a directory
+/// where a link should be.
+pub fn repair_permissions<I: AsRef<Path>>(root: I) -> Result<RuntimesRepair> {
+    let runtimes_dir = root
+        .as_ref()
+        .join(RUNTIMES_DIR);
+    let mut repair = RuntimesRepair::default();
+    if !runtimes_dir.is_dir() {
+        return Ok(repair);
+    }
+
+    for entry in std::fs::read_dir(&runtimes_dir)? {
+        let destination = entry?.path();
+        let Ok(manifest) = std::fs::read(destination.join(RUNTIME_MANIFEST)) else {
+            continue;
+        };
+        let Ok(manifest) = serde_json::from_slice::<RuntimeManifest>(&manifest) else {
+            warn!(
+                "{} has a corrupted manifest, skipped",
+                destination.display()
+            );
+            repair
+                .corrupted
+                .push(destination);
+            continue;
+        };
+        create_links(&destination, &manifest.files)?;
+        set_permissions(&destination, &manifest.files)?;
+        info!("{} runtime repaired", destination.display());
+        repair
+            .repaired
+            .push(destination);
+    }
+    repair.repaired.sort();
+    repair.corrupted.sort();
+    Ok(repair)
+}
+
+#[cfg(unix)]
+fn create_links(destination: &Path, files: &HashMap<String, RuntimeFile>) -> Result<()> {
+    for (name, file) in files {
+        if let RuntimeFile::Link { target } = file {
+            let path = destination.join(name);
+            match path.symlink_metadata() {
+                // Never removed, it may hold files which are not ours.
+                Ok(metadata) if metadata.is_dir() => {
+                    return Err(UraniumError::OtherWithReason(format!(
+                        "{} is a directory where a link should be",
+                        path.display()
+                    )));
+                }
+                Ok(_) => std::fs::remove_file(&path)?,
+                Err(_) => {}
+            }
+            std::os::unix::fs::symlink(target, &path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn create_links(_destination: &Path, files: &HashMap<String, RuntimeFile>) -> Result<()> {
+    let links = files
+        .values()
+        .filter(|f| matches!(f, RuntimeFile::Link { .. }))
+        .count();
+    if links > 0 {
+        warn!("{links} runtime links skipped, symlinks are not supported here");
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_permissions(destination: &Path, files: &HashMap<String, RuntimeFile>) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    for (name, file) in files {
+        if let RuntimeFile::File {
+            executable: true, ..
+        } = file
+        {
+            std::fs::set_permissions(
+                destination.join(name),
+                std::fs::Permissions::from_mode(0o755),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_permissions(_destination: &Path, _files: &HashMap<String, RuntimeFile>) -> Result<()> {
+    Ok(())
+}
+
+/// Returns the path of the java executable inside the runtime, `javaw.exe` on
+/// Windows so no console is opened.
+fn java_path(manifest: &RuntimeManifest, os: Os) -> Option<&str> {
+    let executable = match os {
+        Os::Windows => "bin/javaw.exe",
+        _ => "bin/java",
+    };
+    manifest
+        .files
+        .iter()
+        .filter(|(name, file)| {
+            name.ends_with(executable) && matches!(file, RuntimeFile::File { .. })
+        })
+        .map(|(name, _)| name.as_str())
+        .min_by_key(|name| name.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn java_executable() {
+        let manifest: RuntimeManifest = serde_json::from_str(
+            r#"{ "files": {
+                "jre.bundle/Contents/Home": { "type": "directory" },
+                "jre.bundle/Contents/Home/bin/java": {
+                    "type": "file", "executable": true,
+                    "downloads": { "raw": { "sha1": "a", "size": 1, "url": "https://a.b/java" } }
+                },
+                "jre.bundle/Contents/Home/lib/jspawnhelper/bin/java": {
+                    "type": "link", "target": "../../../bin/java"
+                },
+                "jre.bundle/Contents/MacOS/libjli.dylib": {
+                    "type": "link", "target": "../Home/lib/libjli.dylib"
+                }
+            } }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            java_path(&manifest, Os::MacOs),
+            Some("jre.bundle/Contents/Home/bin/java")
+        );
+        assert_eq!(java_path(&manifest, Os::Windows), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn repairs_synced_runtime() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        let runtime = root
+            .join(RUNTIMES_DIR)
+            .join("java-runtime-gamma");
+        std::fs::create_dir_all(runtime.join("bin")).unwrap();
+        std::fs::write(runtime.join("bin/java"), "java").unwrap();
+        std::fs::write(
+            runtime.join(RUNTIME_MANIFEST),
+            r#"{ "files": {
+                "bin": { "type": "directory" },
+                "bin/java": {
+                    "type": "file", "executable": true,
+                    "downloads": { "raw": { "sha1": "a", "size": 4, "url": "https://a.b/java" } }
+                },
+                "java": { "type": "link", "target": "bin/java" }
+            } }"#,
+        )
+        .unwrap();
+
+        let legacy = root
+            .join(RUNTIMES_DIR)
+            .join("jre-legacy");
+        std::fs::create_dir_all(&legacy).unwrap();
+        std::fs::write(legacy.join(RUNTIME_MANIFEST), "{ corrupted").unwrap();
+
+        let repair = repair_permissions(root).unwrap();
+        assert_eq!(repair.repaired, std::slice::from_ref(&runtime));
+        assert_eq!(repair.corrupted, [legacy]);
+        let mode = std::fs::metadata(runtime.join("bin/java"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+        assert_eq!(
+            std::fs::read_link(runtime.join("java")).unwrap(),
+            Path::new("bin/java")
+        );
+
+        // A directory where the link should be is left alone.
+        std::fs::remove_file(runtime.join("java")).unwrap();
+        std::fs::create_dir(runtime.join("java")).unwrap();
+        std::fs::write(runtime.join("java/notes.txt"), "mine").unwrap();
+        assert!(repair_permissions(root).is_err());
+        assert!(runtime
+            .join("java/notes.txt")
+            .exists());
+    }
+}
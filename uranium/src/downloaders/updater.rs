@@ -1,14 +1,26 @@
 use std::{
     collections::HashMap,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
+use futures::{stream, StreamExt};
+use log::{info, warn};
 use mine_data_structs::rinth::RinthVersion;
 use serde::{Deserialize, Serialize};
 
-use crate::error::Result;
-use crate::hashes::rinth_hash;
+use super::gen_downloader::{download_single_file, DownloadableObject, HashType};
+use super::RetryPolicy;
+use crate::code_functions::N_THREADS;
+use crate::error::{Result, UraniumError};
+use crate::hashes::sha1_file_async;
+use crate::http::{default_client, rinth_json_limited};
+use crate::instance::{InstalledFile, InstanceMetadata};
+use crate::modpack_maker::is_mod_file;
+use crate::rate_limit::rinth_limiter;
 use crate::searcher::rinth::{SearchBuilder, SearchType};
+use crate::storage::LocalStorage;
+use crate::variables::constants::{DISABLED_SUFFIX, MOD_EXTENSIONS, OLD_MODS_DIR};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 struct Content {
@@ -19,54 +31,423 @@ struct Content {
 }
 
 impl Content {
-    pub fn new(hashes: Vec<String>, game_versions: Vec<String>) -> Content {
+    pub fn new(hashes: Vec<String>, game_version: &str, loaders: Vec<String>) -> Content {
         Content {
             hashes,
             algorithm: "sha1".to_owned(),
-            loaders: vec!["fabric".to_owned()],
-            game_versions,
+            loaders,
+            game_versions: vec![game_version.to_owned()],
         }
     }
 }
 
-pub async fn update_modpack<I: AsRef<Path>>(minecraft_path: I) -> Result<()> {
+/// Kind of content checked by [`check_updates`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentType {
+    Mod,
+    ResourcePack,
+    ShaderPack,
+}
+
+impl ContentType {
+    pub const ALL: [ContentType; 3] = [
+        ContentType::Mod,
+        ContentType::ResourcePack,
+        ContentType::ShaderPack,
+    ];
+
+    /// Folder of the instance with this content.
+    #[must_use]
+    pub fn folder(self) -> &'static str {
+        match self {
+            ContentType::Mod => "mods",
+            ContentType::ResourcePack => "resourcepacks",
+            ContentType::ShaderPack => "shaderpacks",
+        }
+    }
+
+    /// The content of a Modrinth `project_type`, `None` for the types
+    /// without a folder, like modpacks and datapacks.
+    #[must_use]
+    pub fn from_project_type(project_type: &str) -> Option<ContentType> {
+        match project_type {
+            "mod" => Some(ContentType::Mod),
+            "resourcepack" => Some(ContentType::ResourcePack),
+            "shader" => Some(ContentType::ShaderPack),
+            _ => None,
+        }
+    }
+
+    /// Modrinth loaders of the content for an instance with `loader`.
+    pub(crate) fn loaders(self, loader: &str) -> Vec<String> {
+        let loaders: &[&str] = match self {
+            ContentType::Mod => &[loader],
+            ContentType::ResourcePack => &["minecraft"],
+            ContentType::ShaderPack => &["iris", "optifine", "canvas", "vanilla"],
+        };
+        loaders
+            .iter()
+            .map(|l| (*l).to_owned())
+            .collect()
+    }
+
+    fn extensions(self) -> Vec<String> {
+        let extensions: &[&str] = match self {
+            ContentType::Mod => MOD_EXTENSIONS,
+            ContentType::ResourcePack | ContentType::ShaderPack => &["zip"],
+        };
+        extensions
+            .iter()
+            .map(|e| (*e).to_owned())
+            .collect()
+    }
+}
+
+/// A file with a newer Modrinth version, returned by [`check_updates`].
+#[derive(Debug)]
+pub struct UpdateCandidate {
+    /// File name inside the content folder.
+    pub file: String,
+    pub new_version: RinthVersion,
+}
+
+/// A mod replaced by a newer version.
+#[derive(Debug)]
+pub struct UpdatedMod {
+    /// File name of the replaced jar, now in `mods/.uranium_old/`.
+    pub old_file: String,
+    pub new_version: RinthVersion,
+}
+
+/// A mod which couldn't be updated, the old jar is kept.
+#[derive(Debug)]
+pub struct FailedUpdate {
+    pub file: String,
+    pub error: UraniumError,
+}
+
+/// Result of [`update_modpack`].
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct UpdateReport {
+    pub updated: Vec<UpdatedMod>,
+    /// Mods already up to date or not found in Modrinth.
+    pub skipped: Vec<String>,
+    /// Mods not updated because they are pinned in the instance metadata.
+    pub pinned: Vec<String>,
+    /// Disabled mods (`.jar.disabled`), never updated.
+    pub disabled: Vec<String>,
+    pub failed: Vec<FailedUpdate>,
+}
+
+/// Updates the mods of the instance in `minecraft_path` to their latest
+/// Modrinth version for the instance game version and loader, read from its
+/// [`InstanceMetadata`].
+///
+/// See [`update_modpack_for`].
+///
+/// # Errors
+/// Returns an error if the instance has no game version or loader set, or if
+/// the mods can't be read or Modrinth can't be reached.
+pub async fn update_modpack<I: AsRef<Path>>(minecraft_path: I) -> Result<UpdateReport> {
+    let metadata = InstanceMetadata::load(&minecraft_path)?;
+    let (Some(game_version), Some(loader)) = (metadata.game_version, metadata.loader) else {
+        return Err(UraniumError::OtherWithReason(
+            "The instance has no game version or loader set".to_owned(),
+        ));
+    };
+
+    update_modpack_for(minecraft_path, &game_version, &loader).await
+}
+
+/// Updates the mods of `minecraft_path` to their latest Modrinth version for
+/// `game_version` and `loader`.
+///
+/// The new jars are downloaded into `mods/` and the replaced ones are moved
+/// into `mods/.uranium_old/`. A mod whose download fails keeps its old jar and
+/// is listed in [`UpdateReport::failed`]. Mods pinned in the instance
+/// [`InstanceMetadata`] and disabled mods are never replaced.
+///
+/// # Errors
+/// Returns an error if the mods can't be read or Modrinth can't be reached.
+pub async fn update_modpack_for<I: AsRef<Path>>(
+    minecraft_path: I,
+    game_version: &str,
+    loader: &str,
+) -> Result<UpdateReport> {
     let mods_path = PathBuf::from(minecraft_path.as_ref()).join("mods/");
-    let mods_names = std::fs::read_dir(&mods_path)?;
-    let mods_hashes = mods_names
-        .map(|f| rinth_hash(f.unwrap().path().as_path()))
+    let extensions = ContentType::Mod.extensions();
+
+    let mut mods = Vec::new();
+    let mut disabled = Vec::new();
+    for entry in std::fs::read_dir(&mods_path)? {
+        let path = entry?.path();
+        let file = file_name(&path);
+        if path.is_file() && file.ends_with(DISABLED_SUFFIX) {
+            disabled.push(file);
+        } else if is_mod_file(&path, &extensions) {
+            let hash = sha1_file_async(&path).await?;
+            mods.push((path, hash));
+        }
+    }
+
+    let hashes = mods
+        .iter()
+        .map(|(_, hash)| hash.clone())
         .collect::<Vec<String>>();
+    let updates = get_updates(hashes, game_version, ContentType::Mod.loaders(loader)).await?;
+    let mut metadata = InstanceMetadata::load(&minecraft_path)?;
+
+    let mut report = UpdateReport {
+        disabled,
+        ..Default::default()
+    };
+    let mut outdated = Vec::new();
+    for (path, hash) in mods {
+        let file = file_name(&path);
+        let project_id = updates
+            .get(&hash)
+            .map(|v| v.project_id.as_str());
+        if metadata.is_pinned(&file, project_id) {
+            report.pinned.push(file);
+            continue;
+        }
+        match newer_version(&hash, &updates) {
+            Some(version) => outdated.push((path, version.clone())),
+            None => report.skipped.push(file),
+        }
+    }
 
-    let updates = get_updates(&mods_hashes).await?;
+    let client = default_client()?;
+    let results: Vec<_> = stream::iter(outdated)
+        .map(|(path, version)| {
+            let client = &client;
+            let mods_path = &mods_path;
+            async move {
+                let result = replace_mod(client, mods_path, &path, &version).await;
+                (file_name(&path), version, result)
+            }
+        })
+        .buffer_unordered(N_THREADS())
+        .collect()
+        .await;
 
-    for hash in mods_hashes {
-        match updates.get(&hash) {
-            Some(v) if v.get_hashes().sha1 != hash => {
-                println!("Update available for {}", v.name);
+    for (old_file, new_version, result) in results {
+        match result {
+            Ok(()) => {
+                info!("{old_file} updated to {}", new_version.version_number);
+                metadata.forget_file(Path::new("mods").join(&old_file));
+                if let Some(file) = new_version.get_primary_file() {
+                    metadata.record_file(
+                        Path::new("mods").join(&file.filename),
+                        InstalledFile::from_version(&new_version),
+                    );
+                }
+                report
+                    .updated
+                    .push(UpdatedMod {
+                        old_file,
+                        new_version,
+                    });
             }
-            Some(v) => {
-                println!("{} is up to date!", v.name);
+            Err(error) => {
+                warn!("Can't update {old_file}: {error}");
+                report
+                    .failed
+                    .push(FailedUpdate {
+                        file: old_file,
+                        error,
+                    });
             }
-            None => {}
         }
     }
+    metadata.save(&minecraft_path)?;
+
+    Ok(report)
+}
+
+/// Looks for newer Modrinth versions of the mods, resource packs and shader
+/// packs of the instance in `minecraft_path`, without downloading anything.
+///
+/// Files are matched by their sha1, so only content from Modrinth is found.
+/// Pinned files and content types without a folder in the instance are
+/// skipped.
+///
+/// ```no_run
+/// # async fn foo() -> uranium::error::Result<()> {
+/// use uranium::downloaders::{check_updates, ContentType};
+///
+/// let candidates = check_updates("path/to/instance", "1.21", "fabric").await?;
+/// for candidate in &candidates[&ContentType::ShaderPack] {
+///     println!("{} -> {}", candidate.file, candidate.new_version.version_number);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+/// Returns an error if a folder can't be read or Modrinth can't be reached.
+pub async fn check_updates<I: AsRef<Path>>(
+    minecraft_path: I,
+    game_version: &str,
+    loader: &str,
+) -> Result<HashMap<ContentType, Vec<UpdateCandidate>>> {
+    let metadata = InstanceMetadata::load(&minecraft_path)?;
+    let mut candidates = HashMap::new();
+
+    for content_type in ContentType::ALL {
+        let folder = minecraft_path
+            .as_ref()
+            .join(content_type.folder());
+        let mut files = Vec::new();
+        if folder.is_dir() {
+            let extensions = content_type.extensions();
+            for entry in std::fs::read_dir(&folder)? {
+                let path = entry?.path();
+                if is_mod_file(&path, &extensions) {
+                    let hash = sha1_file_async(&path).await?;
+                    files.push((file_name(&path), hash));
+                }
+            }
+        }
+
+        let mut found = Vec::new();
+        if !files.is_empty() {
+            let hashes = files
+                .iter()
+                .map(|(_, hash)| hash.clone())
+                .collect();
+            let updates = get_updates(hashes, game_version, content_type.loaders(loader)).await?;
+            for (file, hash) in files {
+                let Some(version) = newer_version(&hash, &updates) else {
+                    continue;
+                };
+                if !metadata.is_pinned(&file, Some(&version.project_id)) {
+                    found.push(UpdateCandidate {
+                        file,
+                        new_version: version.clone(),
+                    });
+                }
+            }
+        }
+        candidates.insert(content_type, found);
+    }
+
+    Ok(candidates)
+}
+
+/// Returns the latest version of the file with `hash` if it is not the file
+/// itself.
+fn newer_version<'a>(
+    hash: &str,
+    updates: &'a HashMap<String, RinthVersion>,
+) -> Option<&'a RinthVersion> {
+    updates.get(hash).filter(|v| {
+        v.get_primary_file()
+            .is_some_and(|f| f.hashes.sha1 != hash)
+    })
+}
+
+/// Moves `old` into the old mods dir and downloads `version`, `old` is put
+/// back if the download fails.
+async fn replace_mod(
+    client: &reqwest::Client,
+    mods_path: &Path,
+    old: &Path,
+    version: &RinthVersion,
+) -> Result<()> {
+    let file = version
+        .get_primary_file()
+        .ok_or(UraniumError::OtherWithReason(format!(
+            "{} has no files",
+            version.name
+        )))?;
+
+    let archive = mods_path.join(OLD_MODS_DIR);
+    std::fs::create_dir_all(&archive)?;
+    let archived = archive.join(
+        old.file_name()
+            .unwrap_or_default(),
+    );
+    std::fs::rename(old, &archived)?;
+
+    let obj = DownloadableObject::new(
+        &file.url,
+        &file.filename,
+        mods_path,
+        Some(HashType::Sha1(file.hashes.sha1.clone())),
+    )
+    .with_size(file.size as u64);
+
+    let retry = RetryPolicy::default();
+    if let Err(e) = download_single_file(client, &obj, Arc::new(LocalStorage), &retry).await {
+        std::fs::rename(&archived, old)?;
+        return Err(e);
+    }
 
     Ok(())
-    // TODO update!
 }
 
-async fn get_updates(mods_hashes: &[String]) -> Result<HashMap<String, RinthVersion>> {
-    let client = reqwest::Client::new();
-    let post_content = Content::new(mods_hashes.to_owned(), vec!["1.19.2".to_owned()]);
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+async fn get_updates(
+    hashes: Vec<String>,
+    game_version: &str,
+    loaders: Vec<String>,
+) -> Result<HashMap<String, RinthVersion>> {
+    let client = default_client()?;
+    let post_content = Content::new(hashes, game_version, loaders);
     let url = SearchBuilder::new()
-        .search_type(SearchType::VersionFile { hash: "".into() })
+        .search_type(SearchType::LatestVersions)
         .build_url();
-    let response = client
-        .post(&url)
-        .json(&post_content)
-        .send()
+    let response = rinth_limiter()
+        .send(
+            client
+                .post(&url)
+                .json(&post_content),
+        )
         .await?;
 
-    Ok(response
-        .json::<HashMap<String, RinthVersion>>()
-        .await?)
+    rinth_json_limited::<HashMap<String, RinthVersion>>(response).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{rinth_version, rinth_version_file};
+
+    fn version(sha1: &str) -> RinthVersion {
+        let mut file = rinth_version_file("c.jar", b"c", "https://a.b/c.jar");
+        file.hashes.sha1 = sha1.to_owned();
+        RinthVersion {
+            files: vec![file],
+            ..rinth_version("a")
+        }
+    }
+
+    #[test]
+    fn only_newer_versions() {
+        let updates = HashMap::from([
+            ("old".to_owned(), version("new")),
+            ("latest".to_owned(), version("latest")),
+        ]);
+
+        assert!(newer_version("old", &updates).is_some());
+        assert!(newer_version("latest", &updates).is_none());
+        assert!(newer_version("unknown", &updates).is_none());
+    }
+
+    #[test]
+    fn content_loaders() {
+        assert_eq!(ContentType::Mod.loaders("quilt"), ["quilt"]);
+        assert_eq!(ContentType::ResourcePack.loaders("quilt"), ["minecraft"]);
+        assert!(ContentType::ShaderPack
+            .loaders("quilt")
+            .contains(&"iris".to_owned()));
+    }
 }
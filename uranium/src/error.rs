@@ -1,52 +1,263 @@
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
 use reqwest::header::InvalidHeaderValue;
-use thiserror::Error;
+use reqwest::StatusCode;
 use tokio::task::JoinError;
 
+use crate::collisions::CaseCollision;
 use crate::downloaders::DownloadableObject;
+use crate::messages::Message;
 
 pub type Result<T> = std::result::Result<T, UraniumError>;
 
-#[derive(Debug, Error)]
+/// The errors of Uranium.
+///
+/// The `Display` text is the English text of [`UraniumError::message`], so
+/// both never disagree.
+#[derive(Debug)]
+#[non_exhaustive]
 pub enum UraniumError {
-    #[error("Wrong file format")]
     WrongFileFormat,
-    #[error("Wrong modpack format")]
     WrongModpackFormat,
-    #[error("File `{0}` not found")]
     FileNotFound(String),
-    #[error("Can't create dir: `{0}`")]
     CantCreateDir(&'static str),
-    #[error("Error while writing the files: `{0}`")]
     WriteError(std::io::Error),
-    #[error("IO Error: `{0}`")]
     IOError(std::io::Error),
-    #[error("Error downloading files")]
     DownloadError,
-    #[error("Error making the requests: `{0}`")]
     RequestError(reqwest::Error),
-    #[error("File hash doesnt match")]
+    HttpStatus {
+        url: String,
+        status: StatusCode,
+    },
+    RateLimited {
+        retry_after: Option<Duration>,
+    },
+    NotFound(String),
+    /// An API answered with an error payload, e.g. Modrinth `invalid_input`.
+    ApiError {
+        provider: &'static str,
+        code: String,
+        description: String,
+    },
     FileNotMatch(DownloadableObject),
-    #[error("Files hashes doesnt match")]
     FilesDontMatch(Vec<DownloadableObject>),
-    #[error("Zip Error: `{0}`")]
     ZipError(zip::result::ZipError),
-    #[error("Can't compress the modpack")]
+    /// A response or file is not the JSON expected.
+    JsonError(serde_json::Error),
     CantCompress,
-    #[error("Can't remove temp JSON file")]
     CantRemoveJSON,
-    #[error("Can't read mods dir")]
     CantReadModsDir,
-    #[error("Error in async task")]
+    CaseCollisions(Vec<CaseCollision>),
+    MissingApiKey(&'static str),
+    InvalidApiKey(&'static str),
+    HookFailed {
+        hook: String,
+        reason: String,
+    },
+    InvalidConfig {
+        key: String,
+        reason: String,
+    },
+    ResponseTooLarge(u64),
     AsyncRuntimeError,
-    #[error("Error")]
     Other,
-    #[error("Error: `{0}`")]
     OtherWithReason(String),
 }
 
+impl UraniumError {
+    /// Makes the error for a response with a non success `status`, 404 and
+    /// 429 get their own variants.
+    pub fn from_status(url: &str, status: StatusCode, retry_after: Option<Duration>) -> Self {
+        match status {
+            StatusCode::NOT_FOUND => UraniumError::NotFound(url.to_owned()),
+            StatusCode::TOO_MANY_REQUESTS => UraniumError::RateLimited { retry_after },
+            status => UraniumError::HttpStatus {
+                url: url.to_owned(),
+                status,
+            },
+        }
+    }
+
+    /// Returns the stable key of the error, see [`messages`](crate::messages).
+    #[must_use]
+    pub fn message_key(&self) -> &'static str {
+        self.message().key
+    }
+
+    /// Returns the error as a [`Message`] that frontends can translate.
+    #[must_use]
+    pub fn message(&self) -> Message {
+        match self {
+            UraniumError::WrongFileFormat => Message::new("error.wrong_file_format"),
+            UraniumError::WrongModpackFormat => Message::new("error.wrong_modpack_format"),
+            UraniumError::FileNotFound(path) => {
+                Message::new("error.file_not_found").arg("path", path)
+            }
+            UraniumError::CantCreateDir(dir) => {
+                Message::new("error.cant_create_dir").arg("dir", dir)
+            }
+            UraniumError::WriteError(e) => Message::new("error.write_error").arg("error", e),
+            UraniumError::IOError(e) => Message::new("error.io_error").arg("error", e),
+            UraniumError::DownloadError => Message::new("error.download_error"),
+            UraniumError::RequestError(e) => Message::new("error.request_error").arg("error", e),
+            UraniumError::HttpStatus { url, status } => Message::new("error.http_status")
+                .arg("url", url)
+                .arg("status", status),
+            UraniumError::RateLimited {
+                retry_after: Some(after),
+            } => Message::new("error.rate_limited").arg("seconds", after.as_secs()),
+            UraniumError::RateLimited { retry_after: None } => {
+                Message::new("error.rate_limited_later")
+            }
+            UraniumError::NotFound(url) => Message::new("error.not_found").arg("url", url),
+            UraniumError::ApiError {
+                provider,
+                code,
+                description,
+            } => Message::new("error.api_error")
+                .arg("provider", provider)
+                .arg("code", code)
+                .arg("description", description),
+            UraniumError::FileNotMatch(obj) => {
+                Message::new("error.file_not_match").arg("file", &obj.name)
+            }
+            UraniumError::FilesDontMatch(objs) => {
+                Message::new("error.files_dont_match").arg("count", objs.len())
+            }
+            UraniumError::ZipError(e) => Message::new("error.zip_error").arg("error", e),
+            UraniumError::JsonError(e) => Message::new("error.json_error").arg("error", e),
+            UraniumError::CantCompress => Message::new("error.cant_compress"),
+            UraniumError::CantRemoveJSON => Message::new("error.cant_remove_json"),
+            UraniumError::CantReadModsDir => Message::new("error.cant_read_mods_dir"),
+            UraniumError::CaseCollisions(collisions) => {
+                Message::new("error.case_collisions").arg("count", collisions.len())
+            }
+            UraniumError::MissingApiKey(provider) => {
+                Message::new("error.missing_api_key").arg("provider", provider)
+            }
+            UraniumError::InvalidApiKey(provider) => {
+                Message::new("error.invalid_api_key").arg("provider", provider)
+            }
+            UraniumError::HookFailed { hook, reason } => Message::new("error.hook_failed")
+                .arg("hook", hook)
+                .arg("reason", reason),
+            UraniumError::InvalidConfig { key, reason } => Message::new("error.invalid_config")
+                .arg("key", key)
+                .arg("reason", reason),
+            UraniumError::ResponseTooLarge(bytes) => {
+                Message::new("error.response_too_large").arg("bytes", bytes)
+            }
+            UraniumError::AsyncRuntimeError => Message::new("error.async_runtime_error"),
+            UraniumError::Other => Message::new("error.other"),
+            UraniumError::OtherWithReason(reason) => {
+                Message::new("error.other_with_reason").arg("reason", reason)
+            }
+        }
+    }
+
+    /// Returns what the user can do to fix the error, so frontends can show an
+    /// actionable message without matching the `Display` output.
+    ///
+    /// Returns `None` if there is nothing the user can do.
+    pub fn recovery_hint(&self) -> Option<String> {
+        type Ioe = std::io::ErrorKind;
+        let hint = match self {
+            UraniumError::WriteError(e) | UraniumError::IOError(e) => match e.kind() {
+                Ioe::PermissionDenied => "Check the permissions of the destination folder",
+                Ioe::NotFound => "Check that the path exists",
+                Ioe::StorageFull => "Free some disk space and try again",
+                _ => return None,
+            },
+            UraniumError::RequestError(e) => match e.status().map(|s| s.as_u16()) {
+                Some(429) => "Too many requests, wait a few minutes and try again",
+                Some(401 | 403) => "Check your API key (e.g. CURSE_API_KEY)",
+                Some(404) => "The requested file or version doesn't exist anymore",
+                Some(500..=599) => "The server is having problems, try again later",
+                _ if e.is_timeout() || e.is_connect() => {
+                    "Check your internet connection and try again"
+                }
+                _ => return None,
+            },
+            UraniumError::HttpStatus { status, .. } => match status.as_u16() {
+                401 | 403 => "Check your API key (e.g. CURSE_API_KEY)",
+                500..=599 => "The server is having problems, try again later",
+                _ => return None,
+            },
+            UraniumError::RateLimited {
+                retry_after: Some(after),
+            } => {
+                return Some(format!(
+                    "Too many requests, wait {} seconds and try again",
+                    after.as_secs()
+                ));
+            }
+            UraniumError::RateLimited { retry_after: None } => {
+                "Too many requests, wait a few minutes and try again"
+            }
+            UraniumError::NotFound(_) => "The requested file or version doesn't exist anymore",
+            UraniumError::ApiError { code, .. } if code == "unauthorized" => {
+                "Check your API token and its scopes"
+            }
+            UraniumError::MissingApiKey("curse") | UraniumError::InvalidApiKey("curse") => {
+                "Set CURSE_API_KEY in the keyring or the env, keys are made in \
+                 https://console.curseforge.com"
+            }
+            UraniumError::InvalidConfig { key, .. } => {
+                return Some(format!("Fix `{key}` in the config file or in its env var"));
+            }
+            UraniumError::DownloadError => "Check your internet connection and try again",
+            UraniumError::FileNotMatch(obj) => {
+                return Some(format!(
+                    "{} is corrupted, run the download again to repair it",
+                    obj.name
+                ));
+            }
+            UraniumError::FilesDontMatch(objs) => {
+                return Some(format!(
+                    "{} files are corrupted, run the download again to repair them",
+                    objs.len()
+                ));
+            }
+            UraniumError::WrongFileFormat | UraniumError::WrongModpackFormat => {
+                "The file is not a valid modpack, download it again"
+            }
+            UraniumError::FileNotFound(_) => "Check that the path exists",
+            UraniumError::CantCreateDir(_) => "Check the permissions of the destination folder",
+            UraniumError::CantReadModsDir => "Check that the instance has a mods folder",
+            UraniumError::CaseCollisions(_) => "Rename the files so they don't differ only in case",
+            UraniumError::ResponseTooLarge(_) => {
+                "The server sent an unexpected response, try again later or raise the limit with \
+                 set_max_metadata_size"
+            }
+            _ => return None,
+        };
+
+        Some(hint.to_owned())
+    }
+}
+
+impl Display for UraniumError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message().to_english())
+    }
+}
+
+impl std::error::Error for UraniumError {}
+
 impl From<reqwest::Error> for UraniumError {
     fn from(value: reqwest::Error) -> Self {
-        UraniumError::RequestError(value)
+        // Errors made by `error_for_status`.
+        match value.status() {
+            Some(status) => {
+                let url = value
+                    .url()
+                    .map(ToString::to_string)
+                    .unwrap_or_default();
+                UraniumError::from_status(&url, status, None)
+            }
+            None => UraniumError::RequestError(value),
+        }
     }
 }
 
@@ -78,3 +289,43 @@ impl From<JoinError> for UraniumError {
         Self::AsyncRuntimeError
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovery_hints() {
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert_eq!(
+            UraniumError::from(denied)
+                .recovery_hint()
+                .as_deref(),
+            Some("Check the permissions of the destination folder")
+        );
+        assert!(UraniumError::Other
+            .recovery_hint()
+            .is_none());
+    }
+
+    #[test]
+    fn status_errors() {
+        let url = "https://api.modrinth.com/v2/project/a";
+        assert!(matches!(
+            UraniumError::from_status(url, StatusCode::NOT_FOUND, None),
+            UraniumError::NotFound(u) if u == url
+        ));
+        let retry_after = Some(Duration::from_secs(3));
+        assert!(matches!(
+            UraniumError::from_status(url, StatusCode::TOO_MANY_REQUESTS, retry_after),
+            UraniumError::RateLimited { retry_after: Some(d) } if d.as_secs() == 3
+        ));
+        assert!(matches!(
+            UraniumError::from_status(url, StatusCode::BAD_GATEWAY, None),
+            UraniumError::HttpStatus {
+                status: StatusCode::BAD_GATEWAY,
+                ..
+            }
+        ));
+    }
+}
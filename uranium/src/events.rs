@@ -0,0 +1,241 @@
+//! Crate wide events for GUIs and other embedders.
+//!
+//! Every long running component (`Downloader`, `RinthDownloader`,
+//! `CurseDownloader` and `MinecraftDownloader`) accepts an [`EventSender`]
+//! through `set_event_sender`, then all of them report to the same channel:
+//!
+//! ```no_run
+//! # async fn foo() -> uranium::error::Result<()> {
+//! use uranium::downloaders::{Downloader, MinecraftDownloader};
+//! use uranium::events::UraniumEvent;
+//!
+//! let (tx, mut rx) = tokio::sync::mpsc::channel(256);
+//! tokio::spawn(async move {
+//!     while let Some(event) = rx.recv().await {
+//!         if let UraniumEvent::Failed(reason) = event {
+//!             eprintln!("{reason}");
+//!         }
+//!     }
+//! });
+//!
+//! let mut minecraft = MinecraftDownloader::<Downloader>::init(".minecraft", "1.20.1").await?;
+//! minecraft.set_event_sender(tx);
+//! minecraft.start().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Events are sent with `try_send`, if the channel is full the event is
+//! dropped instead of slowing down the download.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use tokio::sync::mpsc::Sender;
+
+use crate::error::UraniumError;
+use crate::messages::Message;
+use crate::progress::{ProgressEvent, ProgressReporter};
+
+/// Channel where [`UraniumEvent`]s are sent.
+pub type EventSender = Sender<UraniumEvent>;
+
+/// Steps of the long running operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Phase {
+    ExtractingPack,
+    GettingSources,
+    DownloadingIndexes,
+    DownloadingAssets,
+    DownloadingLibraries,
+    CheckingFiles,
+    DownloadingMods,
+    RunningHooks,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UraniumEvent {
+    /// A downloader started with `files` files, `bytes` is the total size if
+    /// it is known.
+    DownloadStarted {
+        files: usize,
+        bytes: Option<u64>,
+    },
+    /// A file was downloaded and verified.
+    FileCompleted {
+        path: PathBuf,
+    },
+    PhaseChanged(Phase),
+    /// `done` of the `total` files of the modpack were extracted.
+    PackExtracting {
+        done: usize,
+        total: usize,
+    },
+    /// Something went wrong but the operation goes on.
+    Warning(String),
+    /// A request to `url` failed, it will be sent again after `delay`.
+    RetryScheduled {
+        url: String,
+        attempt: u32,
+        delay: Duration,
+    },
+    /// A newer version of the modpack `instance` was installed from, see
+    /// [`Watcher`](crate::watcher::Watcher).
+    PackUpdateAvailable {
+        instance: PathBuf,
+        project_id: String,
+        version_id: String,
+    },
+    /// A newer version of the mod `file` of `instance`, relative to the
+    /// instance.
+    ModUpdateAvailable {
+        instance: PathBuf,
+        file: String,
+        project_id: String,
+        version_id: String,
+    },
+    /// A line printed by an install hook, see [`hooks`](crate::hooks).
+    HookOutput {
+        hook: String,
+        line: String,
+    },
+    Completed,
+    Failed(String),
+}
+
+impl Phase {
+    /// Returns the phase as a [`Message`] that frontends can translate.
+    #[must_use]
+    pub fn message(&self) -> Message {
+        Message::new(match self {
+            Phase::ExtractingPack => "phase.extracting_pack",
+            Phase::GettingSources => "phase.getting_sources",
+            Phase::DownloadingIndexes => "phase.downloading_indexes",
+            Phase::DownloadingAssets => "phase.downloading_assets",
+            Phase::DownloadingLibraries => "phase.downloading_libraries",
+            Phase::CheckingFiles => "phase.checking_files",
+            Phase::DownloadingMods => "phase.downloading_mods",
+            Phase::RunningHooks => "phase.running_hooks",
+        })
+    }
+}
+
+impl UraniumEvent {
+    /// Returns the stable key of the event, see [`messages`](crate::messages).
+    #[must_use]
+    pub fn message_key(&self) -> &'static str {
+        self.message().key
+    }
+
+    /// Returns the event as a [`Message`] that frontends can translate, a
+    /// `PhaseChanged` is the message of its phase.
+    #[must_use]
+    pub fn message(&self) -> Message {
+        match self {
+            UraniumEvent::DownloadStarted { files, bytes } => {
+                let message = Message::new("event.download_started").arg("files", files);
+                match bytes {
+                    Some(bytes) => message.arg("bytes", bytes),
+                    None => message,
+                }
+            }
+            UraniumEvent::FileCompleted { path } => {
+                Message::new("event.file_completed").arg("path", path.display())
+            }
+            UraniumEvent::PhaseChanged(phase) => phase.message(),
+            UraniumEvent::PackExtracting { done, total } => Message::new("event.pack_extracting")
+                .arg("done", done)
+                .arg("total", total),
+            UraniumEvent::Warning(message) => Message::new("event.warning").arg("message", message),
+            UraniumEvent::RetryScheduled {
+                url,
+                attempt,
+                delay,
+            } => Message::new("event.retry_scheduled")
+                .arg("url", url)
+                .arg("attempt", attempt)
+                .arg("seconds", delay.as_secs()),
+            UraniumEvent::PackUpdateAvailable {
+                instance,
+                project_id,
+                version_id,
+            } => Message::new("event.pack_update_available")
+                .arg("instance", instance.display())
+                .arg("project_id", project_id)
+                .arg("version_id", version_id),
+            UraniumEvent::ModUpdateAvailable {
+                instance,
+                file,
+                project_id,
+                version_id,
+            } => Message::new("event.mod_update_available")
+                .arg("instance", instance.display())
+                .arg("file", file)
+                .arg("project_id", project_id)
+                .arg("version_id", version_id),
+            UraniumEvent::HookOutput { hook, line } => Message::new("event.hook_output")
+                .arg("hook", hook)
+                .arg("line", line),
+            UraniumEvent::Completed => Message::new("event.completed"),
+            UraniumEvent::Failed(reason) => Message::new("event.failed").arg("reason", reason),
+        }
+    }
+}
+
+pub(crate) fn emit(sender: Option<&EventSender>, event: UraniumEvent) {
+    if let Some(sender) = sender {
+        // A full or closed channel must not stop the download.
+        let _ = sender.try_send(event);
+    }
+}
+
+/// Sends `Completed` if `result` is `Ok(true)`, or `Failed` if it is an error.
+pub(crate) fn emit_result(sender: Option<&EventSender>, result: Result<bool, &UraniumError>) {
+    match result {
+        Ok(true) => emit(sender, UraniumEvent::Completed),
+        Ok(false) => {}
+        Err(e) => emit(sender, UraniumEvent::Failed(e.to_string())),
+    }
+}
+
+/// Lets an [`EventSender`] be used where a [`ProgressReporter`] is expected,
+/// byte counts are not forwarded.
+impl ProgressReporter for EventSender {
+    fn report(&self, event: ProgressEvent) {
+        let event = match event {
+            ProgressEvent::FileVerified { path } => UraniumEvent::FileCompleted { path },
+            ProgressEvent::FileFailed { path, reason } => {
+                UraniumEvent::Warning(format!("{}: {reason}", path.display()))
+            }
+            ProgressEvent::FileStarted { .. } | ProgressEvent::BytesDownloaded { .. } => return,
+        };
+        emit(Some(self), event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_events_are_translated() {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        let path = PathBuf::from("mods/sodium.jar");
+
+        tx.report(ProgressEvent::BytesDownloaded {
+            path: path.clone(),
+            bytes: 10,
+        });
+        tx.report(ProgressEvent::FileVerified { path: path.clone() });
+        // The channel is full, this one is dropped.
+        tx.report(ProgressEvent::FileVerified { path: path.clone() });
+
+        assert_eq!(
+            rx.try_recv().ok(),
+            Some(UraniumEvent::FileCompleted { path })
+        );
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -1,45 +1,372 @@
-use std::{fs, io::Read, path::Path};
+//! Hashing helpers used to verify the downloaded files.
+//!
+//! All the functions return the hash as a lowercase hex string, which is the
+//! format used by Mojang and Modrinth.
+//!
+//! The `*_reader` and `*_file` functions hash the content in chunks of
+//! [`CHUNK_SIZE`] bytes as it is read, so big files (shader packs, modpacks...)
+//! are never fully loaded into memory. The `*_file_async` variants do the
+//! same without blocking the async runtime.
 
-use hex::ToHex;
+use std::{
+    fs,
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+use md5::Md5;
 use murmurhash32::murmurhash2;
 use sha1::{Digest, Sha1};
+use sha2::Sha512;
+use tokio::io::AsyncReadExt;
+
+/// Size of the chunks read while hashing.
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+fn hash_reader<D: Digest, R: Read>(reader: R) -> io::Result<String> {
+    let mut reader = BufReader::with_capacity(CHUNK_SIZE, reader);
+    let mut hasher = D::new();
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => hasher.update(&buffer[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+async fn hash_file_async<D: Digest, P: AsRef<Path>>(path: P) -> io::Result<String> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = D::new();
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        match file.read(&mut buffer).await? {
+            0 => break,
+            n => hasher.update(&buffer[..n]),
+        }
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Hashes content which arrives in chunks, e.g. while it is downloaded.
+pub(crate) enum ChunkHasher {
+    Sha1(Sha1),
+    Sha512(Sha512),
+    Md5(Md5),
+}
+
+impl ChunkHasher {
+    pub(crate) fn update(&mut self, chunk: &[u8]) {
+        match self {
+            ChunkHasher::Sha1(hasher) => hasher.update(chunk),
+            ChunkHasher::Sha512(hasher) => hasher.update(chunk),
+            ChunkHasher::Md5(hasher) => hasher.update(chunk),
+        }
+    }
+
+    /// Hashes everything read from `reader` and returns how many bytes were
+    /// read.
+    ///
+    /// # Errors
+    /// Returns the `io::Error` if reading fails.
+    pub(crate) fn update_reader<R: Read>(&mut self, mut reader: R) -> io::Result<u64> {
+        let mut buffer = vec![0; CHUNK_SIZE];
+        let mut len = 0;
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => return Ok(len),
+                Ok(n) => {
+                    self.update(&buffer[..n]);
+                    len += n as u64;
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Returns the hash of all the chunks as a lowercase hex string.
+    pub(crate) fn finalize(self) -> String {
+        match self {
+            ChunkHasher::Sha1(hasher) => hex::encode(hasher.finalize()),
+            ChunkHasher::Sha512(hasher) => hex::encode(hasher.finalize()),
+            ChunkHasher::Md5(hasher) => hex::encode(hasher.finalize()),
+        }
+    }
+}
+
+/// Returns the sha1 of `bytes`.
+pub fn sha1_bytes(bytes: &[u8]) -> String {
+    hex::encode(Sha1::digest(bytes))
+}
+
+/// Returns the sha512 of `bytes`.
+pub fn sha512_bytes(bytes: &[u8]) -> String {
+    hex::encode(Sha512::digest(bytes))
+}
+
+/// Returns the md5 of `bytes`, only some CDNs still use it.
+pub fn md5_bytes(bytes: &[u8]) -> String {
+    hex::encode(Md5::digest(bytes))
+}
+
+/// Returns the sha1 of everything read from `reader`.
+///
+/// # Errors
+/// Returns the `io::Error` if reading fails.
+pub fn sha1_reader<R: Read>(reader: R) -> io::Result<String> {
+    hash_reader::<Sha1, R>(reader)
+}
+
+/// Returns the sha512 of everything read from `reader`.
+///
+/// # Errors
+/// Returns the `io::Error` if reading fails.
+pub fn sha512_reader<R: Read>(reader: R) -> io::Result<String> {
+    hash_reader::<Sha512, R>(reader)
+}
+
+/// Returns the md5 of everything read from `reader`.
+///
+/// # Errors
+/// Returns the `io::Error` if reading fails.
+pub fn md5_reader<R: Read>(reader: R) -> io::Result<String> {
+    hash_reader::<Md5, R>(reader)
+}
 
-// TODO: 
-// Remove unwraps
+/// Returns the sha1 of the file in `path`.
+///
+/// # Errors
+/// Returns the `io::Error` if the file can't be opened or read.
+pub fn sha1_file<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    sha1_reader(fs::File::open(path)?)
+}
+
+/// Returns the sha512 of the file in `path`.
+///
+/// # Errors
+/// Returns the `io::Error` if the file can't be opened or read.
+pub fn sha512_file<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    sha512_reader(fs::File::open(path)?)
+}
+
+/// Async version of [`sha1_file`].
+///
+/// # Errors
+/// Returns the `io::Error` if the file can't be opened or read.
+pub async fn sha1_file_async<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    hash_file_async::<Sha1, P>(path).await
+}
+
+/// Async version of [`sha512_file`].
+///
+/// # Errors
+/// Returns the `io::Error` if the file can't be opened or read.
+pub async fn sha512_file_async<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    hash_file_async::<Sha512, P>(path).await
+}
+
+/// Returns the md5 of the file in `path`.
+///
+/// # Errors
+/// Returns the `io::Error` if the file can't be opened or read.
+pub fn md5_file<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    md5_reader(fs::File::open(path)?)
+}
 
-fn get_sha1_from_file<I: AsRef<Path>>(file_path: I) -> String {
-    let mut hasher = Sha1::new();
-    let mut file = fs::File::open(&file_path).unwrap();
+/// Hash used by Modrinth to identify the files.
+///
+/// # Errors
+/// Returns the `io::Error` if the file can't be opened or read.
+pub(crate) fn rinth_hash(path: &Path) -> io::Result<String> {
+    sha1_file(path)
+}
+
+/// Fingerprint used by CurseForge to identify the files: the murmur2 of the
+/// content without whitespace (tabs, new lines and spaces).
+///
+/// The file is read in chunks twice, murmur2 needs the length of the content
+/// before hashing it.
+///
+/// # Errors
+/// Returns the `io::Error` if the file can't be read.
+pub fn curse_fingerprint<P: AsRef<Path>>(path: P) -> io::Result<u32> {
+    let mut len: u32 = 0;
+    for_each_chunk(fs::File::open(&path)?, |chunk| {
+        let kept = chunk
+            .iter()
+            .filter(|&&b| !is_curse_whitespace(b))
+            .count();
+        len = len.wrapping_add(kept as u32);
+    })?;
 
-    let metadata = fs::metadata(&file_path).unwrap();
+    let mut hasher = Murmur2::new(len);
+    for_each_chunk(fs::File::open(&path)?, |chunk| {
+        chunk
+            .iter()
+            .filter(|&&b| !is_curse_whitespace(b))
+            .for_each(|&b| hasher.push(b));
+    })?;
+    Ok(hasher.finish())
+}
 
-    // let mut buffer = Vec::with_capacity(
-    //     metadata
-    //         .len()
-    //         .try_into()
-    //         .unwrap_or_default(),
-    // ); //vec![0; metadata.len() as usize];
+/// Returns the [`curse_fingerprint`] of `content`.
+#[must_use]
+pub fn curse_fingerprint_bytes(content: &[u8]) -> u32 {
+    let buffer: Vec<u8> = content
+        .iter()
+        .copied()
+        .filter(|&b| !is_curse_whitespace(b))
+        .collect();
+    murmurhash2(&buffer)
+}
 
-    let mut buffer = vec![0; metadata.len() as usize];
-    buffer.clear();
+fn is_curse_whitespace(byte: u8) -> bool {
+    matches!(byte, 9 | 10 | 13 | 32)
+}
 
-    let _ = file.read_to_end(&mut buffer);
+fn for_each_chunk<R: Read>(reader: R, mut f: impl FnMut(&[u8])) -> io::Result<()> {
+    let mut reader = BufReader::with_capacity(CHUNK_SIZE, reader);
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(n) => f(&buffer[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
 
-    hasher.update(buffer);
-    let temp = hasher.finalize().to_vec();
-    temp.encode_hex::<String>()
+/// Incremental version of `murmurhash2`, fed byte by byte.
+struct Murmur2 {
+    h: u32,
+    block: [u8; 4],
+    filled: usize,
 }
 
-pub fn rinth_hash(path: &Path) -> String {
-    get_sha1_from_file(path)
+impl Murmur2 {
+    /// Same seed as `murmurhash32::murmurhash2`.
+    const SEED: u32 = 3_242_157_231;
+    const M: u32 = 0x5bd1_e995;
+
+    /// `len` is how many bytes will be pushed.
+    fn new(len: u32) -> Self {
+        Murmur2 {
+            h: Self::SEED ^ len,
+            block: [0; 4],
+            filled: 0,
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.block[self.filled] = byte;
+        self.filled += 1;
+        if self.filled == 4 {
+            let mut k = u32::from_le_bytes(self.block);
+            k = k.wrapping_mul(Self::M);
+            k ^= k >> 24;
+            k = k.wrapping_mul(Self::M);
+            self.h = self.h.wrapping_mul(Self::M) ^ k;
+            self.filled = 0;
+        }
+    }
+
+    fn finish(self) -> u32 {
+        let mut h = self.h;
+        if self.filled > 0 {
+            for (i, &byte) in self.block[..self.filled]
+                .iter()
+                .enumerate()
+            {
+                h ^= u32::from(byte) << (8 * i);
+            }
+            h = h.wrapping_mul(Self::M);
+        }
+        h ^= h >> 13;
+        h = h.wrapping_mul(Self::M);
+        h ^ (h >> 15)
+    }
 }
 
-// TODO! Remove curse
-pub fn _curse_hash(path: &String) -> String {
-    let mut file = std::fs::File::open(path).unwrap();
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)
-        .unwrap();
-    buffer.retain(|&x| (x != 9 && x != 10 && x != 13 && x != 32));
-    murmurhash2(&buffer).to_string()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_hashes() {
+        assert_eq!(
+            sha1_bytes(b"sodium"),
+            "6f07dc1d0a287f925d7672e6884d85ceb3f339ed"
+        );
+        assert_eq!(sha1_reader(&b"sodium"[..]).unwrap(), sha1_bytes(b"sodium"));
+        assert_eq!(
+            sha512_reader(&b"sodium"[..]).unwrap(),
+            sha512_bytes(b"sodium")
+        );
+        assert!(sha512_bytes(b"sodium").starts_with("961b4af816284b41547aaff17bef5eae"));
+        assert!(sha1_file("this/file/does/not/exist").is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fingerprint");
+        fs::write(&path, b"a b\tc\r\nd").unwrap();
+        let with_spaces = curse_fingerprint(&path).unwrap();
+        fs::write(&path, b"abcd").unwrap();
+        assert_eq!(with_spaces, curse_fingerprint(&path).unwrap());
+        assert_eq!(with_spaces, curse_fingerprint_bytes(b"abcd"));
+    }
+
+    #[tokio::test]
+    async fn chunked_hashes() {
+        // Bigger than a chunk and not a multiple of it.
+        let content: Vec<u8> = (0..CHUNK_SIZE * 3 + 17)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chunked");
+        fs::write(&path, &content).unwrap();
+
+        assert_eq!(sha1_file(&path).unwrap(), sha1_bytes(&content));
+        assert_eq!(
+            curse_fingerprint(&path).unwrap(),
+            curse_fingerprint_bytes(&content)
+        );
+        // Every length of the last block.
+        for len in 0..8 {
+            let mut hasher = Murmur2::new(len as u32);
+            content[..len]
+                .iter()
+                .for_each(|&b| hasher.push(b));
+            assert_eq!(hasher.finish(), murmurhash2(&content[..len]));
+        }
+        assert_eq!(
+            sha1_file_async(&path)
+                .await
+                .unwrap(),
+            sha1_bytes(&content)
+        );
+        assert_eq!(
+            sha512_file_async(&path)
+                .await
+                .unwrap(),
+            sha512_bytes(&content)
+        );
+
+        let mut hasher = ChunkHasher::Md5(Md5::new());
+        for chunk in content.chunks(1000) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hasher.finalize(), md5_bytes(&content));
+
+        let mut hasher = ChunkHasher::Sha1(Sha1::new());
+        hasher.update(&content[..10]);
+        let read = hasher
+            .update_reader(&content[10..])
+            .unwrap();
+        assert_eq!(read, content.len() as u64 - 10);
+        assert_eq!(hasher.finalize(), sha1_bytes(&content));
+    }
 }
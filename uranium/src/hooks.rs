@@ -0,0 +1,600 @@
+//! Steps run before and after installing a modpack or creating an instance.
+//!
+//! Pack authors sometimes need steps the modpack formats can't express, like
+//! accepting the EULA, generating configs or making scripts executable.
+//! Hooks are opt-in, they only run when set on a `RinthDownloader`,
+//! `CurseDownloader` or [`InstanceManager`](crate::instance::InstanceManager):
+//!
+//! ```no_run
+//! # async fn foo() -> uranium::error::Result<()> {
+//! use uranium::downloaders::{Downloader, RinthDownloader};
+//! use uranium::hooks::Hooks;
+//!
+//! let hooks = Hooks::new()
+//!     .after_install("accept eula", |ctx| {
+//!         std::fs::write(ctx.instance.join("eula.txt"), "eula=true\n")?;
+//!         ctx.output("EULA accepted");
+//!         Ok(())
+//!     })
+//!     .pack_hooks(true);
+//!
+//! let mut rinth = RinthDownloader::<Downloader>::new("pack.mrpack", "instance")?;
+//! rinth.set_hooks(hooks);
+//! rinth.complete().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! With [`Hooks::pack_hooks`] the modpack can also declare its own steps in
+//! an `uranium_hooks.json` inside its overrides, run after the install once
+//! the overrides are in place:
+//!
+//! ```json
+//! {
+//!   "after_install": [
+//!     { "write": { "path": "eula.txt", "content": "eula=true\n" } },
+//!     { "chmod": { "path": "start.sh", "mode": 493 } },
+//!     { "run": { "command": ["sh", "setup.sh"] } }
+//!   ]
+//! }
+//! ```
+//!
+//! The paths of these steps must stay inside the instance, also through
+//! symlinks, and `run` steps are refused unless the [`CommandPolicy`] allows
+//! commands. The output of the commands is sent as
+//! [`UraniumEvent::HookOutput`].
+
+use std::{
+    fmt,
+    path::{Component, Path, PathBuf},
+    process::Stdio,
+    sync::Arc,
+    time::Duration,
+};
+
+use log::info;
+use serde::Deserialize;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::Command,
+};
+
+use crate::{
+    error::{Result, UraniumError},
+    events::{emit, EventSender, Phase, UraniumEvent},
+    variables::constants::HOOKS_FILE,
+};
+
+/// When a hook runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HookPhase {
+    /// Before the first file is downloaded, the instance may not exist yet.
+    BeforeInstall,
+    /// After every file and override is in place.
+    AfterInstall,
+}
+
+/// What a hook gets when it runs.
+pub struct HookContext<'a> {
+    pub phase: HookPhase,
+    /// The instance being installed.
+    pub instance: &'a Path,
+    hook: &'a str,
+    events: Option<&'a EventSender>,
+}
+
+impl HookContext<'_> {
+    /// Sends `line` as output of the hook.
+    pub fn output(&self, line: &str) {
+        emit(
+            self.events,
+            UraniumEvent::HookOutput {
+                hook: self.hook.to_owned(),
+                line: line.to_owned(),
+            },
+        );
+    }
+}
+
+type HookFn = Arc<dyn Fn(&HookContext) -> Result<()> + Send + Sync>;
+
+#[derive(Clone)]
+enum Step {
+    Closure(HookFn),
+    Pack(PackStep),
+}
+
+#[derive(Clone)]
+struct Hook {
+    name: String,
+    phase: HookPhase,
+    step: Step,
+}
+
+/// A step of the `uranium_hooks.json` of a modpack.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PackStep {
+    Write { path: PathBuf, content: String },
+    Chmod { path: PathBuf, mode: u32 },
+    Run { command: Vec<String> },
+}
+
+#[derive(Debug, Deserialize)]
+struct PackHooks {
+    #[serde(default)]
+    after_install: Vec<PackStep>,
+}
+
+/// Whether and how the `run` steps of the modpack run their command.
+///
+/// By default they can't run commands, and if allowed the commands run with
+/// the instance as working directory, without the env vars of the process
+/// (but `PATH`) and are killed after 5 minutes.
+///
+/// This is not a sandbox: the commands run as the current user and can read
+/// and write any file, use the network and start other processes. Only allow
+/// commands for modpacks you trust.
+#[derive(Debug, Clone)]
+pub struct CommandPolicy {
+    allow_commands: bool,
+    inherit_env: bool,
+    timeout: Duration,
+}
+
+impl Default for CommandPolicy {
+    fn default() -> Self {
+        CommandPolicy {
+            allow_commands: false,
+            inherit_env: false,
+            timeout: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+impl CommandPolicy {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lets the `run` steps of the modpack run their command.
+    #[must_use]
+    pub fn allow_commands(mut self, allow: bool) -> Self {
+        self.allow_commands = allow;
+        self
+    }
+
+    /// Passes every env var of the process to the commands.
+    #[must_use]
+    pub fn inherit_env(mut self, inherit: bool) -> Self {
+        self.inherit_env = inherit;
+        self
+    }
+
+    /// Time a command can run before being killed.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// The hooks of an install, see the [module docs](self).
+#[derive(Clone, Default)]
+pub struct Hooks {
+    hooks: Vec<Hook>,
+    pack_hooks: bool,
+    commands: CommandPolicy,
+    events: Option<EventSender>,
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field(
+                "hooks",
+                &self
+                    .hooks
+                    .iter()
+                    .map(|h| (&h.name, h.phase))
+                    .collect::<Vec<_>>(),
+            )
+            .field("pack_hooks", &self.pack_hooks)
+            .field("commands", &self.commands)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Hooks {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `hook` before the install, an error aborts it.
+    #[must_use]
+    pub fn before_install<F>(self, name: &str, hook: F) -> Self
+    where
+        F: Fn(&HookContext) -> Result<()> + Send + Sync + 'static,
+    {
+        self.add(
+            name,
+            HookPhase::BeforeInstall,
+            Step::Closure(Arc::new(hook)),
+        )
+    }
+
+    /// Runs `hook` after the install, an error makes the install fail.
+    #[must_use]
+    pub fn after_install<F>(self, name: &str, hook: F) -> Self
+    where
+        F: Fn(&HookContext) -> Result<()> + Send + Sync + 'static,
+    {
+        self.add(name, HookPhase::AfterInstall, Step::Closure(Arc::new(hook)))
+    }
+
+    /// Also runs the steps in the `uranium_hooks.json` of the modpack,
+    /// disabled by default.
+    #[must_use]
+    pub fn pack_hooks(mut self, enabled: bool) -> Self {
+        self.pack_hooks = enabled;
+        self
+    }
+
+    /// Whether the hooks of the modpack can run commands, see
+    /// [`CommandPolicy`].
+    #[must_use]
+    pub fn command_policy(mut self, policy: CommandPolicy) -> Self {
+        self.commands = policy;
+        self
+    }
+
+    /// Where the [`UraniumEvent`]s of the hooks are sent, the downloaders use
+    /// their own sender if this one is not set.
+    #[must_use]
+    pub fn event_sender(mut self, sender: EventSender) -> Self {
+        self.events = Some(sender);
+        self
+    }
+
+    /// Runs the hooks of `phase` on `instance`, in the order they were added
+    /// and then the ones of the modpack.
+    ///
+    /// # Errors
+    /// Returns `Err(UraniumError::HookFailed)` with the first hook that
+    /// fails, the next ones are not run.
+    pub async fn run<I: AsRef<Path>>(&self, phase: HookPhase, instance: I) -> Result<()> {
+        self.run_reporting(phase, instance.as_ref(), None)
+            .await
+    }
+
+    /// Like [`Hooks::run`], using `events` if the hooks have no sender.
+    pub(crate) async fn run_reporting(
+        &self,
+        phase: HookPhase,
+        instance: &Path,
+        events: Option<&EventSender>,
+    ) -> Result<()> {
+        let events = self
+            .events
+            .as_ref()
+            .or(events);
+        let mut hooks: Vec<Hook> = self
+            .hooks
+            .iter()
+            .filter(|h| h.phase == phase)
+            .cloned()
+            .collect();
+        if self.pack_hooks && phase == HookPhase::AfterInstall {
+            hooks.extend(read_pack_hooks(instance)?);
+        }
+        if hooks.is_empty() {
+            return Ok(());
+        }
+
+        emit(events, UraniumEvent::PhaseChanged(Phase::RunningHooks));
+        for hook in &hooks {
+            info!("Running the hook {}", hook.name);
+            let result = match &hook.step {
+                Step::Closure(f) => f(&HookContext {
+                    phase,
+                    instance,
+                    hook: &hook.name,
+                    events,
+                }),
+                Step::Pack(step) => {
+                    self.run_step(step, instance, &hook.name, events)
+                        .await
+                }
+            };
+            result.map_err(|e| UraniumError::HookFailed {
+                hook: hook.name.clone(),
+                reason: e.to_string(),
+            })?;
+        }
+        Ok(())
+    }
+
+    fn add(mut self, name: &str, phase: HookPhase, step: Step) -> Self {
+        self.hooks.push(Hook {
+            name: name.to_owned(),
+            phase,
+            step,
+        });
+        self
+    }
+
+    async fn run_step(
+        &self,
+        step: &PackStep,
+        instance: &Path,
+        name: &str,
+        events: Option<&EventSender>,
+    ) -> Result<()> {
+        match step {
+            PackStep::Write { path, content } => {
+                let path = inside(instance, path)?;
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(path, content)?;
+            }
+            PackStep::Chmod { path, mode } => {
+                let path = inside(instance, path)?;
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    // No setuid nor setgid.
+                    let mode = *mode & !0o6000;
+                    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+                }
+                #[cfg(not(unix))]
+                let _ = (path, mode);
+            }
+            PackStep::Run { command } => {
+                self.run_command(command, instance, name, events)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn run_command(
+        &self,
+        command: &[String],
+        instance: &Path,
+        name: &str,
+        events: Option<&EventSender>,
+    ) -> Result<()> {
+        if !self.commands.allow_commands {
+            return Err(UraniumError::OtherWithReason(
+                "Commands are not allowed, see CommandPolicy::allow_commands".to_owned(),
+            ));
+        }
+        let Some((program, args)) = command.split_first() else {
+            return Err(UraniumError::OtherWithReason("Empty command".to_owned()));
+        };
+
+        let mut process = Command::new(program);
+        process
+            .args(args)
+            .current_dir(instance)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if !self.commands.inherit_env {
+            process.env_clear();
+            if let Some(path) = std::env::var_os("PATH") {
+                process.env("PATH", path);
+            }
+        }
+
+        let mut child = process.spawn()?;
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let finished = async {
+            tokio::join!(forward(stdout, name, events), forward(stderr, name, events));
+            child.wait().await
+        };
+        let status = tokio::time::timeout(self.commands.timeout, finished)
+            .await
+            .map_err(|_| {
+                UraniumError::OtherWithReason(format!(
+                    "Killed after {}s",
+                    self.commands
+                        .timeout
+                        .as_secs()
+                ))
+            })??;
+
+        if !status.success() {
+            return Err(UraniumError::OtherWithReason(format!(
+                "{command:?} failed: {status}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Sends each line of `output` as output of the hook `name`.
+async fn forward<R: AsyncRead + Unpin>(
+    output: Option<R>,
+    name: &str,
+    events: Option<&EventSender>,
+) {
+    let Some(output) = output else {
+        return;
+    };
+    let mut lines = BufReader::new(output).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        emit(
+            events,
+            UraniumEvent::HookOutput {
+                hook: name.to_owned(),
+                line,
+            },
+        );
+    }
+}
+
+/// Reads the steps of the `uranium_hooks.json` of the instance, if any.
+fn read_pack_hooks(instance: &Path) -> Result<Vec<Hook>> {
+    let path = instance.join(HOOKS_FILE);
+    if !path.is_file() {
+        return Ok(vec![]);
+    }
+    let file = std::fs::File::open(&path)?;
+    let pack: PackHooks = serde_json::from_reader(std::io::BufReader::new(file)).map_err(|e| {
+        UraniumError::HookFailed {
+            hook: HOOKS_FILE.to_owned(),
+            reason: e.to_string(),
+        }
+    })?;
+
+    Ok(pack
+        .after_install
+        .into_iter()
+        .enumerate()
+        .map(|(i, step)| Hook {
+            name: format!("{HOOKS_FILE} #{}", i + 1),
+            phase: HookPhase::AfterInstall,
+            step: Step::Pack(step),
+        })
+        .collect())
+}
+
+/// Returns `path` joined to `instance` if it doesn't leave it.
+///
+/// A symlink in the instance could point outside of it, so the deepest part
+/// of the path which exists is resolved too. A dangling symlink can't be
+/// resolved and is refused.
+fn inside(instance: &Path, path: &Path) -> Result<PathBuf> {
+    let outside =
+        || UraniumError::OtherWithReason(format!("{} is outside the instance", path.display()));
+    if !path
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        return Err(outside());
+    }
+
+    let joined = instance.join(path);
+    let root = instance.canonicalize()?;
+    let existing = joined
+        .ancestors()
+        .find(|p| p.symlink_metadata().is_ok())
+        .unwrap_or(instance);
+    match existing.canonicalize() {
+        Ok(resolved) if resolved.starts_with(&root) => Ok(joined),
+        _ => Err(outside()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn closures_and_pack_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        std::fs::write(
+            dir.join(HOOKS_FILE),
+            r#"{"after_install": [
+                {"write": {"path": "config/a.toml", "content": "a = 1"}},
+                {"run": {"command": ["sh", "-c", "echo done"]}}
+            ]}"#,
+        )
+        .unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let hooks = Hooks::new()
+            .after_install("eula", |ctx| {
+                std::fs::write(ctx.instance.join("eula.txt"), "eula=true")?;
+                Ok(())
+            })
+            .pack_hooks(true)
+            .event_sender(tx);
+
+        // Commands are not allowed by default.
+        let denied = hooks
+            .run(HookPhase::AfterInstall, &dir)
+            .await;
+        assert!(matches!(
+            denied,
+            Err(UraniumError::HookFailed { hook, .. }) if hook.ends_with("#2")
+        ));
+        assert!(dir.join("eula.txt").exists());
+        assert!(dir
+            .join("config/a.toml")
+            .exists());
+
+        #[cfg(unix)]
+        {
+            hooks
+                .command_policy(CommandPolicy::new().allow_commands(true))
+                .run(HookPhase::AfterInstall, &dir)
+                .await
+                .unwrap();
+            let mut output = vec![];
+            while let Ok(event) = rx.try_recv() {
+                if let UraniumEvent::HookOutput { line, .. } = event {
+                    output.push(line);
+                }
+            }
+            assert_eq!(output, ["done"]);
+        }
+
+        assert!(inside(dir, Path::new("../outside.txt")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn pack_hooks_stay_inside() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = tempfile::tempdir().unwrap();
+        let instance = dir.path().join("instance");
+        let outside = dir.path().join("outside");
+        std::fs::create_dir_all(instance.join("config")).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        symlink(&outside, instance.join("escape")).unwrap();
+        symlink(outside.join("gone.sh"), instance.join("dangling.sh")).unwrap();
+        std::fs::write(instance.join("start.sh"), "").unwrap();
+
+        assert!(inside(&instance, Path::new("config/new/a.toml")).is_ok());
+        assert!(inside(&instance, Path::new("escape/a.toml")).is_err());
+        assert!(inside(&instance, Path::new("escape/new/a.toml")).is_err());
+        assert!(inside(&instance, Path::new("dangling.sh")).is_err());
+
+        std::fs::write(
+            instance.join(HOOKS_FILE),
+            r#"{"after_install": [
+                {"chmod": {"path": "start.sh", "mode": 3565}},
+                {"write": {"path": "escape/a.toml", "content": "a = 1"}}
+            ]}"#,
+        )
+        .unwrap();
+        let result = Hooks::new()
+            .pack_hooks(true)
+            .run(HookPhase::AfterInstall, &instance)
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(UraniumError::HookFailed { hook, .. }) if hook.ends_with("#2")
+        ));
+        assert!(!outside
+            .join("a.toml")
+            .exists());
+        // 0o6755 without setuid nor setgid.
+        let mode = std::fs::metadata(instance.join("start.sh"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o7777, 0o755);
+    }
+}
@@ -0,0 +1,286 @@
+//! Size limited requests for metadata (version manifests, Modrinth and Curse
+//! responses...).
+//!
+//! Metadata responses are small, a response of hundreds of MB means something
+//! is wrong with the endpoint so the body is read in chunks and the request is
+//! aborted once it goes over [`MAX_METADATA_SIZE()`].
+//!
+//! JSON nesting is already limited by `serde_json` (128 levels) so a deeply
+//! nested body fails to parse instead of overflowing the stack.
+
+use std::time::Duration;
+
+use bytes::{Bytes, BytesMut};
+use mine_data_structs::rinth::RinthError;
+use reqwest::{Client, ClientBuilder, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+
+use crate::code_functions::MAX_METADATA_SIZE;
+use crate::downloaders::DownloadConfig;
+use crate::error::{Result, UraniumError};
+use crate::rate_limit::rinth_limiter;
+use crate::variables::constants::PROXY;
+
+/// Returns a client builder using the proxy set with
+/// [`set_proxy`](crate::set_proxy).
+pub(crate) fn client_builder() -> ClientBuilder {
+    let proxy = PROXY
+        .read()
+        .ok()
+        .and_then(|p| p.clone())
+        .and_then(|url| reqwest::Proxy::all(url).ok());
+    match proxy {
+        Some(proxy) => ClientBuilder::new().proxy(proxy),
+        None => ClientBuilder::new(),
+    }
+}
+
+/// Returns a client with the default [`DownloadConfig`].
+pub(crate) fn default_client() -> Result<Client> {
+    DownloadConfig::default().client()
+}
+
+/// Returns `response` if its status is a success, else the matching
+/// [`UraniumError::from_status`] error.
+pub(crate) fn check_status(response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs);
+    Err(UraniumError::from_status(
+        response.url().as_str(),
+        status,
+        retry_after,
+    ))
+}
+
+/// Like [`check_status`] but a Modrinth error payload in the body of a 4xx
+/// response fails with [`UraniumError::ApiError`].
+///
+/// 404 and 429 keep their own variants and 5xx are still
+/// [`UraniumError::HttpStatus`], callers rely on them to retry.
+pub(crate) async fn check_rinth_status(response: Response) -> Result<Response> {
+    let status = response.status();
+    if !status.is_client_error()
+        || status == StatusCode::NOT_FOUND
+        || status == StatusCode::TOO_MANY_REQUESTS
+    {
+        return check_status(response);
+    }
+
+    let url = response.url().to_string();
+    let body = read_body(response, MAX_METADATA_SIZE()).await?;
+    Err(match serde_json::from_slice::<RinthError>(&body) {
+        Ok(error) => UraniumError::ApiError {
+            provider: "modrinth",
+            code: error.error,
+            description: error.description,
+        },
+        Err(_) => UraniumError::from_status(&url, status, None),
+    })
+}
+
+/// Reads the body of `response`, failing with
+/// [`UraniumError::ResponseTooLarge`] if it is bigger than `limit` bytes.
+///
+/// Fails with [`check_status`] if the response is not a success.
+pub(crate) async fn read_limited(response: Response, limit: u64) -> Result<Bytes> {
+    read_body(check_status(response)?, limit).await
+}
+
+async fn read_body(mut response: Response, limit: u64) -> Result<Bytes> {
+    if response
+        .content_length()
+        .is_some_and(|len| len > limit)
+    {
+        return Err(UraniumError::ResponseTooLarge(limit));
+    }
+
+    let mut body = BytesMut::new();
+    while let Some(chunk) = response.chunk().await? {
+        if (body.len() + chunk.len()) as u64 > limit {
+            return Err(UraniumError::ResponseTooLarge(limit));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body.freeze())
+}
+
+/// Parses the body of `response` as JSON, reading at most
+/// [`MAX_METADATA_SIZE()`] bytes.
+pub(crate) async fn json_limited<T: DeserializeOwned>(response: Response) -> Result<T> {
+    let body = read_limited(response, MAX_METADATA_SIZE()).await?;
+    serde_json::from_slice(&body).map_err(UraniumError::JsonError)
+}
+
+/// Sends `request` and parses the response as JSON, see [`json_limited`].
+pub(crate) async fn fetch_json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T> {
+    json_limited(request.send().await?).await
+}
+
+/// Like [`json_limited`] but for Modrinth responses, see
+/// [`check_rinth_status`].
+pub(crate) async fn rinth_json_limited<T: DeserializeOwned>(response: Response) -> Result<T> {
+    json_limited(check_rinth_status(response).await?).await
+}
+
+/// Like [`fetch_json`] but for Modrinth requests, which go through
+/// [`rinth_limiter()`] and fail with [`UraniumError::ApiError`] if Modrinth
+/// answers with an error.
+pub(crate) async fn fetch_rinth_json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T> {
+    rinth_json_limited(
+        rinth_limiter()
+            .send(request)
+            .await?,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Serves `body` once without a `Content-Length` header.
+    async fn serve(body: &'static str) -> String {
+        serve_status("200 OK", body).await
+    }
+
+    async fn serve_status(status: &'static str, body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener
+                .accept()
+                .await
+                .unwrap();
+            let mut buf = [0; 1024];
+            let _ = socket.read(&mut buf).await;
+            let response = format!("HTTP/1.1 {status}\r\nConnection: close\r\n\r\n{body}");
+            let _ = socket
+                .write_all(response.as_bytes())
+                .await;
+        });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn body_limit() {
+        let client = default_client().unwrap();
+
+        let url = serve(r#"{"a": [1, 2, 3]}"#).await;
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            read_limited(response, 1024)
+                .await
+                .unwrap()
+                .len(),
+            16
+        );
+
+        let url = serve(r#"{"a": [1, 2, 3]}"#).await;
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .unwrap();
+        assert!(matches!(
+            read_limited(response, 8).await,
+            Err(UraniumError::ResponseTooLarge(8))
+        ));
+
+        let url = serve(r#"{"a": [1, 2,"#).await;
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .unwrap();
+        let error = json_limited::<serde_json::Value>(response)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, UraniumError::JsonError(_)));
+        assert!(error
+            .to_string()
+            .contains("line 1"));
+    }
+
+    #[tokio::test]
+    async fn rinth_error_payload() {
+        let client = default_client().unwrap();
+
+        let url = serve_status(
+            "400 Bad Request",
+            r#"{"error": "invalid_input", "description": "Invalid hash"}"#,
+        )
+        .await;
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .unwrap();
+        match rinth_json_limited::<serde_json::Value>(response).await {
+            Err(UraniumError::ApiError {
+                provider,
+                code,
+                description,
+            }) => {
+                assert_eq!(provider, "modrinth");
+                assert_eq!(code, "invalid_input");
+                assert_eq!(description, "Invalid hash");
+            }
+            other => panic!("Unexpected {other:?}"),
+        }
+
+        let url = serve_status("404 Not Found", r#"{"error": "not_found"}"#).await;
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .unwrap();
+        assert!(matches!(
+            rinth_json_limited::<serde_json::Value>(response).await,
+            Err(UraniumError::NotFound(_))
+        ));
+
+        // Server errors can be retried.
+        let url = serve_status(
+            "503 Service Unavailable",
+            r#"{"error": "unavailable", "description": "Try later"}"#,
+        )
+        .await;
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .unwrap();
+        let error = rinth_json_limited::<serde_json::Value>(response)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            UraniumError::HttpStatus {
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                ..
+            }
+        ));
+        assert!(crate::downloaders::RetryPolicy::default()
+            .retry_delay(&error, 1)
+            .is_some());
+    }
+}
@@ -0,0 +1,959 @@
+//! Identification of local files across providers.
+//!
+//! A jar is looked up by its sha1 in Modrinth and by its fingerprint in
+//! CurseForge, and its own metadata (`fabric.mod.json`, `quilt.mod.json` or
+//! `mods.toml`) is read as a fallback for files not hosted anywhere.
+//!
+//! Files whose hash is unknown can also be matched by name and size or by
+//! the mod id, see [`FileResolver::fuzzy_matching`]. Those matches have a
+//! lower [`MatchConfidence`] and should be reviewed by the user, see
+//! [`FileIdentity::needs_review`] and [`FileResolver::needs_review`].
+//!
+//! ```no_run
+//! # async fn foo() -> uranium::error::Result<()> {
+//! use uranium::identify::resolve_file;
+//!
+//! let identity = resolve_file("mods/sodium.jar").await?;
+//! if let Some(version) = &identity.modrinth {
+//!     println!("Modrinth project {}", version.project_id);
+//! } else if let Some(meta) = &identity.local_meta {
+//!     println!("Unknown {} mod {}", meta.loader, meta.id);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Cursor, Read, Seek},
+    path::{Path, PathBuf},
+};
+
+use futures::future::join_all;
+use log::warn;
+use mine_data_structs::{
+    curse::curse_mods::{CurseFile, CurseFingerPrint, CurseResponse},
+    rinth::RinthVersion,
+    url_maker::maker::Curse,
+};
+use serde::{Deserialize, Serialize};
+use zip::ZipArchive;
+
+use crate::{
+    cache::VersionCache,
+    downloaders::RetryPolicy,
+    error::{Result, UraniumError},
+    hashes::{curse_fingerprint_bytes, sha1_bytes},
+    http::{default_client, fetch_rinth_json, json_limited, rinth_json_limited},
+    rate_limit::rinth_limiter,
+    searcher::{
+        curse::{CurseClass, CurseSearchBuilder},
+        rinth::{SearchBuilder, SearchType},
+    },
+    secrets::curse_api_key,
+};
+
+/// How a file was matched with a provider, from the weakest to the strongest
+/// match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum MatchConfidence {
+    /// A version of the project with the mod id as slug and the same version
+    /// number.
+    Slug,
+    /// A file of the project with the same name and size.
+    NameAndSize,
+    /// A file with the same sha1 or fingerprint, it is the same file.
+    Hash,
+}
+
+/// What is known about a local file.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct FileIdentity {
+    pub sha1: String,
+    /// CurseForge fingerprint, see
+    /// [`curse_fingerprint`](crate::hashes::curse_fingerprint).
+    pub fingerprint: u32,
+    /// The Modrinth version the file belongs to.
+    pub modrinth: Option<RinthVersion>,
+    /// How `modrinth` was found.
+    pub modrinth_confidence: Option<MatchConfidence>,
+    /// The CurseForge file the file belongs to.
+    pub curse: Option<CurseFile>,
+    /// How `curse` was found.
+    pub curse_confidence: Option<MatchConfidence>,
+    /// Metadata declared inside the jar.
+    pub local_meta: Option<LocalMeta>,
+}
+
+impl FileIdentity {
+    /// Returns `true` if no provider knows the file.
+    #[must_use]
+    pub fn is_unknown(&self) -> bool {
+        self.modrinth.is_none() && self.curse.is_none()
+    }
+
+    /// Returns the strongest match among the providers, `None` if the file
+    /// is unknown.
+    #[must_use]
+    pub fn confidence(&self) -> Option<MatchConfidence> {
+        self.modrinth_confidence
+            .max(self.curse_confidence)
+    }
+
+    /// Returns `true` if any provider matched the file but not by its hash,
+    /// so that match is a guess the user should confirm.
+    #[must_use]
+    pub fn needs_review(&self) -> bool {
+        [self.modrinth_confidence, self.curse_confidence]
+            .into_iter()
+            .flatten()
+            .any(|c| c < MatchConfidence::Hash)
+    }
+}
+
+/// A file matched with a guess, see [`FileResolver::needs_review`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ReviewEntry {
+    pub path: PathBuf,
+    pub identity: FileIdentity,
+}
+
+/// Metadata of a mod read from its own jar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalMeta {
+    pub id: String,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    /// `"fabric"`, `"quilt"`, `"forge"` or `"neoforge"`.
+    pub loader: &'static str,
+}
+
+/// Body of the CurseForge fingerprints request.
+#[derive(Serialize)]
+struct Fingerprints {
+    fingerprints: Vec<u32>,
+}
+
+/// Resolves files against Modrinth and CurseForge, remembering the answers.
+///
+/// Modrinth versions are kept in a [`VersionCache`], so they survive between
+/// runs if the cache is saved with [`FileResolver::save_cache`]. CurseForge
+/// answers are only kept in memory, failed lookups too, and CurseForge isn't
+/// asked again once it rejects or lacks the API key.
+pub struct FileResolver {
+    client: reqwest::Client,
+    cache: Option<VersionCache>,
+    retry: RetryPolicy,
+    curse: CurseLookups,
+    fuzzy: bool,
+    /// Modrinth API used instead of the public one.
+    api: Option<String>,
+    review: Vec<ReviewEntry>,
+}
+
+/// CurseForge answers of a [`FileResolver`].
+#[derive(Default)]
+struct CurseLookups {
+    files: HashMap<u32, Option<CurseFile>>,
+    /// The API key is missing or rejected.
+    disabled: bool,
+}
+
+impl Default for FileResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileResolver {
+    #[must_use]
+    pub fn new() -> FileResolver {
+        FileResolver {
+            client: default_client().unwrap_or_default(),
+            cache: None,
+            retry: RetryPolicy::default(),
+            curse: CurseLookups::default(),
+            fuzzy: false,
+            api: None,
+            review: vec![],
+        }
+    }
+
+    /// Uses `cache` for the Modrinth lookups.
+    #[must_use]
+    pub fn with_cache(mut self, cache: VersionCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Sets how the failed Modrinth requests are retried, see
+    /// [`RetryPolicy`].
+    #[must_use]
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Uses another Modrinth API, `api` ends with `v2/`.
+    #[cfg(test)]
+    #[must_use]
+    pub(crate) fn api_url(mut self, api: &str) -> Self {
+        self.api = Some(api.to_owned());
+        self
+    }
+
+    pub(crate) fn set_cache(&mut self, cache: Option<VersionCache>) {
+        self.cache = cache;
+    }
+
+    pub(crate) fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry = policy;
+    }
+
+    pub(crate) fn set_fuzzy_matching(&mut self, enabled: bool) {
+        self.fuzzy = enabled;
+    }
+
+    /// If a provider doesn't know the hash, looks for the project with the
+    /// mod id of the jar as slug and matches its files by name and size or
+    /// by version number. Disabled by default since it costs another
+    /// request per provider and unknown file.
+    #[must_use]
+    pub fn fuzzy_matching(mut self, enabled: bool) -> Self {
+        self.fuzzy = enabled;
+        self
+    }
+
+    /// Identifies the file in `path`, both providers are asked at the same
+    /// time.
+    ///
+    /// The file is read once, off the async runtime. A provider which can't
+    /// be reached is logged and treated as if it didn't know the file.
+    ///
+    /// Files matched with a guess are also added to
+    /// [`FileResolver::needs_review`].
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be read.
+    pub async fn resolve<P: AsRef<Path>>(&mut self, path: P) -> Result<FileIdentity> {
+        let path = path.as_ref();
+        let (sha1, fingerprint, size, local_meta) = hash_file(path.to_path_buf()).await?;
+
+        let search = self.search();
+        let (mut modrinth, curse) = tokio::join!(
+            modrinth_by_hashes(
+                &self.client,
+                &mut self.cache,
+                &self.retry,
+                search,
+                std::slice::from_ref(&sha1),
+            ),
+            curse_by_fingerprint(&self.client, &mut self.curse, fingerprint)
+        );
+        let modrinth = match modrinth.pop() {
+            Some(Ok(version)) => version,
+            Some(Err(e)) => {
+                warn!("Can't look up {sha1} in Modrinth: {e}");
+                None
+            }
+            None => None,
+        };
+        let mut modrinth = modrinth.map(|v| (v, MatchConfidence::Hash));
+        let mut curse = curse.map(|f| (f, MatchConfidence::Hash));
+        if let (true, Some(meta)) = (self.fuzzy, &local_meta) {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy())
+                .unwrap_or_default();
+            if modrinth.is_none() {
+                modrinth = self
+                    .modrinth_fuzzy(meta, &name, size)
+                    .await;
+            }
+            if curse.is_none() {
+                curse = curse_fuzzy(&mut self.curse, meta, &name, size).await;
+            }
+        }
+        let (modrinth, modrinth_confidence) = modrinth.unzip();
+        let (curse, curse_confidence) = curse.unzip();
+
+        let identity = FileIdentity {
+            sha1,
+            fingerprint,
+            modrinth,
+            modrinth_confidence,
+            curse,
+            curse_confidence,
+            local_meta,
+        };
+        if identity.needs_review() {
+            self.review.push(ReviewEntry {
+                path: path.to_path_buf(),
+                identity: identity.clone(),
+            });
+        }
+        Ok(identity)
+    }
+
+    /// Returns the files resolved so far whose match is a guess, the user
+    /// should confirm them before they are used.
+    #[must_use]
+    pub fn needs_review(&self) -> &[ReviewEntry] {
+        &self.review
+    }
+
+    /// Looks up in Modrinth the files with the sha1 `hashes`, at the same
+    /// time and retrying the transient failures.
+    ///
+    /// Each result is `Ok(None)` if Modrinth doesn't know the file.
+    pub(crate) async fn modrinth_versions(
+        &mut self,
+        hashes: &[String],
+    ) -> Vec<Result<Option<RinthVersion>>> {
+        let search = self.search();
+        modrinth_by_hashes(&self.client, &mut self.cache, &self.retry, search, hashes).await
+    }
+
+    fn search(&self) -> SearchBuilder<()> {
+        let search = SearchBuilder::new();
+        match &self.api {
+            Some(api) => search.api_url(api),
+            None => search,
+        }
+    }
+
+    /// Writes the Modrinth cache, if any.
+    ///
+    /// # Errors
+    /// Returns an error if the cache can't be written.
+    pub fn save_cache(&mut self) -> Result<()> {
+        match &mut self.cache {
+            Some(cache) => cache.save(),
+            None => Ok(()),
+        }
+    }
+
+    async fn modrinth_fuzzy(
+        &self,
+        meta: &LocalMeta,
+        name: &str,
+        size: u64,
+    ) -> Option<(RinthVersion, MatchConfidence)> {
+        let url = self
+            .search()
+            .project_versions(&meta.id)
+            .loaders(vec![meta.loader.to_owned()])
+            .build_url();
+        let versions = match fetch_rinth_json::<Vec<RinthVersion>>(self.client.get(url)).await {
+            Ok(versions) => versions,
+            Err(UraniumError::NotFound(_)) => return None,
+            Err(e) => {
+                warn!("Can't look up {} in Modrinth: {e}", meta.id);
+                return None;
+            }
+        };
+
+        best_candidate(versions, name, size, meta.version.as_deref())
+    }
+}
+
+/// Returns the sha1, the CurseForge fingerprint, the size and the metadata of
+/// the file in `path`, reading it once in a blocking task.
+async fn hash_file(path: PathBuf) -> Result<(String, u32, u64, Option<LocalMeta>)> {
+    tokio::task::spawn_blocking(move || {
+        let content = std::fs::read(path)?;
+        Ok((
+            sha1_bytes(&content),
+            curse_fingerprint_bytes(&content),
+            content.len() as u64,
+            local_meta(Cursor::new(&content)),
+        ))
+    })
+    .await?
+}
+
+async fn modrinth_by_hashes(
+    client: &reqwest::Client,
+    cache: &mut Option<VersionCache>,
+    retry: &RetryPolicy,
+    search: SearchBuilder<()>,
+    hashes: &[String],
+) -> Vec<Result<Option<RinthVersion>>> {
+    let lookups = hashes.iter().map(|hash| {
+        let cached = cache
+            .as_ref()
+            .and_then(|c| c.get(hash))
+            .cloned();
+        let url = search
+            .clone()
+            .search_type(SearchType::VersionFile { hash: hash.clone() })
+            .build_url();
+        async move {
+            if cached.is_some() {
+                return Ok(cached);
+            }
+            match fetch_version(client, &url, retry).await {
+                Ok(version) => Ok(Some(version)),
+                Err(UraniumError::NotFound(_)) => Ok(None),
+                Err(e) => Err(e),
+            }
+        }
+    });
+    let results = join_all(lookups).await;
+
+    if let Some(cache) = cache {
+        for (hash, result) in hashes.iter().zip(&results) {
+            if let Ok(Some(version)) = result {
+                cache.insert(hash.clone(), version.clone());
+            }
+        }
+    }
+    results
+}
+
+/// Asks Modrinth for the version in `url`, retrying the transient failures
+/// following `retry`.
+///
+/// Returns `Err(UraniumError::NotFound)` if the file is not in Modrinth.
+pub(crate) async fn fetch_version(
+    client: &reqwest::Client,
+    url: &str,
+    retry: &RetryPolicy,
+) -> Result<RinthVersion> {
+    let mut attempt = 1;
+    loop {
+        let result = match rinth_limiter()
+            .send(client.get(url))
+            .await
+        {
+            Ok(response) => rinth_json_limited::<RinthVersion>(response).await,
+            Err(e) => Err(e.into()),
+        };
+
+        match result {
+            Err(e) => match retry.retry_delay(&e, attempt) {
+                Some(delay) => {
+                    warn!("{url}: {e}, retrying ({attempt})");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                None => return Err(e),
+            },
+            ok => return ok,
+        }
+    }
+}
+
+async fn curse_by_fingerprint(
+    client: &reqwest::Client,
+    known: &mut CurseLookups,
+    fingerprint: u32,
+) -> Option<CurseFile> {
+    if known.disabled {
+        return None;
+    }
+    if let Some(file) = known.files.get(&fingerprint) {
+        return file.clone();
+    }
+
+    let file = match search_fingerprints(client, vec![fingerprint]).await {
+        Ok(found) => found
+            .get_files()
+            .find(|f| f.get_file_fingerprint() == fingerprint)
+            .cloned(),
+        Err(e @ (UraniumError::MissingApiKey(_) | UraniumError::InvalidApiKey(_))) => {
+            warn!("Not looking up files in CurseForge: {e}");
+            known.disabled = true;
+            return None;
+        }
+        Err(e) => {
+            warn!("Can't look up {fingerprint} in CurseForge: {e}");
+            None
+        }
+    };
+    known
+        .files
+        .insert(fingerprint, file.clone());
+    file
+}
+
+/// Looks for the CurseForge mod with the mod id of the jar as slug and matches
+/// its latest files as [`best_candidate`] does.
+async fn curse_fuzzy(
+    known: &mut CurseLookups,
+    meta: &LocalMeta,
+    name: &str,
+    size: u64,
+) -> Option<(CurseFile, MatchConfidence)> {
+    if known.disabled {
+        return None;
+    }
+
+    let search = CurseSearchBuilder::new()
+        .class(CurseClass::Mods)
+        .search_filter(&meta.id)
+        .search()
+        .await;
+    let hits = match search {
+        Ok(response) => response.data,
+        Err(e @ (UraniumError::MissingApiKey(_) | UraniumError::InvalidApiKey(_))) => {
+            warn!("Not looking up files in CurseForge: {e}");
+            known.disabled = true;
+            return None;
+        }
+        Err(e) => {
+            warn!("Can't look up {} in CurseForge: {e}", meta.id);
+            return None;
+        }
+    };
+
+    let files = hits
+        .iter()
+        .filter(|hit| hit.get_slug() == meta.id)
+        .flat_map(|hit| hit.get_latest_files());
+    best_curse_candidate(files, name, size, meta.version.as_deref())
+}
+
+/// Same as [`best_candidate`] for CurseForge files, which have no version
+/// number so it is looked for in their names.
+fn best_curse_candidate<'a>(
+    files: impl Iterator<Item = &'a CurseFile>,
+    name: &str,
+    size: u64,
+    version_number: Option<&str>,
+) -> Option<(CurseFile, MatchConfidence)> {
+    files
+        .filter_map(|file| {
+            let file_name = file
+                .get_file_name()
+                .to_string_lossy();
+            let confidence = if file_name == name && file.get_file_length() as u64 == size {
+                MatchConfidence::NameAndSize
+            } else if version_number.is_some_and(|v| {
+                file_name.contains(v)
+                    || file
+                        .get_display_name()
+                        .contains(v)
+            }) {
+                MatchConfidence::Slug
+            } else {
+                return None;
+            };
+            Some((file.clone(), confidence))
+        })
+        .max_by_key(|(_, confidence)| *confidence)
+}
+
+/// Picks the version of `versions` which best matches a file called `name`
+/// with `size` bytes. A version is never guessed, if none has the same file
+/// or version number `None` is returned.
+fn best_candidate(
+    versions: Vec<RinthVersion>,
+    name: &str,
+    size: u64,
+    version_number: Option<&str>,
+) -> Option<(RinthVersion, MatchConfidence)> {
+    versions
+        .into_iter()
+        .filter_map(|version| {
+            let same_file = version
+                .files
+                .iter()
+                .any(|f| f.filename == name && f.size as u64 == size);
+            let confidence = if same_file {
+                MatchConfidence::NameAndSize
+            } else if version_number
+                == Some(
+                    version
+                        .version_number
+                        .as_str(),
+                )
+            {
+                MatchConfidence::Slug
+            } else {
+                return None;
+            };
+            Some((version, confidence))
+        })
+        .max_by_key(|(_, confidence)| *confidence)
+}
+
+/// Identifies the file in `path` with a [`FileResolver`] using the default
+/// [`VersionCache`], if it can be opened.
+///
+/// # Errors
+/// Returns an error if the file can't be read.
+pub async fn resolve_file<P: AsRef<Path>>(path: P) -> Result<FileIdentity> {
+    let mut resolver = match VersionCache::open_default() {
+        Ok(cache) => FileResolver::new().with_cache(cache),
+        Err(_) => FileResolver::new(),
+    };
+    let identity = resolver.resolve(path).await?;
+    if let Err(e) = resolver.save_cache() {
+        warn!("Can't save the versions cache: {e}");
+    }
+    Ok(identity)
+}
+
+/// Asks CurseForge for the files with any of `fingerprints`.
+pub(crate) async fn search_fingerprints(
+    client: &reqwest::Client,
+    fingerprints: Vec<u32>,
+) -> Result<CurseFingerPrint> {
+    let response = client
+        .post(Curse::hash())
+        .header("x-api-key", curse_api_key()?)
+        .json(&Fingerprints { fingerprints })
+        .send()
+        .await?;
+
+    Ok(json_limited::<CurseResponse<CurseFingerPrint>>(response)
+        .await?
+        .data)
+}
+
+#[derive(Deserialize)]
+struct FabricModJson {
+    id: String,
+    name: Option<String>,
+    version: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct QuiltModJson {
+    quilt_loader: QuiltLoader,
+}
+
+#[derive(Deserialize)]
+struct QuiltLoader {
+    id: String,
+    version: Option<String>,
+    #[serde(default)]
+    metadata: QuiltMetadata,
+}
+
+#[derive(Deserialize, Default)]
+struct QuiltMetadata {
+    name: Option<String>,
+}
+
+/// Reads the mod metadata of the jar in `path`, `None` if it is not a jar or
+/// doesn't declare any.
+pub fn read_local_meta<P: AsRef<Path>>(path: P) -> Option<LocalMeta> {
+    local_meta(File::open(path).ok()?)
+}
+
+fn local_meta<R: Read + Seek>(jar: R) -> Option<LocalMeta> {
+    let mut zip = ZipArchive::new(jar).ok()?;
+    let mut read = |name: &str| {
+        let mut content = String::new();
+        zip.by_name(name)
+            .ok()?
+            .read_to_string(&mut content)
+            .ok()?;
+        Some(content)
+    };
+
+    if let Some(json) = read("fabric.mod.json") {
+        let meta: FabricModJson = serde_json::from_str(&json).ok()?;
+        return Some(LocalMeta {
+            id: meta.id,
+            name: meta.name,
+            version: meta.version,
+            loader: "fabric",
+        });
+    }
+    if let Some(json) = read("quilt.mod.json") {
+        let meta: QuiltModJson = serde_json::from_str(&json).ok()?;
+        return Some(LocalMeta {
+            id: meta.quilt_loader.id,
+            name: meta
+                .quilt_loader
+                .metadata
+                .name,
+            version: meta.quilt_loader.version,
+            loader: "quilt",
+        });
+    }
+    if let Some(toml) = read("META-INF/neoforge.mods.toml") {
+        return parse_mods_toml(&toml, "neoforge");
+    }
+    if let Some(toml) = read("META-INF/mods.toml") {
+        return parse_mods_toml(&toml, "forge");
+    }
+    None
+}
+
+/// Reads the first `[[mods]]` entry of a `mods.toml`.
+///
+/// Only the `key = "value"` lines are understood, which is all the fields
+/// used here need.
+fn parse_mods_toml(toml: &str, loader: &'static str) -> Option<LocalMeta> {
+    let mut fields = HashMap::new();
+    let mut in_mods = false;
+    for line in toml.lines().map(str::trim) {
+        if line.starts_with('[') {
+            if in_mods {
+                break;
+            }
+            in_mods = line == "[[mods]]";
+            continue;
+        }
+        if !in_mods {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            let value = value.trim();
+            if let Some(value) = value
+                .strip_prefix('"')
+                .and_then(|v| v.split('"').next())
+            {
+                fields.insert(key.trim(), value.to_owned());
+            }
+        }
+    }
+
+    Some(LocalMeta {
+        id: fields.remove("modId")?,
+        name: fields.remove("displayName"),
+        // Forge replaces `${file.jarVersion}` with the jar manifest version.
+        version: fields
+            .remove("version")
+            .filter(|v| !v.starts_with("${")),
+        loader,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::time::Duration;
+
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+    use crate::test_utils::{rinth_version, rinth_version_file, FixtureServer};
+
+    #[test]
+    fn mods_toml() {
+        let toml = r#"
+modLoader = "javafml"
+[[mods]]
+modId = "jei"
+version = "${file.jarVersion}"
+displayName = "Just Enough Items" # the name
+[[dependencies.jei]]
+modId = "forge"
+"#;
+        assert_eq!(
+            parse_mods_toml(toml, "forge"),
+            Some(LocalMeta {
+                id: "jei".to_owned(),
+                name: Some("Just Enough Items".to_owned()),
+                version: None,
+                loader: "forge",
+            })
+        );
+    }
+
+    fn version(number: &str, filename: &str, size: usize) -> RinthVersion {
+        RinthVersion {
+            files: vec![rinth_version_file(filename, &vec![0; size], "")],
+            ..rinth_version(number)
+        }
+    }
+
+    #[test]
+    fn candidates_confidence() {
+        let versions = || {
+            vec![
+                version("0.6.0", "sodium-0.6.0.jar", 10),
+                version("0.5.0", "sodium-0.5.0.jar", 20),
+            ]
+        };
+
+        let (found, confidence) =
+            best_candidate(versions(), "sodium-0.5.0.jar", 20, Some("0.6.0")).unwrap();
+        assert_eq!(found.version_number, "0.5.0");
+        assert_eq!(confidence, MatchConfidence::NameAndSize);
+
+        let (found, confidence) =
+            best_candidate(versions(), "sodium.jar", 20, Some("0.6.0")).unwrap();
+        assert_eq!(found.version_number, "0.6.0");
+        assert_eq!(confidence, MatchConfidence::Slug);
+
+        assert!(best_candidate(versions(), "sodium-0.5.0.jar", 15, None).is_none());
+    }
+
+    #[tokio::test]
+    async fn resolve_by_hash() {
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let content = b"sodium jar";
+        let body = serde_json::to_vec(&version("0.6.0", "sodium.jar", content.len())).unwrap();
+        server.add(
+            &format!("/v2/version_file/{}", crate::hashes::sha1_bytes(content)),
+            body,
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sodium.jar");
+        std::fs::write(&path, content).unwrap();
+
+        let mut resolver = FileResolver::new().api_url(&server.url("/v2/"));
+        let identity = resolver
+            .resolve(&path)
+            .await
+            .unwrap();
+        assert_eq!(
+            identity
+                .modrinth
+                .map(|v| v.id),
+            Some("0.6.0".to_owned())
+        );
+        assert_eq!(identity.modrinth_confidence, Some(MatchConfidence::Hash));
+        assert_eq!(identity.fingerprint, curse_fingerprint_bytes(content));
+        assert_eq!(server.requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn guesses_need_review() {
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir
+            .path()
+            .join("sodium-0.5.0.jar");
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        zip.start_file("fabric.mod.json", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(br#"{"id": "sodium", "version": "0.5.0"}"#)
+            .unwrap();
+        zip.finish().unwrap();
+        let size = std::fs::metadata(&path)
+            .unwrap()
+            .len() as usize;
+        let versions = vec![version("0.5.0", "sodium-0.5.0.jar", size)];
+        server.add(
+            "/v2/project/sodium/version?loaders=[%22fabric%22]",
+            serde_json::to_vec(&versions).unwrap(),
+        );
+
+        let mut resolver = FileResolver::new()
+            .api_url(&server.url("/v2/"))
+            .fuzzy_matching(true);
+        let identity = resolver
+            .resolve(&path)
+            .await
+            .unwrap();
+        assert_eq!(
+            identity.modrinth_confidence,
+            Some(MatchConfidence::NameAndSize)
+        );
+        assert!(identity.needs_review());
+        let review = resolver.needs_review();
+        assert_eq!(review.len(), 1);
+        assert_eq!(review[0].path, path);
+    }
+
+    #[test]
+    fn any_guess_needs_review() {
+        let file: CurseFile = serde_json::from_value(serde_json::json!({
+            "id": 1, "modId": 2, "displayName": "Sodium 0.5.0",
+            "fileName": "sodium-fabric-0.5.0.jar", "fileLength": 10, "gameVersions": []
+        }))
+        .unwrap();
+        let (found, confidence) = best_curse_candidate(
+            std::iter::once(&file),
+            "sodium-0.5.0.jar",
+            10,
+            Some("0.5.0"),
+        )
+        .unwrap();
+        assert_eq!(confidence, MatchConfidence::Slug);
+
+        let identity = FileIdentity {
+            sha1: String::new(),
+            fingerprint: 0,
+            modrinth: Some(version("0.5.0", "sodium-0.5.0.jar", 10)),
+            modrinth_confidence: Some(MatchConfidence::Hash),
+            curse: Some(found),
+            curse_confidence: Some(confidence),
+            local_meta: None,
+        };
+        assert_eq!(identity.confidence(), Some(MatchConfidence::Hash));
+        assert!(identity.needs_review());
+    }
+
+    #[tokio::test]
+    async fn fetch_version_retries() {
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let url = server.add(
+            "/v2/version_file/abc",
+            serde_json::to_vec(&rinth_version("v1")).unwrap(),
+        );
+        let client = reqwest::Client::new();
+        let retry = RetryPolicy::default()
+            .jitter(false)
+            .max_attempts(3)
+            .base_delay(Duration::from_millis(1));
+
+        server.fail("/v2/version_file/abc", 503, 2);
+        let version = fetch_version(&client, &url, &retry)
+            .await
+            .unwrap();
+        assert_eq!(version.id, "v1");
+        assert_eq!(server.requests(), 1);
+
+        server.fail("/v2/version_file/abc", 503, 3);
+        assert!(matches!(
+            fetch_version(&client, &url, &retry).await,
+            Err(UraniumError::HttpStatus { status, .. }) if status.as_u16() == 503
+        ));
+
+        // A missing file is not retried.
+        server.fail("/v2/version_file/abc", 404, 2);
+        assert!(matches!(
+            fetch_version(&client, &url, &retry).await,
+            Err(UraniumError::NotFound(_))
+        ));
+        // The first call used only one of the two failures.
+        assert!(fetch_version(&client, &url, &retry)
+            .await
+            .is_err());
+        assert!(fetch_version(&client, &url, &retry)
+            .await
+            .is_ok());
+    }
+
+    #[test]
+    fn fabric_jar_meta() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir
+            .path()
+            .join("local_meta_test.jar");
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        zip.start_file("fabric.mod.json", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(br#"{"id": "sodium", "version": "0.6.0", "name": "Sodium"}"#)
+            .unwrap();
+        zip.finish().unwrap();
+
+        let meta = read_local_meta(&path);
+        assert_eq!(
+            meta,
+            Some(LocalMeta {
+                id: "sodium".to_owned(),
+                name: Some("Sodium".to_owned()),
+                version: Some("0.6.0".to_owned()),
+                loader: "fabric",
+            })
+        );
+    }
+}
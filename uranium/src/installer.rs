@@ -0,0 +1,651 @@
+//! Installation of Modrinth content into an existing instance.
+
+use std::{
+    collections::{HashMap, HashSet},
+    marker::PhantomData,
+    path::Path,
+};
+
+use futures::future::join_all;
+use log::{info, warn};
+use mine_data_structs::rinth::{RinthCollection, RinthProject, RinthVersion, RinthVersions};
+
+use crate::{
+    downloaders::{ContentType, DownloadableObject, Downloader, FileDownloader, HashType},
+    error::{Result, UraniumError},
+    http::{default_client, fetch_rinth_json},
+    instance::{InstalledFile, InstanceMetadata},
+    searcher::rinth::{SearchBuilder, SearchType},
+    variables::constants::DISABLED_SUFFIX,
+};
+
+/// Something that still has to be resolved into a version.
+enum Pending {
+    /// The best version of the project for the instance.
+    Project(String),
+    /// A dependency pinned to an exact version, used even if its project has
+    /// newer versions.
+    Version(String),
+}
+
+/// Installs Modrinth projects into an instance, resolving the version that
+/// matches the instance game version and loader and its required
+/// dependencies.
+///
+/// The instance game version and loader are read from its
+/// [`InstanceMetadata`].
+pub struct ContentInstaller<T: FileDownloader = Downloader> {
+    client: reqwest::Client,
+    downloader: PhantomData<T>,
+    /// Modrinth API used instead of the public one.
+    api: Option<String>,
+}
+
+impl Default for ContentInstaller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: FileDownloader> ContentInstaller<T> {
+    #[must_use]
+    pub fn new() -> ContentInstaller<T> {
+        ContentInstaller {
+            client: default_client().unwrap_or_default(),
+            downloader: PhantomData,
+            api: None,
+        }
+    }
+
+    fn search(&self) -> SearchBuilder<()> {
+        let search = SearchBuilder::new();
+        match &self.api {
+            Some(api) => search.api_url(api),
+            None => search,
+        }
+    }
+
+    /// Installs every project of the Modrinth collection `id` and their
+    /// required dependencies into `instance`.
+    ///
+    /// Mods go to `mods/`, resource packs to `resourcepacks/` and shaders to
+    /// `shaderpacks/`. Projects of other types or without a version for the
+    /// instance are skipped with a warning. Returns the installed versions.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn foo() -> uranium::error::Result<()> {
+    /// use uranium::installer::ContentInstaller;
+    ///
+    /// let installer: ContentInstaller = ContentInstaller::new();
+    /// let installed = installer
+    ///     .install_collection("Q7eFz9xF", "path/to/instance")
+    ///     .await?;
+    /// println!("{} mods installed", installed.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the instance has no game version or loader set, if
+    /// the collection can't be fetched or if the download fails.
+    pub async fn install_collection<I: AsRef<Path>>(
+        &self,
+        id: &str,
+        instance: I,
+    ) -> Result<Vec<RinthVersion>> {
+        let instance = instance.as_ref();
+        let metadata = InstanceMetadata::load(instance)?;
+        let (Some(game_version), Some(loader)) = (metadata.game_version, metadata.loader) else {
+            return Err(UraniumError::OtherWithReason(
+                "The instance has no game version or loader set".to_owned(),
+            ));
+        };
+
+        let url = self
+            .search()
+            .search_type(SearchType::Collection { id: id.to_owned() })
+            .build_url();
+        let collection: RinthCollection = fetch_rinth_json(self.client.get(url)).await?;
+        info!(
+            "Installing collection {} ({} projects)",
+            collection.name,
+            collection.projects.len()
+        );
+
+        let versions = self
+            .resolve(collection.projects, &game_version, &loader)
+            .await?;
+
+        let mut files = Vec::new();
+        for (version, content_type) in &versions {
+            let Some(file) = version.get_primary_file() else {
+                continue;
+            };
+            let folder = instance.join(content_type.folder());
+            std::fs::create_dir_all(&folder)?;
+            files.push(
+                DownloadableObject::new(
+                    &file.url,
+                    &file.filename,
+                    &folder,
+                    Some(HashType::Sha1(file.hashes.sha1.clone())),
+                )
+                .with_size(file.size as u64),
+            );
+        }
+
+        T::new(files)
+            .complete()
+            .await?;
+
+        InstanceMetadata::update(instance, |metadata| {
+            for (version, content_type) in &versions {
+                if let Some(file) = version.get_primary_file() {
+                    metadata.record_file(
+                        Path::new(content_type.folder()).join(&file.filename),
+                        InstalledFile::from_version(version),
+                    );
+                }
+            }
+        })?;
+        Ok(versions
+            .into_iter()
+            .map(|(version, _)| version)
+            .collect())
+    }
+
+    /// Resolves `projects` and their required dependencies, one level of
+    /// dependencies at a time.
+    ///
+    /// Each project is installed once, the first version resolved for it is
+    /// kept.
+    async fn resolve(
+        &self,
+        projects: Vec<String>,
+        game_version: &str,
+        loader: &str,
+    ) -> Result<Vec<(RinthVersion, ContentType)>> {
+        // Project ids, or version ids for the dependencies without a project.
+        let mut seen: HashSet<String> = projects
+            .iter()
+            .cloned()
+            .collect();
+        let mut pending: Vec<Pending> = projects
+            .into_iter()
+            .map(Pending::Project)
+            .collect();
+        let mut types = HashMap::new();
+        let mut resolved_projects = HashSet::new();
+        let mut resolved = Vec::new();
+
+        while !pending.is_empty() {
+            let projects = pending
+                .iter()
+                .filter_map(|p| match p {
+                    Pending::Project(id) => Some(id.clone()),
+                    Pending::Version(_) => None,
+                })
+                .collect();
+            types.extend(
+                self.content_types(projects)
+                    .await?,
+            );
+
+            let versions = join_all(
+                pending
+                    .drain(..)
+                    .map(|p| self.resolve_one(p, &types, game_version, loader)),
+            )
+            .await;
+
+            for version in versions {
+                let Some(version) = version? else {
+                    continue;
+                };
+                if !resolved_projects.insert(version.project_id.clone()) {
+                    continue;
+                }
+                seen.insert(version.project_id.clone());
+
+                for dependency in version
+                    .dependencies
+                    .iter()
+                    .filter(|d| d.is_required())
+                {
+                    let project_id = dependency.get_project_id();
+                    let version_id = dependency.get_version_id();
+                    let key = if project_id.is_empty() {
+                        version_id
+                    } else {
+                        project_id
+                    };
+                    if key.is_empty() || !seen.insert(key.to_owned()) {
+                        continue;
+                    }
+                    pending.push(if version_id.is_empty() {
+                        Pending::Project(project_id.to_owned())
+                    } else {
+                        Pending::Version(version_id.to_owned())
+                    });
+                }
+
+                resolved.push(version);
+            }
+        }
+
+        // The pinned versions were resolved without knowing their project.
+        let unknown = resolved
+            .iter()
+            .map(|v| v.project_id.clone())
+            .filter(|id| !types.contains_key(id))
+            .collect();
+        types.extend(
+            self.content_types(unknown)
+                .await?,
+        );
+
+        Ok(resolved
+            .into_iter()
+            .filter_map(|version| match types.get(&version.project_id) {
+                Some(content_type) => Some((version, *content_type)),
+                None => {
+                    warn!("{} can't be installed, skipping it", version.project_id);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Gets the [`ContentType`] of each of `projects` with a folder in the
+    /// instance.
+    async fn content_types(&self, projects: Vec<String>) -> Result<HashMap<String, ContentType>> {
+        if projects.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let url = self
+            .search()
+            .search_type(SearchType::MultiProject { ids: projects })
+            .build_url();
+        let projects: Vec<RinthProject> = fetch_rinth_json(self.client.get(url)).await?;
+        Ok(projects
+            .into_iter()
+            .filter_map(|p| Some((p.id, ContentType::from_project_type(&p.project_type)?)))
+            .collect())
+    }
+
+    async fn resolve_one(
+        &self,
+        pending: Pending,
+        types: &HashMap<String, ContentType>,
+        game_version: &str,
+        loader: &str,
+    ) -> Result<Option<RinthVersion>> {
+        match pending {
+            Pending::Project(id) => {
+                let Some(content_type) = types.get(&id) else {
+                    warn!("{id} is not a mod, resource pack nor shader, skipping it");
+                    return Ok(None);
+                };
+                let url = self
+                    .search()
+                    .project_versions(&id)
+                    .add_game_version(game_version)
+                    .loaders(content_type.loaders(loader))
+                    .build_url();
+                let versions: RinthVersions = fetch_rinth_json(self.client.get(url)).await?;
+
+                let best = best_version(versions);
+                if best.is_none() {
+                    warn!("{id} has no version for {loader} {game_version}, skipping it");
+                }
+                Ok(best)
+            }
+            Pending::Version(id) => {
+                let url = self
+                    .search()
+                    .search_type(SearchType::Version { id })
+                    .build_url();
+                Ok(Some(fetch_rinth_json(self.client.get(url)).await?))
+            }
+        }
+    }
+}
+
+/// Removes the files of the project `project_id` from `instance` and forgets
+/// them in its [`InstanceMetadata`].
+///
+/// Only files recorded in the metadata can be removed. The mod is removed even
+/// if other installed mods require it, those are logged and returned so the
+/// user can be told.
+///
+/// ```no_run
+/// # fn foo() -> uranium::error::Result<()> {
+/// use uranium::installer::remove_mod;
+///
+/// for dependent in remove_mod("path/to/instance", "P7dR8mSH")? {
+///     println!("{dependent} needs the removed mod");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+/// Returns an error if the project is not installed or if a file or the
+/// metadata can't be written.
+pub fn remove_mod<I: AsRef<Path>>(instance: I, project_id: &str) -> Result<Vec<String>> {
+    let instance = instance.as_ref();
+    let mut metadata = InstanceMetadata::load(instance)?;
+
+    let files: Vec<String> = metadata
+        .project_files(project_id)
+        .into_iter()
+        .map(str::to_owned)
+        .collect();
+    if files.is_empty() {
+        return Err(UraniumError::OtherWithReason(format!(
+            "{project_id} is not installed"
+        )));
+    }
+
+    let dependents: Vec<String> = metadata
+        .dependents(project_id)
+        .into_iter()
+        .map(str::to_owned)
+        .collect();
+    for dependent in &dependents {
+        warn!("{dependent} requires {project_id}, which is being removed");
+    }
+
+    for file in &files {
+        match std::fs::remove_file(instance.join(file)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => info!("Removed {file}"),
+        }
+        metadata.forget_file(file);
+    }
+    metadata.save(instance)?;
+
+    Ok(dependents)
+}
+
+/// Enables or disables the files of the project `project_id` in `instance`.
+///
+/// Disabled files get the `.disabled` suffix, so the game and the updater
+/// ignore them, and are renamed back when enabled. The new names are saved in
+/// the instance [`InstanceMetadata`].
+///
+/// # Errors
+/// Returns an error if the project is not installed or if a file or the
+/// metadata can't be renamed or written.
+pub fn set_mod_enabled<I: AsRef<Path>>(instance: I, project_id: &str, enabled: bool) -> Result<()> {
+    let instance = instance.as_ref();
+    let mut metadata = InstanceMetadata::load(instance)?;
+
+    let files: Vec<String> = metadata
+        .project_files(project_id)
+        .into_iter()
+        .map(str::to_owned)
+        .collect();
+    if files.is_empty() {
+        return Err(UraniumError::OtherWithReason(format!(
+            "{project_id} is not installed"
+        )));
+    }
+
+    for file in files {
+        let new_name = match (enabled, file.strip_suffix(DISABLED_SUFFIX)) {
+            (true, Some(name)) => name.to_owned(),
+            (false, None) => format!("{file}{DISABLED_SUFFIX}"),
+            _ => continue,
+        };
+
+        std::fs::rename(instance.join(&file), instance.join(&new_name))?;
+        if let Some(source) = metadata
+            .installed_file(&file)
+            .cloned()
+        {
+            metadata.forget_file(&file);
+            metadata.record_file(&new_name, source);
+        }
+    }
+    metadata.save(instance)
+}
+
+/// Modrinth sorts the versions from newest to oldest, the newest release is
+/// preferred over betas and alphas.
+fn best_version(versions: RinthVersions) -> Option<RinthVersion> {
+    let versions: RinthVersions = versions
+        .into_iter()
+        .filter(|v| !v.files.is_empty())
+        .collect();
+
+    versions
+        .iter()
+        .find(|v| v.version_type == "release")
+        .or(versions.first())
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use mine_data_structs::rinth::Dependency;
+
+    use super::*;
+    use crate::test_utils::{rinth_version, rinth_version_file, FixtureServer};
+
+    fn project(id: &str, project_type: &str) -> serde_json::Value {
+        serde_json::json!({
+            "slug": id, "title": id, "description": "", "categories": [],
+            "client_side": "required", "server_side": "optional", "body": "",
+            "status": "approved", "project_type": project_type, "downloads": 0,
+            "id": id, "team": "t", "updated": "2024-07-03T01:02:03Z", "icon_url": ""
+        })
+    }
+
+    /// Returns the version `id` of `project` with the file `filename` served
+    /// by `server`.
+    fn served_version(
+        server: &FixtureServer,
+        project: &str,
+        id: &str,
+        filename: &str,
+    ) -> RinthVersion {
+        let content = filename.as_bytes();
+        let url = server.add(&format!("/{filename}"), content.to_vec());
+        RinthVersion {
+            project_id: project.to_owned(),
+            files: vec![rinth_version_file(filename, content, &url)],
+            ..rinth_version(id)
+        }
+    }
+
+    fn version(id: &str, version_type: &str) -> RinthVersion {
+        RinthVersion {
+            version_type: version_type.to_owned(),
+            files: vec![rinth_version_file("c.jar", b"c", "https://a.b/c.jar")],
+            ..rinth_version(id)
+        }
+    }
+
+    #[test]
+    fn prefers_newest_release() {
+        let versions = vec![
+            version("beta", "beta"),
+            version("new", "release"),
+            version("old", "release"),
+        ];
+        assert_eq!(
+            best_version(versions)
+                .unwrap()
+                .id,
+            "new"
+        );
+
+        let versions = vec![version("alpha", "alpha"), version("beta", "beta")];
+        assert_eq!(
+            best_version(versions)
+                .unwrap()
+                .id,
+            "alpha"
+        );
+
+        assert!(best_version(vec![]).is_none());
+    }
+
+    #[tokio::test]
+    async fn collection_folders_and_pinned_dependencies() {
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let json = |value: serde_json::Value| serde_json::to_vec(&value).unwrap();
+        let versions = |versions: Vec<RinthVersion>| serde_json::to_vec(&versions).unwrap();
+
+        server.add(
+            "/v3/collection/col",
+            json(serde_json::json!({ "id": "col", "name": "Col", "projects": ["sodium", "faithful"] })),
+        );
+        server.add(
+            "/v2/projects?ids=[%22sodium%22,%22faithful%22]",
+            json(serde_json::json!([
+                project("sodium", "mod"),
+                project("faithful", "resourcepack")
+            ])),
+        );
+        server.add(
+            "/v2/projects?ids=[%22fabric-api%22]",
+            json(serde_json::json!([project("fabric-api", "mod")])),
+        );
+        let mut sodium = served_version(&server, "sodium", "sodium-1", "sodium.jar");
+        sodium.dependencies = vec![Dependency::new(
+            Some("fabric-api"),
+            Some("api-pinned"),
+            "required",
+        )];
+        server.add(
+            "/v2/project/sodium/version?game_versions=[%221.21%22]&loaders=[%22fabric%22]",
+            versions(vec![sodium]),
+        );
+        server.add(
+            "/v2/project/faithful/version?game_versions=[%221.21%22]&loaders=[%22minecraft%22]",
+            versions(vec![served_version(
+                &server,
+                "faithful",
+                "faithful-1",
+                "faithful.zip",
+            )]),
+        );
+        server.add(
+            "/v2/version/api-pinned",
+            serde_json::to_vec(&served_version(
+                &server,
+                "fabric-api",
+                "api-pinned",
+                "fabric-api.jar",
+            ))
+            .unwrap(),
+        );
+
+        let instance = tempfile::tempdir().unwrap();
+        InstanceMetadata::update(instance.path(), |metadata| {
+            metadata.game_version = Some("1.21".to_owned());
+            metadata.loader = Some("fabric".to_owned());
+        })
+        .unwrap();
+
+        let mut installer: ContentInstaller = ContentInstaller::new();
+        installer.api = Some(server.url("/v2/"));
+        let installed = installer
+            .install_collection("col", instance.path())
+            .await
+            .unwrap();
+
+        let mut ids: Vec<&str> = installed
+            .iter()
+            .map(|v| v.id.as_str())
+            .collect();
+        ids.sort_unstable();
+        assert_eq!(ids, ["api-pinned", "faithful-1", "sodium-1"]);
+        for file in [
+            "mods/sodium.jar",
+            "mods/fabric-api.jar",
+            "resourcepacks/faithful.zip",
+        ] {
+            assert!(
+                instance
+                    .path()
+                    .join(file)
+                    .exists(),
+                "{file}"
+            );
+        }
+        assert!(InstanceMetadata::load(instance.path())
+            .unwrap()
+            .installed_file("resourcepacks/faithful.zip")
+            .is_some());
+    }
+
+    #[test]
+    fn remove_with_dependents() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        std::fs::create_dir_all(dir.join("mods")).unwrap();
+        std::fs::write(dir.join("mods/fabric-api.jar"), b"").unwrap();
+
+        let mut metadata = InstanceMetadata::default();
+        metadata.record_file(
+            "mods/fabric-api.jar",
+            InstalledFile::modrinth("P7dR8mSH", "a"),
+        );
+        metadata.record_file(
+            "mods/sodium.jar",
+            InstalledFile {
+                dependencies: vec!["P7dR8mSH".to_owned()],
+                ..InstalledFile::modrinth("AANobbMI", "b")
+            },
+        );
+        metadata.save(dir).unwrap();
+
+        let dependents = remove_mod(dir, "P7dR8mSH").unwrap();
+        let removed = !dir
+            .join("mods/fabric-api.jar")
+            .exists();
+        let metadata = InstanceMetadata::load(dir).unwrap();
+        let again = remove_mod(dir, "P7dR8mSH");
+
+        assert_eq!(dependents, ["mods/sodium.jar"]);
+        assert!(removed);
+        assert!(metadata
+            .installed_file("mods/fabric-api.jar")
+            .is_none());
+        assert!(again.is_err());
+    }
+
+    #[test]
+    fn toggle_mod() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        std::fs::create_dir_all(dir.join("mods")).unwrap();
+        std::fs::write(dir.join("mods/sodium.jar"), b"").unwrap();
+
+        let mut metadata = InstanceMetadata::default();
+        metadata.record_file("mods/sodium.jar", InstalledFile::modrinth("AANobbMI", "a"));
+        metadata.save(dir).unwrap();
+
+        set_mod_enabled(dir, "AANobbMI", false).unwrap();
+        let disabled = dir
+            .join("mods/sodium.jar.disabled")
+            .exists();
+        let recorded = InstanceMetadata::load(dir)
+            .unwrap()
+            .installed_file("mods/sodium.jar.disabled")
+            .is_some();
+        set_mod_enabled(dir, "AANobbMI", true).unwrap();
+        let enabled = dir
+            .join("mods/sodium.jar")
+            .exists();
+
+        assert!(disabled && recorded && enabled);
+    }
+}
@@ -0,0 +1,1018 @@
+//! Per-instance metadata.
+//!
+//! Uranium keeps its own data about an instance in a `uranium_instance.json`
+//! file inside the instance directory, next to `mods/`, `config/`...
+//!
+//! The file is optional, if it doesn't exist the default metadata is used.
+//!
+//! [`InstanceManager`] lists the instances of a directory so frontends can
+//! filter and sort them without keeping their own database, and creates,
+//! clones, renames and deletes them.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::Utc;
+use log::{info, warn};
+use mine_data_structs::{
+    minecraft::{Profile, ProfilesJson},
+    rinth::RinthVersion,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Result, UraniumError},
+    hooks::{HookPhase, Hooks},
+    paths,
+    variables::constants::{INSTANCE_FILE, PROFILES_FILE},
+};
+
+/// Data stored by uranium for an instance.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InstanceMetadata {
+    /// Minecraft version of the instance, e.g. `"1.21"`.
+    #[serde(default)]
+    pub game_version: Option<String>,
+
+    /// Mod loader of the instance, e.g. `"fabric"`.
+    #[serde(default)]
+    pub loader: Option<String>,
+
+    /// Launch settings applied only to this instance.
+    #[serde(default)]
+    pub launch: LaunchOverrides,
+
+    /// User defined tags, e.g. `"modded"` or `"survival"`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    #[serde(default)]
+    pub notes: String,
+
+    #[serde(default)]
+    pub favorite: bool,
+
+    /// Total seconds played, accumulated by
+    /// [`GameProcess`](crate::launcher::GameProcess).
+    #[serde(default)]
+    pub playtime_secs: u64,
+
+    /// Unix timestamp (seconds) of the last time the game exited.
+    #[serde(default)]
+    pub last_played: Option<i64>,
+
+    /// Modrinth project ids of the mods kept at their current version.
+    #[serde(default)]
+    pub pinned_projects: Vec<String>,
+
+    /// File names (`sodium.jar`) of the mods kept at their current version.
+    #[serde(default)]
+    pub pinned_files: Vec<String>,
+
+    /// Source of the files installed by uranium, by their path relative to
+    /// the instance, e.g. `"mods/sodium.jar"`.
+    #[serde(default)]
+    pub files: HashMap<String, InstalledFile>,
+
+    /// The modpack project and version the instance was installed from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pack: Option<InstalledFile>,
+}
+
+/// Where an installed file was downloaded from.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    Modrinth,
+    Curse,
+}
+
+/// The project and version an installed file belongs to.
+///
+/// For Curse files `version_id` is the file id.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InstalledFile {
+    pub provider: Provider,
+    pub project_id: String,
+    pub version_id: String,
+
+    /// Project ids of the required dependencies of the file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+}
+
+impl InstalledFile {
+    pub fn modrinth(project_id: &str, version_id: &str) -> Self {
+        InstalledFile {
+            provider: Provider::Modrinth,
+            project_id: project_id.to_owned(),
+            version_id: version_id.to_owned(),
+            dependencies: vec![],
+        }
+    }
+
+    pub fn curse(project_id: usize, file_id: usize) -> Self {
+        InstalledFile {
+            provider: Provider::Curse,
+            project_id: project_id.to_string(),
+            version_id: file_id.to_string(),
+            dependencies: vec![],
+        }
+    }
+
+    /// Source of a file of the Modrinth `version`, with its required
+    /// dependencies.
+    pub fn from_version(version: &RinthVersion) -> Self {
+        InstalledFile {
+            dependencies: version
+                .dependencies
+                .iter()
+                .filter(|d| d.is_required() && !d.get_project_id().is_empty())
+                .map(|d| d.get_project_id().to_owned())
+                .collect(),
+            ..Self::modrinth(&version.project_id, &version.id)
+        }
+    }
+}
+
+impl InstanceMetadata {
+    /// Returns the path of the metadata file for `instance_dir`.
+    pub fn path<I: AsRef<Path>>(instance_dir: I) -> PathBuf {
+        instance_dir
+            .as_ref()
+            .join(INSTANCE_FILE)
+    }
+
+    /// Reads the metadata of the instance in `instance_dir`.
+    ///
+    /// If the instance has no metadata file the default metadata is returned.
+    ///
+    /// # Errors
+    /// Returns `Err(UraniumError::WrongFileFormat)` if the file exists but is
+    /// not valid and `Err(UraniumError::IOError)` if it can't be read.
+    pub fn load<I: AsRef<Path>>(instance_dir: I) -> Result<InstanceMetadata> {
+        let path = Self::path(instance_dir);
+        if !path.exists() {
+            return Ok(InstanceMetadata::default());
+        }
+
+        let content = std::fs::read(path)?;
+        serde_json::from_slice(&content).map_err(|_| UraniumError::WrongFileFormat)
+    }
+
+    /// Adds a play session of `duration` to the playtime and sets
+    /// `last_played` to now.
+    pub fn record_session(&mut self, duration: Duration) {
+        self.playtime_secs += duration.as_secs();
+        self.last_played = Some(Utc::now().timestamp());
+    }
+
+    /// Keeps the mods of the Modrinth project `project_id` out of updates.
+    pub fn pin_project(&mut self, project_id: &str) {
+        if !self
+            .pinned_projects
+            .iter()
+            .any(|p| p == project_id)
+        {
+            self.pinned_projects
+                .push(project_id.to_owned());
+        }
+    }
+
+    pub fn unpin_project(&mut self, project_id: &str) {
+        self.pinned_projects
+            .retain(|p| p != project_id);
+    }
+
+    /// Keeps the mod file `file_name` out of updates.
+    pub fn pin_file(&mut self, file_name: &str) {
+        if !self
+            .pinned_files
+            .iter()
+            .any(|f| f == file_name)
+        {
+            self.pinned_files
+                .push(file_name.to_owned());
+        }
+    }
+
+    pub fn unpin_file(&mut self, file_name: &str) {
+        self.pinned_files
+            .retain(|f| f != file_name);
+    }
+
+    /// Returns `true` if the mod `file_name`, of the Modrinth project
+    /// `project_id` if it's known, is pinned.
+    pub fn is_pinned(&self, file_name: &str, project_id: Option<&str>) -> bool {
+        self.pinned_files
+            .iter()
+            .any(|f| f == file_name)
+            || project_id.is_some_and(|id| {
+                self.pinned_projects
+                    .iter()
+                    .any(|p| p == id)
+            })
+    }
+
+    /// Records that the file in `path` (relative to the instance) comes from
+    /// `source`.
+    pub fn record_file<P: AsRef<Path>>(&mut self, path: P, source: InstalledFile) {
+        self.files
+            .insert(file_key(path.as_ref()), source);
+    }
+
+    /// Returns the source of the file in `path`, `None` if it wasn't
+    /// installed by uranium.
+    pub fn installed_file<P: AsRef<Path>>(&self, path: P) -> Option<&InstalledFile> {
+        self.files
+            .get(&file_key(path.as_ref()))
+    }
+
+    pub fn forget_file<P: AsRef<Path>>(&mut self, path: P) {
+        self.files
+            .remove(&file_key(path.as_ref()));
+    }
+
+    /// Returns the files of the project `project_id`.
+    pub fn project_files(&self, project_id: &str) -> Vec<&str> {
+        self.files
+            .iter()
+            .filter(|(_, f)| f.project_id == project_id)
+            .map(|(path, _)| path.as_str())
+            .collect()
+    }
+
+    /// Returns the files of other projects which require `project_id`.
+    pub fn dependents(&self, project_id: &str) -> Vec<&str> {
+        self.files
+            .iter()
+            .filter(|(_, f)| {
+                f.project_id != project_id
+                    && f.dependencies
+                        .iter()
+                        .any(|d| d == project_id)
+            })
+            .map(|(path, _)| path.as_str())
+            .collect()
+    }
+
+    /// Loads the metadata of `instance_dir`, applies `f` and saves it.
+    ///
+    /// # Errors
+    /// Returns an error if the metadata can't be read or written.
+    pub fn update<I: AsRef<Path>, F: FnOnce(&mut InstanceMetadata)>(
+        instance_dir: I,
+        f: F,
+    ) -> Result<()> {
+        let mut metadata = Self::load(&instance_dir)?;
+        f(&mut metadata);
+        metadata.save(instance_dir)
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags
+            .iter()
+            .any(|t| t == tag)
+    }
+
+    /// Writes the metadata into `instance_dir`.
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be written.
+    pub fn save<I: AsRef<Path>>(&self, instance_dir: I) -> Result<()> {
+        let content = serde_json::to_vec_pretty(self).map_err(|_| UraniumError::WrongFileFormat)?;
+        std::fs::write(Self::path(instance_dir), content)?;
+        Ok(())
+    }
+}
+
+/// Key of `path` in [`InstanceMetadata::files`], always with `/`.
+fn file_key(path: &Path) -> String {
+    path.components()
+        .map(|c| {
+            c.as_os_str()
+                .to_string_lossy()
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// An instance directory and its metadata.
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub path: PathBuf,
+    pub metadata: InstanceMetadata,
+}
+
+impl Instance {
+    /// Reads the instance in `path`.
+    ///
+    /// # Errors
+    /// Returns an error if its metadata file is not valid.
+    pub fn open<I: AsRef<Path>>(path: I) -> Result<Instance> {
+        Ok(Instance {
+            path: path.as_ref().to_path_buf(),
+            metadata: InstanceMetadata::load(&path)?,
+        })
+    }
+
+    /// Name of the instance directory.
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
+/// Queries over the instances in a directory, every subdirectory is an
+/// instance.
+///
+/// ```no_run
+/// # fn foo() -> uranium::error::Result<()> {
+/// use uranium::instance::InstanceManager;
+///
+/// let manager = InstanceManager::new("path/to/instances");
+/// for instance in manager.by_last_played()? {
+///     println!("{} ({}s played)", instance.path.display(), instance.metadata.playtime_secs);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// With [`InstanceManager::with_launcher_profiles`] every instance also gets a
+/// profile in the `launcher_profiles.json` of a `.minecraft`, whose game dir
+/// is the instance dir.
+#[derive(Debug, Clone)]
+pub struct InstanceManager {
+    root: PathBuf,
+    dot_minecraft: Option<PathBuf>,
+    hooks: Hooks,
+}
+
+impl InstanceManager {
+    pub fn new<I: AsRef<Path>>(root: I) -> InstanceManager {
+        InstanceManager {
+            root: root.as_ref().to_path_buf(),
+            dot_minecraft: None,
+            hooks: Hooks::default(),
+        }
+    }
+
+    /// Runs `hooks` around [`InstanceManager::create_with_hooks`].
+    #[must_use]
+    pub fn with_hooks(mut self, hooks: Hooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Keeps the launcher profiles of `dot_minecraft` in sync with the
+    /// instances created, cloned, renamed and deleted by this manager.
+    #[must_use]
+    pub fn with_launcher_profiles<I: AsRef<Path>>(mut self, dot_minecraft: I) -> Self {
+        self.dot_minecraft = Some(
+            dot_minecraft
+                .as_ref()
+                .to_path_buf(),
+        );
+        self
+    }
+
+    /// Same as [`InstanceManager::with_launcher_profiles`] with the
+    /// `.minecraft` given by [`paths::minecraft_dir`].
+    ///
+    /// # Errors
+    /// Returns an error if the `.minecraft` directory can't be found.
+    pub fn with_default_launcher_profiles(self) -> Result<Self> {
+        Ok(self.with_launcher_profiles(paths::minecraft_dir()?))
+    }
+
+    /// Returns the instances of the profiles in the `launcher_profiles.json`
+    /// of `dot_minecraft`, by profile name. Profiles without a game dir play
+    /// in `dot_minecraft` itself.
+    ///
+    /// # Errors
+    /// Returns an error if the profiles file can't be read.
+    pub fn from_launcher_profiles<I: AsRef<Path>>(
+        dot_minecraft: I,
+    ) -> Result<Vec<(String, Instance)>> {
+        let dot_minecraft = dot_minecraft.as_ref();
+        let profiles = ProfilesJson::read_json_from(dot_minecraft.join(PROFILES_FILE))?;
+
+        let mut instances = Vec::new();
+        for (name, profile) in profiles.get_profiles() {
+            let path = profile
+                .game_dir
+                .clone()
+                .unwrap_or_else(|| dot_minecraft.to_path_buf());
+            match Instance::open(&path) {
+                Ok(instance) => instances.push((name.clone(), instance)),
+                Err(e) => warn!("Skipping profile {name}: {e}"),
+            }
+        }
+        Ok(instances)
+    }
+
+    /// Returns the instance called `name`.
+    ///
+    /// # Errors
+    /// Returns an error if `name` is not a valid instance name or there is no
+    /// such instance.
+    pub fn get(&self, name: &str) -> Result<Instance> {
+        let path = self.existing_path(name)?;
+        Instance::open(path)
+    }
+
+    /// Creates the instance `name` with `metadata`, with empty `mods/` and
+    /// `config/` dirs.
+    ///
+    /// The launcher profile, if any, plays `metadata.game_version` or the
+    /// latest release if it's not set.
+    ///
+    /// # Errors
+    /// Returns an error if `name` is not valid or already exists, or if the
+    /// instance can't be written.
+    pub fn create(&self, name: &str, metadata: InstanceMetadata) -> Result<Instance> {
+        let path = self.new_path(name)?;
+        self.create_at(name, path, metadata)
+    }
+
+    /// Same as [`InstanceManager::create`] running the before install hooks
+    /// set with [`InstanceManager::with_hooks`] first and the after install
+    /// ones once the instance exists.
+    ///
+    /// # Errors
+    /// Same as [`InstanceManager::create`], or
+    /// `Err(UraniumError::HookFailed)` if a hook fails. The instance is kept
+    /// if an after install hook fails.
+    pub async fn create_with_hooks(
+        &self,
+        name: &str,
+        metadata: InstanceMetadata,
+    ) -> Result<Instance> {
+        let path = self.new_path(name)?;
+        self.hooks
+            .run(HookPhase::BeforeInstall, &path)
+            .await?;
+        let instance = self.create_at(name, path, metadata)?;
+        self.hooks
+            .run(HookPhase::AfterInstall, &instance.path)
+            .await?;
+        Ok(instance)
+    }
+
+    fn create_at(&self, name: &str, path: PathBuf, metadata: InstanceMetadata) -> Result<Instance> {
+        std::fs::create_dir_all(path.join("mods"))?;
+        std::fs::create_dir_all(path.join("config"))?;
+        metadata.save(&path)?;
+
+        let version = metadata
+            .game_version
+            .as_deref()
+            .unwrap_or("latest-release");
+        self.update_profiles(|profiles| {
+            profiles.insert(
+                name,
+                Profile::new("Furnace", version, name, "custom", Some(&path)),
+            );
+        })?;
+
+        info!("Instance {name} created");
+        Ok(Instance { path, metadata })
+    }
+
+    /// Copies the mods, config and metadata of the instance `from` into the
+    /// new instance `to`. Worlds, logs and the rest are not copied.
+    ///
+    /// # Errors
+    /// Returns an error if any name is not valid, if `from` doesn't exist or
+    /// `to` does, or if the files can't be copied.
+    pub fn clone_instance(&self, from: &str, to: &str) -> Result<Instance> {
+        let source = self.existing_path(from)?;
+        let path = self.new_path(to)?;
+        std::fs::create_dir_all(&path)?;
+
+        let options = fs_extra::dir::CopyOptions::new();
+        for dir in ["mods", "config"] {
+            if source.join(dir).is_dir() {
+                fs_extra::dir::copy(source.join(dir), &path, &options)
+                    .map_err(|e| UraniumError::OtherWithReason(e.to_string()))?;
+            }
+        }
+        let metadata = InstanceMetadata::load(&source)?;
+        metadata.save(&path)?;
+
+        self.update_profiles(|profiles| {
+            if let Some(profile) = profiles
+                .get_profiles()
+                .get(from)
+                .cloned()
+            {
+                profiles.insert(
+                    to,
+                    Profile {
+                        name: to.to_owned(),
+                        game_dir: Some(path.clone()),
+                        ..profile
+                    },
+                );
+            }
+        })?;
+
+        info!("Instance {from} cloned into {to}");
+        Ok(Instance { path, metadata })
+    }
+
+    /// Renames the instance `from` to `to`.
+    ///
+    /// # Errors
+    /// Returns an error if any name is not valid, if `from` doesn't exist or
+    /// `to` does, or if the dir can't be renamed.
+    pub fn rename(&self, from: &str, to: &str) -> Result<Instance> {
+        let source = self.existing_path(from)?;
+        let path = self.new_path(to)?;
+        std::fs::rename(&source, &path)?;
+
+        self.update_profiles(|profiles| {
+            if let Some(profile) = profiles
+                .get_profiles()
+                .get(from)
+                .cloned()
+            {
+                profiles.remove(from);
+                profiles.insert(
+                    to,
+                    Profile {
+                        name: to.to_owned(),
+                        game_dir: Some(path.clone()),
+                        ..profile
+                    },
+                );
+            }
+        })?;
+
+        Instance::open(path)
+    }
+
+    /// Deletes the instance `name` and everything inside it, worlds
+    /// included.
+    ///
+    /// # Errors
+    /// Returns an error if `name` is not valid or doesn't exist, or if the
+    /// dir can't be removed.
+    pub fn delete(&self, name: &str) -> Result<()> {
+        let path = self.existing_path(name)?;
+        std::fs::remove_dir_all(path)?;
+        self.update_profiles(|profiles| profiles.remove(name))?;
+        info!("Instance {name} deleted");
+        Ok(())
+    }
+
+    /// Returns the dir of the instance `name`, which must be a single path
+    /// component so no dir outside the root is touched.
+    fn instance_path(&self, name: &str) -> Result<PathBuf> {
+        let mut components = Path::new(name).components();
+        match (components.next(), components.next()) {
+            (Some(std::path::Component::Normal(_)), None) => Ok(self.root.join(name)),
+            _ => Err(UraniumError::OtherWithReason(format!(
+                "{name:?} is not a valid instance name"
+            ))),
+        }
+    }
+
+    fn existing_path(&self, name: &str) -> Result<PathBuf> {
+        let path = self.instance_path(name)?;
+        if !path.is_dir() {
+            return Err(UraniumError::OtherWithReason(format!(
+                "There is no instance called {name}"
+            )));
+        }
+        Ok(path)
+    }
+
+    fn new_path(&self, name: &str) -> Result<PathBuf> {
+        let path = self.instance_path(name)?;
+        if path.exists() {
+            return Err(UraniumError::OtherWithReason(format!(
+                "The instance {name} already exists"
+            )));
+        }
+        Ok(path)
+    }
+
+    /// Applies `f` to the launcher profiles, if they are managed.
+    fn update_profiles<F: FnOnce(&mut ProfilesJson)>(&self, f: F) -> Result<()> {
+        let Some(dot_minecraft) = &self.dot_minecraft else {
+            return Ok(());
+        };
+
+        let path = dot_minecraft.join(PROFILES_FILE);
+        let mut profiles = if path.exists() {
+            ProfilesJson::read_json_from(&path)?
+        } else {
+            std::fs::create_dir_all(dot_minecraft)?;
+            ProfilesJson::default()
+        };
+        f(&mut profiles);
+
+        let content =
+            serde_json::to_string_pretty(&profiles).map_err(|_| UraniumError::WrongFileFormat)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Returns every instance, instances with a broken metadata file are
+    /// skipped.
+    ///
+    /// # Errors
+    /// Returns an error if the instances directory can't be read.
+    pub fn list(&self) -> Result<Vec<Instance>> {
+        let mut instances = Vec::new();
+        for entry in std::fs::read_dir(&self.root)? {
+            let path = entry?.path();
+            if !path.is_dir() {
+                continue;
+            }
+
+            match InstanceMetadata::load(&path) {
+                Ok(metadata) => instances.push(Instance { path, metadata }),
+                Err(e) => warn!("Skipping {}: {e}", path.display()),
+            }
+        }
+        Ok(instances)
+    }
+
+    /// Returns the instances tagged with `tag`.
+    ///
+    /// # Errors
+    /// Returns an error if the instances directory can't be read.
+    pub fn with_tag(&self, tag: &str) -> Result<Vec<Instance>> {
+        Ok(self
+            .list()?
+            .into_iter()
+            .filter(|i| i.metadata.has_tag(tag))
+            .collect())
+    }
+
+    /// Returns the instances marked as favorite.
+    ///
+    /// # Errors
+    /// Returns an error if the instances directory can't be read.
+    pub fn favorites(&self) -> Result<Vec<Instance>> {
+        Ok(self
+            .list()?
+            .into_iter()
+            .filter(|i| i.metadata.favorite)
+            .collect())
+    }
+
+    /// Returns the instances sorted from the most recently played, the never
+    /// played ones go last.
+    ///
+    /// # Errors
+    /// Returns an error if the instances directory can't be read.
+    pub fn by_last_played(&self) -> Result<Vec<Instance>> {
+        let mut instances = self.list()?;
+        instances.sort_by_key(|i| std::cmp::Reverse(i.metadata.last_played));
+        Ok(instances)
+    }
+}
+
+/// Launch settings of a single instance, applied on top of the default ones
+/// by [`LaunchCommand`](crate::launcher::LaunchCommand).
+///
+/// ```json
+/// {
+///   "jvm_args": ["-Xmx6G", "-XX:+UseZGC"],
+///   "env": { "MESA_GL_VERSION_OVERRIDE": "4.6" },
+///   "wrapper": ["gamemoderun"],
+///   "pre_launch": ["sh", "backup_worlds.sh"],
+///   "post_exit": ["sh", "notify.sh"],
+///   "java_runtimes": [{ "path": "/opt/graalvm-21/bin/java", "major_version": 21 }]
+/// }
+/// ```
+///
+/// In code use the builder methods or a struct literal ending with
+/// `..Default::default()`, new fields are added with a default:
+///
+/// ```
+/// use uranium::instance::LaunchOverrides;
+///
+/// let overrides = LaunchOverrides::default()
+///     .jvm_args(vec!["-Xmx6G".to_owned()])
+///     .wrapper(vec!["gamemoderun".to_owned()]);
+/// assert_eq!(
+///     overrides,
+///     LaunchOverrides {
+///         jvm_args: vec!["-Xmx6G".to_owned()],
+///         wrapper: vec!["gamemoderun".to_owned()],
+///         ..Default::default()
+///     }
+/// );
+/// ```
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LaunchOverrides {
+    /// Extra JVM arguments, added after the default ones so they take
+    /// precedence.
+    #[serde(default)]
+    pub jvm_args: Vec<String>,
+
+    /// Extra environment variables for the game process.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+
+    /// Command the game is launched through, e.g. `["gamemoderun"]` or
+    /// `["prime-run"]`.
+    #[serde(default)]
+    pub wrapper: Vec<String>,
+
+    /// Command run before launching the game, the launch is aborted if it
+    /// fails.
+    #[serde(default)]
+    pub pre_launch: Vec<String>,
+
+    /// Command run after the game exits.
+    #[serde(default)]
+    pub post_exit: Vec<String>,
+
+    /// Java runtimes registered with the instance, preferred over the
+    /// default one when their version is the one the game needs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub java_runtimes: Vec<JavaRuntime>,
+}
+
+impl LaunchOverrides {
+    /// Sets the extra JVM arguments.
+    #[must_use]
+    pub fn jvm_args(mut self, args: Vec<String>) -> Self {
+        self.jvm_args = args;
+        self
+    }
+
+    /// Adds the environment variable `key` for the game process.
+    #[must_use]
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.env
+            .insert(key.to_owned(), value.to_owned());
+        self
+    }
+
+    /// Sets the command the game is launched through.
+    #[must_use]
+    pub fn wrapper(mut self, command: Vec<String>) -> Self {
+        self.wrapper = command;
+        self
+    }
+
+    /// Sets the command run before launching the game.
+    #[must_use]
+    pub fn pre_launch(mut self, command: Vec<String>) -> Self {
+        self.pre_launch = command;
+        self
+    }
+
+    /// Sets the command run after the game exits.
+    #[must_use]
+    pub fn post_exit(mut self, command: Vec<String>) -> Self {
+        self.post_exit = command;
+        self
+    }
+
+    /// Registers `runtime`, replacing the one with the same major version.
+    pub fn register_runtime(&mut self, runtime: JavaRuntime) {
+        self.java_runtimes
+            .retain(|r| r.major_version != runtime.major_version);
+        self.java_runtimes
+            .push(runtime);
+    }
+
+    /// Returns the registered runtime for Java `major_version`.
+    pub fn runtime_for(&self, major_version: usize) -> Option<&JavaRuntime> {
+        self.java_runtimes
+            .iter()
+            .find(|r| r.major_version == major_version)
+    }
+}
+
+/// A Java runtime installed by the user, e.g. a GraalVM.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct JavaRuntime {
+    /// The `java` executable.
+    pub path: PathBuf,
+    pub major_version: usize,
+}
+
+impl JavaRuntime {
+    pub fn new<I: AsRef<Path>>(path: I, major_version: usize) -> JavaRuntime {
+        JavaRuntime {
+            path: path.as_ref().to_path_buf(),
+            major_version,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+
+        assert_eq!(
+            InstanceMetadata::load(dir).unwrap(),
+            InstanceMetadata::default()
+        );
+
+        let mut metadata = InstanceMetadata::default();
+        metadata.launch.jvm_args = vec!["-Xmx6G".to_owned()];
+        metadata.launch.wrapper = vec!["gamemoderun".to_owned()];
+        metadata.save(dir).unwrap();
+
+        assert_eq!(InstanceMetadata::load(dir).unwrap(), metadata);
+    }
+
+    #[test]
+    fn installed_files() {
+        let mut metadata = InstanceMetadata::default();
+        metadata.record_file(
+            Path::new("mods").join("sodium.jar"),
+            InstalledFile::modrinth("AANobbMI", "abc"),
+        );
+        metadata.record_file("mods/jei.jar", InstalledFile::curse(238222, 1));
+
+        assert_eq!(
+            metadata
+                .installed_file("mods/sodium.jar")
+                .map(|f| f.provider),
+            Some(Provider::Modrinth)
+        );
+        let json = serde_json::to_value(&metadata).unwrap();
+        assert_eq!(json["files"]["mods/jei.jar"]["provider"], "curse");
+
+        metadata.forget_file("mods/sodium.jar");
+        assert!(metadata
+            .installed_file("mods/sodium.jar")
+            .is_none());
+    }
+
+    #[test]
+    fn dependents() {
+        let mut metadata = InstanceMetadata::default();
+        metadata.record_file(
+            "mods/fabric-api.jar",
+            InstalledFile::modrinth("P7dR8mSH", "a"),
+        );
+        metadata.record_file(
+            "mods/sodium-extra.jar",
+            InstalledFile {
+                dependencies: vec!["P7dR8mSH".to_owned(), "AANobbMI".to_owned()],
+                ..InstalledFile::modrinth("PtjYWJkn", "b")
+            },
+        );
+
+        assert_eq!(metadata.project_files("P7dR8mSH"), ["mods/fabric-api.jar"]);
+        assert_eq!(metadata.dependents("P7dR8mSH"), ["mods/sodium-extra.jar"]);
+        assert!(metadata
+            .dependents("PtjYWJkn")
+            .is_empty());
+    }
+
+    #[test]
+    fn pins() {
+        let mut metadata = InstanceMetadata::default();
+        metadata.pin_project("AANobbMI");
+        metadata.pin_project("AANobbMI");
+        metadata.pin_file("iris.jar");
+
+        assert_eq!(metadata.pinned_projects.len(), 1);
+        assert!(metadata.is_pinned("sodium.jar", Some("AANobbMI")));
+        assert!(metadata.is_pinned("iris.jar", None));
+        assert!(!metadata.is_pinned("sodium.jar", None));
+
+        metadata.unpin_project("AANobbMI");
+        metadata.unpin_file("iris.jar");
+        assert!(!metadata.is_pinned("sodium.jar", Some("AANobbMI")));
+        assert!(!metadata.is_pinned("iris.jar", None));
+    }
+
+    #[test]
+    fn manager_queries() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        for name in ["vanilla", "modded", "new"] {
+            std::fs::create_dir_all(root.join(name)).unwrap();
+        }
+
+        let mut modded = InstanceMetadata {
+            tags: vec!["modded".to_owned()],
+            favorite: true,
+            ..Default::default()
+        };
+        modded.record_session(Duration::from_secs(90));
+        modded.last_played = Some(200);
+        modded
+            .save(root.join("modded"))
+            .unwrap();
+
+        let vanilla = InstanceMetadata {
+            last_played: Some(100),
+            ..Default::default()
+        };
+        vanilla
+            .save(root.join("vanilla"))
+            .unwrap();
+
+        let manager = InstanceManager::new(root);
+        let tagged = manager
+            .with_tag("modded")
+            .unwrap();
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(
+            tagged[0]
+                .metadata
+                .playtime_secs,
+            90
+        );
+        assert_eq!(
+            manager
+                .favorites()
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let names: Vec<_> = manager
+            .by_last_played()
+            .unwrap()
+            .into_iter()
+            .map(|i| {
+                i.path
+                    .file_name()
+                    .unwrap()
+                    .to_owned()
+            })
+            .collect();
+        assert_eq!(names, ["modded", "vanilla", "new"]);
+    }
+
+    #[test]
+    fn manager_lifecycle() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        let dot_minecraft = root.join(".minecraft");
+        let manager = InstanceManager::new(root).with_launcher_profiles(&dot_minecraft);
+
+        let metadata = InstanceMetadata {
+            game_version: Some("1.21".to_owned()),
+            ..Default::default()
+        };
+        manager
+            .create("survival", metadata)
+            .unwrap();
+        std::fs::write(root.join("survival/mods/sodium.jar"), b"").unwrap();
+        manager
+            .clone_instance("survival", "copy")
+            .unwrap();
+        manager
+            .rename("copy", "creative")
+            .unwrap();
+        manager
+            .delete("survival")
+            .unwrap();
+
+        let creative = manager
+            .get("creative")
+            .unwrap();
+        let mut profiles: Vec<_> = InstanceManager::from_launcher_profiles(&dot_minecraft)
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        profiles.sort();
+        let escapes = manager.delete("..").is_err()
+            && manager
+                .create("a/b", Default::default())
+                .is_err();
+        let has_mod = creative
+            .path
+            .join("mods/sodium.jar")
+            .exists();
+
+        assert_eq!(
+            creative
+                .metadata
+                .game_version
+                .as_deref(),
+            Some("1.21")
+        );
+        assert!(has_mod);
+        assert_eq!(profiles, ["creative"]);
+        assert!(escapes);
+    }
+}
@@ -0,0 +1,480 @@
+//! Launching the game.
+//!
+//! [`LaunchCommand`] builds the command used to run an instance, applying the
+//! instance [`LaunchOverrides`]. [`GameProcess`] supervises the running game.
+
+use std::{
+    path::{Path, PathBuf},
+    process::{ExitStatus, Stdio},
+    time::Instant,
+};
+
+use log::{error, info, warn};
+use mine_data_structs::minecraft::{GameArgument, PlaceholderMap};
+use tokio::{
+    io::AsyncRead,
+    process::{Child, Command},
+    task::JoinHandle,
+};
+
+use crate::{
+    error::{Result, UraniumError},
+    instance::{InstanceMetadata, LaunchOverrides},
+    variables::constants::GAME_LOG_FILE,
+};
+
+/// Builder for the command which launches an instance.
+///
+/// # Example
+///
+/// ```no_run
+/// use uranium::launcher::LaunchCommand;
+///
+/// # async fn foo() -> uranium::error::Result<()> {
+/// let mut child = LaunchCommand::new("/usr/bin/java", "net.minecraft.client.main.Main", "my/instance")
+///     .jvm_args(vec!["-cp".to_owned(), "libs.jar:client.jar".to_owned()])
+///     .game_args(vec!["--username".to_owned(), "Steve".to_owned()])
+///     .with_instance_overrides()?
+///     .launch()
+///     .await?;
+/// child.wait().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LaunchCommand {
+    java: PathBuf,
+    /// Major Java version the game needs.
+    java_version: Option<usize>,
+    main_class: String,
+    instance_dir: PathBuf,
+    jvm_args: Vec<String>,
+    game_args: Vec<String>,
+    overrides: LaunchOverrides,
+}
+
+impl LaunchCommand {
+    pub fn new<I: AsRef<Path>, J: AsRef<Path>>(java: I, main_class: &str, instance_dir: J) -> Self {
+        LaunchCommand {
+            java: java.as_ref().to_path_buf(),
+            java_version: None,
+            main_class: main_class.to_owned(),
+            instance_dir: instance_dir
+                .as_ref()
+                .to_path_buf(),
+            jvm_args: vec![],
+            game_args: vec![],
+            overrides: LaunchOverrides::default(),
+        }
+    }
+
+    /// Sets the major Java version the game needs, usually the one in
+    /// [`Root::java_version`](mine_data_structs::minecraft::Root).
+    ///
+    /// If a runtime for it was registered in the overrides it is used instead
+    /// of the default `java`.
+    pub fn java_version(mut self, major_version: usize) -> Self {
+        self.java_version = Some(major_version);
+        self
+    }
+
+    /// Returns the `java` executable which will run the game.
+    pub fn java(&self) -> &Path {
+        self.java_version
+            .and_then(|v| self.overrides.runtime_for(v))
+            .map_or(&self.java, |r| &r.path)
+    }
+
+    /// Sets the default JVM arguments (classpath, natives path...).
+    pub fn jvm_args(mut self, args: Vec<String>) -> Self {
+        self.jvm_args = args;
+        self
+    }
+
+    /// Sets the arguments given to the game main class.
+    pub fn game_args(mut self, args: Vec<String>) -> Self {
+        self.game_args = args;
+        self
+    }
+
+    /// Sets the game arguments resolving the ones of a version json, see
+    /// [`GameArgument::resolve`].
+    pub fn resolved_game_args(self, args: &[GameArgument], placeholders: &PlaceholderMap) -> Self {
+        self.game_args(
+            args.iter()
+                .flat_map(|arg| arg.resolve(placeholders))
+                .collect(),
+        )
+    }
+
+    /// Sets the overrides to apply.
+    pub fn overrides(mut self, overrides: LaunchOverrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Uses the overrides stored in the instance metadata.
+    ///
+    /// # Errors
+    /// Returns an error if the instance metadata can't be read.
+    pub fn with_instance_overrides(self) -> Result<Self> {
+        let metadata = InstanceMetadata::load(&self.instance_dir)?;
+        Ok(self.overrides(metadata.launch))
+    }
+
+    /// Returns the overrides which will be applied.
+    pub fn get_overrides(&self) -> &LaunchOverrides {
+        &self.overrides
+    }
+
+    /// Returns the program and the arguments to run, the wrapper (if any)
+    /// goes first.
+    pub fn args(&self) -> Vec<String> {
+        self.overrides
+            .wrapper
+            .iter()
+            .cloned()
+            .chain(std::iter::once(
+                self.java()
+                    .display()
+                    .to_string(),
+            ))
+            .chain(self.jvm_args.iter().cloned())
+            .chain(
+                self.overrides
+                    .jvm_args
+                    .iter()
+                    .cloned(),
+            )
+            .chain(std::iter::once(self.main_class.clone()))
+            .chain(self.game_args.iter().cloned())
+            .collect()
+    }
+
+    /// Builds the `Command` without running it.
+    pub fn build(&self) -> Command {
+        let args = self.args();
+        let mut command = Command::new(&args[0]);
+        command
+            .args(&args[1..])
+            .envs(&self.overrides.env)
+            .current_dir(&self.instance_dir);
+        command
+    }
+
+    /// Runs the pre-launch hook and then spawns the game.
+    ///
+    /// # Errors
+    /// Returns an error if the pre-launch hook fails or the game can't be
+    /// spawned.
+    pub async fn launch(&self) -> Result<Child> {
+        self.run_hook(&self.overrides.pre_launch)
+            .await?;
+        info!("Launching {}", self.instance_dir.display());
+        Ok(self.build().spawn()?)
+    }
+
+    /// Launches the game with its output captured into the instance log
+    /// (`logs/uranium_game.log`) and returns a handle to supervise it.
+    ///
+    /// # Errors
+    /// Returns an error if the game can't be launched or the log file can't be
+    /// created.
+    pub async fn spawn(&self, policy: RestartPolicy) -> Result<GameProcess> {
+        let (child, log_tasks) = self.spawn_logged().await?;
+        Ok(GameProcess {
+            command: self.clone(),
+            child,
+            log_tasks,
+            policy,
+            restarts: 0,
+            started: Instant::now(),
+        })
+    }
+
+    async fn spawn_logged(&self) -> Result<(Child, Vec<JoinHandle<()>>)> {
+        self.run_hook(&self.overrides.pre_launch)
+            .await?;
+
+        let log_path = self
+            .instance_dir
+            .join(GAME_LOG_FILE);
+        if let Some(parent) = log_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let log = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)?;
+
+        info!("Launching {}", self.instance_dir.display());
+        let mut child = self
+            .build()
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let mut log_tasks = Vec::with_capacity(2);
+        if let Some(stdout) = child.stdout.take() {
+            log_tasks.push(capture(stdout, log.try_clone()?));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            log_tasks.push(capture(stderr, log));
+        }
+
+        Ok((child, log_tasks))
+    }
+
+    /// Runs the post-exit hook, it should be called once the game has exited.
+    ///
+    /// # Errors
+    /// Returns an error if the hook fails.
+    pub async fn post_exit(&self) -> Result<()> {
+        self.run_hook(&self.overrides.post_exit)
+            .await
+    }
+
+    async fn run_hook(&self, hook: &[String]) -> Result<()> {
+        let Some((program, args)) = hook.split_first() else {
+            return Ok(());
+        };
+
+        let status = Command::new(program)
+            .args(args)
+            .envs(&self.overrides.env)
+            .current_dir(&self.instance_dir)
+            .status()
+            .await?;
+
+        if !status.success() {
+            error!("Hook {hook:?} failed: {status}");
+            return Err(UraniumError::OtherWithReason(format!(
+                "Hook {hook:?} failed: {status}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn capture<R: AsyncRead + Unpin + Send + 'static>(
+    mut output: R,
+    log: std::fs::File,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut log = tokio::fs::File::from_std(log);
+        if let Err(e) = tokio::io::copy(&mut output, &mut log).await {
+            warn!("Error writing the game log: {e}");
+        }
+    })
+}
+
+/// How the game process ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExitKind {
+    /// The game exited with code 0.
+    Normal,
+    /// The game exited with a non zero code.
+    Crash(i32),
+    /// The game was killed by a signal.
+    Killed,
+}
+
+impl From<ExitStatus> for ExitKind {
+    fn from(status: ExitStatus) -> Self {
+        match status.code() {
+            Some(0) => ExitKind::Normal,
+            Some(code) => ExitKind::Crash(code),
+            None => ExitKind::Killed,
+        }
+    }
+}
+
+/// When a [`GameProcess`] must be launched again after exiting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RestartPolicy {
+    #[default]
+    Never,
+    /// Restart when the game crashes, at most `max_restarts` times.
+    OnCrash { max_restarts: u32 },
+    /// Restart every time the game exits, at most `max_restarts` times.
+    Always { max_restarts: u32 },
+}
+
+impl RestartPolicy {
+    fn must_restart(self, exit: ExitKind, restarts: u32) -> bool {
+        match self {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnCrash { max_restarts } => {
+                exit != ExitKind::Normal && restarts < max_restarts
+            }
+            RestartPolicy::Always { max_restarts } => restarts < max_restarts,
+        }
+    }
+}
+
+/// Handle to a running game made by [`LaunchCommand::spawn`].
+pub struct GameProcess {
+    command: LaunchCommand,
+    child: Child,
+    log_tasks: Vec<JoinHandle<()>>,
+    policy: RestartPolicy,
+    restarts: u32,
+    started: Instant,
+}
+
+impl GameProcess {
+    /// Returns the OS id of the current game process, `None` if it has
+    /// already exited.
+    pub fn id(&self) -> Option<u32> {
+        self.child.id()
+    }
+
+    /// Returns how many times the game has been restarted.
+    pub fn restarts(&self) -> u32 {
+        self.restarts
+    }
+
+    /// Waits until the game exits, restarting it according to the
+    /// `RestartPolicy`, and returns how it ended the last time.
+    ///
+    /// The post-exit hook runs after each exit, and the session is added to
+    /// the instance playtime.
+    ///
+    /// # Errors
+    /// Returns an error if waiting fails, the post-exit hook fails or the game
+    /// can't be restarted.
+    pub async fn wait(&mut self) -> Result<ExitKind> {
+        loop {
+            let exit = ExitKind::from(self.child.wait().await?);
+            for task in self.log_tasks.drain(..) {
+                task.await?;
+            }
+            info!("Game exited: {exit:?}");
+            self.record_session();
+
+            self.command
+                .post_exit()
+                .await?;
+
+            if !self
+                .policy
+                .must_restart(exit, self.restarts)
+            {
+                return Ok(exit);
+            }
+
+            self.restarts += 1;
+            warn!("Restarting the game ({})", self.restarts);
+            (self.child, self.log_tasks) = self
+                .command
+                .spawn_logged()
+                .await?;
+            self.started = Instant::now();
+        }
+    }
+
+    /// Adds the time since the game started to the instance metadata, a
+    /// failure here must not stop the supervision.
+    fn record_session(&self) {
+        let instance_dir = &self.command.instance_dir;
+        let result = InstanceMetadata::load(instance_dir).and_then(|mut metadata| {
+            metadata.record_session(self.started.elapsed());
+            metadata.save(instance_dir)
+        });
+
+        if let Err(e) = result {
+            warn!("Can't record the playtime: {e}");
+        }
+    }
+
+    /// Kills the game, it won't be restarted.
+    ///
+    /// # Errors
+    /// Returns an error if the process can't be killed.
+    pub async fn kill(&mut self) -> Result<()> {
+        self.policy = RestartPolicy::Never;
+        Ok(self.child.kill().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::JavaRuntime;
+
+    #[test]
+    fn overrides_applied() {
+        let overrides = LaunchOverrides {
+            jvm_args: vec!["-Xmx6G".to_owned()],
+            wrapper: vec!["gamemoderun".to_owned()],
+            ..Default::default()
+        };
+
+        let command = LaunchCommand::new("java", "Main", ".")
+            .jvm_args(vec!["-Xmx2G".to_owned()])
+            .game_args(vec!["--demo".to_owned()])
+            .overrides(overrides);
+
+        assert_eq!(
+            command.args(),
+            vec!["gamemoderun", "java", "-Xmx2G", "-Xmx6G", "Main", "--demo"]
+        );
+    }
+
+    #[test]
+    fn registered_runtime() {
+        let mut overrides = LaunchOverrides::default();
+        overrides.register_runtime(JavaRuntime::new("/opt/graalvm-21/bin/java", 21));
+
+        let command = LaunchCommand::new("java", "Main", ".").overrides(overrides);
+        assert_eq!(command.java(), Path::new("java"));
+        assert_eq!(
+            command
+                .clone()
+                .java_version(17)
+                .java(),
+            Path::new("java")
+        );
+        assert_eq!(
+            command
+                .java_version(21)
+                .java(),
+            Path::new("/opt/graalvm-21/bin/java")
+        );
+    }
+
+    #[test]
+    fn restart_policy() {
+        let on_crash = RestartPolicy::OnCrash { max_restarts: 1 };
+        assert!(on_crash.must_restart(ExitKind::Crash(1), 0));
+        assert!(!on_crash.must_restart(ExitKind::Crash(1), 1));
+        assert!(!on_crash.must_restart(ExitKind::Normal, 0));
+        assert!(RestartPolicy::Always { max_restarts: 2 }.must_restart(ExitKind::Normal, 1));
+        assert!(!RestartPolicy::Never.must_restart(ExitKind::Killed, 0));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn game_process_logs() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+
+        // `sh -c 'echo ...; exit 3' Main` stands for the java process.
+        let mut process = LaunchCommand::new("sh", "Main", dir)
+            .jvm_args(vec!["-c".to_owned(), "echo started; exit 3".to_owned()])
+            .spawn(RestartPolicy::OnCrash { max_restarts: 1 })
+            .await
+            .unwrap();
+
+        assert_eq!(process.wait().await.unwrap(), ExitKind::Crash(3));
+        assert_eq!(process.restarts(), 1);
+
+        let log = std::fs::read_to_string(dir.join(GAME_LOG_FILE)).unwrap();
+        assert_eq!(log, "started\nstarted\n");
+        assert!(InstanceMetadata::load(dir)
+            .unwrap()
+            .last_played
+            .is_some());
+    }
+}
@@ -1,4 +1,3 @@
-#![feature(path_add_extension)]
 #![forbid(unsafe_code)]
 #![warn(clippy::all)]
 
@@ -35,25 +34,82 @@
 //!
 //! This crate is under development so breaking changes may occur in later
 //! versions, but I'll try to avoid them.
+//!
+//! # Stability
+//!
+//! The state, event and error enums and the reports returned by the crate are
+//! `#[non_exhaustive]`, so new variants and fields are not breaking changes.
+//! A `match` needs a wildcard arm:
+//!
+//! ```compile_fail
+//! use uranium::verifier::ProblemKind;
+//!
+//! fn describe(kind: &ProblemKind) -> &'static str {
+//!     match kind {
+//!         ProblemKind::Missing => "missing",
+//!         ProblemKind::WrongSize { .. } => "truncated",
+//!         ProblemKind::WrongHash => "corrupted",
+//!     }
+//! }
+//! ```
+//!
+//! and the reports can only be read:
+//!
+//! ```compile_fail
+//! let report = uranium::verifier::PackVerificationReport {
+//!     files_checked: 0,
+//!     overrides_checked: 0,
+//!     problems: vec![],
+//! };
+//! ```
+//!
+//! The types given to the crate are built with constructors and builders, like
+//! [`RetryPolicy`](downloaders::RetryPolicy), or with a struct literal ending
+//! with `..Default::default()`, like
+//! [`LaunchOverrides`](instance::LaunchOverrides).
 
 use std::path::Path;
 
 use downloaders::{
     CurseDownloader, Downloader, FileDownloader, MinecraftDownloader as MD, RinthDownloader,
 };
-use error::{Result, UraniumError};
+use error::Result;
 use log::info;
 pub use mine_data_structs;
 use modpack_maker::{ModpackMaker, State};
 use variables::constants::*;
 
+pub mod api;
+pub mod artifact_cache;
+pub mod cache;
+pub mod collisions;
+pub mod config;
+pub mod diagnostics;
 pub mod downloaders;
 pub mod error;
+pub mod events;
+pub mod hashes;
+pub mod hooks;
+pub mod identify;
+pub mod installer;
+pub mod instance;
+pub mod launcher;
+pub mod messages;
+pub mod modpack_diff;
 pub mod modpack_maker;
+pub mod paths;
+pub mod progress;
+pub mod rate_limit;
 pub mod searcher;
+pub mod secrets;
+pub mod storage;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+pub mod verifier;
+pub mod watcher;
 
 mod code_functions;
-mod hashes;
+mod http;
 mod variables;
 mod zipper;
 
@@ -119,6 +175,10 @@ pub async fn curse_pack_download<I: AsRef<Path>, J: AsRef<Path>>(
 /// will be created.
 ///
 ///
+/// An existing installation can be checked against the modpack without
+/// downloading anything with
+/// [`verify_pack_installation`](verifier::verify_pack_installation).
+///
 /// # Errors
 /// This function will return an `UraniumError` in case the download
 /// fails or when one or more paths are wrong.
@@ -161,12 +221,64 @@ pub fn set_threads(t: usize) -> Option<()> {
     Some(())
 }
 
+/// This function will set the max size in bytes allowed for metadata
+/// responses (version manifests, Modrinth and Curse API responses...).
+///
+/// Bigger responses fail with `UraniumError::ResponseTooLarge`. The default
+/// limit is 64 MiB.
+///
+/// In case the limit can't be updated this function will return None, in case
+/// of success Some(()) is returned.
+pub fn set_max_metadata_size(bytes: u64) -> Option<()> {
+    let mut aux = MAX_METADATA_SIZE
+        .write()
+        .ok()?;
+    *aux = bytes;
+    Some(())
+}
+
+/// Sends the downloads through `proxy`, like `http://127.0.0.1:8080`.
+///
+/// With `None` the system proxy, from the `HTTPS_PROXY` and `HTTP_PROXY` env
+/// vars, is used.
+///
+/// # Errors
+/// Returns `Err(UraniumError::InvalidConfig)` if `proxy` is not a valid proxy
+/// url.
+pub fn set_proxy(proxy: Option<&str>) -> Result<()> {
+    if let Some(url) = proxy {
+        reqwest::Proxy::all(url).map_err(|e| error::UraniumError::InvalidConfig {
+            key: "proxy".to_owned(),
+            reason: e.to_string(),
+        })?;
+    }
+    let mut aux = PROXY
+        .write()
+        .map_err(|_| error::UraniumError::Other)?;
+    *aux = proxy.map(str::to_owned);
+    Ok(())
+}
+
+/// Sets the [`ArtifactCache`](artifact_cache::ArtifactCache) used by the
+/// downloaders created from now on, `None` disables it. There is no cache by
+/// default.
+///
+/// In case the cache can't be updated this function will return None, in case
+/// of success Some(()) is returned.
+pub fn set_artifact_cache(cache: Option<artifact_cache::ArtifactCache>) -> Option<()> {
+    let mut aux = ARTIFACT_CACHE.write().ok()?;
+    *aux = cache.map(|c| c.dir().to_path_buf());
+    Some(())
+}
+
 /// Init the logger and make a log.txt file to write logs content.
 ///
 /// If this function is not called then there will be no
 /// log.txt or any kind of debug info/warn/warning message will
 /// be show in console.
 ///
+/// The log files are written in [`paths::uranium_dir`].
+///
 /// # Panics
 /// Will panic in case log files or `CombinedLogger` cant be created.
 pub fn init_logger() -> Result<()> {
@@ -177,22 +289,13 @@ pub fn init_logger() -> Result<()> {
         ColorChoice, CombinedLogger, Config, LevelFilter, TermLogger, TerminalMode, WriteLogger,
     };
 
-    let home_dir = dirs::home_dir().ok_or(UraniumError::OtherWithReason(
-        "Cant get user home directory".to_string(),
-    ))?;
-
-    let log_file_name = home_dir
-        .join(".uranium")
-        .join(format!(
-            "log_{}",
-            Local::now()
-                .format("%H-%M-%S_%d-%m-%Y")
-                .to_string()
-        ));
-
-    let latest_log_file = home_dir
-        .join(".uranium")
-        .join("latest_log_file.txt");
+    let uranium_dir = paths::uranium_dir()?;
+    std::fs::create_dir_all(&uranium_dir)?;
+
+    let log_file_name =
+        uranium_dir.join(format!("log_{}", Local::now().format("%H-%M-%S_%d-%m-%Y")));
+
+    let latest_log_file = uranium_dir.join(LATEST_LOG_FILE);
 
     CombinedLogger::init(vec![
         TermLogger::new(
@@ -0,0 +1,341 @@
+//! Stable keys for the messages shown to users, so frontends can translate
+//! them without parsing the English text.
+//!
+//! Errors, phases and events return a [`Message`], its key never changes
+//! between versions and its arguments fill the `{name}` placeholders of the
+//! text. [`ENGLISH`] is the catalog used by [`Message::to_english`], a
+//! translation is another table with the same keys:
+//!
+//! ```
+//! use uranium::error::UraniumError;
+//!
+//! let error = UraniumError::MissingApiKey("curse");
+//! let message = error.message();
+//! assert_eq!(message.key, "error.missing_api_key");
+//!
+//! let spanish = "Falta la clave de la API de {provider}";
+//! assert_eq!(message.format(spanish), "Falta la clave de la API de curse");
+//! assert_eq!(message.to_english(), "Missing curse API key");
+//! ```
+
+/// A user facing message, see the [module docs](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub key: &'static str,
+    /// Values of the placeholders, by name.
+    pub args: Vec<(&'static str, String)>,
+}
+
+impl Message {
+    pub(crate) fn new(key: &'static str) -> Self {
+        Message {
+            key,
+            args: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub(crate) fn arg(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.args
+            .push((name, value.to_string()));
+        self
+    }
+
+    /// Returns the value of the placeholder `name`.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.args
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Replaces the `{name}` placeholders of `template` with the arguments,
+    /// unknown placeholders are left as they are.
+    ///
+    /// The template is read once, so placeholders inside the values are not
+    /// replaced.
+    #[must_use]
+    pub fn format(&self, template: &str) -> String {
+        let mut text = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            text.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            match after
+                .find('}')
+                .and_then(|end| Some((end, self.get(&after[..end])?)))
+            {
+                Some((end, value)) => {
+                    text.push_str(value);
+                    rest = &after[end + 1..];
+                }
+                None => {
+                    text.push('{');
+                    rest = after;
+                }
+            }
+        }
+        text.push_str(rest);
+        text
+    }
+
+    /// Returns the text of the [`ENGLISH`] catalog.
+    #[must_use]
+    pub fn to_english(&self) -> String {
+        self.format(english(self.key).unwrap_or(self.key))
+    }
+}
+
+/// Returns the English text of `key`.
+#[must_use]
+pub fn english(key: &str) -> Option<&'static str> {
+    ENGLISH
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, text)| *text)
+}
+
+/// Every key with its English text.
+pub const ENGLISH: &[(&str, &str)] = &[
+    ("error.wrong_file_format", "Wrong file format"),
+    ("error.wrong_modpack_format", "Wrong modpack format"),
+    ("error.file_not_found", "File `{path}` not found"),
+    ("error.cant_create_dir", "Can't create dir: `{dir}`"),
+    (
+        "error.write_error",
+        "Error while writing the files: `{error}`",
+    ),
+    ("error.io_error", "IO Error: `{error}`"),
+    ("error.download_error", "Error downloading files"),
+    (
+        "error.request_error",
+        "Error making the requests: `{error}`",
+    ),
+    ("error.http_status", "`{url}` returned {status}"),
+    (
+        "error.rate_limited",
+        "Rate limited, retry after {seconds} seconds",
+    ),
+    ("error.rate_limited_later", "Rate limited, retry later"),
+    ("error.not_found", "`{url}` not found"),
+    (
+        "error.api_error",
+        "{provider} error `{code}`: {description}",
+    ),
+    ("error.file_not_match", "File hash of `{file}` doesnt match"),
+    (
+        "error.files_dont_match",
+        "Hashes of {count} files doesnt match",
+    ),
+    ("error.zip_error", "Zip Error: `{error}`"),
+    ("error.json_error", "JSON Error: `{error}`"),
+    ("error.cant_compress", "Can't compress the modpack"),
+    ("error.cant_remove_json", "Can't remove temp JSON file"),
+    ("error.cant_read_mods_dir", "Can't read mods dir"),
+    (
+        "error.case_collisions",
+        "{count} paths only differing in case",
+    ),
+    ("error.missing_api_key", "Missing {provider} API key"),
+    (
+        "error.invalid_api_key",
+        "The {provider} API key was rejected",
+    ),
+    ("error.hook_failed", "Hook `{hook}` failed: {reason}"),
+    ("error.invalid_config", "Invalid setting `{key}`: {reason}"),
+    (
+        "error.response_too_large",
+        "Response is bigger than {bytes} bytes",
+    ),
+    ("error.async_runtime_error", "Error in async task"),
+    ("error.other", "Error"),
+    ("error.other_with_reason", "Error: `{reason}`"),
+    ("phase.extracting_pack", "Extracting the modpack"),
+    ("phase.getting_sources", "Getting sources"),
+    ("phase.downloading_indexes", "Downloading indexes"),
+    ("phase.downloading_assets", "Downloading assets"),
+    ("phase.downloading_libraries", "Downloading libraries"),
+    ("phase.checking_files", "Checking files"),
+    ("phase.downloading_mods", "Downloading mods"),
+    ("phase.running_hooks", "Running hooks"),
+    ("event.download_started", "Downloading {files} files"),
+    ("event.file_completed", "`{path}` downloaded"),
+    ("event.pack_extracting", "Extracted {done} of {total} files"),
+    ("event.warning", "{message}"),
+    (
+        "event.retry_scheduled",
+        "Request to `{url}` failed, retrying in {seconds} seconds (attempt {attempt})",
+    ),
+    (
+        "event.pack_update_available",
+        "A new version of the modpack of `{instance}` is available",
+    ),
+    (
+        "event.mod_update_available",
+        "A new version of `{file}` in `{instance}` is available",
+    ),
+    ("event.hook_output", "[{hook}] {line}"),
+    ("event.completed", "Completed"),
+    ("event.failed", "Failed: {reason}"),
+];
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::{path::Path, time::Duration};
+
+    use reqwest::StatusCode;
+
+    use super::*;
+    use crate::downloaders::DownloadableObject;
+    use crate::error::UraniumError;
+    use crate::events::{Phase, UraniumEvent};
+
+    /// Returns one error of each variant, the match fails to build when a
+    /// variant is added so it must be added here too.
+    fn every_error() -> Vec<UraniumError> {
+        let io = || std::io::Error::other("disk");
+        let file =
+            || DownloadableObject::new("https://a.b/c.jar", "c.jar", Path::new("c.jar"), None);
+        let request = reqwest::Client::new()
+            .get("not a url")
+            .build()
+            .unwrap_err();
+        let errors = vec![
+            UraniumError::WrongFileFormat,
+            UraniumError::WrongModpackFormat,
+            UraniumError::FileNotFound("a.jar".to_owned()),
+            UraniumError::CantCreateDir("mods"),
+            UraniumError::WriteError(io()),
+            UraniumError::IOError(io()),
+            UraniumError::DownloadError,
+            UraniumError::RequestError(request),
+            UraniumError::HttpStatus {
+                url: "https://a.b".to_owned(),
+                status: StatusCode::BAD_GATEWAY,
+            },
+            UraniumError::RateLimited {
+                retry_after: Some(Duration::from_secs(3)),
+            },
+            UraniumError::RateLimited { retry_after: None },
+            UraniumError::NotFound("https://a.b".to_owned()),
+            UraniumError::ApiError {
+                provider: "modrinth",
+                code: "invalid_input".to_owned(),
+                description: "bad".to_owned(),
+            },
+            UraniumError::FileNotMatch(file()),
+            UraniumError::FilesDontMatch(vec![file(), file()]),
+            UraniumError::ZipError(zip::result::ZipError::FileNotFound),
+            UraniumError::JsonError(serde_json::from_str::<serde_json::Value>("[").unwrap_err()),
+            UraniumError::CantCompress,
+            UraniumError::CantRemoveJSON,
+            UraniumError::CantReadModsDir,
+            UraniumError::CaseCollisions(vec![]),
+            UraniumError::MissingApiKey("curse"),
+            UraniumError::InvalidApiKey("curse"),
+            UraniumError::HookFailed {
+                hook: "backup".to_owned(),
+                reason: "exit code 1".to_owned(),
+            },
+            UraniumError::InvalidConfig {
+                key: "threads".to_owned(),
+                reason: "not a number".to_owned(),
+            },
+            UraniumError::ResponseTooLarge(10),
+            UraniumError::AsyncRuntimeError,
+            UraniumError::Other,
+            UraniumError::OtherWithReason("reason".to_owned()),
+        ];
+        for error in &errors {
+            match error {
+                UraniumError::WrongFileFormat
+                | UraniumError::WrongModpackFormat
+                | UraniumError::FileNotFound(_)
+                | UraniumError::CantCreateDir(_)
+                | UraniumError::WriteError(_)
+                | UraniumError::IOError(_)
+                | UraniumError::DownloadError
+                | UraniumError::RequestError(_)
+                | UraniumError::HttpStatus { .. }
+                | UraniumError::RateLimited { .. }
+                | UraniumError::NotFound(_)
+                | UraniumError::ApiError { .. }
+                | UraniumError::FileNotMatch(_)
+                | UraniumError::FilesDontMatch(_)
+                | UraniumError::ZipError(_)
+                | UraniumError::JsonError(_)
+                | UraniumError::CantCompress
+                | UraniumError::CantRemoveJSON
+                | UraniumError::CantReadModsDir
+                | UraniumError::CaseCollisions(_)
+                | UraniumError::MissingApiKey(_)
+                | UraniumError::InvalidApiKey(_)
+                | UraniumError::HookFailed { .. }
+                | UraniumError::InvalidConfig { .. }
+                | UraniumError::ResponseTooLarge(_)
+                | UraniumError::AsyncRuntimeError
+                | UraniumError::Other
+                | UraniumError::OtherWithReason(_) => {}
+            }
+        }
+        errors
+    }
+
+    #[test]
+    fn every_error_in_catalog() {
+        let errors = every_error();
+        let keys: HashSet<&str> = errors
+            .iter()
+            .map(UraniumError::message_key)
+            .collect();
+        assert_eq!(keys.len(), errors.len());
+
+        for error in &errors {
+            let message = error.message();
+            assert!(english(message.key).is_some(), "{}", message.key);
+            let text = message.to_english();
+            assert!(!text.contains('{'), "{text}");
+            assert_eq!(error.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn format_single_pass() {
+        let message = Message::new("error.hook_failed")
+            .arg("hook", "{reason}")
+            .arg("reason", "{hook} failed");
+        assert_eq!(
+            message.format("`{hook}`: {reason} {unknown} {"),
+            "`{reason}`: {hook} failed {unknown} {"
+        );
+    }
+
+    #[test]
+    fn catalog_keys() {
+        let keys: HashSet<&str> = ENGLISH
+            .iter()
+            .map(|(key, _)| *key)
+            .collect();
+        assert_eq!(keys.len(), ENGLISH.len());
+
+        let messages = [
+            UraniumError::Other.message(),
+            UraniumError::FileNotFound("a.jar".to_owned()).message(),
+            UraniumError::RateLimited { retry_after: None }.message(),
+            Phase::RunningHooks.message(),
+            UraniumEvent::PhaseChanged(Phase::DownloadingMods).message(),
+            UraniumEvent::PackExtracting { done: 1, total: 2 }.message(),
+            UraniumEvent::Completed.message(),
+        ];
+        for message in &messages {
+            assert!(keys.contains(message.key), "{}", message.key);
+            assert!(!message
+                .to_english()
+                .contains('{'));
+        }
+        assert_eq!(messages[1].to_english(), "File `a.jar` not found");
+        assert_eq!(messages[4].key, "phase.downloading_mods");
+    }
+}
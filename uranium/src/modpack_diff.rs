@@ -0,0 +1,330 @@
+//! Differences between two versions of a Modrinth modpack.
+//!
+//! A [`PackDiff`] tells which mods were added, removed or changed their
+//! version and which override files changed, so an installed modpack can be
+//! upgraded with [`RinthDownloader::apply_diff`] downloading only what
+//! changed.
+//!
+//! ```no_run
+//! # async fn foo() -> uranium::error::Result<()> {
+//! use uranium::downloaders::{Downloader, RinthDownloader};
+//! use uranium::modpack_diff::diff_files;
+//!
+//! let diff = diff_files("pack-1.0.mrpack", "pack-1.1.mrpack")?;
+//! println!("{} new mods, {} updated", diff.added.len(), diff.changed.len());
+//!
+//! let mut rinth = RinthDownloader::<Downloader>::new("pack-1.1.mrpack", "instance")?;
+//! rinth.apply_diff(diff)?;
+//! rinth.complete().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`RinthDownloader::apply_diff`]: crate::downloaders::RinthDownloader::apply_diff
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use mine_data_structs::rinth::{RinthMdFiles, RinthModpack};
+use zip::ZipArchive;
+
+use crate::{
+    error::{Result, UraniumError},
+    variables::constants::{OVERRIDES_FOLDER, RINTH_JSON},
+};
+
+/// A file of the modpack which changed between versions.
+#[derive(Debug, Clone)]
+pub struct ChangedFile {
+    pub old: RinthMdFiles,
+    pub new: RinthMdFiles,
+}
+
+/// What changed between two versions of a modpack.
+///
+/// Files hosted in Modrinth are matched by project, so a mod whose jar name
+/// changed with the new version is `changed` and not removed and added. The
+/// other files are matched by path.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct PackDiff {
+    pub added: Vec<RinthMdFiles>,
+    pub removed: Vec<RinthMdFiles>,
+    pub changed: Vec<ChangedFile>,
+    /// Override files only in the new modpack, relative to the instance root.
+    pub overrides_added: Vec<PathBuf>,
+    /// Override files only in the old modpack, relative to the instance root.
+    pub overrides_removed: Vec<PathBuf>,
+    /// Override files with other content in the new modpack.
+    pub overrides_changed: Vec<PathBuf>,
+}
+
+impl PackDiff {
+    /// Returns `true` if both modpacks have the same files.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self
+                .overrides_added
+                .is_empty()
+            && self
+                .overrides_removed
+                .is_empty()
+            && self
+                .overrides_changed
+                .is_empty()
+    }
+
+    /// Returns the files of the new modpack that must be downloaded.
+    pub fn to_download(&self) -> impl Iterator<Item = &RinthMdFiles> {
+        self.added.iter().chain(
+            self.changed
+                .iter()
+                .map(|c| &c.new),
+        )
+    }
+
+    /// Returns the files of the old modpack which are no longer needed,
+    /// relative to the instance root.
+    ///
+    /// A changed file with the same path is not included, it is replaced by
+    /// the download.
+    #[must_use]
+    pub fn obsolete_files(&self) -> Vec<&Path> {
+        self.removed
+            .iter()
+            .map(RinthMdFiles::get_path)
+            .chain(
+                self.changed
+                    .iter()
+                    .filter(|c| c.old.get_path() != c.new.get_path())
+                    .map(|c| c.old.get_path()),
+            )
+            .chain(
+                self.overrides_removed
+                    .iter()
+                    .map(PathBuf::as_path),
+            )
+            .collect()
+    }
+}
+
+/// Compares the files of two modpacks, the overrides are not compared since
+/// the index doesn't list them, see [`diff_files`].
+#[must_use]
+pub fn diff(old: &RinthModpack, new: &RinthModpack) -> PackDiff {
+    let mut old_files: HashMap<String, &RinthMdFiles> = old
+        .get_files()
+        .iter()
+        .map(|f| (file_key(f), f))
+        .collect();
+
+    let mut pack_diff = PackDiff::default();
+    for file in new.get_files() {
+        match old_files.remove(&file_key(file)) {
+            None => pack_diff
+                .added
+                .push(file.clone()),
+            Some(old) => {
+                if old.get_hashes().sha1 != file.get_hashes().sha1
+                    || old.get_path() != file.get_path()
+                {
+                    pack_diff
+                        .changed
+                        .push(ChangedFile {
+                            old: old.clone(),
+                            new: file.clone(),
+                        });
+                }
+            }
+        }
+    }
+
+    // Keep the order of the old modpack.
+    pack_diff.removed = old
+        .get_files()
+        .iter()
+        .filter(|f| old_files.contains_key(&file_key(f)))
+        .cloned()
+        .collect();
+    pack_diff
+}
+
+/// Compares two `.mrpack` files, including their overrides.
+///
+/// # Errors
+/// Returns `Err(UraniumError::FileNotFound)` if a modpack doesn't exist and
+/// `Err(UraniumError::WrongFileFormat)` if it is not a valid modpack.
+pub fn diff_files<P: AsRef<Path>, Q: AsRef<Path>>(old: P, new: Q) -> Result<PackDiff> {
+    let (old_pack, old_overrides) = read_pack(old.as_ref())?;
+    let (new_pack, mut new_overrides) = read_pack(new.as_ref())?;
+
+    let mut pack_diff = diff(&old_pack, &new_pack);
+    for (path, crc) in old_overrides {
+        match new_overrides.remove(&path) {
+            None => pack_diff
+                .overrides_removed
+                .push(path),
+            Some(new_crc) if new_crc != crc => pack_diff
+                .overrides_changed
+                .push(path),
+            Some(_) => {}
+        }
+    }
+    pack_diff
+        .overrides_added
+        .extend(new_overrides.into_keys());
+
+    pack_diff
+        .overrides_added
+        .sort();
+    pack_diff
+        .overrides_removed
+        .sort();
+    pack_diff
+        .overrides_changed
+        .sort();
+    Ok(pack_diff)
+}
+
+fn file_key(file: &RinthMdFiles) -> String {
+    file.project_id().map_or_else(
+        || {
+            file.get_path()
+                .to_string_lossy()
+                .to_string()
+        },
+        str::to_owned,
+    )
+}
+
+/// Reads the index and the crc32 of each override file of a modpack.
+fn read_pack(path: &Path) -> Result<(RinthModpack, HashMap<PathBuf, u32>)> {
+    let file =
+        File::open(path).map_err(|_| UraniumError::FileNotFound(path.display().to_string()))?;
+    let mut zip = ZipArchive::new(file).map_err(|_| UraniumError::WrongFileFormat)?;
+
+    let pack: RinthModpack = serde_json::from_reader(
+        zip.by_name(RINTH_JSON)
+            .map_err(|_| UraniumError::WrongFileFormat)?,
+    )
+    .map_err(|_| UraniumError::WrongFileFormat)?;
+
+    let mut overrides = HashMap::new();
+    for i in 0..zip.len() {
+        let entry = zip
+            .by_index_raw(i)
+            .map_err(|_| UraniumError::WrongFileFormat)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let Some(path) = entry.enclosed_name() else {
+            continue;
+        };
+        if let Ok(relative) = path.strip_prefix(OVERRIDES_FOLDER) {
+            overrides.insert(relative.to_path_buf(), entry.crc32());
+        }
+    }
+
+    Ok((pack, overrides))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use super::*;
+
+    fn md_file(path: &str, sha1: &str, project: &str) -> RinthMdFiles {
+        serde_json::from_value(serde_json::json!({
+            "path": path,
+            "hashes": { "sha1": sha1, "sha512": "" },
+            "downloads": [format!("https://cdn.modrinth.com/data/{project}/versions/{sha1}/{path}")],
+            "fileSize": 1
+        }))
+        .unwrap()
+    }
+
+    fn pack(files: Vec<RinthMdFiles>) -> RinthModpack {
+        let mut pack = RinthModpack::new();
+        pack.files = files;
+        pack
+    }
+
+    fn write_pack(path: &Path, modpack: &RinthModpack, overrides: &[(&str, &str)]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        zip.start_file(RINTH_JSON, SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(&serde_json::to_vec(modpack).unwrap())
+            .unwrap();
+        for (name, content) in overrides {
+            zip.start_file(
+                format!("{OVERRIDES_FOLDER}{name}"),
+                SimpleFileOptions::default(),
+            )
+            .unwrap();
+            zip.write_all(content.as_bytes())
+                .unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn modpack_files() {
+        let old = pack(vec![
+            md_file("mods/sodium-0.5.jar", "a", "AANobbMI"),
+            md_file("mods/lithium.jar", "b", "gvQqBUqZ"),
+            md_file("mods/iris.jar", "c", "YL57xq9U"),
+        ]);
+        let new = pack(vec![
+            md_file("mods/sodium-0.6.jar", "d", "AANobbMI"),
+            md_file("mods/lithium.jar", "b", "gvQqBUqZ"),
+            md_file("mods/jei.jar", "e", "u6dRKJwZ"),
+        ]);
+
+        let pack_diff = diff(&old, &new);
+        assert_eq!(pack_diff.added[0].get_name(), "jei.jar");
+        assert_eq!(pack_diff.removed[0].get_name(), "iris.jar");
+        assert_eq!(pack_diff.changed.len(), 1);
+        assert_eq!(
+            pack_diff.obsolete_files(),
+            [Path::new("mods/iris.jar"), Path::new("mods/sodium-0.5.jar")]
+        );
+        assert!(diff(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn pack_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let modpack = pack(vec![]);
+        write_pack(
+            &dir.join("old.mrpack"),
+            &modpack,
+            &[("config/a.toml", "1"), ("config/b.toml", "1")],
+        );
+        write_pack(
+            &dir.join("new.mrpack"),
+            &modpack,
+            &[("config/b.toml", "2"), ("config/c.toml", "1")],
+        );
+
+        let pack_diff = diff_files(dir.join("old.mrpack"), dir.join("new.mrpack")).unwrap();
+        assert_eq!(pack_diff.overrides_added, [PathBuf::from("config/c.toml")]);
+        assert_eq!(
+            pack_diff.overrides_removed,
+            [PathBuf::from("config/a.toml")]
+        );
+        assert_eq!(
+            pack_diff.overrides_changed,
+            [PathBuf::from("config/b.toml")]
+        );
+    }
+}
@@ -0,0 +1,298 @@
+use std::{
+    collections::HashMap,
+    fs::read_dir,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use log::{info, warn};
+use mine_data_structs::{
+    curse::{
+        curse_modpacks::{CursePack, CursePackFiles},
+        curse_mods::{CurseFile, CurseFingerPrint, CurseResponse},
+    },
+    url_maker::maker::Curse,
+};
+use serde::Serialize;
+
+use super::maker::is_mod_file;
+use crate::{
+    error::{Result, UraniumError},
+    hashes::curse_fingerprint,
+    http::default_client,
+    identify::search_fingerprints,
+    storage::{LocalStorage, StorageBackend},
+    variables::constants::MOD_EXTENSIONS,
+    zipper::{compress_curse_pack_into, Overrides},
+};
+
+/// This struct is responsible for the creation of Curse modpacks (a zip with
+/// a `manifest.json` and the overrides) given a minecraft path.
+///
+/// The mods are identified with their CurseForge fingerprint, mods not found
+/// in CurseForge are added to the overrides. The API key is read from
+/// [`secrets`](crate::secrets), the `CURSE_API_KEY` env var by default.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn foo() -> uranium::error::Result<()> {
+/// use uranium::modpack_maker::CurseModpackMaker;
+///
+/// CurseModpackMaker::new("path/to/minecraft", "my_modpack", "1.20.1", "forge-47.2.0")
+///     .author("Steve")
+///     .make()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct CurseModpackMaker {
+    path: PathBuf,
+    modpack_path: PathBuf,
+    storage: Arc<dyn StorageBackend>,
+    pack: CursePack,
+    raw_mods: Vec<PathBuf>,
+    overrides: Overrides,
+}
+
+impl CurseModpackMaker {
+    /// `loader` is the CurseForge loader id, e.g. `"fabric-0.15.11"` or
+    /// `"forge-47.2.0"`.
+    pub fn new<I: AsRef<Path>, J: AsRef<Path>>(
+        path: I,
+        modpack_name: J,
+        minecraft_version: &str,
+        loader: &str,
+    ) -> CurseModpackMaker {
+        let modpack_path = modpack_name
+            .as_ref()
+            .to_path_buf();
+        let name = modpack_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        CurseModpackMaker {
+            path: path.as_ref().to_path_buf(),
+            modpack_path,
+            storage: Arc::new(LocalStorage),
+            pack: CursePack::new(&name, "", minecraft_version, loader),
+            raw_mods: vec![],
+            overrides: Overrides::default(),
+        }
+    }
+
+    /// The modpack will be written through `storage` instead of the local
+    /// filesystem.
+    #[must_use]
+    pub fn with_storage(mut self, storage: Arc<dyn StorageBackend>) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    /// Sets the instance folders added to the overrides of the modpack.
+    #[must_use]
+    pub fn with_overrides(mut self, overrides: Overrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    #[must_use]
+    pub fn author(mut self, author: &str) -> Self {
+        author.clone_into(&mut self.pack.author);
+        self
+    }
+
+    /// Sets the version of the modpack written in the manifest, `1.0.0` by
+    /// default.
+    #[must_use]
+    pub fn version(mut self, version: &str) -> Self {
+        version.clone_into(&mut self.pack.version);
+        self
+    }
+
+    /// Returns the mods not found in CurseForge, which are added to the
+    /// overrides. Available after [`CurseModpackMaker::make`].
+    #[must_use]
+    pub fn raw_mods(&self) -> &[PathBuf] {
+        &self.raw_mods
+    }
+
+    /// Fingerprints the mods, looks them up in CurseForge and writes the
+    /// modpack. The `.zip` extension is added to the modpack name if missing.
+    ///
+    /// # Errors
+    /// Returns `Err(UraniumError::CantReadModsDir)` if the mods can't be read,
+    /// the request error if CurseForge can't be reached and
+    /// `Err(UraniumError::CantCompress)` if the modpack can't be written.
+    pub async fn make(&mut self) -> Result<()> {
+        let fingerprints = self.read_mods()?;
+
+        let mut raw_mods = if fingerprints.is_empty() {
+            vec![]
+        } else {
+            let matches = self
+                .search_fingerprints(
+                    fingerprints
+                        .keys()
+                        .copied()
+                        .collect(),
+                )
+                .await?;
+            self.add_matches(fingerprints, matches.get_files())
+        };
+        raw_mods.sort();
+        self.raw_mods = raw_mods;
+        info!(
+            "{} mods found in CurseForge, {} added raw",
+            self.pack.get_files().len(),
+            self.raw_mods.len()
+        );
+
+        let manifest =
+            serde_json::to_vec_pretty(&self.pack).map_err(|_| UraniumError::WrongFileFormat)?;
+        let buffer = compress_curse_pack_into(
+            Cursor::new(Vec::new()),
+            &self.path,
+            &self.raw_mods,
+            &self.overrides,
+            &manifest,
+        )
+        .map_err(|e| {
+            warn!("Error while compressing the modpack: {e}");
+            UraniumError::CantCompress
+        })?
+        .into_inner();
+
+        let mut modpack_path = self.modpack_path.clone();
+        if modpack_path
+            .extension()
+            .is_none_or(|e| e != "zip")
+        {
+            modpack_path.add_extension("zip");
+        }
+
+        self.storage
+            .open_write(&modpack_path)?
+            .write_all(&buffer)?;
+
+        Ok(())
+    }
+
+    /// Adds the CurseForge `files` to the manifest and returns the mods that
+    /// must be added raw.
+    ///
+    /// Jars sharing a fingerprint are copies of the same file, only one of
+    /// them (the one named like the CurseForge file if any) goes in the
+    /// manifest and the other copies are added raw.
+    fn add_matches<'a>(
+        &mut self,
+        mut fingerprints: HashMap<u32, Vec<PathBuf>>,
+        files: impl IntoIterator<Item = &'a CurseFile>,
+    ) -> Vec<PathBuf> {
+        let mut raw_mods = vec![];
+        for file in files {
+            let Some(mut paths) = fingerprints.remove(&file.get_file_fingerprint()) else {
+                continue;
+            };
+            paths.sort();
+            let kept = paths
+                .iter()
+                .position(|p| p == file.get_file_name())
+                .unwrap_or_default();
+            paths.remove(kept);
+            if !paths.is_empty() {
+                warn!(
+                    "{} has the same fingerprint as {paths:?}, the copies are added raw",
+                    file.get_file_name().display()
+                );
+            }
+            raw_mods.extend(paths);
+            self.pack
+                .add_file(CursePackFiles::new(file.get_mod_id(), file.get_id()));
+        }
+
+        raw_mods.extend(
+            fingerprints
+                .into_values()
+                .flatten(),
+        );
+        raw_mods
+    }
+
+    /// Returns the fingerprint -> file names of every mod.
+    fn read_mods(&self) -> Result<HashMap<u32, Vec<PathBuf>>> {
+        let extensions: Vec<String> = MOD_EXTENSIONS
+            .iter()
+            .map(|e| (*e).to_owned())
+            .collect();
+
+        let entries =
+            read_dir(self.path.join("mods/")).map_err(|_| UraniumError::CantReadModsDir)?;
+
+        let mut fingerprints: HashMap<u32, Vec<PathBuf>> = HashMap::new();
+        for path in entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| is_mod_file(p, &extensions))
+        {
+            let fingerprint = curse_fingerprint(&path)?;
+            let file_name = PathBuf::from(
+                path.file_name()
+                    .unwrap_or_default(),
+            );
+            fingerprints
+                .entry(fingerprint)
+                .or_default()
+                .push(file_name);
+        }
+
+        Ok(fingerprints)
+    }
+
+    async fn search_fingerprints(&self, fingerprints: Vec<u32>) -> Result<CurseFingerPrint> {
+        search_fingerprints(&default_client()?, fingerprints).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn curse_file(fingerprint: u32, file_name: &str) -> CurseFile {
+        serde_json::from_value(serde_json::json!({
+            "id": 2,
+            "modId": 1,
+            "displayName": file_name,
+            "fileName": file_name,
+            "fileLength": 8,
+            "gameVersions": [],
+            "fileFingerprint": fingerprint,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn same_fingerprint_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        fs::create_dir(dir.join("mods")).unwrap();
+        fs::write(dir.join("mods/a.jar"), b"same mod").unwrap();
+        fs::write(dir.join("mods/b.jar"), b"same mod").unwrap();
+        fs::write(dir.join("mods/c.jar"), b"other mod").unwrap();
+
+        let mut maker = CurseModpackMaker::new(dir, "pack", "1.20.1", "forge-47.2.0");
+        let fingerprints = maker.read_mods().unwrap();
+        let fingerprint = curse_fingerprint(dir.join("mods/a.jar")).unwrap();
+        assert_eq!(fingerprints.len(), 2);
+        assert_eq!(fingerprints[&fingerprint].len(), 2);
+
+        let mut raw_mods = maker.add_matches(fingerprints, &[curse_file(fingerprint, "b.jar")]);
+        raw_mods.sort();
+        assert_eq!(raw_mods, [PathBuf::from("a.jar"), PathBuf::from("c.jar")]);
+        assert_eq!(maker.pack.get_files().len(), 1);
+    }
+}
@@ -1,17 +1,26 @@
 use std::{
-    fs::read_dir,
+    fs::{read_dir, File},
+    io::{Seek, Write},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
-use futures::future::join_all;
-use log::error;
+use log::{error, info, warn};
 use mine_data_structs::rinth::{RinthModpack, RinthVersion};
+use rayon::prelude::*;
 use reqwest::Response;
 
-use crate::searcher::rinth::{SearchBuilder, SearchType};
+use crate::cache::VersionCache;
+use crate::downloaders::RetryPolicy;
+use crate::identify::{FileResolver, ReviewEntry};
+use crate::storage::{LocalStorage, StorageBackend};
 use crate::{
-    code_functions::N_THREADS, error::Result, error::UraniumError, hashes::rinth_hash,
-    variables::constants, zipper::compress_pack,
+    code_functions::N_THREADS,
+    error::Result,
+    error::UraniumError,
+    hashes::rinth_hash,
+    variables::constants,
+    zipper::{compress_pack, compress_pack_into, Overrides},
 };
 
 type HashFilename = Vec<(String, String)>;
@@ -19,50 +28,163 @@ type HashFilename = Vec<(String, String)>;
 /// Good -> Means Uranium found the mod
 /// Raw  -> Means the mod need to be added raw
 enum ParseState {
-    Good(RinthVersion),
+    Good(Box<RinthVersion>),
     Raw(String),
 }
 
-#[derive(Clone, Copy)]
+/// The step [`ModpackMaker::chunk`] is in.
+///
+/// New steps may be added, like `Hashing` was, so matches on it need a
+/// wildcard arm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum State {
     Starting,
+    /// The mods are being hashed, `done` out of `total`.
+    Hashing {
+        done: usize,
+        total: usize,
+    },
     Searching,
     Checking,
     Writing,
     Finish,
 }
 
+/// Where the finished modpack is written.
+enum PackOutput<W> {
+    /// A named `.mrpack` written through a `StorageBackend`.
+    Named {
+        modpack_path: PathBuf,
+        storage: Arc<dyn StorageBackend>,
+    },
+    /// Any sink given by the user, it is taken while the pack is written.
+    Writer(Option<W>),
+}
+
 /// This struct is responsible for the creation
 /// of the modpacks given a minecraft path.
-pub struct ModpackMaker {
+///
+/// By default the modpack is written into a named file but it can also be
+/// written into any `Write + Seek` sink with [`ModpackMaker::with_writer`].
+pub struct ModpackMaker<W: Write + Seek = File> {
     path: PathBuf,
     current_state: State,
     hash_filenames: HashFilename,
+    /// Mods still to be hashed.
+    pending_mods: Vec<PathBuf>,
     mods_states: Vec<ParseState>,
     rinth_pack: RinthModpack,
     raw_mods: Vec<PathBuf>,
-    client: reqwest::Client,
-    modpack_path: PathBuf,
     threads: usize,
+    output: PackOutput<W>,
+    /// Looks up the mods in Modrinth, with the versions cache.
+    resolver: FileResolver,
+    fuzzy: bool,
+    mod_extensions: Vec<String>,
+    ignored: Vec<PathBuf>,
+    overrides: Overrides,
+    failures: Vec<LookupFailure>,
+}
+
+/// A mod which couldn't be looked up in Modrinth because of an error, it was
+/// added raw to the modpack.
+///
+/// Mods which are just not in Modrinth are not failures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LookupFailure {
+    pub file: String,
+    pub reason: String,
 }
 
 impl ModpackMaker {
     pub fn new<I: AsRef<Path>, J: AsRef<Path>>(path: I, modpack_name: J) -> ModpackMaker {
+        Self::with_storage(path, modpack_name, Arc::new(LocalStorage))
+    }
+
+    /// Same as `ModpackMaker::new` but the modpack will be written through
+    /// `storage` instead of the local filesystem.
+    pub fn with_storage<I: AsRef<Path>, J: AsRef<Path>>(
+        path: I,
+        modpack_name: J,
+        storage: Arc<dyn StorageBackend>,
+    ) -> ModpackMaker {
+        ModpackMaker::from_output(
+            path,
+            PackOutput::Named {
+                modpack_path: modpack_name
+                    .as_ref()
+                    .to_path_buf(),
+                storage,
+            },
+        )
+    }
+}
+
+impl<W: Write + Seek> ModpackMaker<W> {
+    /// Same as `ModpackMaker::new` but the modpack will be written into
+    /// `writer`, e.g. a `Cursor<Vec<u8>>` used as an upload body.
+    ///
+    /// Once the maker has finished the writer can be recovered with
+    /// [`ModpackMaker::into_writer`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn foo() -> uranium::error::Result<()> {
+    /// use std::io::Cursor;
+    ///
+    /// use uranium::modpack_maker::ModpackMaker;
+    ///
+    /// let mut maker = ModpackMaker::with_writer("path/to/minecraft", Cursor::new(Vec::new()));
+    /// maker.finish().await?;
+    ///
+    /// let pack_bytes = maker
+    ///     .into_writer()
+    ///     .map(Cursor::into_inner);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_writer<I: AsRef<Path>>(path: I, writer: W) -> ModpackMaker<W> {
+        ModpackMaker::from_output(path, PackOutput::Writer(Some(writer)))
+    }
+
+    /// Returns the writer given in [`ModpackMaker::with_writer`].
+    ///
+    /// Returns `None` if the maker writes into a named file or if the writer
+    /// was lost because writing the modpack failed.
+    #[must_use]
+    pub fn into_writer(self) -> Option<W> {
+        match self.output {
+            PackOutput::Writer(writer) => writer,
+            PackOutput::Named { .. } => None,
+        }
+    }
+
+    fn from_output<I: AsRef<Path>>(path: I, output: PackOutput<W>) -> ModpackMaker<W> {
         ModpackMaker {
             path: path.as_ref().to_path_buf(),
             current_state: State::Starting,
             hash_filenames: vec![],
+            pending_mods: vec![],
             mods_states: vec![],
             rinth_pack: RinthModpack::new(),
             raw_mods: vec![],
-            client: reqwest::ClientBuilder::new()
-                .user_agent("uranium-rs/mp-maker contact: sergious234@gmail.com")
-                .build()
-                .unwrap(),
-            modpack_path: modpack_name
-                .as_ref()
-                .to_path_buf(),
             threads: N_THREADS(),
+            output,
+            resolver: match VersionCache::open_default() {
+                Ok(cache) => FileResolver::new().with_cache(cache),
+                Err(_) => FileResolver::new(),
+            },
+            fuzzy: false,
+            mod_extensions: constants::MOD_EXTENSIONS
+                .iter()
+                .map(|e| (*e).to_owned())
+                .collect(),
+            ignored: vec![],
+            overrides: Overrides::default(),
+            failures: vec![],
         }
     }
 
@@ -146,7 +268,7 @@ impl ModpackMaker {
     /// directory
     #[must_use]
     pub fn len(&self) -> usize {
-        self.hash_filenames.len()
+        self.hash_filenames.len() + self.pending_mods.len()
     }
 
     /// Returns true if there are no mods in the minecraft directory
@@ -180,9 +302,37 @@ impl ModpackMaker {
         self.current_state = match self.current_state {
             State::Starting => {
                 if self.hash_filenames.is_empty() {
-                    self.hash_filenames = self.read_mods()?;
+                    self.pending_mods = self.list_mods()?;
+                    State::Hashing {
+                        done: 0,
+                        total: self.pending_mods.len(),
+                    }
+                } else {
+                    State::Searching
+                }
+            }
+            State::Hashing { done, total } => {
+                let batch: Vec<PathBuf> = self
+                    .pending_mods
+                    .drain(
+                        ..self
+                            .threads
+                            .min(self.pending_mods.len()),
+                    )
+                    .collect();
+                let hashed = batch.len();
+                let hashes = tokio::task::spawn_blocking(move || hash_mods(&batch)).await??;
+                self.hash_filenames
+                    .extend(hashes);
+
+                if self.pending_mods.is_empty() {
+                    State::Searching
+                } else {
+                    State::Hashing {
+                        done: done + hashed,
+                        total,
+                    }
                 }
-                State::Searching
             }
             State::Searching => {
                 if self.hash_filenames.is_empty() {
@@ -193,11 +343,15 @@ impl ModpackMaker {
                 }
             }
             State::Checking => {
+                if let Err(e) = self.resolver.save_cache() {
+                    warn!("Can't save the versions cache: {e}");
+                }
+
                 for rinth_mod in &self.mods_states {
                     match rinth_mod {
                         ParseState::Good(m) => self
                             .rinth_pack
-                            .add_mod(m.clone().into()),
+                            .add_mod((**m).clone().into()),
                         ParseState::Raw(file_name) => self
                             .raw_mods
                             .push(PathBuf::from(file_name)),
@@ -209,7 +363,29 @@ impl ModpackMaker {
                 self.rinth_pack
                     .write_mod_pack_with_name();
 
-                if let Err(e) = compress_pack(&self.modpack_path, &self.path, &self.raw_mods) {
+                let written = match &mut self.output {
+                    PackOutput::Named {
+                        modpack_path,
+                        storage,
+                    } => compress_pack(
+                        modpack_path,
+                        &self.path,
+                        &self.raw_mods,
+                        &self.overrides,
+                        storage.as_ref(),
+                    ),
+                    PackOutput::Writer(writer) => match writer.take() {
+                        Some(w) => {
+                            compress_pack_into(w, &self.path, &self.raw_mods, &self.overrides)
+                                .map(|w| *writer = Some(w))
+                        }
+                        None => Err(UraniumError::OtherWithReason(
+                            "The modpack writer was already used".to_owned(),
+                        )),
+                    },
+                };
+
+                if let Err(e) = written {
                     error!("Error while compressing the modpack: {}", e);
                     return Err(UraniumError::CantCompress);
                 }
@@ -225,6 +401,95 @@ impl ModpackMaker {
         Ok(self.current_state)
     }
 
+    /// Sets the cache used to avoid asking Modrinth about the same mods on
+    /// every run, `None` disables it.
+    ///
+    /// By default the cache in `~/.uranium/cache/` is used.
+    #[must_use]
+    pub fn with_cache(mut self, cache: Option<VersionCache>) -> Self {
+        self.resolver.set_cache(cache);
+        self
+    }
+
+    /// Sets the extensions (without the dot) of the files in `mods/` that are
+    /// added to the modpack, by default only `.jar` files.
+    ///
+    /// Directories and hidden files are always ignored.
+    #[must_use]
+    pub fn with_mod_extensions(mut self, extensions: &[&str]) -> Self {
+        self.mod_extensions = extensions
+            .iter()
+            .map(|e| (*e).to_owned())
+            .collect();
+        self
+    }
+
+    /// Sets the instance folders added to the overrides of the modpack, by
+    /// default the ones in [`Overrides::default`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn foo() -> uranium::error::Result<()> {
+    /// use uranium::modpack_maker::{ModpackMaker, Overrides};
+    ///
+    /// let overrides = Overrides::with_folders(&["config", "resourcepacks"])
+    ///     .exclude("config/**/*.log")?;
+    /// let mut maker = ModpackMaker::new("path/to/minecraft", "my_modpack")
+    ///     .with_overrides(overrides);
+    /// maker.finish().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn with_overrides(mut self, overrides: Overrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Sets how the failed Modrinth requests are retried, see
+    /// [`RetryPolicy`].
+    #[must_use]
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.resolver
+            .set_retry_policy(policy);
+        self
+    }
+
+    /// Makes the mods whose hash Modrinth doesn't know be matched by name
+    /// and size or by mod id too, see [`FileResolver::fuzzy_matching`].
+    ///
+    /// Those matches are never added to the modpack as Modrinth files, the
+    /// mods are added raw and listed in [`ModpackMaker::needs_review`].
+    #[must_use]
+    pub fn with_fuzzy_matching(mut self, enabled: bool) -> Self {
+        self.fuzzy = enabled;
+        self.resolver
+            .set_fuzzy_matching(enabled);
+        self
+    }
+
+    /// Returns the mods added raw which were matched with a guess, with the
+    /// guess and its confidence, so the user can review them.
+    #[must_use]
+    pub fn needs_review(&self) -> &[ReviewEntry] {
+        self.resolver.needs_review()
+    }
+
+    /// Returns the mods which couldn't be looked up in Modrinth because of an
+    /// error, with the reason. They are added raw to the modpack.
+    #[must_use]
+    pub fn failures(&self) -> &[LookupFailure] {
+        &self.failures
+    }
+
+    /// Returns the entries of `mods/` that were not added to the modpack,
+    /// available after [`ModpackMaker::start`].
+    #[must_use]
+    pub fn ignored_files(&self) -> &[PathBuf] {
+        &self.ignored
+    }
+
     async fn search_mods(&mut self) {
         let end = if self.threads > self.hash_filenames.len() {
             self.hash_filenames.len()
@@ -232,61 +497,72 @@ impl ModpackMaker {
             self.threads
         };
 
-        let chunk: HashFilename = self
+        let (hashes, file_names): (Vec<String>, Vec<String>) = self
             .hash_filenames
             .drain(0..end)
-            .collect();
-
-        // Get rinth_responses
-        let mut rinth_responses = Vec::with_capacity(chunk.len());
-
-        let reqs = chunk
-            .iter()
-            .map(|f| {
-                tokio::task::spawn(
-                    self.client
-                        .get(
-                            SearchBuilder::new()
-                                .search_type(SearchType::VersionFile { hash: f.0.clone() })
-                                .build_url(),
-                        )
-                        .send(),
-                )
-            })
-            .collect::<Vec<tokio::task::JoinHandle<std::result::Result<Response, reqwest::Error>>>>(
-            );
-
-        let responses = join_all(reqs)
-            .await
-            .into_iter()
-            .flatten()
-            .map(|x| x.map_err(|e| e.into()))
-            .collect::<Vec<Result<Response>>>();
+            .unzip();
 
-        rinth_responses.extend(responses);
+        let versions = self
+            .resolver
+            .modrinth_versions(&hashes)
+            .await;
 
-        let rinth_parses = parse_responses(rinth_responses).await;
-        for (file_name, rinth) in chunk
+        for (file_name, rinth) in file_names
             .into_iter()
-            .zip(rinth_parses.into_iter())
+            .zip(versions)
         {
-            if let Ok(m) = rinth {
-                self.mods_states
-                    .push(ParseState::Good(m));
-            } else {
-                self.mods_states
-                    .push(ParseState::Raw(file_name.1));
+            match rinth {
+                Ok(Some(m)) => self
+                    .mods_states
+                    .push(ParseState::Good(Box::new(m))),
+                Ok(None) => {
+                    if self.fuzzy {
+                        let path = self
+                            .path
+                            .join("mods")
+                            .join(&file_name);
+                        if let Err(e) = self
+                            .resolver
+                            .resolve(&path)
+                            .await
+                        {
+                            warn!("Can't identify {file_name}: {e}");
+                        }
+                    }
+                    self.mods_states
+                        .push(ParseState::Raw(file_name));
+                }
+                Err(e) => {
+                    warn!("Can't look up {file_name}, adding it raw: {e}");
+                    self.failures
+                        .push(LookupFailure {
+                            file: file_name.clone(),
+                            reason: e.to_string(),
+                        });
+                    self.mods_states
+                        .push(ParseState::Raw(file_name));
+                }
             }
         }
     }
 
+    /// Lists and hashes the mods, the hashing is done in parallel.
+    ///
     /// # Errors
     /// If the path dir cant be read then `Err(MakeError::CantReadModsDir)` will
-    /// be returned.
-    ///
-    /// # Panic
-    /// This function will panic when path is not a dir.
+    /// be returned, if a mod can't be read its IO error.
     fn read_mods(&mut self) -> Result<HashFilename> {
+        let mods = self.list_mods()?;
+        hash_mods(&mods)
+    }
+
+    /// Returns the mods of the instance, the other files of `mods/` are kept
+    /// in `self.ignored`.
+    ///
+    /// # Errors
+    /// If the path dir cant be read then `Err(MakeError::CantReadModsDir)` will
+    /// be returned.
+    fn list_mods(&mut self) -> Result<Vec<PathBuf>> {
         if !self.path.is_dir() {
             return Err(UraniumError::CantReadModsDir);
         }
@@ -304,36 +580,176 @@ impl ModpackMaker {
             }
         };
 
-        let mut hashes_names = Vec::with_capacity(mods.len());
+        let (mods, ignored): (Vec<PathBuf>, Vec<PathBuf>) = mods
+            .into_iter()
+            .partition(|path| is_mod_file(path, &self.mod_extensions));
+
+        for path in &ignored {
+            info!("Ignoring {}", path.display());
+        }
+        self.ignored = ignored;
 
-        // Push all the (has, file_name) to the vector
-        for path in mods {
-            let mod_hash = rinth_hash(path.as_path());
+        Ok(mods)
+    }
+}
+
+/// Returns the (hash, file name) of each mod, hashing them in parallel.
+///
+/// # Errors
+/// Returns the error of the first mod which can't be read.
+fn hash_mods(mods: &[PathBuf]) -> Result<HashFilename> {
+    mods.par_iter()
+        .map(|path| {
+            let mod_hash = rinth_hash(path.as_path())?;
             let file_name = path
                 .file_name()
                 .unwrap()
                 .to_str()
                 .unwrap_or_default()
                 .to_owned();
-            hashes_names.push((mod_hash, file_name));
-        }
+            Ok((mod_hash, file_name))
+        })
+        .collect()
+}
 
-        Ok(hashes_names)
-    }
+/// Returns true if `path` is a non hidden file with one of `extensions`.
+///
+/// Other launchers leave things like `.index/` or `.connector/` dirs and
+/// `.DS_Store` files in `mods/`, those must not be sent to Modrinth.
+pub(crate) fn is_mod_file(path: &Path, extensions: &[String]) -> bool {
+    let hidden = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .is_none_or(|n| n.starts_with('.'));
+
+    !hidden
+        && path.is_file()
+        && path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| {
+                extensions
+                    .iter()
+                    .any(|ext| ext.eq_ignore_ascii_case(e))
+            })
 }
 
-async fn parse_responses(responses: Vec<Result<Response>>) -> Vec<Result<RinthVersion>> {
-    join_all(
-        responses
-            .into_iter()
-            .map(|request| {
-                request
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mod_files_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        std::fs::create_dir_all(dir.join(".index")).unwrap();
+        std::fs::create_dir_all(dir.join("folder.jar")).unwrap();
+        for file in [
+            "sodium.jar",
+            "Iris.JAR",
+            ".DS_Store",
+            ".hidden.jar",
+            "notes.txt",
+        ] {
+            std::fs::write(dir.join(file), b"").unwrap();
+        }
+
+        let extensions = vec!["jar".to_owned()];
+        let mut mods: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| is_mod_file(p, &extensions))
+            .map(|p| {
+                p.file_name()
                     .unwrap()
-                    .json::<RinthVersion>()
-            }),
-    )
-    .await
-    .into_iter()
-    .map(|x| x.map_err(|e| e.into()))
-    .collect::<Vec<Result<RinthVersion>>>()
+                    .to_owned()
+            })
+            .collect();
+        mods.sort();
+
+        assert_eq!(mods, ["Iris.JAR", "sodium.jar"]);
+    }
+
+    #[test]
+    fn unreadable_mod() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("ok.jar"), b"ok").unwrap();
+        let mods = [dir.path().join("ok.jar"), dir.path().join("gone.jar")];
+
+        assert!(hash_mods(&mods[..1]).is_ok());
+        assert!(matches!(
+            hash_mods(&mods),
+            Err(UraniumError::WriteError(e)) if e.kind() == std::io::ErrorKind::NotFound
+        ));
+    }
+
+    #[tokio::test]
+    async fn hashing_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        std::fs::create_dir_all(dir.join("mods")).unwrap();
+        for i in 0..5 {
+            std::fs::write(
+                dir.join("mods")
+                    .join(format!("mod{i}.jar")),
+                [i],
+            )
+            .unwrap();
+        }
+
+        let mut maker = ModpackMaker::new(dir, dir.join("pack"));
+        maker.threads = 2;
+        let mut states = vec![];
+        loop {
+            let state = maker.chunk().await.unwrap();
+            if state == State::Searching {
+                break;
+            }
+            states.push(state);
+        }
+        let hashed = maker.hash_filenames.len();
+
+        assert_eq!(
+            states,
+            [
+                State::Hashing { done: 0, total: 5 },
+                State::Hashing { done: 2, total: 5 },
+                State::Hashing { done: 4, total: 5 },
+            ]
+        );
+        assert_eq!(hashed, 5);
+    }
+
+    #[tokio::test]
+    async fn writer_round_trip() {
+        use std::io::Cursor;
+
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        std::fs::create_dir_all(dir.join("mods")).unwrap();
+        std::fs::create_dir_all(dir.join("config")).unwrap();
+        std::fs::write(dir.join("config/sodium.json"), b"{}").unwrap();
+
+        // No mods, so Modrinth is never asked.
+        let mut maker = ModpackMaker::with_writer(dir, Cursor::new(Vec::new()));
+        maker.finish().await.unwrap();
+        let pack = maker
+            .into_writer()
+            .unwrap()
+            .into_inner();
+
+        let mut zip = zip::ZipArchive::new(Cursor::new(pack)).unwrap();
+        let names: Vec<&str> = zip.file_names().collect();
+        assert!(names.contains(&"overrides/config/sodium.json"));
+        assert!(names.contains(&constants::OVERRIDES_HASHES));
+        let index: RinthModpack = serde_json::from_reader(
+            zip.by_name(constants::RINTH_JSON)
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(index.get_files().is_empty());
+
+        let named = ModpackMaker::new(dir, dir.join("pack"));
+        assert!(named.into_writer().is_none());
+    }
 }
@@ -4,21 +4,26 @@ use std::fs::read_dir;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+pub use curse_maker::CurseModpackMaker;
 use derive_more::Display;
+pub(crate) use maker::is_mod_file;
 pub use maker::ModpackMaker;
-pub use maker::State;
+pub use maker::{LookupFailure, State};
 use mine_data_structs::minecraft::Profile;
 use mine_data_structs::rinth::{RinthModpack, RinthVersion, RinthVersionFile, RinthVersions};
 use reqwest::header::{HeaderMap, CONTENT_TYPE};
-use reqwest::{Body, ClientBuilder};
+use reqwest::Body;
 use serde::{Deserialize, Serialize};
 use tokio::task::JoinHandle;
 use zip::ZipWriter;
 
 use crate::error::{Result, UraniumError};
 use crate::hashes::rinth_hash;
+use crate::http::{default_client, json_limited};
 use crate::searcher::rinth::{SearchBuilder, SearchType};
+pub use crate::zipper::Overrides;
 
+mod curse_maker;
 mod maker;
 
 #[derive(Clone, Copy, Debug)]
@@ -41,7 +46,7 @@ enum InnerState {
     End,
 }
 
-#[derive(Display)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
 pub enum ModLoaders {
     #[display("forge")]
     Forge,
@@ -49,6 +54,26 @@ pub enum ModLoaders {
     Fabric,
     #[display("quilt")]
     Quilt,
+    #[display("neoforge")]
+    NeoForge,
+}
+
+impl ModLoaders {
+    /// Parses a loader name as written in the modpack manifests, e.g.
+    /// `"forge"` or `"neoforge"`.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<ModLoaders> {
+        match name
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "forge" => Some(ModLoaders::Forge),
+            "fabric" => Some(ModLoaders::Fabric),
+            "quilt" => Some(ModLoaders::Quilt),
+            "neoforge" => Some(ModLoaders::NeoForge),
+            _ => None,
+        }
+    }
 }
 
 struct ModpackMaker2 {
@@ -66,15 +91,13 @@ impl ModpackMaker2 {
         let path = profile
             .game_dir
             .clone()
-            .unwrap_or_else(|| PathBuf::new());
+            .unwrap_or_default();
         if !Path::exists(&path) {
-            log::error!("Path: {:?}, not found", &path);
+            log::error!("Path: {:?}, not found", path);
             return Err(UraniumError::FileNotFound(path.display().to_string()));
         }
 
-        let client = ClientBuilder::new()
-            .user_agent("uranium-rs/ModpackMaker contact: sergious234@gmail.com")
-            .build()?;
+        let client = default_client()?;
 
         let dir = read_dir(path.join("mods"))?;
 
@@ -86,7 +109,7 @@ impl ModpackMaker2 {
             state: MakingProgress::ReadingProfile,
             inner: InnerState::ReadingMods {
                 data: HashMap::new(),
-                dir: dir,
+                dir,
             },
             modpack: RinthModpack::new(),
         })
@@ -107,7 +130,7 @@ impl ModpackMaker2 {
                     i += 1;
                     let minecraft_mod = minecraft_mod?;
                     let path = minecraft_mod.path();
-                    let hash = rinth_hash(&path);
+                    let hash = rinth_hash(&path)?;
                     data.insert(hash, path);
                 }
 
@@ -139,9 +162,8 @@ impl ModpackMaker2 {
                         algorithm: "sha1".to_string(),
                     })
                     .send()
-                    .await?
-                    .json::<HashMap<String, RinthVersionFile>>()
                     .await?;
+                let x = json_limited::<HashMap<String, RinthVersionFile>>(x).await?;
 
                 for hash in &hashes {
                     if !x.contains_key(hash) {
@@ -170,7 +192,7 @@ impl ModpackMaker2 {
                 MP::Finished
             }
 
-            IS::WritingModpack { .. } => {
+            IS::WritingModpack => {
                 const OVERRIDES_FOLDERS: [&str; 2] = ["resourcepacks", "config"];
                 let mut zip = ZipWriter::new(std::fs::File::open("test")?);
 
@@ -212,7 +234,7 @@ mod test {
     #[tokio::test]
     async fn make_test() {
         let path = "/home/sergio/.minecraft/Quilt1.19.2";
-        if !std::fs::exists(&path).unwrap() {
+        if !std::fs::exists(path).unwrap() {
             println!("F");
             return;
         }
@@ -0,0 +1,179 @@
+//! Where Uranium keeps its own files and where it looks for `.minecraft`.
+//!
+//! By default both live in the user home, `~/.uranium` and the launcher
+//! `.minecraft`. Without a home directory, e.g. in a container or a server,
+//! they can be set with [`set_dirs`] or with the `URANIUM_HOME` and
+//! `URANIUM_MINECRAFT_DIR` env vars. The caches go in `cache/` inside the
+//! uranium dir unless [`Dirs::with_cache_dir`] or `URANIUM_CACHE_DIR` say
+//! otherwise.
+//!
+//! ```
+//! use uranium::paths::{set_dirs, uranium_dir, Dirs};
+//!
+//! set_dirs(Dirs::new().with_uranium_dir("/srv/uranium"));
+//! assert_eq!(uranium_dir().unwrap(), std::path::Path::new("/srv/uranium"));
+//! ```
+
+use std::{path::PathBuf, sync::RwLock};
+
+use mine_data_structs::minecraft::get_minecraft_path;
+
+use crate::error::{Result, UraniumError};
+
+/// Env var with the directory used instead of `~/.uranium`.
+pub const URANIUM_HOME_VAR: &str = "URANIUM_HOME";
+/// Env var with the `.minecraft` directory.
+pub const MINECRAFT_DIR_VAR: &str = "URANIUM_MINECRAFT_DIR";
+/// Env var with the directory used instead of `cache/` in the uranium dir.
+pub const CACHE_DIR_VAR: &str = "URANIUM_CACHE_DIR";
+
+pub(crate) static DIRS: RwLock<Dirs> = RwLock::new(Dirs::new());
+
+/// The directories used by Uranium, the ones not set are looked up in the
+/// env vars and then in the user home.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dirs {
+    uranium: Option<PathBuf>,
+    minecraft: Option<PathBuf>,
+    cache: Option<PathBuf>,
+}
+
+impl Dirs {
+    #[must_use]
+    pub const fn new() -> Dirs {
+        Dirs {
+            uranium: None,
+            minecraft: None,
+            cache: None,
+        }
+    }
+
+    /// Where the logs are written, and the caches if no cache dir is set.
+    #[must_use]
+    pub fn with_uranium_dir<I: Into<PathBuf>>(mut self, dir: I) -> Self {
+        self.uranium = Some(dir.into());
+        self
+    }
+
+    /// The `.minecraft` directory, used for the launcher profiles.
+    #[must_use]
+    pub fn with_minecraft_dir<I: Into<PathBuf>>(mut self, dir: I) -> Self {
+        self.minecraft = Some(dir.into());
+        self
+    }
+
+    /// Where the [`VersionCache`](crate::cache::VersionCache) and the
+    /// [`ArtifactCache`](crate::artifact_cache::ArtifactCache) are kept.
+    #[must_use]
+    pub fn with_cache_dir<I: Into<PathBuf>>(mut self, dir: I) -> Self {
+        self.cache = Some(dir.into());
+        self
+    }
+
+    fn uranium_dir(&self) -> Result<PathBuf> {
+        self.uranium
+            .clone()
+            .or_else(|| std::env::var_os(URANIUM_HOME_VAR).map(PathBuf::from))
+            .or_else(|| dirs::home_dir().map(|home| home.join(".uranium")))
+            .ok_or_else(|| {
+                UraniumError::OtherWithReason(format!(
+                    "Cant get user home directory, set {URANIUM_HOME_VAR}"
+                ))
+            })
+    }
+
+    fn cache_dir(&self) -> Result<PathBuf> {
+        match self
+            .cache
+            .clone()
+            .or_else(|| std::env::var_os(CACHE_DIR_VAR).map(PathBuf::from))
+        {
+            Some(dir) => Ok(dir),
+            None => Ok(self
+                .uranium_dir()?
+                .join("cache")),
+        }
+    }
+
+    fn minecraft_dir(&self) -> Result<PathBuf> {
+        self.minecraft
+            .clone()
+            .or_else(|| std::env::var_os(MINECRAFT_DIR_VAR).map(PathBuf::from))
+            .or_else(get_minecraft_path)
+            .ok_or_else(|| {
+                UraniumError::OtherWithReason(format!(
+                    ".minecraft not found, set {MINECRAFT_DIR_VAR}"
+                ))
+            })
+    }
+}
+
+/// Sets the directories used by Uranium from now on.
+///
+/// In case the directories can't be updated this function will return None,
+/// in case of success Some(()) is returned.
+pub fn set_dirs(dirs: Dirs) -> Option<()> {
+    let mut aux = DIRS.write().ok()?;
+    *aux = dirs;
+    Some(())
+}
+
+/// Returns the directory where Uranium writes its logs, and its caches unless
+/// a cache dir is set.
+///
+/// # Errors
+/// Returns an error if it was not set and there is no home directory.
+pub fn uranium_dir() -> Result<PathBuf> {
+    current().uranium_dir()
+}
+
+/// Returns the directory where Uranium keeps its caches, `cache/` inside
+/// [`uranium_dir`] by default.
+///
+/// # Errors
+/// Returns an error if it was not set and there is no home directory.
+pub fn cache_dir() -> Result<PathBuf> {
+    current().cache_dir()
+}
+
+/// Returns the `.minecraft` directory.
+///
+/// # Errors
+/// Returns an error if it was not set and it can't be found.
+pub fn minecraft_dir() -> Result<PathBuf> {
+    current().minecraft_dir()
+}
+
+pub(crate) fn current() -> Dirs {
+    DIRS.read()
+        .map(|d| d.clone())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configured_dirs_first() {
+        let dirs = Dirs::new()
+            .with_uranium_dir("/srv/uranium")
+            .with_minecraft_dir("/srv/minecraft");
+
+        assert_eq!(dirs.uranium_dir().unwrap(), PathBuf::from("/srv/uranium"));
+        assert_eq!(
+            dirs.minecraft_dir().unwrap(),
+            PathBuf::from("/srv/minecraft")
+        );
+        assert_eq!(
+            dirs.cache_dir().unwrap(),
+            PathBuf::from("/srv/uranium/cache")
+        );
+        assert_eq!(
+            dirs.with_cache_dir("/var/cache/uranium")
+                .cache_dir()
+                .unwrap(),
+            PathBuf::from("/var/cache/uranium")
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! Byte-level progress events pushed by the downloaders.
+//!
+//! A [`ProgressReporter`] can be a closure or a tokio channel sender, so a GUI
+//! can follow the download without polling `progress()`:
+//!
+//! ```no_run
+//! # async fn foo() -> uranium::error::Result<()> {
+//! use std::sync::Arc;
+//!
+//! use uranium::downloaders::{Downloader, RinthDownloader};
+//! use uranium::progress::ProgressEvent;
+//!
+//! let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<ProgressEvent>();
+//! tokio::spawn(async move {
+//!     while let Some(event) = rx.recv().await {
+//!         println!("{event:?}");
+//!     }
+//! });
+//!
+//! let mut rinth = RinthDownloader::<Downloader>::new("modpack.mrpack", "instance")?;
+//! rinth.set_progress_reporter(Arc::new(tx));
+//! rinth.complete().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::Stream;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+/// Something that happened to a file while downloading it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProgressEvent {
+    /// The download of the file started, `size` is the expected size if known.
+    FileStarted { path: PathBuf, size: Option<u64> },
+    /// `bytes` more bytes of the file were written.
+    BytesDownloaded { path: PathBuf, bytes: u64 },
+    /// The file is complete and its hash is right, also sent for files which
+    /// were already up to date.
+    FileVerified { path: PathBuf },
+    /// The file couldn't be downloaded or its hash was wrong.
+    FileFailed { path: PathBuf, reason: String },
+}
+
+/// A file of the download is done, see
+/// [`FileDownloader::completions`](crate::downloaders::FileDownloader::completions).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CompletedFile {
+    pub path: PathBuf,
+    /// Bytes downloaded, 0 if the file was skipped.
+    pub bytes: u64,
+    /// Time since its download started.
+    pub duration: Duration,
+    /// The file was already up to date or taken from the
+    /// [`ArtifactCache`](crate::artifact_cache::ArtifactCache), nothing was
+    /// downloaded.
+    pub was_skipped: bool,
+}
+
+/// Receives the [`ProgressEvent`]s of a download.
+///
+/// It is implemented for closures and `tokio::sync::mpsc::UnboundedSender`.
+/// `report` is called from the download tasks so it should return quickly.
+pub trait ProgressReporter: Send + Sync {
+    fn report(&self, event: ProgressEvent);
+}
+
+impl<F: Fn(ProgressEvent) + Send + Sync> ProgressReporter for F {
+    fn report(&self, event: ProgressEvent) {
+        self(event);
+    }
+}
+
+impl ProgressReporter for UnboundedSender<ProgressEvent> {
+    fn report(&self, event: ProgressEvent) {
+        // The receiver may be gone, the download goes on anyway.
+        let _ = self.send(event);
+    }
+}
+
+impl ProgressReporter for Vec<Arc<dyn ProgressReporter>> {
+    fn report(&self, event: ProgressEvent) {
+        for reporter in self {
+            reporter.report(event.clone());
+        }
+    }
+}
+
+/// Turns the [`ProgressEvent`]s of each file into a [`CompletedFile`] once it
+/// is verified.
+pub(crate) struct CompletionTracker {
+    sender: UnboundedSender<CompletedFile>,
+    /// Start and bytes downloaded of the files being downloaded.
+    started: Mutex<HashMap<PathBuf, (Instant, u64)>>,
+}
+
+impl CompletionTracker {
+    pub(crate) fn new(sender: UnboundedSender<CompletedFile>) -> Self {
+        CompletionTracker {
+            sender,
+            started: Mutex::default(),
+        }
+    }
+}
+
+impl ProgressReporter for CompletionTracker {
+    fn report(&self, event: ProgressEvent) {
+        let Ok(mut started) = self.started.lock() else {
+            return;
+        };
+        match event {
+            ProgressEvent::FileStarted { path, .. } => {
+                started.insert(path, (Instant::now(), 0));
+            }
+            ProgressEvent::BytesDownloaded { path, bytes } => {
+                if let Some((_, total)) = started.get_mut(&path) {
+                    *total += bytes;
+                }
+            }
+            ProgressEvent::FileVerified { path } => {
+                let file = match started.remove(&path) {
+                    Some((start, bytes)) => CompletedFile {
+                        path,
+                        bytes,
+                        duration: start.elapsed(),
+                        was_skipped: false,
+                    },
+                    None => CompletedFile {
+                        path,
+                        bytes: 0,
+                        duration: Duration::ZERO,
+                        was_skipped: true,
+                    },
+                };
+                // The stream may be gone, the download goes on anyway.
+                let _ = self.sender.send(file);
+            }
+            ProgressEvent::FileFailed { path, .. } => {
+                started.remove(&path);
+            }
+        }
+    }
+}
+
+/// Returns the files sent into `receiver` as a stream.
+pub(crate) fn completion_stream(
+    receiver: UnboundedReceiver<CompletedFile>,
+) -> impl Stream<Item = CompletedFile> + Send + Unpin + 'static {
+    CompletionStream(receiver)
+}
+
+struct CompletionStream(UnboundedReceiver<CompletedFile>);
+
+impl Stream for CompletionStream {
+    type Item = CompletedFile;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<CompletedFile>> {
+        self.0.poll_recv(cx)
+    }
+}
+
+/// Shared handle to an optional reporter.
+pub(crate) type Reporter = Option<Arc<dyn ProgressReporter>>;
+
+pub(crate) fn report(reporter: &Reporter, event: ProgressEvent) {
+    if let Some(reporter) = reporter {
+        reporter.report(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn closure_and_channel_reporters() {
+        let seen = Arc::new(Mutex::new(vec![]));
+        let seen_clone = seen.clone();
+        let closure: Reporter = Some(Arc::new(move |e: ProgressEvent| {
+            seen_clone
+                .lock()
+                .unwrap()
+                .push(e)
+        }));
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let channel: Reporter = Some(Arc::new(tx));
+
+        let event = ProgressEvent::BytesDownloaded {
+            path: PathBuf::from("mods/sodium.jar"),
+            bytes: 42,
+        };
+        report(&closure, event.clone());
+        report(&channel, event.clone());
+        report(&None, event.clone());
+
+        assert_eq!(*seen.lock().unwrap(), vec![event.clone()]);
+        assert_eq!(rx.try_recv().ok(), Some(event));
+    }
+}
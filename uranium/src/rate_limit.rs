@@ -0,0 +1,335 @@
+//! Client side rate limiting for the Modrinth API.
+//!
+//! Modrinth allows 300 requests per minute, big mod folders easily go over
+//! that when the maker or the updater look up every mod at once. All the
+//! requests to Modrinth go through [`rinth_limiter()`], a token bucket which
+//! also stops sending requests when the `X-Ratelimit-Remaining` header says
+//! there are none left until `X-Ratelimit-Reset`.
+//!
+//! The same bucket limits the download speed when [`set_max_bandwidth`] is
+//! used, with a token per byte.
+
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::{Duration, Instant};
+
+use log::warn;
+use reqwest::header::HeaderMap;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use tokio::sync::Mutex;
+
+use crate::error::{Result, UraniumError};
+
+/// Requests per minute allowed by Modrinth.
+const RINTH_REQUESTS_PER_MINUTE: u32 = 300;
+/// How many requests can be sent at once before being throttled.
+const RINTH_BURST: u32 = 50;
+/// How many times a rate limited request is sent again.
+const RATE_LIMITED_RETRIES: u32 = 3;
+
+static RINTH_LIMITER: LazyLock<RateLimiter> =
+    LazyLock::new(|| RateLimiter::new(RINTH_BURST, RINTH_REQUESTS_PER_MINUTE));
+
+pub(crate) static BANDWIDTH: RwLock<Option<Arc<RateLimiter>>> = RwLock::new(None);
+
+/// Limits the speed of the file downloads to `bytes_per_second`, shared by
+/// every download. `None` removes the limit.
+///
+/// # Errors
+/// Returns `Err(UraniumError::Other)` if the limit can't be updated.
+pub fn set_max_bandwidth(bytes_per_second: Option<u64>) -> Result<()> {
+    let mut aux = BANDWIDTH
+        .write()
+        .map_err(|_| UraniumError::Other)?;
+    *aux = bytes_per_second.map(|bytes| {
+        // A second of burst.
+        let bytes = bytes.max(1) as f64;
+        Arc::new(RateLimiter::with_rate(bytes, bytes))
+    });
+    Ok(())
+}
+
+/// Waits until `bytes` more bytes can be downloaded, see
+/// [`set_max_bandwidth`].
+pub(crate) async fn throttle_download(bytes: usize) {
+    let limiter = BANDWIDTH
+        .read()
+        .ok()
+        .and_then(|l| l.clone());
+    if let Some(limiter) = limiter {
+        limiter
+            .acquire_many(bytes as f64)
+            .await;
+    }
+}
+
+/// Returns the limiter shared by every request to Modrinth.
+pub fn rinth_limiter() -> &'static RateLimiter {
+    &RINTH_LIMITER
+}
+
+/// A token bucket, each request takes a token and tokens come back at a
+/// fixed rate.
+pub struct RateLimiter {
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    capacity: f64,
+    per_second: f64,
+    tokens: f64,
+    last_refill: Instant,
+    blocked_until: Option<Instant>,
+}
+
+impl Bucket {
+    /// Takes `n` tokens, if there are not enough returns how long to wait
+    /// for them.
+    ///
+    /// More than the capacity is taken once the bucket is full, leaving it in
+    /// debt so the next takes wait for the extra tokens.
+    fn take(&mut self, now: Instant, n: f64) -> Option<Duration> {
+        if let Some(until) = self.blocked_until {
+            if until > now {
+                return Some(until - now);
+            }
+            self.blocked_until = None;
+        }
+
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.capacity);
+        self.last_refill = now;
+
+        let needed = n.min(self.capacity);
+        if self.tokens >= needed {
+            self.tokens -= n;
+            None
+        } else {
+            Some(Duration::from_secs_f64(
+                (needed - self.tokens) / self.per_second,
+            ))
+        }
+    }
+}
+
+impl RateLimiter {
+    /// Makes a limiter which allows `burst` requests at once and
+    /// `per_minute` requests each minute.
+    #[must_use]
+    pub fn new(burst: u32, per_minute: u32) -> Self {
+        Self::with_rate(f64::from(burst.max(1)), f64::from(per_minute.max(1)) / 60.0)
+    }
+
+    fn with_rate(capacity: f64, per_second: f64) -> Self {
+        RateLimiter {
+            bucket: Mutex::new(Bucket {
+                capacity,
+                per_second,
+                tokens: capacity,
+                last_refill: Instant::now(),
+                blocked_until: None,
+            }),
+        }
+    }
+
+    /// Waits until a request can be sent.
+    pub async fn acquire(&self) {
+        self.acquire_many(1.0).await;
+    }
+
+    async fn acquire_many(&self, n: f64) {
+        // The lock is held while sleeping so waiting requests go in order.
+        let mut bucket = self.bucket.lock().await;
+        while let Some(wait) = bucket.take(Instant::now(), n) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Blocks new requests if the rate limit headers say the server won't
+    /// accept more until the limit resets.
+    ///
+    /// The wait is the `Retry-After` header, or `X-Ratelimit-Reset` if there
+    /// is none.
+    pub async fn update_from_headers(&self, status: StatusCode, headers: &HeaderMap) {
+        let header = |name: &str| -> Option<u64> {
+            headers
+                .get(name)?
+                .to_str()
+                .ok()?
+                .parse()
+                .ok()
+        };
+
+        let exhausted =
+            status == StatusCode::TOO_MANY_REQUESTS || header("x-ratelimit-remaining") == Some(0);
+        if !exhausted {
+            return;
+        }
+
+        let reset = Duration::from_secs(
+            header("retry-after")
+                .or_else(|| header("x-ratelimit-reset"))
+                .unwrap_or(60),
+        );
+        warn!("Rate limit reached, waiting {}s", reset.as_secs());
+        let mut bucket = self.bucket.lock().await;
+        bucket.blocked_until = Some(Instant::now() + reset);
+        bucket.tokens = 0.0;
+    }
+
+    /// Sends `request` once a token is available and updates the limiter with
+    /// the response headers.
+    ///
+    /// A 429 response is sent again once the limit resets, up to
+    /// [`RATE_LIMITED_RETRIES`] times.
+    pub(crate) async fn send(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        let mut retries = 0;
+        loop {
+            // Requests with a streamed body can't be cloned, those are sent
+            // only once.
+            let next = match request.try_clone() {
+                Some(next) if retries < RATE_LIMITED_RETRIES => next,
+                _ => return self.send_once(request).await,
+            };
+
+            let response = self.send_once(next).await?;
+            if response.status() != StatusCode::TOO_MANY_REQUESTS {
+                return Ok(response);
+            }
+            retries += 1;
+            warn!(
+                "{} is rate limited, retrying ({retries}/{RATE_LIMITED_RETRIES})",
+                response.url()
+            );
+        }
+    }
+
+    async fn send_once(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        self.acquire().await;
+        let response = request.send().await?;
+        self.update_from_headers(response.status(), response.headers())
+            .await;
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_refills() {
+        let start = Instant::now();
+        let mut bucket = Bucket {
+            capacity: 2.0,
+            per_second: 1.0,
+            tokens: 2.0,
+            last_refill: start,
+            blocked_until: None,
+        };
+
+        assert_eq!(bucket.take(start, 1.0), None);
+        assert_eq!(bucket.take(start, 1.0), None);
+        assert_eq!(bucket.take(start, 1.0), Some(Duration::from_secs(1)));
+        assert_eq!(bucket.take(start + Duration::from_secs(1), 1.0), None);
+
+        // Never more than `capacity` tokens.
+        let later = start + Duration::from_secs(100);
+        assert_eq!(bucket.take(later, 1.0), None);
+        assert_eq!(bucket.take(later, 1.0), None);
+        assert!(bucket
+            .take(later, 1.0)
+            .is_some());
+
+        // Taking more than `capacity` waits for a full bucket, then the extra
+        // tokens are waited by the next take.
+        let full = later + Duration::from_secs(100);
+        assert_eq!(bucket.take(full, 5.0), None);
+        assert_eq!(bucket.take(full, 1.0), Some(Duration::from_secs(4)));
+        assert_eq!(bucket.take(full + Duration::from_secs(4), 1.0), None);
+    }
+
+    #[tokio::test]
+    async fn headers_block_requests() {
+        let limiter = RateLimiter::new(10, 60);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", "5".parse().unwrap());
+        limiter
+            .update_from_headers(StatusCode::OK, &headers)
+            .await;
+        assert!(limiter
+            .bucket
+            .lock()
+            .await
+            .blocked_until
+            .is_none());
+
+        headers.insert("x-ratelimit-remaining", "0".parse().unwrap());
+        headers.insert("x-ratelimit-reset", "30".parse().unwrap());
+        limiter
+            .update_from_headers(StatusCode::OK, &headers)
+            .await;
+        let wait = limiter
+            .bucket
+            .lock()
+            .await
+            .take(Instant::now(), 1.0)
+            .unwrap();
+        assert!(wait > Duration::from_secs(29));
+
+        headers.insert("retry-after", "5".parse().unwrap());
+        limiter
+            .update_from_headers(StatusCode::TOO_MANY_REQUESTS, &headers)
+            .await;
+        let wait = limiter
+            .bucket
+            .lock()
+            .await
+            .take(Instant::now(), 1.0)
+            .unwrap();
+        assert!(wait > Duration::from_secs(4) && wait <= Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn retries_rate_limited() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap();
+        let url = format!(
+            "http://{}/v2/version_file/abc",
+            listener.local_addr().unwrap()
+        );
+        let server = tokio::spawn(async move {
+            let mut requests = 0;
+            for status in ["429 Too Many Requests", "200 OK"] {
+                let (mut socket, _) = listener
+                    .accept()
+                    .await
+                    .unwrap();
+                let mut buf = [0; 1024];
+                let _ = socket.read(&mut buf).await;
+                requests += 1;
+                let response = format!(
+                    "HTTP/1.1 {status}\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                );
+                socket
+                    .write_all(response.as_bytes())
+                    .await
+                    .unwrap();
+            }
+            requests
+        });
+
+        let response = RateLimiter::new(10, 60)
+            .send(reqwest::Client::new().get(&url))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(server.await.unwrap(), 2);
+    }
+}
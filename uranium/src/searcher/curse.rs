@@ -0,0 +1,370 @@
+use mine_data_structs::curse::curse_search::{CurseSearchHit, CurseSearchResponse};
+use mine_data_structs::url_maker::maker::Curse;
+use reqwest::Url;
+
+use crate::error::Result;
+use crate::http::{default_client, fetch_json};
+use crate::modpack_maker::ModLoaders;
+use crate::secrets::curse_api_key;
+
+/// Curse id of Minecraft.
+pub const MINECRAFT_GAME_ID: u32 = 432;
+
+/// Max results per page accepted by Curse.
+pub const MAX_PAGE_SIZE: u32 = 50;
+
+/// Curse doesn't return results past this index, `index + page_size` can't
+/// be greater.
+pub const MAX_SEARCH_RESULTS: u32 = 10_000;
+
+/// Some of the Curse classes (project types) of Minecraft.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CurseClass {
+    Mods,
+    Modpacks,
+    ResourcePacks,
+    Worlds,
+    Shaders,
+}
+
+impl CurseClass {
+    pub fn id(self) -> u32 {
+        match self {
+            Self::Mods => 6,
+            Self::Modpacks => 4471,
+            Self::ResourcePacks => 12,
+            Self::Worlds => 17,
+            Self::Shaders => 6552,
+        }
+    }
+}
+
+/// A list specifying the different orders the search results can be sorted
+/// by.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CurseSortField {
+    Featured,
+    Popularity,
+    LastUpdated,
+    Name,
+    Author,
+    TotalDownloads,
+    Category,
+    GameVersion,
+}
+
+impl CurseSortField {
+    fn id(self) -> u32 {
+        match self {
+            Self::Featured => 1,
+            Self::Popularity => 2,
+            Self::LastUpdated => 3,
+            Self::Name => 4,
+            Self::Author => 5,
+            Self::TotalDownloads => 6,
+            Self::Category => 7,
+            Self::GameVersion => 8,
+        }
+    }
+}
+
+/// Curse `modLoaderType` of each loader.
+fn loader_id(loader: ModLoaders) -> u32 {
+    match loader {
+        ModLoaders::Forge => 1,
+        ModLoaders::Fabric => 4,
+        ModLoaders::Quilt => 5,
+        ModLoaders::NeoForge => 6,
+    }
+}
+
+/// A builder for CurseForge searches, the Curse counterpart of
+/// [`SearchBuilder`](super::rinth::SearchBuilder).
+///
+/// Searches Minecraft projects unless [`CurseSearchBuilder::game_id`] is
+/// used.
+///
+/// ```rust
+/// # use uranium::searcher::curse::{CurseClass, CurseSearchBuilder, CurseSortField};
+/// let url = CurseSearchBuilder::new()
+///     .class(CurseClass::Mods)
+///     .search_filter("jei")
+///     .game_version("1.20.1")
+///     .sort_field(CurseSortField::TotalDownloads)
+///     .page_size(10)
+///     .build_url();
+/// assert_eq!(
+///     "https://api.curseforge.com/v1/mods/search?gameId=432&classId=6&searchFilter=jei\
+///     &gameVersion=1.20.1&sortField=6&sortOrder=desc&pageSize=10",
+///     url
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct CurseSearchBuilder {
+    game_id: u32,
+    class_id: Option<u32>,
+    category_ids: Vec<u32>,
+    search_filter: Option<String>,
+    mod_loader: Option<ModLoaders>,
+    game_version: Option<String>,
+    sort_field: Option<CurseSortField>,
+    index: Option<u32>,
+    page_size: Option<u32>,
+}
+
+impl Default for CurseSearchBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CurseSearchBuilder {
+    pub fn new() -> CurseSearchBuilder {
+        CurseSearchBuilder {
+            game_id: MINECRAFT_GAME_ID,
+            class_id: None,
+            category_ids: vec![],
+            search_filter: None,
+            mod_loader: None,
+            game_version: None,
+            sort_field: None,
+            index: None,
+            page_size: None,
+        }
+    }
+
+    pub fn game_id(mut self, game_id: u32) -> Self {
+        self.game_id = game_id;
+        self
+    }
+
+    /// Sets the project type to search, see [`CurseSearchBuilder::class`].
+    pub fn class_id(mut self, class_id: u32) -> Self {
+        self.class_id = Some(class_id);
+        self
+    }
+
+    pub fn class(self, class: CurseClass) -> Self {
+        self.class_id(class.id())
+    }
+
+    /// Only returns projects in any of `category_ids`, Curse accepts up to
+    /// 10 categories.
+    pub fn category_ids(mut self, category_ids: Vec<u32>) -> Self {
+        self.category_ids = category_ids;
+        self
+    }
+
+    /// Sets the text to search in the projects name, summary and authors.
+    pub fn search_filter(mut self, filter: &str) -> Self {
+        self.search_filter = Some(filter.to_owned());
+        self
+    }
+
+    pub fn mod_loader(mut self, loader: ModLoaders) -> Self {
+        self.mod_loader = Some(loader);
+        self
+    }
+
+    pub fn game_version(mut self, version: &str) -> Self {
+        self.game_version = Some(version.to_owned());
+        self
+    }
+
+    /// Sets how the results are sorted, always in descending order.
+    pub fn sort_field(mut self, field: CurseSortField) -> Self {
+        self.sort_field = Some(field);
+        self
+    }
+
+    /// Sets the index of the first result, `index + page_size` can't be
+    /// greater than 10000.
+    pub fn index(mut self, index: u32) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    /// Sets the number of results per page, 50 at most.
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = Some(page_size);
+        self
+    }
+
+    /// Builds the search url, the parameters are percent encoded.
+    pub fn build_url(&self) -> String {
+        let mut params = vec![("gameId", self.game_id.to_string())];
+
+        if let Some(class_id) = self.class_id {
+            params.push(("classId", class_id.to_string()));
+        }
+
+        if !self.category_ids.is_empty() {
+            let ids = self
+                .category_ids
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>()
+                .join(",");
+            params.push(("categoryIds", format!("[{ids}]")));
+        }
+
+        if let Some(filter) = &self.search_filter {
+            params.push(("searchFilter", filter.clone()));
+        }
+
+        if let Some(loader) = self.mod_loader {
+            params.push(("modLoaderType", loader_id(loader).to_string()));
+        }
+
+        if let Some(version) = &self.game_version {
+            params.push(("gameVersion", version.clone()));
+        }
+
+        if let Some(field) = self.sort_field {
+            params.push(("sortField", field.id().to_string()));
+            params.push(("sortOrder", "desc".to_owned()));
+        }
+
+        if let Some(index) = self.index {
+            params.push(("index", index.to_string()));
+        }
+
+        if let Some(page_size) = self.page_size {
+            params.push(("pageSize", page_size.to_string()));
+        }
+
+        Url::parse_with_params(&Curse::search(), &params)
+            .map(String::from)
+            .unwrap_or_else(|_| Curse::search())
+    }
+
+    /// Sends the search with the Curse API key.
+    ///
+    /// # Errors
+    /// Returns an error if the request fails or the response is not a valid
+    /// search response.
+    pub async fn search(&self) -> Result<CurseSearchResponse> {
+        let request = default_client()?
+            .get(self.build_url())
+            .header("x-api-key", curse_api_key()?);
+        fetch_json(request).await
+    }
+
+    /// Returns a [`CursePages`] going through every page of the search,
+    /// starting at [`CurseSearchBuilder::index`].
+    ///
+    /// Pages of [`MAX_PAGE_SIZE`] results are requested unless
+    /// [`CurseSearchBuilder::page_size`] is set.
+    pub fn pages(self) -> CursePages {
+        CursePages {
+            next_index: self.index.or(Some(0)),
+            builder: self,
+        }
+    }
+}
+
+/// Pages of a Curse search, requested one by one with
+/// [`CursePages::next_page`].
+///
+/// ```no_run
+/// # async fn foo() -> uranium::error::Result<()> {
+/// use uranium::searcher::curse::{CurseClass, CurseSearchBuilder};
+///
+/// let mut pages = CurseSearchBuilder::new()
+///     .class(CurseClass::Mods)
+///     .search_filter("create")
+///     .pages();
+/// while let Some(page) = pages.next_page().await {
+///     for hit in page?.data {
+///         println!("{}", hit.get_name());
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CursePages {
+    builder: CurseSearchBuilder,
+    next_index: Option<u32>,
+}
+
+impl CursePages {
+    /// Requests the next page, `None` once all the results were returned or
+    /// after an error.
+    pub async fn next_page(&mut self) -> Option<Result<CurseSearchResponse>> {
+        let index = self.next_index.take()?;
+        let page_size = self
+            .builder
+            .page_size
+            .unwrap_or(MAX_PAGE_SIZE)
+            .min(MAX_SEARCH_RESULTS.checked_sub(index)?);
+        if page_size == 0 {
+            return None;
+        }
+
+        let page = self
+            .builder
+            .clone()
+            .index(index)
+            .page_size(page_size)
+            .search()
+            .await;
+
+        if let Ok(response) = &page {
+            if response.has_more()
+                && response
+                    .pagination
+                    .result_count
+                    > 0
+            {
+                self.next_index = u32::try_from(
+                    response
+                        .pagination
+                        .next_index(),
+                )
+                .ok();
+            }
+        }
+        Some(page)
+    }
+
+    /// Returns the hits of every remaining page, `limit` at most.
+    ///
+    /// # Errors
+    /// Returns the error of the first page that can't be requested.
+    pub async fn collect_hits(mut self, limit: usize) -> Result<Vec<CurseSearchHit>> {
+        let mut hits = Vec::new();
+        while hits.len() < limit {
+            let Some(page) = self.next_page().await else {
+                break;
+            };
+            hits.extend(page?.data);
+        }
+        hits.truncate(limit);
+        Ok(hits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curse_search_url() {
+        let url = CurseSearchBuilder::new()
+            .class(CurseClass::Modpacks)
+            .category_ids(vec![4471, 4472])
+            .search_filter("all the mods")
+            .mod_loader(ModLoaders::NeoForge)
+            .index(50)
+            .page_size(25)
+            .build_url();
+
+        assert_eq!(
+            "https://api.curseforge.com/v1/mods/search?gameId=432&classId=4471\
+            &categoryIds=%5B4471%2C4472%5D&searchFilter=all+the+mods&modLoaderType=6\
+            &index=50&pageSize=25",
+            url
+        );
+    }
+}
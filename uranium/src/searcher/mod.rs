@@ -1 +1,2 @@
+pub mod curse;
 pub mod rinth;
@@ -1,4 +1,13 @@
 use std::fmt::{Display, Formatter};
+use std::ops::Not;
+
+use futures::{stream, Stream, StreamExt};
+use mine_data_structs::rinth::{RinthHit, RinthResponse};
+
+use crate::{error::Result, http::fetch_rinth_json, instance::InstanceMetadata};
+
+/// Max results per page accepted by Modrinth, the `limit` of a search.
+pub const MAX_LIMIT: u32 = 100;
 
 /// A type for representing that no search type is set.
 type NoSearchType = ();
@@ -13,10 +22,18 @@ pub enum SearchType {
     Project { id: String },
     /// /project/{id|slug}/version
     ProjectVersion { id: String },
+    /// /version/{id}
+    Version { id: String },
+    /// /collection/{id}, only available in the v3 API.
+    Collection { id: String },
     /// /projects
-    MultiProject { ids: Vec<&'static str> },
+    MultiProject { ids: Vec<String> },
     /// /version_file/{hash}
     VersionFile { hash: String },
+    /// /version_files/update, the latest versions of many files (POST).
+    LatestVersions,
+    /// /version_files, the versions of many files (POST).
+    VersionFiles,
     /// /project/{id|slug}/dependencies
     Dependencies { id: String },
     /// /tag/category
@@ -25,10 +42,36 @@ pub enum SearchType {
     Loaders,
 }
 
+/// Search type of [`SearchBuilder::search`], the only route which accepts
+/// facets, sorting and pagination.
+#[derive(Debug, Clone)]
+pub struct ProjectSearch;
+
+/// Search type of [`SearchBuilder::project_versions`], the versions of a
+/// project can be filtered by game version, loader and `featured`.
+#[derive(Debug, Clone)]
+pub struct ProjectVersionSearch {
+    id: String,
+}
+
 /// A builder for building the URL with the indicated parameters
 /// This struct works with TypeState Programming so the `build_url()` method
 /// can't be called unless search_type is set.
 ///
+/// The parameters of each route can only be set on its own type state, e.g.
+/// `limit` only exists after [`SearchBuilder::search`] and `game_versions`
+/// after [`SearchBuilder::project_versions`], so using them with a route that
+/// ignores them doesn't compile:
+///
+/// ```compile_fail
+/// # use uranium::searcher::rinth::{SearchBuilder, SearchType};
+/// SearchBuilder::new()
+///     .search_type(SearchType::Categories)
+///     .limit(10);
+/// ```
+///
+/// The routes without parameters are set with [`SearchBuilder::search_type`].
+///
 /// The `facets` field works as a conjunction (AND) of disjunctions (OR):
 ///
 /// I.e: (pseudocode)
@@ -40,13 +83,27 @@ pub enum SearchType {
 /// ```
 ///
 /// That means: (version = 1.20 **OR** 1.21) **AND** (category = fabric)
+///
+/// Facets can also be negated with `!`, e.g. `category != fabric`.
+#[derive(Debug, Clone)]
 pub struct SearchBuilder<T> {
     search_type: T,
     facets: Option<Vec<FacetsDisjunction>>,
     query: Option<String>,
+    index: Option<SortIndex>,
     limit: Option<u32>,
     offset: Option<u32>,
     game_versions: Vec<String>,
+    loaders: Vec<String>,
+    featured: Option<bool>,
+    /// Base of the API, `https://api.modrinth.com/v2/` if not set.
+    api: Option<String>,
+}
+
+impl Default for SearchBuilder<NoSearchType> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SearchBuilder<NoSearchType> {
@@ -54,20 +111,72 @@ impl SearchBuilder<NoSearchType> {
         SearchBuilder {
             search_type: (),
             facets: None,
+            index: None,
             limit: None,
             offset: None,
             query: None,
             game_versions: vec![],
+            loaders: vec![],
+            featured: None,
+            api: None,
         }
     }
+
+    /// Starts a project search (`/search`), same as `SearchType::Search` but
+    /// facets, sorting and pagination can be set.
+    ///
+    /// ```rust
+    /// # use uranium::searcher::rinth::{SearchBuilder, SortIndex};
+    /// let url = SearchBuilder::new()
+    ///     .search()
+    ///     .sort(SortIndex::Downloads)
+    ///     .limit(5)
+    ///     .build_url();
+    /// assert_eq!("https://api.modrinth.com/v2/search?index=downloads&limit=5", url);
+    /// ```
+    pub fn search(self) -> SearchBuilder<ProjectSearch> {
+        self.with_search_type(ProjectSearch)
+    }
+
+    /// Starts a search of the versions of the project `id`, same as
+    /// `SearchType::ProjectVersion` but the versions can be filtered.
+    ///
+    /// ```rust
+    /// # use uranium::searcher::rinth::SearchBuilder;
+    /// let url = SearchBuilder::new()
+    ///     .project_versions("AANobbMI")
+    ///     .loaders(vec!["fabric".to_owned()])
+    ///     .featured(true)
+    ///     .build_url();
+    /// assert_eq!(
+    ///     "https://api.modrinth.com/v2/project/AANobbMI/version?loaders=[\"fabric\"]&featured=true",
+    ///     url
+    /// );
+    /// ```
+    pub fn project_versions(self, id: &str) -> SearchBuilder<ProjectVersionSearch> {
+        self.with_search_type(ProjectVersionSearch { id: id.to_owned() })
+    }
+
+    /// Sets a route without parameters, use [`SearchBuilder::search`] and
+    /// [`SearchBuilder::project_versions`] to filter the results.
+    pub fn search_type(self, search_type: SearchType) -> SearchBuilder<SearchType> {
+        self.with_search_type(search_type)
+    }
 }
 
-impl<T> SearchBuilder<T> {
+impl SearchBuilder<ProjectSearch> {
     pub fn facets(mut self, facets: Vec<FacetsDisjunction>) -> Self {
         self.facets = Some(facets);
         self
     }
 
+    /// Sets how the search results are sorted, by default Modrinth sorts them
+    /// by relevance.
+    pub fn sort(mut self, index: SortIndex) -> Self {
+        self.index = Some(index);
+        self
+    }
+
     pub fn limit(mut self, limit: u32) -> Self {
         self.limit = Some(limit);
         self
@@ -78,123 +187,270 @@ impl<T> SearchBuilder<T> {
         self
     }
 
-    /// Sets the game versions filter for the project version search.
+    /// Restricts the results to those installable in the instance described
+    /// by `metadata`: its game version, its loader and client side.
     ///
-    /// This method allows you to specify the game versions for which you want
-    /// to filter project versions. It accepts a vector of strings, where
-    /// each string represents a specific game version (e.g., `"1.16.5"`,
-    /// `"1.17.1"`).
+    /// The facets are added to the ones already set.
     ///
-    /// # Parameters
-    ///
-    /// - `versions`: A vector of strings representing the game versions to
-    ///   filter by.
+    /// ```rust
+    /// # use uranium::instance::InstanceMetadata;
+    /// # use uranium::searcher::rinth::SearchBuilder;
+    /// let metadata = InstanceMetadata {
+    ///     game_version: Some("1.21".to_owned()),
+    ///     loader: Some("fabric".to_owned()),
+    ///     ..Default::default()
+    /// };
+    /// let url = SearchBuilder::new()
+    ///     .search()
+    ///     .compatible_with(&metadata)
+    ///     .build_url();
+    /// assert_eq!(
+    ///     "https://api.modrinth.com/v2/search?facets=[[\"versions:1.21\"],\
+    ///     [\"categories:fabric\"],[\"client_side!=unsupported\"]]",
+    ///     url
+    /// );
+    /// ```
+    pub fn compatible_with(mut self, metadata: &InstanceMetadata) -> Self {
+        let mut facets = self
+            .facets
+            .take()
+            .unwrap_or_default();
+        let mut push = |facet| {
+            let mut disjunction = FacetsDisjunction::new();
+            disjunction.push(facet);
+            facets.push(disjunction);
+        };
+
+        if let Some(version) = &metadata.game_version {
+            push(Facets::Version(version.clone()));
+        }
+        if let Some(loader) = &metadata.loader {
+            push(Facets::Categories(loader.clone()));
+        }
+        push(!Facets::ClientSide(Requirement::Unsupported));
+
+        self.facets = Some(facets);
+        self
+    }
+
+    pub fn build_url(self) -> String {
+        self.with_search_type(SearchType::Search)
+            .build_url()
+    }
+
+    /// Returns a [`RinthPages`] going through every page of the search with
+    /// `client`, starting at [`SearchBuilder::offset`].
     ///
-    /// # Returns
+    /// [`SearchBuilder::limit`] is the size of each page, [`MAX_LIMIT`] if
+    /// it's not set.
+    pub fn pages(self, client: reqwest::Client) -> RinthPages {
+        RinthPages {
+            next_offset: Some(self.offset.unwrap_or(0)),
+            builder: self,
+            client,
+        }
+    }
+
+    /// Returns every hit of the search, the pages are requested as the
+    /// stream is polled, see [`SearchBuilder::pages`].
+    ///
+    /// The stream ends after the first error.
+    ///
+    /// ```no_run
+    /// # async fn foo() -> uranium::error::Result<()> {
+    /// use futures::StreamExt;
+    /// use uranium::searcher::rinth::{SearchBuilder, SortIndex};
+    ///
+    /// let mut hits = SearchBuilder::new()
+    ///     .search()
+    ///     .sort(SortIndex::Downloads)
+    ///     .limit(50)
+    ///     .into_stream(reqwest::Client::new());
+    /// while let Some(hit) = hits.next().await {
+    ///     println!("{}", hit?.title);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn into_stream(
+        self,
+        client: reqwest::Client,
+    ) -> impl Stream<Item = Result<RinthHit>> + Send + Unpin {
+        stream::unfold(self.pages(client), |mut pages| async move {
+            let page = pages.next_page().await?;
+            Some((page, pages))
+        })
+        .flat_map(|page| {
+            stream::iter(match page {
+                Ok(page) => page
+                    .hits
+                    .into_iter()
+                    .map(Ok)
+                    .collect(),
+                Err(e) => vec![Err(e)],
+            })
+        })
+        .boxed()
+    }
+}
+
+/// Pages of a Modrinth search, requested one by one with
+/// [`RinthPages::next_page`] until `total_hits` is reached.
+#[derive(Debug, Clone)]
+pub struct RinthPages {
+    builder: SearchBuilder<ProjectSearch>,
+    client: reqwest::Client,
+    next_offset: Option<u32>,
+}
+
+impl RinthPages {
+    /// Requests the next page, `None` once all the results were returned or
+    /// after an error.
+    pub async fn next_page(&mut self) -> Option<Result<RinthResponse>> {
+        let offset = self.next_offset.take()?;
+        let page = fetch_rinth_json::<RinthResponse>(
+            self.client
+                .get(self.page_url(offset)),
+        )
+        .await;
+
+        if let Ok(response) = &page {
+            self.next_offset = next_offset(response);
+        }
+        Some(page)
+    }
+
+    /// Returns the hits of every remaining page, `limit` at most.
     ///
-    /// Returns an updated instance of the `SearchBuilder` with the specified
-    /// game versions filter applied.
+    /// # Errors
+    /// Returns the error of the first page that can't be requested.
+    pub async fn collect_hits(mut self, limit: usize) -> Result<Vec<RinthHit>> {
+        let mut hits = Vec::new();
+        while hits.len() < limit {
+            let Some(page) = self.next_page().await else {
+                break;
+            };
+            hits.extend(page?.hits);
+        }
+        hits.truncate(limit);
+        Ok(hits)
+    }
+
+    fn page_url(&self, offset: u32) -> String {
+        let limit = self
+            .builder
+            .limit
+            .unwrap_or(MAX_LIMIT)
+            .clamp(1, MAX_LIMIT);
+        self.builder
+            .clone()
+            .limit(limit)
+            .offset(offset)
+            .build_url()
+    }
+}
+
+/// Returns the offset of the page after `response`, `None` if it was the
+/// last one.
+fn next_offset(response: &RinthResponse) -> Option<u32> {
+    if response.is_empty() {
+        return None;
+    }
+    let next = response
+        .offset
+        .checked_add(u32::try_from(response.len()).ok()?)?;
+    (u64::from(next) < response.total_hits).then_some(next)
+}
+
+impl SearchBuilder<ProjectVersionSearch> {
+    /// Sets the game versions filter, e.g. `"1.16.5"`, `"1.17.1"`.
     ///
     /// # Example
     ///
     /// ```rust no_run
-    /// use uranium::searcher::rinth::{SearchType, SearchBuilder};
+    /// use uranium::searcher::rinth::SearchBuilder;
     /// let builder = SearchBuilder::new()
-    ///     .search_type(SearchType::ProjectVersion {id: "example_id".to_owned()})
+    ///     .project_versions("example_id")
     ///     .game_versions(vec!["1.16.5".to_string(), "1.17.1".to_string()])
     ///     .build_url();
     /// ```
-    ///
-    /// # Restrictions
-    ///
-    /// This method is only available when the search type is `ProjectVersion`.
-    /// Attempting to call this method for other search types will do nothing.
-    ///
-    /// # Panics
-    ///
-    /// This method does not panic.
-    ///
-    /// # Errors
-    ///
-    /// This method does not return errors.
-    ///
-    /// # Notes
-    ///
-    /// - The `game_versions` parameter allows you to filter results to only
-    ///   include project versions compatible with the specified game versions.
-    /// - Ensure that you are using this method within the `ProjectVersion`
-    ///   search context, as it is specifically designed for filtering versions
-    ///   based on game compatibility.
     pub fn game_versions(mut self, versions: Vec<String>) -> Self {
         self.game_versions = versions;
         self
     }
 
-    /// Adds a single game version to the game versions filter for the project
-    /// version search.
-    ///
-    /// This method allows you to append a game version to the existing filter
-    /// criteria in the `SearchBuilder`. The version is added to the vector
-    /// of game versions that will be used to filter the results of the
-    /// search.
-    ///
-    /// # Parameters
-    ///
-    /// - `version`: A string slice representing a single game version (e.g.,
-    ///   `"1.17.1"`) to be added to the game versions filter.
-    ///
-    /// # Returns
-    ///
-    /// Returns an updated instance of the `SearchBuilder` with the specified
-    /// game version added to the filter criteria.
+    /// Adds a single game version to the game versions filter.
     ///
     /// # Example
     ///
     /// ```rust no_run
-    /// use uranium::searcher::rinth::{SearchType, SearchBuilder};
+    /// use uranium::searcher::rinth::SearchBuilder;
     /// let builder = SearchBuilder::new()
-    ///     .search_type(SearchType::ProjectVersion {id: "example_id".to_string()})
+    ///     .project_versions("example_id")
     ///     .add_game_version("1.16.5")
     ///     .add_game_version("1.17.1")
     ///     .build_url();
     /// ```
-    ///
-    /// # Restrictions
-    ///
-    /// This method only has an effect when the search type is `ProjectVersion`.
-    /// If used with a different search type, it will have no impact on the
-    /// search builder and will silently ignore the call.
-    ///
-    /// # Panics
-    ///
-    /// This method does not panic.
-    ///
-    /// # Errors
-    ///
-    /// This method does not return errors.
-    ///
-    /// # Notes
-    /// - Ensure that this method is called within the `ProjectVersion` search
-    ///   context, as it is specifically designed for filtering project versions
-    ///   based on game compatibility.
-    /// - If used outside the `ProjectVersion` context, the method will not
-    ///   modify the builder and will effectively do nothing.
     pub fn add_game_version(mut self, version: &str) -> Self {
         self.game_versions
             .push(version.to_owned());
         self
     }
 
-    pub fn search_type(self, search_type: SearchType) -> SearchBuilder<SearchType> {
+    /// Sets the loaders filter, e.g. `"fabric"`, `"forge"`.
+    pub fn loaders(mut self, loaders: Vec<String>) -> Self {
+        self.loaders = loaders;
+        self
+    }
+
+    /// Only returns the featured versions if `true`, or the not featured
+    /// ones if `false`.
+    pub fn featured(mut self, featured: bool) -> Self {
+        self.featured = Some(featured);
+        self
+    }
+
+    /// Filters the versions by the game version and loader of the instance
+    /// described by `metadata`.
+    pub fn compatible_with(mut self, metadata: &InstanceMetadata) -> Self {
+        if let Some(version) = &metadata.game_version {
+            self.game_versions = vec![version.clone()];
+        }
+        if let Some(loader) = &metadata.loader {
+            self.loaders = vec![loader.clone()];
+        }
+        self
+    }
+
+    pub fn build_url(self) -> String {
+        let id = self.search_type.id.clone();
+        self.with_search_type(SearchType::ProjectVersion { id })
+            .build_url()
+    }
+}
+
+impl<T> SearchBuilder<T> {
+    fn with_search_type<U>(self, search_type: U) -> SearchBuilder<U> {
         SearchBuilder {
             search_type,
             query: self.query,
             facets: self.facets,
+            index: self.index,
             offset: self.offset,
             limit: self.limit,
             game_versions: self.game_versions,
+            loaders: self.loaders,
+            featured: self.featured,
+            api: self.api,
         }
     }
+
+    /// Builds the URL against another Modrinth API, `api` ends with `v2/`.
+    #[must_use]
+    pub(crate) fn api_url(mut self, api: &str) -> Self {
+        self.api = Some(api.to_owned());
+        self
+    }
 }
 
 impl SearchBuilder<SearchType> {
@@ -214,15 +470,16 @@ impl SearchBuilder<SearchType> {
     /// ```rust
     /// # use uranium::searcher::rinth::{SearchBuilder, SearchType};
     /// let search_builder: String = SearchBuilder::new()
-    ///     .limit(10)
-    ///     .offset(5)
-    ///     .search_type(SearchType::Search)
+    ///     .search_type(SearchType::Categories)
     ///     .build_url();
-    /// assert_eq!("https://api.modrinth.com/v2/search?limit=10&offset=5", &search_builder);
+    /// assert_eq!("https://api.modrinth.com/v2/tag/category", &search_builder);
     /// ```
     pub fn build_url(self) -> String {
         use std::mem::discriminant;
-        let mut url: String = "https://api.modrinth.com/v2/".to_string();
+        let mut url: String = self
+            .api
+            .clone()
+            .unwrap_or_else(|| "https://api.modrinth.com/v2/".to_string());
 
         let component = match &self.search_type {
             SearchType::Project { id } => &format!("project/{id}?"),
@@ -237,7 +494,19 @@ impl SearchBuilder<SearchType> {
             }
             SearchType::Search => "search?",
             SearchType::VersionFile { hash } => &format!("version_file/{hash}"),
-            SearchType::Dependencies { .. } => todo!(),
+            SearchType::Version { id } => &format!("version/{id}"),
+            SearchType::LatestVersions => "version_files/update",
+            SearchType::VersionFiles => "version_files",
+            SearchType::Collection { id } => {
+                let base = url
+                    .strip_suffix("v2/")
+                    .unwrap_or(&url);
+                return format!("{base}v3/collection/{id}");
+            }
+            SearchType::Dependencies { id } => {
+                url.push_str(&format!("project/{id}/dependencies"));
+                return url;
+            }
 
             // If SearchType is Categories or Loaders there is no need to apply
             // queries/facets...
@@ -253,18 +522,32 @@ impl SearchBuilder<SearchType> {
         };
         url.push_str(component);
 
-        if !self.game_versions.is_empty()
-            && discriminant(&self.search_type)
-                == discriminant(&SearchType::ProjectVersion { id: "".to_string() })
+        if discriminant(&self.search_type)
+            == discriminant(&SearchType::ProjectVersion { id: "".to_string() })
         {
-            url.push('?');
-            url.push_str("game_versions=[");
-            for version in self.game_versions {
-                url.push_str(&format!("\"{version}\","))
+            let mut filters: Vec<String> = [
+                ("game_versions", &self.game_versions),
+                ("loaders", &self.loaders),
+            ]
+            .into_iter()
+            .filter(|(_, values)| !values.is_empty())
+            .map(|(key, values)| {
+                let values = values
+                    .iter()
+                    .map(|v| format!("\"{v}\""))
+                    .collect::<Vec<String>>()
+                    .join(",");
+                format!("{key}=[{values}]")
+            })
+            .collect();
+            if let Some(featured) = self.featured {
+                filters.push(format!("featured={featured}"));
+            }
+
+            if !filters.is_empty() {
+                url.push('?');
+                url.push_str(&filters.join("&"));
             }
-            // Remove trailing comma
-            url.pop();
-            url.push(']');
 
             // Since ProjectVersion doesn't accept facets, offset or limit
             // return is a right thing to do.
@@ -275,6 +558,10 @@ impl SearchBuilder<SearchType> {
             url.push_str(format!("query={query}&").as_str())
         }
 
+        if let Some(index) = self.index {
+            url.push_str(format!("index={index}&").as_str())
+        }
+
         if let Some(limit) = self.limit {
             url.push_str(format!("limit={limit}&").as_str())
         }
@@ -286,7 +573,7 @@ impl SearchBuilder<SearchType> {
         if let Some(facets) = self.facets {
             url.push_str("facets=[");
             for conjunction in facets {
-                url.push_str("[");
+                url.push('[');
                 for face in conjunction.facets {
                     url.push_str(format!("{face},").as_str())
                 }
@@ -314,6 +601,12 @@ pub struct FacetsDisjunction {
     facets: Vec<Facets>,
 }
 
+impl Default for FacetsDisjunction {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl FacetsDisjunction {
     pub fn new() -> Self {
         Self { facets: vec![] }
@@ -333,7 +626,53 @@ pub enum Facets {
     Version(String),
     ClientSide(Requirement),
     ServerSide(Requirement),
+    /// Only open source projects.
     OpenSource,
+    /// Matches the projects which **don't** match the inner facet, rendered
+    /// with the `!=` operator.
+    Not(Box<Facets>),
+}
+
+impl Not for Facets {
+    type Output = Facets;
+
+    /// Negates this facet.
+    ///
+    /// ```rust
+    /// # use uranium::searcher::rinth::Facets;
+    /// let facet = !Facets::Categories("fabric".to_owned());
+    /// assert_eq!(facet.to_string(), "\"categories!=fabric\"");
+    /// ```
+    fn not(self) -> Facets {
+        match self {
+            Facets::Not(facet) => *facet,
+            facet => Facets::Not(Box::new(facet)),
+        }
+    }
+}
+
+/// A list specifying the different orders the search results can be sorted
+/// by.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SortIndex {
+    Relevance,
+    Downloads,
+    Follows,
+    Newest,
+    Updated,
+}
+
+impl Display for SortIndex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Relevance => "relevance",
+            Self::Downloads => "downloads",
+            Self::Follows => "follows",
+            Self::Newest => "newest",
+            Self::Updated => "updated",
+        };
+        f.write_str(s)
+    }
 }
 
 /// A list specifying the different kinds of requirements types.
@@ -355,34 +694,46 @@ impl Display for Requirement {
     }
 }
 
+impl Facets {
+    /// Returns the facet type and its value, e.g. `("versions", "1.21")`.
+    fn key_value(&self) -> (&'static str, String) {
+        match self {
+            Facets::ProjectType(t) => ("project_type", t.clone()),
+            Facets::Categories(c) => ("categories", c.clone()),
+            Facets::Version(v) => ("versions", v.clone()),
+            Facets::ClientSide(r) => ("client_side", r.to_string()),
+            Facets::ServerSide(r) => ("server_side", r.to_string()),
+            Facets::OpenSource => ("open_source", "true".to_owned()),
+            Facets::Not(facet) => facet.key_value(),
+        }
+    }
+}
+
 impl Display for Facets {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let s = match self {
-            Facets::ProjectType(t) => format!("\"project_type:{t}\""),
-            Facets::Categories(c) => format!("\"categories:{c}\""),
-            Facets::Version(v) => format!("\"versions:{v}\""),
-            Facets::ClientSide(r) => format!("\"client_side:{r}\""),
-            Facets::ServerSide(r) => format!("\"server_side:{r}\""),
-            Facets::OpenSource => todo!(),
+        let (key, value) = self.key_value();
+        let operation = match self {
+            Facets::Not(_) => "!=",
+            _ => ":",
         };
-        f.write_str(s.as_str())
+        write!(f, "\"{key}{operation}{value}\"")
     }
 }
 
 #[cfg(test)]
 mod tests {
     use mine_data_structs::rinth::RinthCategories;
-    use reqwest::{ClientBuilder, Method};
+    use reqwest::Method;
 
     use super::*;
-    use crate::searcher::rinth::SearchType::Categories;
+    use crate::{http::default_client, searcher::rinth::SearchType::Categories};
 
     #[test]
     pub fn search_builder() {
         let url = SearchBuilder::new()
+            .search()
             .offset(10)
             .limit(5)
-            .search_type(SearchType::Search)
             .build_url();
 
         assert_eq!("https://api.modrinth.com/v2/search?limit=5&offset=10", url)
@@ -395,10 +746,10 @@ mod tests {
         versions_facets.push(Facets::Version("1.20".to_string()));
 
         let url = SearchBuilder::new()
+            .search()
             .offset(10)
             .limit(5)
             .facets(vec![versions_facets])
-            .search_type(SearchType::Search)
             .build_url();
         assert_eq!(
             "https://api.modrinth.com/v2/search?limit=5&offset=10&facets=[\
@@ -418,10 +769,10 @@ mod tests {
         type_facets.push(Facets::ProjectType("modpack".to_string()));
 
         let url = SearchBuilder::new()
+            .search()
             .offset(10)
             .limit(5)
             .facets(vec![versions_facets, type_facets])
-            .search_type(SearchType::Search)
             .build_url();
 
         assert_eq!(
@@ -447,10 +798,10 @@ mod tests {
         categories_facets.push(Facets::Categories("adventure".to_string()));
 
         let url = SearchBuilder::new()
+            .search()
             .offset(10)
             .limit(5)
             .facets(vec![versions_facets, type_facets, categories_facets])
-            .search_type(SearchType::Search)
             .build_url();
 
         assert_eq!(
@@ -463,11 +814,49 @@ mod tests {
         );
     }
 
+    #[test]
+    pub fn search_builder_sort_not_facets() {
+        let mut versions_facets = FacetsDisjunction::new();
+        versions_facets.push(Facets::Version("1.21".to_string()));
+
+        let mut type_facets = FacetsDisjunction::new();
+        type_facets.push(Facets::ProjectType("shader".to_string()));
+
+        let mut loader_facets = FacetsDisjunction::new();
+        loader_facets.push(!Facets::Categories("fabric".to_string()));
+
+        let url = SearchBuilder::new()
+            .search()
+            .sort(SortIndex::Downloads)
+            .limit(5)
+            .facets(vec![versions_facets, type_facets, loader_facets])
+            .build_url();
+
+        assert_eq!(
+            "https://api.modrinth.com/v2/search?index=downloads&limit=5&facets=[\
+                [\"versions:1.21\"],\
+                [\"project_type:shader\"],\
+                [\"categories!=fabric\"]\
+            ]",
+            url
+        );
+    }
+
+    #[test]
+    pub fn facets_display() {
+        assert_eq!(Facets::OpenSource.to_string(), "\"open_source:true\"");
+        assert_eq!((!Facets::OpenSource).to_string(), "\"open_source!=true\"");
+        assert_eq!(
+            (!!Facets::Version("1.21".to_owned())).to_string(),
+            "\"versions:1.21\""
+        );
+    }
+
     #[test]
     pub fn search_builder_projects() {
         let url = SearchBuilder::new()
             .search_type(SearchType::MultiProject {
-                ids: vec!["AAA", "BBB"],
+                ids: vec!["AAA".to_owned(), "BBB".to_owned()],
             })
             .build_url();
 
@@ -480,29 +869,76 @@ mod tests {
     #[test]
     pub fn search_builder_project_versions() {
         let url = SearchBuilder::new()
-            .search_type(SearchType::ProjectVersion {
-                id: "Jw3Wx1KR".to_string(),
-            })
+            .project_versions("Jw3Wx1KR")
             .game_versions(vec!["1.18".to_string(), "1.18.2".to_string()])
             .build_url();
 
         assert_eq!("https://api.modrinth.com/v2/project/Jw3Wx1KR/version?game_versions=[\"1.18\",\"1.18.2\"]",
         url);
     }
+
+    #[test]
+    pub fn search_builder_loaders_collection() {
+        let url = SearchBuilder::new()
+            .project_versions("AANobbMI")
+            .add_game_version("1.21")
+            .loaders(vec!["fabric".to_string()])
+            .build_url();
+
+        assert_eq!(
+            "https://api.modrinth.com/v2/project/AANobbMI/version?game_versions=[\"1.21\"]&loaders=[\"fabric\"]",
+            url
+        );
+
+        let url = SearchBuilder::new()
+            .search_type(SearchType::Collection {
+                id: "Q7eFz9xF".to_string(),
+            })
+            .build_url();
+        assert_eq!("https://api.modrinth.com/v3/collection/Q7eFz9xF", url);
+    }
+
+    #[test]
+    pub fn search_pages() {
+        let pages = SearchBuilder::new()
+            .search()
+            .sort(SortIndex::Downloads)
+            .pages(default_client().unwrap());
+        assert_eq!(pages.next_offset, Some(0));
+        assert_eq!(
+            "https://api.modrinth.com/v2/search?index=downloads&limit=100&offset=40",
+            pages.page_url(40)
+        );
+
+        let hit: RinthHit = serde_json::from_value(serde_json::json!({
+            "slug": "sodium", "title": "Sodium", "description": "",
+            "client_side": "required", "server_side": "unsupported",
+            "project_type": "mod", "downloads": 0, "project_id": "AANobbMI",
+            "author": "jellysquid3", "versions": [], "follows": 0,
+            "license": "LGPL-3.0-only", "icon_url": null
+        }))
+        .unwrap();
+        let response = |offset, hits, total_hits| RinthResponse {
+            hits: vec![hit.clone(); hits],
+            offset,
+            limit: 2,
+            total_hits,
+        };
+        assert_eq!(next_offset(&response(0, 2, 5)), Some(2));
+        assert_eq!(next_offset(&response(2, 2, 5)), Some(4));
+        assert_eq!(next_offset(&response(4, 1, 5)), None);
+        assert_eq!(next_offset(&response(4, 0, 10)), None);
+    }
+
     #[tokio::test]
     pub async fn search_categories() {
         let url = SearchBuilder::new()
             .search_type(Categories)
             .build_url();
 
-        let categories: RinthCategories = ClientBuilder::new()
-            .build()
+        let categories: RinthCategories = default_client()
             .unwrap()
             .get(&url)
-            .header(
-                "User-Agent",
-                "sergious234/uranium-rs (tests)/ (sergious234@gmail.com)",
-            )
             .send()
             .await
             .unwrap()
@@ -516,7 +952,8 @@ mod tests {
 
     #[test]
     pub fn request_builder() {
-        let c = reqwest::Client::new()
+        let c = default_client()
+            .unwrap()
             .request(Method::GET, "https://api.modrinth.com/v2/search")
             .query(&[("query", "pokemon")])
             .query(&[("offset", 10)])
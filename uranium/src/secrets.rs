@@ -0,0 +1,354 @@
+//! Where API keys (like `CURSE_API_KEY`) are read from.
+//!
+//! Env vars end up in logs and process lists, with the `keyring` feature the
+//! keys can be kept in the system keyring instead (the kernel keyutils on
+//! Linux, Windows Credential Manager or the macOS Keychain). Keyutils keys
+//! don't survive a reboot and the Secret Service (GNOME Keyring, KWallet) is
+//! not used. Without the feature, or if the keyring doesn't have the key, the
+//! env var with the same name is used.
+//!
+//! ```no_run
+//! # fn foo() -> uranium::error::Result<()> {
+//! use uranium::secrets::{default_store, SecretStore, CURSE_API_KEY};
+//!
+//! default_store().set(CURSE_API_KEY, "my key")?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{collections::BTreeMap, sync::RwLock};
+
+use mine_data_structs::url_maker::maker::Curse;
+use reqwest::{RequestBuilder, StatusCode};
+
+use crate::{
+    error::{Result, UraniumError},
+    http::{check_status, default_client},
+};
+
+/// Name of the CurseForge API key.
+pub const CURSE_API_KEY: &str = "CURSE_API_KEY";
+/// Name of the Modrinth personal access token.
+pub const MODRINTH_TOKEN: &str = "MODRINTH_TOKEN";
+
+pub(crate) static ENV_VARS: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap::new());
+
+/// Makes [`EnvStore`] read the secret called `name` from the env var `var`,
+/// e.g. to keep the CurseForge key in `MY_CURSEFORGE_KEY`.
+///
+/// In case the env var can't be updated this function will return None, in
+/// case of success Some(()) is returned.
+pub fn set_env_var(name: &str, var: &str) -> Option<()> {
+    let mut aux = ENV_VARS.write().ok()?;
+    aux.insert(name.to_owned(), var.to_owned());
+    Some(())
+}
+
+/// Loads and stores secrets by name.
+pub trait SecretStore: Send + Sync {
+    /// Returns the secret called `name`, `None` if it doesn't exist.
+    ///
+    /// # Errors
+    /// Returns an error if the store can't be read.
+    fn get(&self, name: &str) -> Result<Option<String>>;
+
+    /// Stores `value` as the secret called `name`.
+    ///
+    /// # Errors
+    /// Returns an error if the store can't be written or it's read only.
+    fn set(&self, name: &str, value: &str) -> Result<()>;
+
+    /// Removes the secret called `name`, it's not an error if it doesn't
+    /// exist.
+    ///
+    /// # Errors
+    /// Returns an error if the store can't be written or it's read only.
+    fn delete(&self, name: &str) -> Result<()>;
+}
+
+/// Reads the secrets from env vars, it can't store them.
+///
+/// The env var has the name of the secret unless [`set_env_var`] says
+/// otherwise.
+pub struct EnvStore;
+
+impl SecretStore for EnvStore {
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        let var = ENV_VARS
+            .read()
+            .ok()
+            .and_then(|vars| vars.get(name).cloned())
+            .unwrap_or_else(|| name.to_owned());
+        Ok(std::env::var(var).ok())
+    }
+
+    fn set(&self, name: &str, _value: &str) -> Result<()> {
+        Err(UraniumError::OtherWithReason(format!(
+            "Can't store {name}, env vars are read only"
+        )))
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        self.set(name, "")
+    }
+}
+
+/// Stores the secrets in the system keyring under the `service` name.
+#[cfg(feature = "keyring")]
+pub struct KeyringStore {
+    service: String,
+}
+
+#[cfg(feature = "keyring")]
+impl KeyringStore {
+    #[must_use]
+    pub fn new(service: &str) -> Self {
+        KeyringStore {
+            service: service.to_owned(),
+        }
+    }
+
+    fn entry(&self, name: &str) -> Result<keyring::Entry> {
+        keyring::Entry::new(&self.service, name).map_err(keyring_error)
+    }
+}
+
+#[cfg(feature = "keyring")]
+impl Default for KeyringStore {
+    fn default() -> Self {
+        KeyringStore::new("uranium")
+    }
+}
+
+#[cfg(feature = "keyring")]
+impl SecretStore for KeyringStore {
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        match self
+            .entry(name)?
+            .get_password()
+        {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(keyring_error(e)),
+        }
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<()> {
+        self.entry(name)?
+            .set_password(value)
+            .map_err(keyring_error)
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        match self
+            .entry(name)?
+            .delete_credential()
+        {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(keyring_error(e)),
+        }
+    }
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_error(e: keyring::Error) -> UraniumError {
+    UraniumError::OtherWithReason(format!("Keyring error: {e}"))
+}
+
+/// Tries each store in order, the first one is used to store secrets.
+pub struct ChainStore {
+    stores: Vec<Box<dyn SecretStore>>,
+}
+
+impl ChainStore {
+    #[must_use]
+    pub fn new(stores: Vec<Box<dyn SecretStore>>) -> Self {
+        ChainStore { stores }
+    }
+}
+
+impl SecretStore for ChainStore {
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        for store in &self.stores {
+            // A broken keyring must not hide the env var.
+            if let Ok(Some(secret)) = store.get(name) {
+                return Ok(Some(secret));
+            }
+        }
+        Ok(None)
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<()> {
+        self.stores
+            .first()
+            .ok_or(UraniumError::Other)?
+            .set(name, value)
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        self.stores
+            .first()
+            .ok_or(UraniumError::Other)?
+            .delete(name)
+    }
+}
+
+/// Returns the keyring (with the `keyring` feature) followed by the env vars.
+#[must_use]
+pub fn default_store() -> ChainStore {
+    #[cfg(feature = "keyring")]
+    let stores: Vec<Box<dyn SecretStore>> =
+        vec![Box::new(KeyringStore::default()), Box::new(EnvStore)];
+    #[cfg(not(feature = "keyring"))]
+    let stores: Vec<Box<dyn SecretStore>> = vec![Box::new(EnvStore)];
+
+    ChainStore::new(stores)
+}
+
+/// Returns the CurseForge API key from [`default_store()`].
+///
+/// # Errors
+/// Returns `Err(UraniumError::MissingApiKey)` if there is no key and
+/// `Err(UraniumError::InvalidApiKey)` if it can't be sent in a header.
+pub(crate) fn curse_api_key() -> Result<String> {
+    let key = default_store()
+        .get(CURSE_API_KEY)
+        .ok()
+        .flatten();
+    validate_api_key("curse", key)
+}
+
+pub(crate) fn validate_api_key(provider: &'static str, key: Option<String>) -> Result<String> {
+    let key = key
+        .as_deref()
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+        .ok_or(UraniumError::MissingApiKey(provider))?;
+    if !key
+        .bytes()
+        .all(|b| b.is_ascii_graphic())
+    {
+        return Err(UraniumError::InvalidApiKey(provider));
+    }
+    Ok(key.to_owned())
+}
+
+/// Checks with a cheap request that CurseForge accepts the API key, so a bad
+/// key fails before downloading anything instead of with a 403 per file.
+///
+/// # Errors
+/// Returns `Err(UraniumError::MissingApiKey)` if there is no key,
+/// `Err(UraniumError::InvalidApiKey)` if CurseForge rejects it and the
+/// request error if CurseForge can't be reached.
+pub async fn check_curse_api_key() -> Result<()> {
+    let key = curse_api_key()?;
+    ping_curse(
+        default_client()?
+            .get(Curse::minecraft())
+            .header("x-api-key", key),
+    )
+    .await
+}
+
+/// Sends `request`, an authenticated CurseForge request, see
+/// [`check_curse_api_key`].
+pub(crate) async fn ping_curse(request: RequestBuilder) -> Result<()> {
+    let response = request.send().await?;
+    match response.status() {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            Err(UraniumError::InvalidApiKey("curse"))
+        }
+        _ => check_status(response).map(|_| ()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct MemoryStore(Mutex<HashMap<String, String>>);
+
+    impl SecretStore for MemoryStore {
+        fn get(&self, name: &str) -> Result<Option<String>> {
+            Ok(self
+                .0
+                .lock()
+                .unwrap()
+                .get(name)
+                .cloned())
+        }
+
+        fn set(&self, name: &str, value: &str) -> Result<()> {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(name.to_owned(), value.to_owned());
+            Ok(())
+        }
+
+        fn delete(&self, name: &str) -> Result<()> {
+            self.0
+                .lock()
+                .unwrap()
+                .remove(name);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn api_key_format() {
+        assert_eq!(
+            validate_api_key("curse", Some(" $2a$10$abc\n".to_owned())).unwrap(),
+            "$2a$10$abc"
+        );
+        assert!(matches!(
+            validate_api_key("curse", Some("  ".to_owned())),
+            Err(UraniumError::MissingApiKey("curse"))
+        ));
+        assert!(matches!(
+            validate_api_key("curse", None),
+            Err(UraniumError::MissingApiKey("curse"))
+        ));
+        assert!(matches!(
+            validate_api_key("curse", Some("my key".to_owned())),
+            Err(UraniumError::InvalidApiKey("curse"))
+        ));
+    }
+
+    #[test]
+    fn chain_falls_back_to_env() {
+        std::env::set_var("URANIUM_TEST_SECRET", "from env");
+        let chain = ChainStore::new(vec![Box::<MemoryStore>::default(), Box::new(EnvStore)]);
+
+        assert_eq!(
+            chain
+                .get("URANIUM_TEST_SECRET")
+                .unwrap()
+                .as_deref(),
+            Some("from env")
+        );
+        chain
+            .set("URANIUM_TEST_SECRET", "from store")
+            .unwrap();
+        assert_eq!(
+            chain
+                .get("URANIUM_TEST_SECRET")
+                .unwrap()
+                .as_deref(),
+            Some("from store")
+        );
+        assert!(EnvStore
+            .set("URANIUM_TEST_SECRET", "x")
+            .is_err());
+        assert_eq!(
+            chain
+                .get("URANIUM_MISSING_SECRET")
+                .unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,336 @@
+//! Storage backends used by the downloaders and the zipper.
+//!
+//! By default everything is written into the local filesystem through
+//! [`LocalStorage`], but any type implementing [`StorageBackend`] can be
+//! plugged into [`Downloader`](crate::downloaders::Downloader) or
+//! [`ModpackMaker`](crate::modpack_maker::ModpackMaker) so the files end up in
+//! other places (an object storage, a container volume...).
+//!
+//! [`MemoryStorage`] keeps everything in memory, it is mostly useful for
+//! testing.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Cursor, Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// Abstraction over the place where the files are written.
+pub trait StorageBackend: Send + Sync {
+    /// Opens `path` for writing.
+    ///
+    /// If the file already exists it must be truncated.
+    ///
+    /// # Errors
+    /// Returns an `io::Error` if the file can't be created.
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn Write + Send>>;
+
+    /// Opens `path` for writing at its end, used to resume downloads.
+    ///
+    /// The default implementation reads the file and writes it again, backends
+    /// which can append should override it.
+    ///
+    /// # Errors
+    /// Returns an `io::Error` if the file doesn't exist or can't be written.
+    fn open_append(&self, path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        let content = self.read(path)?;
+        let mut writer = self.open_write(path)?;
+        writer.write_all(&content)?;
+        Ok(writer)
+    }
+
+    /// Moves the file at `from` into `to`, replacing `to` if it exists.
+    ///
+    /// # Errors
+    /// Returns an `io::Error` if `from` doesn't exist or can't be moved.
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    /// Returns `true` if there is a file in `path`.
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Reads the whole content of `path`, mainly used for hashing.
+    ///
+    /// # Errors
+    /// Returns an `io::Error` if the file doesn't exist or can't be read.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Removes the file in `path`, used to drop broken downloads.
+    ///
+    /// The file must be gone afterwards, an empty file left behind would look
+    /// like a `.part` file which can be resumed.
+    ///
+    /// # Errors
+    /// Returns an `io::Error` if the file can't be removed, with
+    /// `io::ErrorKind::NotFound` if it doesn't exist.
+    fn remove(&self, path: &Path) -> io::Result<()>;
+
+    /// Opens `path` for reading, used to hash files without loading them.
+    ///
+    /// The default implementation reads the whole file, backends which can
+    /// stream the content should override it.
+    ///
+    /// # Errors
+    /// Returns an `io::Error` if the file doesn't exist or can't be read.
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(Cursor::new(self.read(path)?)))
+    }
+
+    /// Returns the size in bytes of `path`.
+    ///
+    /// The default implementation reads the whole file, backends which can
+    /// get the size cheaply should override it.
+    ///
+    /// # Errors
+    /// Returns an `io::Error` if the file doesn't exist.
+    fn len(&self, path: &Path) -> io::Result<u64> {
+        self.read(path)
+            .map(|content| content.len() as u64)
+    }
+
+    /// Puts a copy of the local file `source` in `to`, used to install files
+    /// from the [`ArtifactCache`](crate::artifact_cache::ArtifactCache).
+    ///
+    /// The copy must not share its content with `source`, e.g. through a
+    /// hard link, instances change their files.
+    ///
+    /// The default implementation copies the content through
+    /// [`StorageBackend::open_write`].
+    ///
+    /// # Errors
+    /// Returns an `io::Error` if `source` can't be read or `to` written.
+    fn copy_from(&self, source: &Path, to: &Path) -> io::Result<()> {
+        let mut writer = self.open_write(to)?;
+        io::copy(&mut fs::File::open(source)?, &mut writer)?;
+        writer.flush()
+    }
+}
+
+/// Default backend, it just uses the local filesystem.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LocalStorage;
+
+impl StorageBackend for LocalStorage {
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)?;
+        Ok(Box::new(file))
+    }
+
+    fn open_append(&self, path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        let file = fs::OpenOptions::new()
+            .append(true)
+            .open(path)?;
+        Ok(Box::new(file))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(fs::File::open(path)?))
+    }
+
+    fn len(&self, path: &Path) -> io::Result<u64> {
+        fs::metadata(path).map(|metadata| metadata.len())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    /// Uses `fs::copy`, which clones the file in filesystems with reflinks.
+    fn copy_from(&self, source: &Path, to: &Path) -> io::Result<()> {
+        fs::copy(source, to).map(|_| ())
+    }
+}
+
+type MemoryFiles = Arc<Mutex<HashMap<PathBuf, Vec<u8>>>>;
+
+/// In-memory backend.
+///
+/// Cloning a `MemoryStorage` returns a handle to the **same** files, so it can
+/// be given to a downloader and inspected afterwards.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryStorage {
+    files: MemoryFiles,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the paths of all the stored files.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.lock()
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, Vec<u8>>> {
+        // A poisoned lock only means other thread panicked while writing, the
+        // map itself is still usable.
+        self.files
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl StorageBackend for MemoryStorage {
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        self.lock()
+            .insert(path.to_path_buf(), Vec::new());
+
+        Ok(Box::new(MemoryWriter {
+            path: path.to_path_buf(),
+            files: self.files.clone(),
+        }))
+    }
+
+    fn open_append(&self, path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        if !self.exists(path) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                path.display().to_string(),
+            ));
+        }
+
+        Ok(Box::new(MemoryWriter {
+            path: path.to_path_buf(),
+            files: self.files.clone(),
+        }))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut files = self.lock();
+        let content = files
+            .remove(from)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, from.display().to_string()))?;
+        files.insert(to.to_path_buf(), content);
+        Ok(())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.lock().contains_key(path)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.lock()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, path.display().to_string()))
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.lock()
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, path.display().to_string()))
+    }
+}
+
+struct MemoryWriter {
+    path: PathBuf,
+    files: MemoryFiles,
+}
+
+impl Write for MemoryWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut files = self
+            .files
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        files
+            .entry(self.path.clone())
+            .or_default()
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_storage_roundtrip() {
+        let storage = MemoryStorage::new();
+        let part = Path::new("mods/sodium.jar.part");
+        let jar = Path::new("mods/sodium.jar");
+
+        let mut writer = storage
+            .open_write(part)
+            .unwrap();
+        writer
+            .write_all(b"sodium")
+            .unwrap();
+        writer
+            .write_all(b" rocks")
+            .unwrap();
+
+        assert!(storage.exists(part));
+        storage
+            .rename(part, jar)
+            .unwrap();
+
+        assert!(!storage.exists(part));
+        assert_eq!(storage.read(jar).unwrap(), b"sodium rocks");
+        assert_eq!(storage.paths(), vec![jar.to_path_buf()]);
+    }
+
+    #[test]
+    fn memory_storage_truncates() {
+        let storage = MemoryStorage::new();
+        let path = Path::new("config.cfg");
+
+        storage
+            .open_write(path)
+            .unwrap()
+            .write_all(b"old content")
+            .unwrap();
+        storage
+            .open_write(path)
+            .unwrap()
+            .write_all(b"new")
+            .unwrap();
+
+        assert_eq!(storage.read(path).unwrap(), b"new");
+        assert!(storage
+            .read(Path::new("missing"))
+            .is_err());
+
+        storage
+            .open_append(path)
+            .unwrap()
+            .write_all(b" content")
+            .unwrap();
+        assert_eq!(storage.read(path).unwrap(), b"new content");
+        assert!(storage
+            .open_append(Path::new("missing"))
+            .is_err());
+
+        storage.remove(path).unwrap();
+        assert!(!storage.exists(path));
+        assert!(storage.remove(path).is_err());
+    }
+}
@@ -0,0 +1,466 @@
+//! Fixtures to test install flows without network access, enabled with the
+//! `test-utils` feature.
+//!
+//! Every fixture is deterministic: the same arguments give the same bytes, so
+//! tests can compare hashes and sizes. [`FixtureServer`] serves the files
+//! over plain HTTP on localhost, the downloaders don't know the difference:
+//!
+//! ```no_run
+//! # async fn foo() -> uranium::error::Result<()> {
+//! use uranium::downloaders::{Downloader, RinthDownloader};
+//! use uranium::test_utils::{rinth_file, rinth_modpack, write_mrpack, FixtureServer};
+//!
+//! let server = FixtureServer::start().await?;
+//! let url = server.add("/sodium.jar", b"fake jar".to_vec());
+//! let pack = rinth_modpack(vec![rinth_file("mods/sodium.jar", b"fake jar", &url)]);
+//! write_mrpack("pack.mrpack", &pack, &[("config/sodium.json", b"{}")])?;
+//!
+//! RinthDownloader::<Downloader>::new("pack.mrpack", "instance")?
+//!     .complete()
+//!     .await?;
+//! assert_eq!(server.requests(), 1);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Write,
+    net::SocketAddr,
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use mine_data_structs::{
+    curse::curse_modpacks::{CursePack, CursePackFiles},
+    minecraft::{ObjectData, Resources, Root},
+    rinth::{RinthFile, RinthMdFiles, RinthModpack, RinthVersion},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+use crate::{
+    error::{Result, UraniumError},
+    hashes::{sha1_bytes, sha512_bytes},
+    variables::constants::{CURSE_JSON, OVERRIDES_FOLDER, RINTH_JSON},
+};
+
+type Routes = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+/// Status and how many more requests fail for each path.
+type Failures = Arc<Mutex<HashMap<String, (u16, usize)>>>;
+
+/// A HTTP server on localhost answering with the files added to it, and 404
+/// for the rest. It stops when dropped.
+///
+/// `Range: bytes=start-[end]` requests are answered with the part of the file,
+/// or 416 if it starts after the end of the file.
+pub struct FixtureServer {
+    addr: SocketAddr,
+    routes: Routes,
+    failures: Failures,
+    requests: Arc<AtomicUsize>,
+    task: JoinHandle<()>,
+}
+
+impl FixtureServer {
+    /// Starts the server on a free port.
+    ///
+    /// # Errors
+    /// Returns an error if no port can be bound.
+    pub async fn start() -> Result<FixtureServer> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let routes = Routes::default();
+        let failures = Failures::default();
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        let task = tokio::spawn({
+            let routes = routes.clone();
+            let failures = failures.clone();
+            let requests = requests.clone();
+            async move {
+                while let Ok((socket, _)) = listener.accept().await {
+                    tokio::spawn(serve(
+                        socket,
+                        routes.clone(),
+                        failures.clone(),
+                        requests.clone(),
+                    ));
+                }
+            }
+        });
+
+        Ok(FixtureServer {
+            addr,
+            routes,
+            failures,
+            requests,
+            task,
+        })
+    }
+
+    /// Serves `body` at `path`, like `/mods/sodium.jar`, and returns its url.
+    pub fn add(&self, path: &str, body: Vec<u8>) -> String {
+        if let Ok(mut routes) = self.routes.lock() {
+            routes.insert(path.to_owned(), body);
+        }
+        self.url(path)
+    }
+
+    /// Answers the next `times` requests to `path` with `status` and an empty
+    /// body, e.g. to test retries.
+    pub fn fail(&self, path: &str, status: u16, times: usize) {
+        if let Ok(mut failures) = self.failures.lock() {
+            failures.insert(path.to_owned(), (status, times));
+        }
+    }
+
+    /// Returns the url of `path` in this server.
+    #[must_use]
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{path}", self.addr)
+    }
+
+    /// Returns how many `GET` requests were answered with a file or a part of
+    /// it.
+    #[must_use]
+    pub fn requests(&self) -> usize {
+        self.requests
+            .load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for FixtureServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Answers the requests of `socket` until the client closes it.
+async fn serve(
+    mut socket: TcpStream,
+    routes: Routes,
+    failures: Failures,
+    requests: Arc<AtomicUsize>,
+) {
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 1024];
+    loop {
+        let Some(end) = buffer
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+        else {
+            match socket.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+            }
+            continue;
+        };
+
+        let head = String::from_utf8_lossy(&buffer[..end]).into_owned();
+        buffer.drain(..end + 4);
+        let mut request_line = head.split_whitespace();
+        let method = request_line
+            .next()
+            .unwrap_or_default();
+        let path = request_line
+            .next()
+            .unwrap_or_default();
+        let header = |header: &str| {
+            head.lines()
+                .skip(1)
+                .filter_map(|line| line.split_once(':'))
+                .find(|(name, _)| name.eq_ignore_ascii_case(header))
+                .map(|(_, value)| value.trim())
+        };
+        let range = header("range").and_then(byte_range);
+
+        // The request bodies are ignored.
+        let body_len = header("content-length")
+            .and_then(|len| len.parse().ok())
+            .unwrap_or(0);
+        while buffer.len() < body_len {
+            match socket.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+            }
+        }
+        buffer.drain(..body_len);
+
+        let failure = failures
+            .lock()
+            .ok()
+            .and_then(|mut failures| {
+                let (status, times) = failures.get_mut(path)?;
+                *times = times.checked_sub(1)?;
+                Some(*status)
+            });
+        let body = routes
+            .lock()
+            .ok()
+            .and_then(|routes| routes.get(path).cloned());
+        let response = match (failure, body, range) {
+            (Some(status), _, _) => format!(
+                "HTTP/1.1 {status} Fixture Failure\r\nRetry-After: 0\r\nContent-Length: 0\r\n\r\n"
+            )
+            .into_bytes(),
+            (None, Some(body), Some((start, _))) if start >= body.len() => format!(
+                "HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */{}\r\nContent-Length: 0\r\n\r\n",
+                body.len()
+            )
+            .into_bytes(),
+            (None, Some(body), Some((start, end))) => {
+                let end = end.map_or(body.len(), |end| (end + 1).min(body.len()));
+                let mut response = format!(
+                    "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {start}-{}/{}\r\nContent-Length: {}\r\n\r\n",
+                    end - 1,
+                    body.len(),
+                    end - start
+                )
+                .into_bytes();
+                if method != "HEAD" {
+                    requests.fetch_add(1, Ordering::Relaxed);
+                    response.extend_from_slice(&body[start..end]);
+                }
+                response
+            }
+            (None, Some(body), None) => {
+                let mut response = format!(
+                    "HTTP/1.1 200 OK\r\nAccept-Ranges: bytes\r\nContent-Length: {}\r\n\r\n",
+                    body.len()
+                )
+                .into_bytes();
+                if method != "HEAD" {
+                    requests.fetch_add(1, Ordering::Relaxed);
+                    response.extend(body);
+                }
+                response
+            }
+            (None, None, _) => b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_vec(),
+        };
+        if socket
+            .write_all(&response)
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+/// Parses the value of a `Range: bytes=start-[end]` header, the end is
+/// inclusive.
+fn byte_range(value: &str) -> Option<(usize, Option<usize>)> {
+    let (start, end) = value
+        .strip_prefix("bytes=")?
+        .split_once('-')?;
+    let end = match end {
+        "" => None,
+        end => Some(end.parse().ok()?),
+    };
+    Some((start.parse().ok()?, end))
+}
+
+/// Returns a vanilla version `id` without libraries nor assets.
+#[must_use]
+pub fn minecraft_root(id: &str) -> Root {
+    serde_json::from_value(serde_json::json!({
+        "assetIndex": { "id": id, "sha1": "", "size": 0, "totalSize": 0, "url": "" },
+        "assets": id,
+        "id": id,
+        "libraries": [],
+        "mainClass": "net.minecraft.client.main.Main",
+        "type": "release"
+    }))
+    .expect("valid version fixture")
+}
+
+/// Returns an assets index with an object for each `(name, content)`.
+#[must_use]
+pub fn resources(objects: &[(&str, &[u8])]) -> Resources {
+    Resources {
+        objects: objects
+            .iter()
+            .map(|(name, content)| {
+                let object = ObjectData {
+                    hash: sha1_bytes(content),
+                    size: content.len(),
+                };
+                ((*name).to_owned(), object)
+            })
+            .collect(),
+    }
+}
+
+/// Returns a modpack file at `path` with the hashes and size of `content`,
+/// downloaded from `url`.
+#[must_use]
+pub fn rinth_file(path: &str, content: &[u8], url: &str) -> RinthMdFiles {
+    serde_json::from_value(serde_json::json!({
+        "path": path,
+        "hashes": { "sha1": sha1_bytes(content), "sha512": sha512_bytes(content) },
+        "downloads": [url],
+        "fileSize": content.len()
+    }))
+    .expect("valid modpack file fixture")
+}
+
+/// Returns the Fabric release `id` of the project `AANobbMI`, without files
+/// nor dependencies.
+#[must_use]
+pub fn rinth_version(id: &str) -> RinthVersion {
+    serde_json::from_value(serde_json::json!({
+        "name": id, "version_number": id, "game_versions": ["1.21"],
+        "version_type": "release", "loaders": ["fabric"], "featured": false,
+        "id": id, "project_id": "AANobbMI", "author_id": "a",
+        "date_published": "2024-07-03T01:02:03Z", "downloads": 0,
+        "files": [], "dependencies": []
+    }))
+    .expect("valid version fixture")
+}
+
+/// Returns the primary file `filename` of a version, with the hashes and size
+/// of `content`, downloaded from `url`.
+#[must_use]
+pub fn rinth_version_file(filename: &str, content: &[u8], url: &str) -> RinthFile {
+    serde_json::from_value(serde_json::json!({
+        "hashes": { "sha1": sha1_bytes(content), "sha512": sha512_bytes(content) },
+        "url": url, "filename": filename, "primary": true, "size": content.len()
+    }))
+    .expect("valid version file fixture")
+}
+
+/// Returns a Fabric 1.20.1 modpack with `files`.
+#[must_use]
+pub fn rinth_modpack(files: Vec<RinthMdFiles>) -> RinthModpack {
+    let mut modpack = RinthModpack::new();
+    modpack.name = "fixture".into();
+    modpack.version_id = "1.0.0".to_owned();
+    modpack.files = files;
+    modpack
+        .dependencies
+        .insert("minecraft".to_owned(), "1.20.1".to_owned());
+    modpack
+        .dependencies
+        .insert("fabric-loader".to_owned(), "0.15.11".to_owned());
+    modpack
+}
+
+/// Returns a Forge 1.20.1 Curse modpack with a file for each
+/// `(project id, file id)`.
+#[must_use]
+pub fn curse_pack(files: &[(usize, usize)]) -> CursePack {
+    let mut pack = CursePack::new("fixture", "uranium", "1.20.1", "forge-47.2.0");
+    for (project, file) in files {
+        pack.add_file(CursePackFiles::new(*project, *file));
+    }
+    pack
+}
+
+/// Writes `modpack` as a `.mrpack` in `path` with the `overrides`, each a
+/// `(path, content)`.
+///
+/// # Errors
+/// Returns an error if the file can't be written.
+pub fn write_mrpack<I: AsRef<Path>>(
+    path: I,
+    modpack: &RinthModpack,
+    overrides: &[(&str, &[u8])],
+) -> Result<()> {
+    let index = serde_json::to_vec(modpack).map_err(|_| UraniumError::WrongModpackFormat)?;
+    write_pack(path.as_ref(), RINTH_JSON, &index, overrides)
+}
+
+/// Writes `pack` as a Curse modpack zip in `path` with the `overrides`, each
+/// a `(path, content)`.
+///
+/// # Errors
+/// Returns an error if the file can't be written.
+pub fn write_curse_zip<I: AsRef<Path>>(
+    path: I,
+    pack: &CursePack,
+    overrides: &[(&str, &[u8])],
+) -> Result<()> {
+    let manifest = serde_json::to_vec(pack).map_err(|_| UraniumError::WrongModpackFormat)?;
+    write_pack(path.as_ref(), CURSE_JSON, &manifest, overrides)
+}
+
+fn write_pack(
+    path: &Path,
+    index_name: &str,
+    index: &[u8],
+    overrides: &[(&str, &[u8])],
+) -> Result<()> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    zip.start_file(index_name, SimpleFileOptions::default())?;
+    zip.write_all(index)?;
+    for (name, content) in overrides {
+        zip.start_file(
+            format!("{OVERRIDES_FOLDER}{name}"),
+            SimpleFileOptions::default(),
+        )?;
+        zip.write_all(content)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        downloaders::{Downloader, RinthDownloader},
+        instance::{InstalledFile, InstanceMetadata},
+    };
+
+    #[tokio::test]
+    async fn offline_modpack_install() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let destination = dir.join("instance");
+        std::fs::create_dir_all(&destination).unwrap();
+
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let url = server.add("/sodium.jar", b"fake jar".to_vec());
+        let modpack = rinth_modpack(vec![rinth_file("mods/sodium.jar", b"fake jar", &url)]);
+        write_mrpack(
+            dir.join("pack.mrpack"),
+            &modpack,
+            &[("config/sodium.json", b"{}")],
+        )
+        .unwrap();
+
+        let mut rinth =
+            RinthDownloader::<Downloader>::new(dir.join("pack.mrpack"), &destination).unwrap();
+        let source = InstalledFile::modrinth("pack", "1.0.0");
+        rinth.set_pack_source(source.clone());
+        rinth
+            .complete()
+            .await
+            .unwrap();
+        let installed = std::fs::read(destination.join("mods/sodium.jar")).unwrap();
+        let metadata = InstanceMetadata::load(&destination).unwrap();
+
+        assert_eq!(installed, b"fake jar");
+        assert_eq!(metadata.pack, Some(source));
+        assert_eq!(server.requests(), 1);
+        assert_eq!(resources(&[("a", b"a")]).objects["a"].size, 1);
+        assert_eq!(minecraft_root("1.20.1").id, "1.20.1");
+        assert_eq!(
+            curse_pack(&[(1, 2)])
+                .get_files()
+                .len(),
+            1
+        );
+    }
+}
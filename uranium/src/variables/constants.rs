@@ -1,12 +1,61 @@
-use std::sync::RwLock;
+use std::{path::PathBuf, sync::RwLock};
 
 pub const EXTENSION: &str = "mrpack";
-pub const TEMP_DIR: &str = "./temp_dir/";
 pub const DEFAULT_NTHREADS: usize = 32;
+/// Sent by the downloaders unless `DownloadConfig::user_agent` is used.
+pub const DEFAULT_USER_AGENT: &str = concat!(
+    "uranium-rs/",
+    env!("CARGO_PKG_VERSION"),
+    " contact: sergious234@gmail.com"
+);
+/// 64 MiB, bigger than any metadata file Uranium requests.
+pub const DEFAULT_MAX_METADATA_SIZE: u64 = 64 * 1024 * 1024;
 pub const RINTH_JSON: &str = "modrinth.index.json";
 pub const CURSE_JSON: &str = "manifest.json";
-pub const CONFIG_DIR: &str = "config/";
+/// Instance folders added to the overrides of a modpack by default.
+pub const OVERRIDE_FOLDERS: &[&str] = &[
+    "config",
+    "defaultconfigs",
+    "kubejs",
+    "resourcepacks",
+    "scripts",
+    "shaderpacks",
+];
+/// Instance files and folders never added to the overrides of a modpack by
+/// default, with everything inside them, see
+/// [`Overrides::remove_exclusion`](crate::modpack_maker::Overrides::remove_exclusion).
+pub const DEFAULT_OVERRIDE_EXCLUSIONS: &[&str] =
+    &["saves", "logs", "crash-reports", "screenshots", "**/*.log"];
+/// Files in `mods/` with other extensions are not added to the modpack.
+pub const MOD_EXTENSIONS: &[&str] = &["jar"];
+/// Added to the name of a disabled mod, `sodium.jar.disabled`.
+pub const DISABLED_SUFFIX: &str = ".disabled";
 pub const OVERRIDES_FOLDER: &str = "overrides/";
+/// Hashes and sizes of the overrides, next to the index of the packs made by
+/// uranium.
+pub const OVERRIDES_HASHES: &str = "uranium_overrides.json";
+/// Settings file inside the uranium dir, see `UraniumConfig`.
+pub const CONFIG_FILE: &str = "config.toml";
+/// Same as [`CONFIG_FILE`] in JSON, only read if there is no TOML file.
+pub const CONFIG_JSON_FILE: &str = "config.json";
+/// Log of the last run, inside the uranium dir.
+pub const LATEST_LOG_FILE: &str = "latest_log_file.txt";
 pub const PROFILES_FILE: &str = "launcher_profiles.json";
+/// Where the Java runtimes are installed, inside `.minecraft`.
+pub const RUNTIMES_DIR: &str = "runtime";
+/// Copy of the runtime manifest kept inside each installed runtime.
+pub const RUNTIME_MANIFEST: &str = "uranium_runtime.json";
+pub const INSTANCE_FILE: &str = "uranium_instance.json";
+/// Steps declared by a modpack in its overrides, see `uranium::hooks`.
+pub const HOOKS_FILE: &str = "uranium_hooks.json";
+pub const GAME_LOG_FILE: &str = "logs/uranium_game.log";
+/// Where the updater moves the replaced mods, inside `mods/`.
+pub const OLD_MODS_DIR: &str = ".uranium_old";
+/// Files installed by an interrupted modpack download.
+pub const JOURNAL_FILE: &str = ".uranium/progress.json";
 
 pub static NTHREADS: RwLock<usize> = RwLock::new(16);
+pub static MAX_METADATA_SIZE: RwLock<u64> = RwLock::new(DEFAULT_MAX_METADATA_SIZE);
+pub static PROXY: RwLock<Option<String>> = RwLock::new(None);
+/// Dir of the `ArtifactCache` used by new downloaders, none by default.
+pub static ARTIFACT_CACHE: RwLock<Option<PathBuf>> = RwLock::new(None);
@@ -0,0 +1,1141 @@
+//! Checks an already installed Minecraft version without downloading anything.
+//!
+//! [`diff_instances`] compares the mods and configs of two instances, e.g. a
+//! server and one of its players, and [`verify_pack_installation`] checks an
+//! instance against the `.mrpack` it was installed from.
+//!
+//! ```no_run
+//! # fn foo() -> uranium::error::Result<()> {
+//! use uranium::verifier::InstallationVerifier;
+//!
+//! let verifier = InstallationVerifier::open(".minecraft", "1.20.1")?.max_problems(10);
+//! let problems = verifier.verify(&|checked, total| println!("{checked}/{total}"));
+//! for problem in problems {
+//!     println!("{}: {:?}", problem.path.display(), problem.kind);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use futures::StreamExt;
+use log::{info, warn};
+use mine_data_structs::minecraft::{Lib, Resources, Root, RuntimeFile, RuntimeManifest};
+use mine_data_structs::rinth::{RinthMdFiles, RinthModpack};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use zip::ZipArchive;
+
+use crate::downloaders::{
+    AssetFilter, DownloadSide, DownloadableObject, FileDownloader, HashType, VerificationLevel,
+};
+use crate::error::{Result, UraniumError};
+use crate::hashes::sha1_file;
+use crate::variables::constants::{
+    OVERRIDES_FOLDER, OVERRIDE_FOLDERS, RINTH_JSON, RUNTIMES_DIR, RUNTIME_MANIFEST,
+};
+
+/// What is wrong with a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProblemKind {
+    Missing,
+    WrongSize { expected: u64, found: u64 },
+    WrongHash,
+}
+
+/// A file of the installation which must be downloaded again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct VerificationProblem {
+    pub path: PathBuf,
+    pub kind: ProblemKind,
+}
+
+/// A file that must exist with the given size and sha1.
+#[derive(Clone)]
+struct ExpectedFile {
+    /// Name of the asset or path of the library.
+    name: String,
+    path: PathBuf,
+    sha1: String,
+    size: u64,
+    /// Where the file is downloaded from to repair it.
+    url: String,
+}
+
+/// Verifies the assets objects, libraries, client jar and Java runtime of an
+/// installed version.
+///
+/// Files are checked in parallel with rayon, the same way modpacks are
+/// extracted. The broken ones can be downloaded again with
+/// [`InstallationVerifier::repair`].
+pub struct InstallationVerifier {
+    objects: Vec<ExpectedFile>,
+    libraries: Vec<ExpectedFile>,
+    client: Option<ExpectedFile>,
+    runtime: Vec<ExpectedFile>,
+    component: String,
+    max_problems: Option<usize>,
+    verification: VerificationLevel,
+}
+
+impl InstallationVerifier {
+    /// Reads `versions/<version>/<version>.json` and its assets index from
+    /// `dot_minecraft`.
+    ///
+    /// # Errors
+    /// Returns an error if any of both files can't be read or parsed.
+    pub fn open<I: AsRef<Path>>(dot_minecraft: I, version: &str) -> Result<Self> {
+        let dot_minecraft = dot_minecraft.as_ref();
+        let version_json = dot_minecraft
+            .join("versions")
+            .join(version)
+            .join(format!("{version}.json"));
+        let root: Root = serde_json::from_reader(BufReader::new(File::open(version_json)?))
+            .map_err(|_| UraniumError::WrongFileFormat)?;
+
+        let index_path = dot_minecraft
+            .join("assets")
+            .join("indexes")
+            .join(root.get_index_name());
+        let resources: Resources = serde_json::from_reader(BufReader::new(File::open(index_path)?))
+            .map_err(|_| UraniumError::WrongFileFormat)?;
+
+        Ok(Self::new(dot_minecraft, &root, &resources))
+    }
+
+    /// Makes a verifier from an already loaded version and assets index.
+    ///
+    /// The Java runtime is looked up in the `runtime` folder of
+    /// `dot_minecraft`, see [`InstallationVerifier::runtimes_dir`].
+    pub fn new(dot_minecraft: &Path, version: &Root, resources: &Resources) -> Self {
+        let objects_path = dot_minecraft
+            .join("assets")
+            .join("objects");
+        let objects = resources
+            .objects
+            .iter()
+            .map(|(name, obj)| ExpectedFile {
+                name: name.clone(),
+                path: objects_path
+                    .join(&obj.hash[..2])
+                    .join(&obj.hash),
+                sha1: obj.hash.clone(),
+                size: obj.size as u64,
+                url: obj.get_link(),
+            })
+            .collect();
+
+        let libraries_path = dot_minecraft.join("libraries");
+        let libraries = version
+            .libraries
+            .get_os_artifacts()
+            .into_iter()
+            .map(|artifact| ExpectedFile {
+                name: artifact
+                    .path
+                    .to_string_lossy()
+                    .to_string(),
+                path: libraries_path.join(&artifact.path),
+                sha1: artifact.sha1.clone(),
+                size: artifact.size,
+                url: artifact.url.clone(),
+            })
+            .collect();
+
+        let client = version
+            .downloads
+            .get("client")
+            .map(|client| ExpectedFile {
+                name: format!("{}.jar", version.id),
+                path: dot_minecraft
+                    .join("versions")
+                    .join(&version.id)
+                    .join(format!("{}.jar", version.id)),
+                sha1: client.sha1.clone(),
+                size: client.size as u64,
+                url: client.url.clone(),
+            });
+
+        let component = version
+            .java_version
+            .component
+            .clone();
+        InstallationVerifier {
+            objects,
+            libraries,
+            client,
+            runtime: runtime_files(&dot_minecraft.join(RUNTIMES_DIR), &component),
+            component,
+            max_problems: None,
+            verification: VerificationLevel::default(),
+        }
+    }
+
+    /// Same as [`InstallationVerifier::new`] with the arguments in the order
+    /// of [`MinecraftDownloader::from_root`], for launchers which already hold
+    /// the version JSON and its assets index.
+    ///
+    /// [`MinecraftDownloader::from_root`]: crate::downloaders::MinecraftDownloader::from_root
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use mine_data_structs::minecraft::{Resources, Root};
+    /// use uranium::verifier::InstallationVerifier;
+    ///
+    /// # fn foo(root: Root, resources: Resources) {
+    /// let verifier = InstallationVerifier::from_parts(&root, &resources, ".minecraft");
+    /// let problems = verifier.verify(&|_, _| {});
+    /// # }
+    /// ```
+    pub fn from_parts<I: AsRef<Path>>(
+        version: &Root,
+        resources: &Resources,
+        dot_minecraft: I,
+    ) -> Self {
+        Self::new(dot_minecraft.as_ref(), version, resources)
+    }
+
+    /// Looks up the Java runtime in `runtimes_dir` instead, for runtimes
+    /// installed outside `.minecraft` with
+    /// [`RuntimeDownloader`](crate::downloaders::RuntimeDownloader).
+    ///
+    /// Only runtimes installed by Uranium can be checked, without the
+    /// manifest they keep the runtime is not verified.
+    #[must_use]
+    pub fn runtimes_dir<I: AsRef<Path>>(mut self, runtimes_dir: I) -> Self {
+        self.runtime = runtime_files(runtimes_dir.as_ref(), &self.component);
+        self
+    }
+
+    /// Only checks the assets installed with `filter`, so the assets skipped
+    /// on purpose are not reported as missing.
+    #[must_use]
+    pub fn asset_filter(mut self, filter: &AssetFilter) -> Self {
+        self.objects
+            .retain(|obj| filter.includes(&obj.name));
+        self
+    }
+
+    /// Stops checking files once `max` problems were found, useful to know
+    /// quickly whether the installation is broken at all.
+    #[must_use]
+    pub fn max_problems(mut self, max: usize) -> Self {
+        self.max_problems = Some(max);
+        self
+    }
+
+    /// Sets how much of each file is checked, by default every file is fully
+    /// hashed. Missing files and files with another size are always found.
+    #[must_use]
+    pub fn verification_level(mut self, verification: VerificationLevel) -> Self {
+        self.verification = verification;
+        self
+    }
+
+    /// Returns how many files will be checked by [`verify`](Self::verify).
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.objects.len()
+            + self.libraries.len()
+            + usize::from(self.client.is_some())
+            + self.runtime.len()
+    }
+
+    /// Returns `true` if there is nothing to check.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Checks the assets objects, `progress` gets `(checked, total)`.
+    pub fn verify_objects<F: Fn(usize, usize) + Sync>(
+        &self,
+        progress: &F,
+    ) -> Vec<VerificationProblem> {
+        self.check(&self.objects, progress)
+    }
+
+    /// Checks the libraries, `progress` gets `(checked, total)`.
+    pub fn verify_libs<F: Fn(usize, usize) + Sync>(
+        &self,
+        progress: &F,
+    ) -> Vec<VerificationProblem> {
+        self.check(&self.libraries, progress)
+    }
+
+    /// Checks the files of the Java runtime, `progress` gets
+    /// `(checked, total)`.
+    pub fn verify_runtime<F: Fn(usize, usize) + Sync>(
+        &self,
+        progress: &F,
+    ) -> Vec<VerificationProblem> {
+        self.check(&self.runtime, progress)
+    }
+
+    /// Checks the objects, the libraries, the client and the runtime with a
+    /// single progress count.
+    pub fn verify<F: Fn(usize, usize) + Sync>(&self, progress: &F) -> Vec<VerificationProblem> {
+        self.check(&self.files(), progress)
+    }
+
+    /// Same as [`verify`](Self::verify) but without blocking the tokio
+    /// runtime, at most `concurrency` files are hashed at the same time in
+    /// blocking threads.
+    ///
+    /// Dropping the future cancels the verification, only the files being
+    /// hashed at that moment are finished:
+    ///
+    /// ```no_run
+    /// # async fn foo() -> uranium::error::Result<()> {
+    /// use std::time::Duration;
+    ///
+    /// use uranium::verifier::InstallationVerifier;
+    ///
+    /// let verifier = InstallationVerifier::open(".minecraft", "1.20.1")?;
+    /// tokio::select! {
+    ///     problems = verifier.verify_async(8, &|checked, total| println!("{checked}/{total}")) => {
+    ///         println!("{} broken files", problems?.len());
+    ///     }
+    ///     _ = tokio::time::sleep(Duration::from_secs(60)) => println!("Too slow, cancelled"),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if a blocking thread panics.
+    pub async fn verify_async<F: Fn(usize, usize) + Sync>(
+        &self,
+        concurrency: usize,
+        progress: &F,
+    ) -> Result<Vec<VerificationProblem>> {
+        let files: Vec<ExpectedFile> = self
+            .files()
+            .into_iter()
+            .cloned()
+            .collect();
+        let total = files.len();
+        let max = self
+            .max_problems
+            .unwrap_or(usize::MAX);
+
+        let verification = self.verification;
+        let mut checks = futures::stream::iter(files)
+            .map(|file| tokio::task::spawn_blocking(move || check_file(&file, verification)))
+            .buffer_unordered(concurrency.max(1));
+
+        let mut problems = Vec::new();
+        let mut done = 0;
+        while let Some(problem) = checks.next().await {
+            done += 1;
+            progress(done, total);
+            if let Some(problem) = problem? {
+                problems.push(problem);
+                if problems.len() >= max {
+                    break;
+                }
+            }
+        }
+        Ok(problems)
+    }
+
+    /// Downloads again the files of `problems` with `T` and checks them
+    /// once more, returning the ones still broken.
+    ///
+    /// Only the files with problems are downloaded. Problems of files unknown
+    /// to this verifier are returned as they are. Repaired runtime files are
+    /// not executable, call
+    /// [`repair_permissions`](crate::downloaders::repair_permissions) after.
+    ///
+    /// ```no_run
+    /// # async fn foo() -> uranium::error::Result<()> {
+    /// use uranium::downloaders::Downloader;
+    /// use uranium::verifier::InstallationVerifier;
+    ///
+    /// let verifier = InstallationVerifier::open(".minecraft", "1.20.1")?;
+    /// let problems = verifier.verify(&|_, _| {});
+    /// let left = verifier.repair::<Downloader>(&problems).await?;
+    /// assert!(left.is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the broken files can't be removed or the
+    /// download fails.
+    pub async fn repair<T: FileDownloader>(
+        &self,
+        problems: &[VerificationProblem],
+    ) -> Result<Vec<VerificationProblem>> {
+        let files: HashMap<&Path, &ExpectedFile> = self
+            .files()
+            .into_iter()
+            .map(|f| (f.path.as_path(), f))
+            .collect();
+
+        let mut unknown = Vec::new();
+        let mut to_repair = Vec::new();
+        for problem in problems {
+            match files.get(problem.path.as_path()) {
+                Some(file) => to_repair.push(*file),
+                None => unknown.push(problem.clone()),
+            }
+        }
+
+        let mut objects = Vec::with_capacity(to_repair.len());
+        for file in &to_repair {
+            let (Some(dir), Some(name)) = (file.path.parent(), file.path.file_name()) else {
+                continue;
+            };
+            std::fs::create_dir_all(dir)?;
+            if file.path.exists() {
+                std::fs::remove_file(&file.path)?;
+            }
+            objects.push(
+                DownloadableObject::new(
+                    &file.url,
+                    &name.to_string_lossy(),
+                    dir,
+                    Some(HashType::Sha1(file.sha1.clone())),
+                )
+                .with_size(file.size),
+            );
+        }
+        info!("Repairing {} files", objects.len());
+        T::new(objects)
+            .complete()
+            .await?;
+
+        let mut left = self.check(&to_repair, &|_, _| {});
+        left.extend(unknown);
+        Ok(left)
+    }
+
+    fn files(&self) -> Vec<&ExpectedFile> {
+        self.objects
+            .iter()
+            .chain(&self.libraries)
+            .chain(&self.client)
+            .chain(&self.runtime)
+            .collect()
+    }
+
+    fn check<E, F>(&self, files: &[E], progress: &F) -> Vec<VerificationProblem>
+    where
+        E: std::borrow::Borrow<ExpectedFile> + Sync,
+        F: Fn(usize, usize) + Sync,
+    {
+        let total = files.len();
+        let done = AtomicUsize::new(0);
+        let found = AtomicUsize::new(0);
+        let max = self
+            .max_problems
+            .unwrap_or(usize::MAX);
+
+        let mut problems: Vec<VerificationProblem> = files
+            .par_iter()
+            .filter_map(|file| {
+                if found.load(Ordering::Relaxed) >= max {
+                    return None;
+                }
+                let problem = check_file(file.borrow(), self.verification);
+                if problem.is_some() {
+                    found.fetch_add(1, Ordering::Relaxed);
+                }
+                progress(done.fetch_add(1, Ordering::Relaxed) + 1, total);
+                problem
+            })
+            .collect();
+
+        // Some threads may have found a problem at the same time.
+        problems.truncate(max);
+        problems
+    }
+}
+
+/// Returns the files of the `component` runtime installed in `runtimes_dir`,
+/// read from the manifest saved by the runtime downloader.
+fn runtime_files(runtimes_dir: &Path, component: &str) -> Vec<ExpectedFile> {
+    if component.is_empty() {
+        return vec![];
+    }
+    let destination = runtimes_dir.join(component);
+    let Ok(manifest) = std::fs::read(destination.join(RUNTIME_MANIFEST)) else {
+        return vec![];
+    };
+    let Ok(manifest) = serde_json::from_slice::<RuntimeManifest>(&manifest) else {
+        warn!("Corrupted manifest of the {component} runtime, it won't be verified");
+        return vec![];
+    };
+
+    manifest
+        .files
+        .into_iter()
+        .filter_map(|(name, file)| match file {
+            RuntimeFile::File { downloads, .. } => Some(ExpectedFile {
+                path: destination.join(&name),
+                name,
+                sha1: downloads.raw.sha1,
+                size: downloads.raw.size as u64,
+                url: downloads.raw.url,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Differences between the files of two instances, serializable so it can be
+/// shared as JSON.
+///
+/// Paths are relative to the instance root.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct InstanceDiff {
+    /// Files only in the second instance.
+    pub added: Vec<PathBuf>,
+    /// Files only in the first instance.
+    pub removed: Vec<PathBuf>,
+    /// Files with other content in each instance.
+    pub changed: Vec<ChangedFile>,
+}
+
+impl InstanceDiff {
+    /// Returns `true` if both instances have the same files.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// A file with other content in each instance.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangedFile {
+    pub path: PathBuf,
+    pub sha1_a: String,
+    pub sha1_b: String,
+}
+
+/// Checks `files` inside `destination`, in parallel with rayon.
+pub(crate) fn verify_pack_files(
+    destination: &Path,
+    files: &[&RinthMdFiles],
+) -> Vec<VerificationProblem> {
+    files
+        .par_iter()
+        .filter_map(|file| {
+            let path = destination.join(file.get_path());
+            let problem = |kind| {
+                Some(VerificationProblem {
+                    path: path.clone(),
+                    kind,
+                })
+            };
+
+            let Ok(metadata) = std::fs::metadata(&path) else {
+                return problem(ProblemKind::Missing);
+            };
+            let expected = file.get_size() as u64;
+            if metadata.len() != expected {
+                return problem(ProblemKind::WrongSize {
+                    expected,
+                    found: metadata.len(),
+                });
+            }
+
+            let hash = HashType::strongest(file.get_hashes());
+            match std::fs::File::open(&path).and_then(|f| hash.matches_reader(f)) {
+                Ok(true) => None,
+                _ => problem(ProblemKind::WrongHash),
+            }
+        })
+        .collect()
+}
+
+/// Result of [`verify_pack_installation`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PackVerificationReport {
+    /// Number of files of the modpack index checked.
+    pub files_checked: usize,
+    /// Number of override files checked.
+    pub overrides_checked: usize,
+    pub problems: Vec<VerificationProblem>,
+}
+
+impl PackVerificationReport {
+    /// Returns `true` if the instance has the exact contents of the modpack.
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Checks that `instance` has every file of the `.mrpack` in `mrpack`, both
+/// the ones of the index and the overrides, without downloading or writing
+/// anything.
+///
+/// Files of the index are checked with their hashes, overrides against
+/// their content in the modpack. Extra files in the instance are not
+/// reported.
+///
+/// # Errors
+/// Returns `Err(UraniumError::FileNotFound)` if the modpack doesn't exist and
+/// `Err(UraniumError::WrongFileFormat)` if it is not a valid modpack.
+pub fn verify_pack_installation<I: AsRef<Path>, J: AsRef<Path>>(
+    mrpack: I,
+    instance: J,
+) -> Result<PackVerificationReport> {
+    verify_pack_installation_for(mrpack, instance, DownloadSide::Both)
+}
+
+/// Same as [`verify_pack_installation`] but only checking the files of the
+/// index installed on `side`, e.g. [`DownloadSide::Server`] for a dedicated
+/// server.
+///
+/// # Errors
+/// Same as [`verify_pack_installation`].
+pub fn verify_pack_installation_for<I: AsRef<Path>, J: AsRef<Path>>(
+    mrpack: I,
+    instance: J,
+    side: DownloadSide,
+) -> Result<PackVerificationReport> {
+    let mrpack = mrpack.as_ref();
+    let instance = instance.as_ref();
+    let file =
+        File::open(mrpack).map_err(|_| UraniumError::FileNotFound(mrpack.display().to_string()))?;
+    let mut zip = ZipArchive::new(file).map_err(|_| UraniumError::WrongFileFormat)?;
+
+    let pack: RinthModpack = serde_json::from_reader(
+        zip.by_name(RINTH_JSON)
+            .map_err(|_| UraniumError::WrongFileFormat)?,
+    )
+    .map_err(|_| UraniumError::WrongFileFormat)?;
+
+    let files: Vec<&RinthMdFiles> = pack
+        .get_files()
+        .iter()
+        .filter(|f| side.includes(f))
+        .collect();
+    let mut report = PackVerificationReport {
+        files_checked: files.len(),
+        problems: verify_pack_files(instance, &files),
+        ..Default::default()
+    };
+
+    for i in 0..zip.len() {
+        let mut entry = zip
+            .by_index(i)
+            .map_err(|_| UraniumError::WrongFileFormat)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let Some(relative) = entry
+            .enclosed_name()
+            .and_then(|p| {
+                p.strip_prefix(OVERRIDES_FOLDER)
+                    .ok()
+                    .map(Path::to_path_buf)
+            })
+        else {
+            continue;
+        };
+
+        report.overrides_checked += 1;
+        let path = instance.join(relative);
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            report
+                .problems
+                .push(VerificationProblem {
+                    path,
+                    kind: ProblemKind::Missing,
+                });
+            continue;
+        };
+        if metadata.len() != entry.size() {
+            report
+                .problems
+                .push(VerificationProblem {
+                    path,
+                    kind: ProblemKind::WrongSize {
+                        expected: entry.size(),
+                        found: metadata.len(),
+                    },
+                });
+            continue;
+        }
+
+        let mut expected = Vec::with_capacity(entry.size() as usize);
+        entry
+            .read_to_end(&mut expected)
+            .map_err(|_| UraniumError::WrongFileFormat)?;
+        if std::fs::read(&path).ok() != Some(expected) {
+            report
+                .problems
+                .push(VerificationProblem {
+                    path,
+                    kind: ProblemKind::WrongHash,
+                });
+        }
+    }
+
+    Ok(report)
+}
+
+/// Compares `mods/` and the default override folders (configs, resource
+/// packs...) of the instances `a` and `b` by sha1.
+///
+/// # Errors
+/// Returns an error if a file can't be read.
+pub fn diff_instances<I: AsRef<Path>, J: AsRef<Path>>(a: I, b: J) -> Result<InstanceDiff> {
+    let folders: Vec<&str> = std::iter::once("mods")
+        .chain(
+            OVERRIDE_FOLDERS
+                .iter()
+                .copied(),
+        )
+        .collect();
+    diff_instances_in(a, b, &folders)
+}
+
+/// Same as [`diff_instances`] but comparing only `folders`. Folders missing
+/// in an instance are treated as empty.
+///
+/// # Errors
+/// Returns an error if a file can't be read.
+pub fn diff_instances_in<I: AsRef<Path>, J: AsRef<Path>>(
+    a: I,
+    b: J,
+    folders: &[&str],
+) -> Result<InstanceDiff> {
+    let files_a = hash_instance(a.as_ref(), folders)?;
+    let mut files_b = hash_instance(b.as_ref(), folders)?;
+
+    let mut diff = InstanceDiff::default();
+    for (path, sha1_a) in files_a {
+        match files_b.remove(&path) {
+            None => diff.removed.push(path),
+            Some(sha1_b) if sha1_b != sha1_a => diff
+                .changed
+                .push(ChangedFile {
+                    path,
+                    sha1_a,
+                    sha1_b,
+                }),
+            Some(_) => {}
+        }
+    }
+    diff.added
+        .extend(files_b.into_keys());
+
+    diff.added.sort();
+    diff.removed.sort();
+    diff.changed
+        .sort_by(|x, y| x.path.cmp(&y.path));
+    Ok(diff)
+}
+
+/// Returns the sha1 of every file in `folders` of the instance, keyed by
+/// their path relative to `instance`.
+fn hash_instance(instance: &Path, folders: &[&str]) -> Result<HashMap<PathBuf, String>> {
+    let mut files = Vec::new();
+    for folder in folders {
+        list_files(instance, Path::new(folder), &mut files)?;
+    }
+    files
+        .into_par_iter()
+        .map(|path| {
+            let sha1 = sha1_file(instance.join(&path))?;
+            Ok((path, sha1))
+        })
+        .collect()
+}
+
+fn list_files(root: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let Ok(entries) = std::fs::read_dir(root.join(relative)) else {
+        return Ok(());
+    };
+    for entry in entries {
+        let entry = entry?;
+        let path = relative.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            list_files(root, &path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn check_file(file: &ExpectedFile, verification: VerificationLevel) -> Option<VerificationProblem> {
+    let problem = |kind| {
+        Some(VerificationProblem {
+            path: file.path.clone(),
+            kind,
+        })
+    };
+
+    let Ok(metadata) = std::fs::metadata(&file.path) else {
+        return problem(ProblemKind::Missing);
+    };
+    if metadata.len() != file.size {
+        return problem(ProblemKind::WrongSize {
+            expected: file.size,
+            found: metadata.len(),
+        });
+    }
+    if !verification.must_hash(&file.path) {
+        return None;
+    }
+    match sha1_file(&file.path) {
+        Ok(hash) if hash == file.sha1 => None,
+        _ => problem(ProblemKind::WrongHash),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mine_data_structs::minecraft::ObjectData;
+
+    use super::*;
+    use crate::hashes::sha1_bytes;
+
+    fn write_object(dot_minecraft: &Path, content: &[u8]) -> ObjectData {
+        let hash = sha1_bytes(content);
+        let dir = dot_minecraft
+            .join("assets/objects")
+            .join(&hash[..2]);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(&hash), content).unwrap();
+        ObjectData {
+            hash,
+            size: content.len(),
+        }
+    }
+
+    #[tokio::test]
+    async fn finds_broken_objects() {
+        let dot_minecraft = tempfile::tempdir().unwrap();
+        let dot_minecraft = dot_minecraft.path();
+
+        let good = write_object(dot_minecraft, b"good object");
+        let corrupted = write_object(dot_minecraft, b"corrupted object");
+        std::fs::write(
+            dot_minecraft
+                .join("assets/objects")
+                .join(&corrupted.hash[..2])
+                .join(&corrupted.hash),
+            b"CORRUPTED OBJECT",
+        )
+        .unwrap();
+        let missing = ObjectData {
+            hash: sha1_bytes(b"missing"),
+            size: 7,
+        };
+
+        let resources = Resources {
+            objects: HashMap::from([
+                ("good".to_owned(), good),
+                ("corrupted".to_owned(), corrupted),
+                ("missing".to_owned(), missing),
+            ]),
+        };
+        let root: Root = serde_json::from_str(
+            r#"{"assetIndex": {"id": "1", "sha1": "", "size": 0, "totalSize": 0, "url": ""},
+                "id": "test", "libraries": [], "type": "release"}"#,
+        )
+        .unwrap();
+
+        let verifier = InstallationVerifier::from_parts(&root, &resources, dot_minecraft);
+        let checked = AtomicUsize::new(0);
+        let mut problems = verifier.verify(&|_, _| {
+            checked.fetch_add(1, Ordering::Relaxed);
+        });
+        problems.sort_by_key(|p| p.kind == ProblemKind::Missing);
+
+        assert_eq!(checked.into_inner(), 3);
+        assert_eq!(
+            problems
+                .iter()
+                .map(|p| &p.kind)
+                .collect::<Vec<_>>(),
+            [&ProblemKind::WrongHash, &ProblemKind::Missing]
+        );
+
+        let mut async_problems = verifier
+            .verify_async(2, &|_, _| {})
+            .await
+            .unwrap();
+        async_problems.sort_by_key(|p| p.kind == ProblemKind::Missing);
+        assert_eq!(async_problems, problems);
+
+        let verifier = verifier.max_problems(1);
+        assert_eq!(
+            verifier
+                .verify_objects(&|_, _| {})
+                .len(),
+            1
+        );
+
+        let skipped = AssetFilter::default()
+            .exclude("missing")
+            .unwrap();
+        let verifier =
+            InstallationVerifier::new(dot_minecraft, &root, &resources).asset_filter(&skipped);
+        assert_eq!(
+            verifier
+                .verify(&|_, _| {})
+                .len(),
+            1
+        );
+
+        let kinds = |level| {
+            let mut kinds: Vec<ProblemKind> =
+                InstallationVerifier::new(dot_minecraft, &root, &resources)
+                    .verification_level(level)
+                    .verify(&|_, _| {})
+                    .into_iter()
+                    .map(|p| p.kind)
+                    .collect();
+            kinds.sort_by_key(|k| *k == ProblemKind::Missing);
+            kinds
+        };
+        assert_eq!(kinds(VerificationLevel::SizeOnly), [ProblemKind::Missing]);
+        assert_eq!(kinds(VerificationLevel::Sampled(0)), [ProblemKind::Missing]);
+        assert_eq!(
+            kinds(VerificationLevel::Sampled(100)),
+            [ProblemKind::WrongHash, ProblemKind::Missing]
+        );
+    }
+
+    #[test]
+    fn checks_runtime() {
+        let dot_minecraft = tempfile::tempdir().unwrap();
+        let dot_minecraft = dot_minecraft.path();
+        let runtime = dot_minecraft.join("runtime/java-runtime-gamma");
+        std::fs::create_dir_all(runtime.join("bin")).unwrap();
+        std::fs::write(runtime.join("bin/java"), "corrupted").unwrap();
+        std::fs::write(
+            runtime.join(RUNTIME_MANIFEST),
+            format!(
+                r#"{{ "files": {{
+                    "bin": {{ "type": "directory" }},
+                    "bin/java": {{
+                        "type": "file", "executable": true,
+                        "downloads": {{ "raw": {{ "sha1": "{}", "size": 9, "url": "https://a.b/java" }} }}
+                    }},
+                    "bin/javac": {{
+                        "type": "file", "executable": true,
+                        "downloads": {{ "raw": {{ "sha1": "a", "size": 1, "url": "https://a.b/javac" }} }}
+                    }}
+                }} }}"#,
+                sha1_bytes(b"java")
+            ),
+        )
+        .unwrap();
+
+        let root: Root = serde_json::from_str(
+            r#"{"assetIndex": {"id": "1", "sha1": "", "size": 0, "totalSize": 0, "url": ""},
+                "id": "test", "libraries": [], "type": "release",
+                "javaVersion": {"component": "java-runtime-gamma", "majorVersion": 17}}"#,
+        )
+        .unwrap();
+        let verifier = InstallationVerifier::new(
+            dot_minecraft,
+            &root,
+            &Resources {
+                objects: HashMap::new(),
+            },
+        );
+        let mut problems = verifier.verify_runtime(&|_, _| {});
+        problems.sort_by(|a, b| a.path.cmp(&b.path));
+
+        assert_eq!(verifier.len(), 2);
+        assert_eq!(
+            problems
+                .iter()
+                .map(|p| &p.kind)
+                .collect::<Vec<_>>(),
+            [&ProblemKind::WrongHash, &ProblemKind::Missing]
+        );
+    }
+
+    #[tokio::test]
+    async fn repair_unknown_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root: Root = serde_json::from_str(
+            r#"{"assetIndex": {"id": "1", "sha1": "", "size": 0, "totalSize": 0, "url": ""},
+                "id": "test", "libraries": [], "type": "release"}"#,
+        )
+        .unwrap();
+        let verifier = InstallationVerifier::new(
+            dir.path(),
+            &root,
+            &Resources {
+                objects: HashMap::new(),
+            },
+        );
+        let unknown = VerificationProblem {
+            path: PathBuf::from("mods/unknown.jar"),
+            kind: ProblemKind::Missing,
+        };
+
+        let left = verifier
+            .repair::<crate::downloaders::Downloader>(std::slice::from_ref(&unknown))
+            .await
+            .unwrap();
+        assert_eq!(left, [unknown]);
+    }
+
+    #[tokio::test]
+    async fn repair_corrupted_files() {
+        use crate::test_utils::FixtureServer;
+
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let url = server.add("/client.jar", b"client jar".to_vec());
+        let dir = tempfile::tempdir().unwrap();
+        let client = dir
+            .path()
+            .join("versions/test/test.jar");
+        std::fs::create_dir_all(client.parent().unwrap()).unwrap();
+        std::fs::write(&client, b"CLIENT JAR").unwrap();
+
+        let root: Root = serde_json::from_value(serde_json::json!({
+            "assetIndex": {"id": "1", "sha1": "", "size": 0, "totalSize": 0, "url": ""},
+            "id": "test", "libraries": [], "type": "release",
+            "downloads": {"client": {"sha1": sha1_bytes(b"client jar"), "size": 10, "url": url}}
+        }))
+        .unwrap();
+        let verifier = InstallationVerifier::new(
+            dir.path(),
+            &root,
+            &Resources {
+                objects: HashMap::new(),
+            },
+        );
+        let problems = verifier.verify(&|_, _| {});
+        assert_eq!(
+            problems,
+            [VerificationProblem {
+                path: client.clone(),
+                kind: ProblemKind::WrongHash,
+            }]
+        );
+
+        let left = verifier
+            .repair::<crate::downloaders::Downloader>(&problems)
+            .await
+            .unwrap();
+        assert!(left.is_empty());
+        assert_eq!(std::fs::read(&client).unwrap(), b"client jar");
+        assert_eq!(server.requests(), 1);
+        assert!(verifier
+            .verify(&|_, _| {})
+            .is_empty());
+    }
+
+    #[test]
+    fn instances_diff() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        for (file, content) in [
+            ("server/mods/sodium.jar", "sodium"),
+            ("server/mods/lithium.jar", "lithium 1"),
+            ("server/config/jei/jei.toml", "a"),
+            ("player/mods/sodium.jar", "sodium"),
+            ("player/mods/lithium.jar", "lithium 2"),
+            ("player/mods/xaero.jar", "xaero"),
+            ("player/saves/world/level.dat", "world"),
+        ] {
+            let file = root.join(file);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(file, content).unwrap();
+        }
+
+        let diff = diff_instances(root.join("server"), root.join("player")).unwrap();
+
+        assert_eq!(diff.added, [PathBuf::from("mods/xaero.jar")]);
+        assert_eq!(diff.removed, [PathBuf::from("config/jei/jei.toml")]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].path, PathBuf::from("mods/lithium.jar"));
+    }
+
+    #[test]
+    fn pack_installation() {
+        use std::io::Write;
+
+        use zip::{write::SimpleFileOptions, ZipWriter};
+
+        use crate::hashes::sha512_bytes;
+
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let instance = dir.join("instance");
+        std::fs::create_dir_all(instance.join("mods")).unwrap();
+        std::fs::create_dir_all(instance.join("config")).unwrap();
+        std::fs::write(instance.join("mods/sodium.jar"), b"sodium").unwrap();
+        std::fs::write(instance.join("config/a.toml"), b"b = 2").unwrap();
+
+        let mut pack = RinthModpack::new();
+        pack.files = ["sodium.jar", "iris.jar"]
+            .iter()
+            .map(|name| {
+                serde_json::from_value(serde_json::json!({
+                    "path": format!("mods/{name}"),
+                    "hashes": { "sha1": sha1_bytes(b"sodium"), "sha512": sha512_bytes(b"sodium") },
+                    "downloads": [],
+                    "fileSize": 6
+                }))
+                .unwrap()
+            })
+            .collect();
+
+        let mrpack = dir.join("pack.mrpack");
+        let mut zip = ZipWriter::new(File::create(&mrpack).unwrap());
+        zip.start_file(RINTH_JSON, SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(&serde_json::to_vec(&pack).unwrap())
+            .unwrap();
+        zip.start_file("overrides/config/a.toml", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"b = 1")
+            .unwrap();
+        zip.finish().unwrap();
+
+        let report = verify_pack_installation(&mrpack, &instance).unwrap();
+
+        assert_eq!(report.files_checked, 2);
+        assert_eq!(report.overrides_checked, 1);
+        assert!(!report.is_ok());
+        let mut problems: Vec<_> = report
+            .problems
+            .into_iter()
+            .map(|p| {
+                (
+                    p.path
+                        .strip_prefix(&instance)
+                        .unwrap()
+                        .to_path_buf(),
+                    p.kind,
+                )
+            })
+            .collect();
+        problems.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            problems,
+            [
+                (PathBuf::from("config/a.toml"), ProblemKind::WrongHash),
+                (PathBuf::from("mods/iris.jar"), ProblemKind::Missing),
+            ]
+        );
+    }
+}
@@ -0,0 +1,593 @@
+//! Polls Modrinth for new versions of the installed modpacks and mods.
+//!
+//! A [`Watcher`] checks the projects recorded in the [`InstanceMetadata`] of
+//! each watched instance and sends [`UraniumEvent::PackUpdateAvailable`] and
+//! [`UraniumEvent::ModUpdateAvailable`] through the event channel, so a
+//! launcher can show badges without its own polling loop.
+//!
+//! ```no_run
+//! # async fn foo() {
+//! use std::time::Duration;
+//!
+//! use uranium::events::UraniumEvent;
+//! use uranium::watcher::Watcher;
+//!
+//! let (tx, mut rx) = tokio::sync::mpsc::channel(64);
+//! Watcher::new(tx)
+//!     .watch("instances/survival")
+//!     .interval(Duration::from_secs(30 * 60))
+//!     .spawn();
+//!
+//! while let Some(event) = rx.recv().await {
+//!     if let UraniumEvent::ModUpdateAvailable { file, .. } = event {
+//!         println!("{file} can be updated");
+//!     }
+//! }
+//! # }
+//! ```
+//!
+//! Requests go through [`rinth_limiter()`] and send the `ETag` of the last
+//! answer, so a project without new versions costs an empty `304` response.
+//! Each new version is only reported once.
+//!
+//! Mods copied into `mods/` by hand are not in the metadata, they are
+//! identified by their hash with a [`FileResolver`] and checked too.
+//!
+//! The modpack of an instance is only known if the downloader was told where
+//! it comes from, see [`RinthDownloader::set_pack_source`].
+//!
+//! [`RinthDownloader::set_pack_source`]: crate::downloaders::RinthDownloader::set_pack_source
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use log::warn;
+use mine_data_structs::rinth::RinthVersion;
+use reqwest::{
+    header::{ETAG, IF_NONE_MATCH},
+    StatusCode,
+};
+use tokio::task::JoinHandle;
+
+use crate::{
+    error::{Result, UraniumError},
+    events::{EventSender, UraniumEvent},
+    hashes::sha1_file_async,
+    http::{default_client, rinth_json_limited},
+    identify::FileResolver,
+    instance::{InstalledFile, InstanceMetadata, Provider},
+    modpack_maker::is_mod_file,
+    rate_limit::rinth_limiter,
+    searcher::rinth::{ProjectVersionSearch, SearchBuilder},
+    variables::constants::MOD_EXTENSIONS,
+};
+
+/// How often the projects are checked by default.
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Versions of a project as they were last answered by Modrinth.
+struct CachedVersions {
+    etag: String,
+    versions: Vec<RinthVersion>,
+}
+
+/// Modrinth source of a mod not recorded in the metadata, identified while
+/// the file had this size and modification time.
+struct IdentifiedMod {
+    len: u64,
+    modified: Option<SystemTime>,
+    source: Option<InstalledFile>,
+}
+
+/// Checks periodically whether the watched instances can be updated, see the
+/// [module docs](self).
+pub struct Watcher {
+    client: reqwest::Client,
+    sender: EventSender,
+    instances: Vec<PathBuf>,
+    interval: Duration,
+    cache: HashMap<String, CachedVersions>,
+    /// `(instance, version id)` already reported.
+    notified: HashSet<(PathBuf, String)>,
+    /// Modrinth API used instead of the public one.
+    api: Option<String>,
+    resolver: FileResolver,
+    /// Mods not in the metadata, by path.
+    identified: HashMap<PathBuf, IdentifiedMod>,
+}
+
+impl Watcher {
+    /// The events are sent to `sender`, the watcher stops once it is closed.
+    #[must_use]
+    pub fn new(sender: EventSender) -> Watcher {
+        Watcher {
+            client: default_client().unwrap_or_default(),
+            sender,
+            instances: vec![],
+            interval: DEFAULT_INTERVAL,
+            cache: HashMap::new(),
+            notified: HashSet::new(),
+            api: None,
+            resolver: FileResolver::new(),
+            identified: HashMap::new(),
+        }
+    }
+
+    /// Adds the instance in `instance_dir` to the watched ones.
+    #[must_use]
+    pub fn watch<I: AsRef<Path>>(mut self, instance_dir: I) -> Self {
+        self.instances.push(
+            instance_dir
+                .as_ref()
+                .to_path_buf(),
+        );
+        self
+    }
+
+    /// Time between two checks, one hour by default.
+    #[must_use]
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Checks every instance once and returns how many updates were
+    /// reported.
+    ///
+    /// An instance whose metadata can't be read or a project Modrinth can't
+    /// answer for is logged and skipped.
+    pub async fn poll(&mut self) -> usize {
+        let mut reported = 0;
+        for instance in self.instances.clone() {
+            match self
+                .poll_instance(&instance)
+                .await
+            {
+                Ok(events) => {
+                    for event in events {
+                        // Unlike `emit` a full channel waits, an update must not be lost.
+                        if self
+                            .sender
+                            .send(event)
+                            .await
+                            .is_ok()
+                        {
+                            reported += 1;
+                        }
+                    }
+                }
+                Err(e) => warn!("Can't check {} for updates: {e}", instance.display()),
+            }
+        }
+        reported
+    }
+
+    /// Polls until the event channel is closed.
+    pub async fn run(mut self) {
+        while !self.sender.is_closed() {
+            self.poll().await;
+            tokio::select! {
+                () = tokio::time::sleep(self.interval) => {}
+                () = self.sender.closed() => break,
+            }
+        }
+    }
+
+    /// Runs the watcher in a tokio task, abort the handle to stop it.
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(self.run())
+    }
+
+    async fn poll_instance(&mut self, instance: &Path) -> Result<Vec<UraniumEvent>> {
+        let metadata = InstanceMetadata::load(instance)?;
+        let mut events = Vec::new();
+
+        if let Some(pack) = metadata
+            .pack
+            .as_ref()
+            .filter(|p| p.provider == Provider::Modrinth)
+        {
+            let url = self
+                .versions_search(&pack.project_id)
+                .build_url();
+            if let Some(version) = self
+                .newer_version(&url, pack, instance)
+                .await?
+            {
+                events.push(UraniumEvent::PackUpdateAvailable {
+                    instance: instance.to_path_buf(),
+                    project_id: pack.project_id.clone(),
+                    version_id: version,
+                });
+            }
+        }
+
+        let mut files: Vec<(String, InstalledFile)> = metadata
+            .files
+            .iter()
+            .map(|(file, installed)| (file.clone(), installed.clone()))
+            .collect();
+        files.extend(
+            self.untracked_mods(instance, &metadata)
+                .await,
+        );
+
+        for (file, installed) in files {
+            let name = file
+                .rsplit('/')
+                .next()
+                .unwrap_or(&file);
+            if installed.provider != Provider::Modrinth
+                || metadata.is_pinned(name, Some(&installed.project_id))
+            {
+                continue;
+            }
+
+            let mut search = self.versions_search(&installed.project_id);
+            if let Some(game_version) = &metadata.game_version {
+                search = search.add_game_version(game_version);
+            }
+            if let Some(loader) = &metadata.loader {
+                search = search.loaders(vec![loader.clone()]);
+            }
+            match self
+                .newer_version(&search.build_url(), &installed, instance)
+                .await
+            {
+                Ok(Some(version)) => events.push(UraniumEvent::ModUpdateAvailable {
+                    instance: instance.to_path_buf(),
+                    file,
+                    project_id: installed.project_id,
+                    version_id: version,
+                }),
+                Ok(None) => {}
+                Err(e) => warn!("Can't check {file} for updates: {e}"),
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Returns the Modrinth source of the mods of `instance` not recorded in
+    /// `metadata`, by their path relative to the instance.
+    ///
+    /// A file is only hashed and looked up again if it changed.
+    async fn untracked_mods(
+        &mut self,
+        instance: &Path,
+        metadata: &InstanceMetadata,
+    ) -> Vec<(String, InstalledFile)> {
+        let Ok(entries) = std::fs::read_dir(instance.join("mods")) else {
+            return vec![];
+        };
+        let extensions: Vec<String> = MOD_EXTENSIONS
+            .iter()
+            .map(|e| (*e).to_owned())
+            .collect();
+
+        let mut found = Vec::new();
+        let mut changed = Vec::new();
+        for path in entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| is_mod_file(p, &extensions))
+        {
+            let Some(name) = path
+                .file_name()
+                .and_then(|n| n.to_str())
+            else {
+                continue;
+            };
+            let file = format!("mods/{name}");
+            let Ok(file_meta) = std::fs::metadata(&path) else {
+                continue;
+            };
+            if metadata
+                .installed_file(&file)
+                .is_some()
+            {
+                continue;
+            }
+
+            let (len, modified) = (file_meta.len(), file_meta.modified().ok());
+            match self.identified.get(&path) {
+                Some(known) if known.len == len && known.modified == modified => {
+                    if let Some(source) = &known.source {
+                        found.push((file, source.clone()));
+                    }
+                }
+                _ => match sha1_file_async(&path).await {
+                    Ok(hash) => changed.push((file, path, len, modified, hash)),
+                    Err(e) => warn!("Can't hash {}: {e}", path.display()),
+                },
+            }
+        }
+
+        let hashes: Vec<String> = changed
+            .iter()
+            .map(|(.., hash)| hash.clone())
+            .collect();
+        let versions = self
+            .resolver
+            .modrinth_versions(&hashes)
+            .await;
+        for ((file, path, len, modified, _), version) in changed
+            .into_iter()
+            .zip(versions)
+        {
+            // Failed lookups are not remembered, they are tried again.
+            let source = match version {
+                Ok(version) => version.map(|v| InstalledFile::from_version(&v)),
+                Err(e) => {
+                    warn!("Can't look up {file}: {e}");
+                    continue;
+                }
+            };
+            if let Some(source) = &source {
+                found.push((file, source.clone()));
+            }
+            self.identified.insert(
+                path,
+                IdentifiedMod {
+                    len,
+                    modified,
+                    source,
+                },
+            );
+        }
+        found
+    }
+
+    fn versions_search(&self, project_id: &str) -> SearchBuilder<ProjectVersionSearch> {
+        let mut search = SearchBuilder::new();
+        if let Some(api) = &self.api {
+            search = search.api_url(api);
+        }
+        search.project_versions(project_id)
+    }
+
+    /// Returns the id of the latest version in `url` if it is an update of
+    /// the installed one and it wasn't reported yet.
+    async fn newer_version(
+        &mut self,
+        url: &str,
+        installed: &InstalledFile,
+        instance: &Path,
+    ) -> Result<Option<String>> {
+        let versions = self
+            .fetch_versions(url)
+            .await?;
+        let Some(latest) = latest_update(&versions, &installed.version_id) else {
+            return Ok(None);
+        };
+        let latest = latest.id.clone();
+        let key = (instance.to_path_buf(), latest.clone());
+        Ok(self
+            .notified
+            .insert(key)
+            .then_some(latest))
+    }
+
+    /// Gets the versions in `url`, reusing the cached answer if Modrinth
+    /// says it didn't change.
+    async fn fetch_versions(&mut self, url: &str) -> Result<Vec<RinthVersion>> {
+        let mut request = self.client.get(url);
+        if let Some(cached) = self.cache.get(url) {
+            request = request.header(IF_NONE_MATCH, &cached.etag);
+        }
+        let response = rinth_limiter()
+            .send(request)
+            .await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return match self.cache.get(url) {
+                Some(cached) => Ok(cached.versions.clone()),
+                None => Err(UraniumError::HttpStatus {
+                    url: url.to_owned(),
+                    status: StatusCode::NOT_MODIFIED,
+                }),
+            };
+        }
+
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|e| e.to_str().ok())
+            .map(str::to_owned);
+        let versions: Vec<RinthVersion> = rinth_json_limited(response).await?;
+        if let Some(etag) = etag {
+            self.cache.insert(
+                url.to_owned(),
+                CachedVersions {
+                    etag,
+                    versions: versions.clone(),
+                },
+            );
+        }
+        Ok(versions)
+    }
+}
+
+/// Returns how stable a `version_type` is, releases are the most stable.
+fn stability(version_type: &str) -> u8 {
+    match version_type {
+        "release" => 2,
+        "beta" => 1,
+        _ => 0,
+    }
+}
+
+/// Returns the newest of `versions` published after `installed` and at least
+/// as stable as it, a beta is only an update of another beta or an alpha.
+///
+/// If `installed` is not in `versions` only releases are updates. Versions
+/// without a valid publish date are ignored.
+fn latest_update<'a>(versions: &'a [RinthVersion], installed: &str) -> Option<&'a RinthVersion> {
+    let current = versions
+        .iter()
+        .find(|v| v.id == installed);
+    let min_stability = current.map_or(stability("release"), |v| stability(&v.version_type));
+    let installed_date = current.and_then(RinthVersion::get_date_published);
+
+    versions
+        .iter()
+        .filter(|v| v.id != installed && stability(&v.version_type) >= min_stability)
+        .filter_map(|v| Some((v.get_date_published()?, v)))
+        .filter(|(date, _)| installed_date.is_none_or(|installed| *date > installed))
+        .max_by_key(|(date, _)| *date)
+        .map(|(_, v)| v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{rinth_version, FixtureServer};
+
+    fn version(id: &str, version_type: &str, date: &str) -> RinthVersion {
+        RinthVersion {
+            version_type: version_type.to_owned(),
+            date_published: date.to_owned(),
+            ..rinth_version(id)
+        }
+    }
+
+    #[test]
+    fn newest_version_only() {
+        let versions = [
+            version("new", "release", "2024-07-02T00:00:00Z"),
+            version("old", "release", "2024-07-01T00:00:00Z"),
+        ];
+
+        assert_eq!(
+            latest_update(&versions, "old").map(|v| v.id.as_str()),
+            Some("new")
+        );
+        assert!(latest_update(&versions, "new").is_none());
+        assert!(latest_update(&[], "old").is_none());
+    }
+
+    #[test]
+    fn newer_and_as_stable() {
+        let versions = [
+            version("beta", "beta", "2024-07-04T00:00:00Z"),
+            version("backport", "release", "2024-07-01T00:00:00Z"),
+            version("release", "release", "2024-07-03T00:00:00Z"),
+            version("installed", "release", "2024-07-02T00:00:00Z"),
+        ];
+
+        assert_eq!(
+            latest_update(&versions, "installed").map(|v| v.id.as_str()),
+            Some("release")
+        );
+        assert!(latest_update(&versions, "release").is_none());
+        assert_eq!(
+            latest_update(&versions, "unknown").map(|v| v.id.as_str()),
+            Some("release")
+        );
+
+        let versions = [
+            version("beta", "beta", "2024-07-04T00:00:00Z"),
+            version("alpha", "alpha", "2024-07-02T00:00:00Z"),
+        ];
+        assert_eq!(
+            latest_update(&versions, "alpha").map(|v| v.id.as_str()),
+            Some("beta")
+        );
+    }
+
+    #[tokio::test]
+    async fn poll_reports_once() {
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let versions = |ids: &[&str]| {
+            let versions: Vec<RinthVersion> = ids
+                .iter()
+                .enumerate()
+                .map(|(i, id)| version(id, "release", &format!("2024-07-0{}T00:00:00Z", 9 - i)))
+                .collect();
+            serde_json::to_vec(&versions).unwrap()
+        };
+        server.add("/v2/project/pack/version", versions(&["pack-2", "pack-1"]));
+        server.add("/v2/project/sodium/version", versions(&["sodium-1"]));
+
+        let instance = tempfile::tempdir().unwrap();
+        InstanceMetadata::update(instance.path(), |metadata| {
+            metadata.pack = Some(InstalledFile::modrinth("pack", "pack-1"));
+            metadata.record_file(
+                "mods/sodium.jar",
+                InstalledFile::modrinth("sodium", "sodium-1"),
+            );
+        })
+        .unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+        let mut watcher = Watcher::new(tx).watch(instance.path());
+        watcher.api = Some(server.url("/v2/"));
+
+        assert_eq!(watcher.poll().await, 1);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(UraniumEvent::PackUpdateAvailable { version_id, .. }) if version_id == "pack-2"
+        ));
+        assert!(rx.try_recv().is_err());
+        assert_eq!(server.requests(), 2);
+
+        assert_eq!(watcher.poll().await, 0);
+    }
+
+    #[tokio::test]
+    async fn untracked_mods_identified_once() {
+        let server = FixtureServer::start()
+            .await
+            .unwrap();
+        let jar = b"iris jar";
+        let installed = RinthVersion {
+            project_id: "iris".to_owned(),
+            ..version("iris-1", "release", "2024-07-01T00:00:00Z")
+        };
+        server.add(
+            &format!("/v2/version_file/{}", crate::hashes::sha1_bytes(jar)),
+            serde_json::to_vec(&installed).unwrap(),
+        );
+        let versions = vec![
+            version("iris-2", "release", "2024-07-02T00:00:00Z"),
+            installed,
+        ];
+        server.add(
+            "/v2/project/iris/version",
+            serde_json::to_vec(&versions).unwrap(),
+        );
+
+        let instance = tempfile::tempdir().unwrap();
+        std::fs::create_dir(instance.path().join("mods")).unwrap();
+        std::fs::write(
+            instance
+                .path()
+                .join("mods/iris.jar"),
+            jar,
+        )
+        .unwrap();
+        InstanceMetadata::default()
+            .save(instance.path())
+            .unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+        let mut watcher = Watcher::new(tx).watch(instance.path());
+        watcher.api = Some(server.url("/v2/"));
+        watcher.resolver = FileResolver::new().api_url(&server.url("/v2/"));
+
+        assert_eq!(watcher.poll().await, 1);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(UraniumEvent::ModUpdateAvailable { file, version_id, .. })
+                if file == "mods/iris.jar" && version_id == "iris-2"
+        ));
+        assert_eq!(watcher.poll().await, 0);
+        // The jar is only looked up once, the versions are asked each poll.
+        assert_eq!(server.requests(), 3);
+    }
+}
@@ -0,0 +1,171 @@
+//! Hashes of the overrides of a modpack, written next to its index when the
+//! pack is created and checked when it is extracted.
+//!
+//! Packs made by other tools don't have the file, their overrides are
+//! installed as they are.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Result, UraniumError},
+    hashes::sha1_bytes,
+    variables::constants::OVERRIDES_HASHES,
+};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct OverridesIndex {
+    /// By the path of each file inside the overrides folder, with `/`.
+    files: BTreeMap<String, OverrideEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+struct OverrideEntry {
+    sha1: String,
+    size: u64,
+}
+
+impl OverridesIndex {
+    /// Records `content` as the override at `path`.
+    pub(crate) fn add(&mut self, path: &Path, content: &[u8]) {
+        self.files.insert(
+            key(path),
+            OverrideEntry {
+                sha1: sha1_bytes(content),
+                size: content.len() as u64,
+            },
+        );
+    }
+
+    pub(crate) fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec_pretty(self).map_err(|_| UraniumError::WrongFileFormat)
+    }
+}
+
+/// Checks the overrides extracted in `pack/overrides_folder` against the
+/// hashes of the pack, if it has them.
+///
+/// Files with another size or hash, missing or not listed are removed, so
+/// they are not installed, and returned relative to the overrides folder.
+///
+/// # Errors
+/// Returns an error if the hashes file is not valid JSON or a file can't be
+/// read.
+pub(crate) fn verify_overrides(pack: &Path, overrides_folder: &str) -> Result<Vec<PathBuf>> {
+    let index_path = pack.join(OVERRIDES_HASHES);
+    if !index_path.is_file() {
+        return Ok(Vec::new());
+    }
+    let index: OverridesIndex = serde_json::from_slice(&std::fs::read(index_path)?)
+        .map_err(|_| UraniumError::WrongFileFormat)?;
+
+    let root = pack.join(overrides_folder);
+    let mut found = Vec::new();
+    if root.is_dir() {
+        list_files(&root, Path::new(""), &mut found)?;
+    }
+
+    let mut corrupted = Vec::new();
+    for file in &found {
+        let content = std::fs::read(root.join(file))?;
+        let valid = index
+            .files
+            .get(&key(file))
+            .is_some_and(|entry| {
+                entry.size == content.len() as u64 && entry.sha1 == sha1_bytes(&content)
+            });
+        if !valid {
+            warn!("Override {} doesn't match the pack hashes", file.display());
+            std::fs::remove_file(root.join(file))?;
+            corrupted.push(file.clone());
+        }
+    }
+
+    for name in index.files.keys() {
+        let file = PathBuf::from(name);
+        if !found.contains(&file) {
+            warn!("Override {name} is missing from the pack");
+            corrupted.push(file);
+        }
+    }
+
+    Ok(corrupted)
+}
+
+fn list_files(root: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(root.join(relative))? {
+        let path = relative.join(entry?.file_name());
+        if root.join(&path).is_dir() {
+            list_files(root, &path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn key(path: &Path) -> String {
+    path.components()
+        .map(|c| {
+            c.as_os_str()
+                .to_string_lossy()
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Cursor};
+
+    use super::*;
+    use crate::zipper::{compress_curse_pack_into, pack_unzipper::unzip_temp_pack, Overrides};
+
+    #[test]
+    fn tampered_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let instance = dir.join("instance");
+        for file in ["config/a.json", "config/b.json", "mods/raw.jar"] {
+            let file = instance.join(file);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, b"data").unwrap();
+        }
+
+        let zip = compress_curse_pack_into(
+            Cursor::new(Vec::new()),
+            &instance,
+            &["raw.jar"],
+            &Overrides::default(),
+            b"{}",
+        )
+        .unwrap();
+        fs::write(dir.join("pack.zip"), zip.into_inner()).unwrap();
+
+        let pack = unzip_temp_pack(dir.join("pack.zip")).unwrap();
+        assert!(verify_overrides(pack.path(), "overrides/")
+            .unwrap()
+            .is_empty());
+
+        let overrides = pack.path().join("overrides");
+        fs::write(overrides.join("config/a.json"), b"changed").unwrap();
+        fs::remove_file(overrides.join("config/b.json")).unwrap();
+        fs::write(overrides.join("config/extra.json"), b"{}").unwrap();
+        let mut corrupted = verify_overrides(pack.path(), "overrides/").unwrap();
+        corrupted.sort();
+        let changed_removed = !overrides
+            .join("config/a.json")
+            .exists();
+
+        assert_eq!(
+            corrupted,
+            ["config/a.json", "config/b.json", "config/extra.json"].map(PathBuf::from)
+        );
+        assert!(changed_removed);
+    }
+}
@@ -1,5 +1,10 @@
-pub use pack_zipper::compress_pack;
+pub(crate) use integrity::verify_overrides;
+pub use overrides::Overrides;
+pub(crate) use pack_zipper::{compress_curse_pack_into, zip_files};
+pub use pack_zipper::{compress_pack, compress_pack_into};
 
+mod integrity;
+mod overrides;
 pub mod pack_unzipper;
 mod pack_zipper;
 mod uranium_structs;
@@ -0,0 +1,129 @@
+use std::path::Path;
+
+use glob::Pattern;
+
+use crate::{
+    error::{Result, UraniumError},
+    variables::constants::{DEFAULT_OVERRIDE_EXCLUSIONS, OVERRIDE_FOLDERS},
+};
+
+/// The folders of the instance copied into the `overrides/` of a modpack.
+///
+/// By default these are [`OVERRIDE_FOLDERS`], folders missing in the
+/// instance are skipped. Worlds, logs, crash reports and the rest of
+/// [`DEFAULT_OVERRIDE_EXCLUSIONS`] are never copied, even if their folder is
+/// added, unless the exclusion is removed.
+///
+/// # Example
+///
+/// ```
+/// use uranium::modpack_maker::Overrides;
+///
+/// let overrides = Overrides::default()
+///     .add_folder("emotes")
+///     .exclude("config/**/*.bak")
+///     .unwrap();
+///
+/// assert!(overrides.is_excluded("config/sodium/options.bak"));
+/// assert!(!overrides.is_excluded("config/sodium/options.json"));
+/// assert!(overrides.is_excluded("saves"));
+/// assert!(overrides.is_excluded("saves/MyWorld/level.dat"));
+/// assert!(!overrides
+///     .remove_exclusion("saves")
+///     .is_excluded("saves"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Overrides {
+    folders: Vec<String>,
+    exclude: Vec<Pattern>,
+}
+
+impl Default for Overrides {
+    fn default() -> Self {
+        Self::with_folders(OVERRIDE_FOLDERS)
+    }
+}
+
+impl Overrides {
+    /// Only `folders` will be copied, they are relative to the instance root.
+    #[must_use]
+    pub fn with_folders(folders: &[&str]) -> Overrides {
+        Overrides {
+            folders: folders
+                .iter()
+                .map(|f| {
+                    f.trim_end_matches('/')
+                        .to_owned()
+                })
+                .collect(),
+            exclude: DEFAULT_OVERRIDE_EXCLUSIONS
+                .iter()
+                .filter_map(|glob| Pattern::new(glob).ok())
+                .collect(),
+        }
+    }
+
+    /// Copies `folder` too.
+    #[must_use]
+    pub fn add_folder(mut self, folder: &str) -> Self {
+        let folder = folder.trim_end_matches('/');
+        if !self
+            .folders
+            .iter()
+            .any(|f| f == folder)
+        {
+            self.folders
+                .push(folder.to_owned());
+        }
+        self
+    }
+
+    /// Skips the files and folders matching `glob`, matched against their
+    /// path relative to the instance root, e.g. `config/**/*.bak`.
+    ///
+    /// # Errors
+    /// Returns `Err(UraniumError::OtherWithReason)` if `glob` is not a valid
+    /// pattern.
+    pub fn exclude(mut self, glob: &str) -> Result<Self> {
+        let pattern = Pattern::new(glob)
+            .map_err(|e| UraniumError::OtherWithReason(format!("Invalid glob {glob}: {e}")))?;
+        self.exclude.push(pattern);
+        Ok(self)
+    }
+
+    /// Removes the exclusion glob `glob`, e.g. `"saves"` to add the worlds
+    /// of the instance to the modpack.
+    #[must_use]
+    pub fn remove_exclusion(mut self, glob: &str) -> Self {
+        self.exclude
+            .retain(|p| p.as_str() != glob);
+        self
+    }
+
+    /// Removes every exclusion, [`DEFAULT_OVERRIDE_EXCLUSIONS`] included.
+    #[must_use]
+    pub fn without_exclusions(mut self) -> Self {
+        self.exclude.clear();
+        self
+    }
+
+    #[must_use]
+    pub fn folders(&self) -> &[String] {
+        &self.folders
+    }
+
+    /// Returns `true` if `path`, relative to the instance root, or any of
+    /// its parent folders matches any of the exclusion globs.
+    ///
+    /// So excluding `saves` excludes `saves/MyWorld` and its files too.
+    pub fn is_excluded<P: AsRef<Path>>(&self, path: P) -> bool {
+        path.as_ref()
+            .ancestors()
+            .filter(|p| !p.as_os_str().is_empty())
+            .any(|p| {
+                self.exclude
+                    .iter()
+                    .any(|glob| glob.matches_path(p))
+            })
+    }
+}
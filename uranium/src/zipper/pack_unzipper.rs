@@ -1,16 +1,34 @@
 use std::{
-    fs::{create_dir, remove_dir_all, File},
-    path::Path,
+    fs::{create_dir_all, File},
+    io,
+    path::{Path, PathBuf},
 };
 
 use log::{error, warn};
+use tempfile::TempDir;
+use zip::ZipArchive;
 
-use crate::{
-    error::{Result, UraniumError},
-    variables::constants::TEMP_DIR,
-};
+use crate::error::{Result, UraniumError};
+
+/// Extracts the modpack in `file_path` into a new temp dir, which is removed
+/// when the returned `TempDir` is dropped.
+///
+/// Every call gets its own dir under the system temp dir, so many modpacks
+/// can be extracted at the same time.
+pub fn unzip_temp_pack<I: AsRef<Path>>(file_path: I) -> Result<TempDir> {
+    unzip_temp_pack_with_progress(file_path, |_, _| {})
+}
 
-pub fn unzip_temp_pack<I: AsRef<Path>>(file_path: I) -> Result<()> {
+/// Same as `unzip_temp_pack` but `progress` is called with the number of
+/// extracted entries and the total entries each time an entry is extracted.
+///
+/// With the `parallel-unzip` feature the entries are extracted by a thread
+/// pool, so `progress` may be called from many threads.
+pub fn unzip_temp_pack_with_progress<I, F>(file_path: I, progress: F) -> Result<TempDir>
+where
+    I: AsRef<Path>,
+    F: Fn(usize, usize) + Sync,
+{
     let zip_file = match File::open(file_path.as_ref()) {
         Ok(file) => file,
         Err(e) => {
@@ -24,24 +42,112 @@ pub fn unzip_temp_pack<I: AsRef<Path>>(file_path: I) -> Result<()> {
         }
     };
 
-    let mut zip = zip::ZipArchive::new(zip_file).map_err(|_| UraniumError::WrongFileFormat)?;
+    let mut zip = ZipArchive::new(zip_file).map_err(|_| UraniumError::WrongFileFormat)?;
 
-    if create_dir(TEMP_DIR).is_err() {
+    let Ok(temp_dir) = tempfile::Builder::new()
+        .prefix("uranium_pack_")
+        .tempdir()
+    else {
         error!("Could not create temporal dir");
-        remove_temp_pack();
         return Err(UraniumError::CantCreateDir("temp_dir"));
-    }
+    };
 
-    if let Err(e) = zip.extract(TEMP_DIR) {
+    if let Err(e) = extract(&mut zip, file_path.as_ref(), temp_dir.path(), &progress) {
         error!("Error while extracting the modpack");
-        return Err(UraniumError::ZipError(e));
+        return Err(e);
+    }
+
+    Ok(temp_dir)
+}
+
+/// Creates every directory needed by the archive before any file is written,
+/// that way the entries can be extracted in any order.
+///
+/// Returns the indexes of the entries which are files.
+fn create_dirs(zip: &mut ZipArchive<File>, destination: &Path) -> Result<Vec<usize>> {
+    let mut files = Vec::with_capacity(zip.len());
+
+    for i in 0..zip.len() {
+        let entry = zip.by_index(i)?;
+        let path = entry_path(&entry, destination)?;
+
+        if entry.is_dir() {
+            create_dir_all(&path)?;
+        } else {
+            if let Some(parent) = path.parent() {
+                create_dir_all(parent)?;
+            }
+            files.push(i);
+        }
     }
 
+    Ok(files)
+}
+
+/// Returns where `entry` must be extracted.
+///
+/// # Errors
+/// Entries trying to escape `destination` (e.g. `../../.bashrc`) are rejected.
+fn entry_path(entry: &zip::read::ZipFile, destination: &Path) -> Result<PathBuf> {
+    match entry.enclosed_name() {
+        Some(name) => Ok(destination.join(name)),
+        None => Err(UraniumError::ZipError(
+            zip::result::ZipError::InvalidArchive("Invalid file path"),
+        )),
+    }
+}
+
+fn extract_entry(zip: &mut ZipArchive<File>, index: usize, destination: &Path) -> Result<()> {
+    let mut entry = zip.by_index(index)?;
+    let mut file = File::create(entry_path(&entry, destination)?)?;
+    io::copy(&mut entry, &mut file)?;
     Ok(())
 }
 
-pub(crate) fn remove_temp_pack() {
-    if remove_dir_all(TEMP_DIR).is_err() {
-        error!("Error at deleting temp dir");
+#[cfg(not(feature = "parallel-unzip"))]
+fn extract<F: Fn(usize, usize) + Sync>(
+    zip: &mut ZipArchive<File>,
+    _zip_path: &Path,
+    destination: &Path,
+    progress: &F,
+) -> Result<()> {
+    let files = create_dirs(zip, destination)?;
+    let total = files.len();
+
+    for (done, index) in files.into_iter().enumerate() {
+        extract_entry(zip, index, destination)?;
+        progress(done + 1, total);
     }
+    Ok(())
+}
+
+#[cfg(feature = "parallel-unzip")]
+fn extract<F: Fn(usize, usize) + Sync>(
+    zip: &mut ZipArchive<File>,
+    zip_path: &Path,
+    destination: &Path,
+    progress: &F,
+) -> Result<()> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use rayon::prelude::*;
+
+    let files = create_dirs(zip, destination)?;
+    let total = files.len();
+    let done = AtomicUsize::new(0);
+
+    // Each thread needs its own reader so every one of them opens the archive.
+    files
+        .into_par_iter()
+        .try_for_each_init(
+            || ZipArchive::new(File::open(zip_path)?).map_err(UraniumError::from),
+            |zip, index| {
+                let zip = zip
+                    .as_mut()
+                    .map_err(|e| UraniumError::OtherWithReason(e.to_string()))?;
+                extract_entry(zip, index, destination)?;
+                progress(done.fetch_add(1, Ordering::Relaxed) + 1, total);
+                Ok(())
+            },
+        )
 }
@@ -1,16 +1,17 @@
 use std::{
-    fs::File,
-    io::{Read, Write},
+    io::{self, Seek, SeekFrom, Write},
     path::{Path, PathBuf},
 };
 
 use log::{error, info, warn};
 use zip::{CompressionMethod, ZipWriter};
 
-use super::uranium_structs::UraniumFile;
+use super::{integrity::OverridesIndex, uranium_structs::UraniumFile, Overrides};
+use crate::collisions::check_case_collisions;
 use crate::error::UraniumError;
+use crate::storage::StorageBackend;
 use crate::variables::constants::EXTENSION;
-use crate::variables::constants::{self, CONFIG_DIR, OVERRIDES_FOLDER};
+use crate::variables::constants::{self, OVERRIDES_FOLDER, OVERRIDES_HASHES};
 use crate::zipper::uranium_structs::FileType;
 
 type FileOptions = zip::write::SimpleFileOptions;
@@ -26,25 +27,36 @@ type FileOptions = zip::write::SimpleFileOptions;
 ///
 /// # Arguments
 ///
-/// * `name` - A string representing the name of the output ZIP archive.
-/// It should include the file extension.
+/// * `name` - A string representing the name of the output ZIP archive. It
+///   should include the file extension.
 ///
 /// * `path` - A [`Path`](std::path::Path) representing the path to the modpack
-/// files to be compressed.
+///   files to be compressed.
 ///
 /// * `raw_mods` - A slice of types that implement
-///   [`AsRef<Path>`](std::path::AsRef)
-/// representing the filenames of raw mods to include in the archive.
+///   [`AsRef<Path>`](std::path::AsRef) representing the filenames of raw mods
+///   to include in the archive.
+///
+/// * `overrides` - The instance folders added to the `overrides/` of the
+///   archive.
+///
+/// * `storage` - The [`StorageBackend`] where the archive will be written. Zip
+///   archives need a seekable sink, so the archive is built in a temporary file
+///   and then streamed into the storage.
 ///
 /// # Errors
 ///
 /// This function can return an error of type `ZipError` in the following cases:
 ///
 /// - If there is an error while creating or writing to the ZIP archive.
+///
+/// See [`compress_pack_into`] to write the archive into any other sink.
 pub fn compress_pack<P: AsRef<Path>>(
     name: &Path,
     path: &Path,
     raw_mods: &[P],
+    overrides: &Overrides,
+    storage: &dyn StorageBackend,
 ) -> Result<(), UraniumError> {
     let name_with_ext = if !name
         .extension()
@@ -57,50 +69,150 @@ pub fn compress_pack<P: AsRef<Path>>(
         name.to_path_buf()
     };
 
-    let zip_file = File::create(name_with_ext)?;
-    let mut zip = ZipWriter::new(zip_file);
+    let mut archive = compress_pack_into(tempfile::tempfile()?, path, raw_mods, overrides)?;
+    archive.seek(SeekFrom::Start(0))?;
+
+    let mut writer = storage.open_write(&name_with_ext)?;
+    io::copy(&mut archive, &mut writer)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Compresses a Minecraft modpack into `writer`.
+///
+/// Works like [`compress_pack`] but instead of writing a named file the archive
+/// goes into any `Write + Seek` sink, e.g. a `Cursor<Vec<u8>>` used as an
+/// upload body or a file opened by the caller.
+///
+/// Once the archive is finished the writer is given back.
+///
+/// # Errors
+///
+/// This function can return an error of type `ZipError` in the following cases:
+///
+/// - If there is an error while creating or writing to the ZIP archive.
+pub fn compress_pack_into<W: Write + Seek, P: AsRef<Path>>(
+    writer: W,
+    path: &Path,
+    raw_mods: &[P],
+    overrides: &Overrides,
+) -> Result<W, UraniumError> {
+    // Add the modpack_temp.json file
+    let modpack_bytes = std::fs::read(constants::RINTH_JSON)?;
+    compress_with_manifest(
+        writer,
+        path,
+        raw_mods,
+        overrides,
+        constants::RINTH_JSON,
+        &modpack_bytes,
+    )
+}
+
+/// Same as [`compress_pack_into`] but for Curse modpacks, `manifest` is the
+/// content of their `manifest.json`.
+pub(crate) fn compress_curse_pack_into<W: Write + Seek, P: AsRef<Path>>(
+    writer: W,
+    path: &Path,
+    raw_mods: &[P],
+    overrides: &Overrides,
+    manifest: &[u8],
+) -> Result<W, UraniumError> {
+    compress_with_manifest(
+        writer,
+        path,
+        raw_mods,
+        overrides,
+        constants::CURSE_JSON,
+        manifest,
+    )
+}
+
+fn compress_with_manifest<W: Write + Seek, P: AsRef<Path>>(
+    writer: W,
+    path: &Path,
+    raw_mods: &[P],
+    overrides: &Overrides,
+    manifest_name: &str,
+    manifest: &[u8],
+) -> Result<W, UraniumError> {
+    let mut zip = ZipWriter::new(writer);
     let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
 
     zip.add_directory(OVERRIDES_FOLDER, options)?;
 
-    zip.add_directory(
-        PathBuf::from(OVERRIDES_FOLDER)
-            .join(CONFIG_DIR)
-            .as_os_str()
-            .to_str()
-            .unwrap_or_default(),
-        options,
-    )?;
-
     let mut config_files: Vec<UraniumFile> = Vec::new();
 
-    // Iter through all the files and subdirectories in "config/" and set the
-    // file type.
-    search_files(path, &PathBuf::from(CONFIG_DIR), &mut config_files)?;
+    // Iter through all the files and subdirectories of each override folder
+    // and set the file type.
+    for folder in overrides.folders() {
+        if !path.join(folder).is_dir() || overrides.is_excluded(folder) {
+            continue;
+        }
+        zip.add_directory(
+            PathBuf::from(OVERRIDES_FOLDER)
+                .join(folder)
+                .as_os_str()
+                .to_str()
+                .unwrap_or_default(),
+            options,
+        )?;
+        search_files(path, &PathBuf::from(folder), overrides, &mut config_files)?;
+    }
 
-    add_files_to_zip(&path, &mut config_files, &mut zip, options)?;
+    // Overrides are extracted into the user instance, which may be on a
+    // case-insensitive filesystem.
+    check_case_collisions(
+        config_files
+            .iter()
+            .map(|f| PathBuf::from(f.get_absolute_path()))
+            .chain(
+                raw_mods
+                    .iter()
+                    .map(|m| PathBuf::from("mods/").join(m)),
+            ),
+    )?;
 
-    // Add the modpack_temp.json file
-    let modpack_json = File::open(constants::RINTH_JSON).unwrap();
-    let modpack_bytes = modpack_json
-        .bytes()
-        .flatten()
-        .collect::<Vec<u8>>();
+    let mut hashes = OverridesIndex::default();
+    add_files_to_zip(path, &mut config_files, &mut zip, options, &mut hashes)?;
 
     // Add the hardcoded .jar mods
-    add_raw_mods(path, &mut zip, raw_mods, options)?;
+    add_raw_mods(path, &mut zip, raw_mods, options, &mut hashes)?;
+
+    // Checked when the pack is extracted, see `verify_overrides`.
+    zip.start_file(OVERRIDES_HASHES, options)?;
+    zip.write_all(&hashes.to_bytes()?)?;
 
     // Finally add the modpack.json file
-    zip.start_file(constants::RINTH_JSON, options)?;
-    zip.write_all(&modpack_bytes)?;
-    zip.finish()?;
+    zip.start_file(manifest_name, options)?;
+    zip.write_all(manifest)?;
 
-    Ok(())
+    Ok(zip.finish()?)
+}
+
+/// Writes a zip archive into `writer` with the given `(path, content)` entries.
+///
+/// Used for archives that are not modpacks, e.g. support reports.
+pub(crate) fn zip_files<W: Write + Seek>(
+    writer: W,
+    files: &[(String, Vec<u8>)],
+) -> Result<W, UraniumError> {
+    let mut zip = ZipWriter::new(writer);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    for (path, content) in files {
+        zip.start_file(path.as_str(), options)?;
+        zip.write_all(content)?;
+    }
+
+    Ok(zip.finish()?)
 }
 
 fn search_files(
     minecraft_path: &Path,
     relative_path: &Path,
+    overrides: &Overrides,
     config_files: &mut Vec<UraniumFile>,
 ) -> Result<(), UraniumError> {
     // Get this directory files
@@ -115,6 +227,9 @@ fn search_files(
     // Go through the sub_config_files vector and set the right type to each
     // file. Then add them to config_files
     for mut config_file in sub_config_files {
+        if overrides.is_excluded(config_file.get_absolute_path()) {
+            continue;
+        }
         let path: PathBuf = minecraft_path
             .to_owned()
             .join(config_file.get_absolute_path());
@@ -126,7 +241,7 @@ fn search_files(
             config_file.set_type(FileType::Dir);
             config_files.push(config_file.clone());
             let new_path = relative_path.join(config_file.get_name());
-            search_files(minecraft_path, &new_path, config_files)?;
+            search_files(minecraft_path, &new_path, overrides, config_files)?;
         }
     }
 
@@ -157,23 +272,25 @@ fn get_new_files(path: &Path, relative_path: &Path) -> Result<Vec<UraniumFile>,
     Ok(sub_config_files)
 }
 
-fn add_files_to_zip(
+fn add_files_to_zip<W: Write + Seek>(
     minecraft_path: &Path,
     config_files: &mut Vec<UraniumFile>,
-    zip: &mut ZipWriter<File>,
+    zip: &mut ZipWriter<W>,
     options: FileOptions,
+    hashes: &mut OverridesIndex,
 ) -> Result<(), UraniumError> {
     for file in config_files {
-        match_file(minecraft_path, zip, options, file)?;
+        match_file(minecraft_path, zip, options, file, hashes)?;
     }
     Ok(())
 }
 
-fn match_file(
+fn match_file<W: Write + Seek>(
     root_path: &Path,
-    zip: &mut ZipWriter<File>,
+    zip: &mut ZipWriter<W>,
     options: FileOptions,
     file: &mut UraniumFile,
+    hashes: &mut OverridesIndex,
 ) -> Result<(), UraniumError> {
     let overrides: PathBuf = PathBuf::from("overrides/");
     match file.get_type() {
@@ -182,7 +299,9 @@ fn match_file(
                 .to_owned()
                 .join(file.get_absolute_path());
             let rel_path = overrides.join(file.get_absolute_path());
-            append_config_file(&absolute_path, &rel_path, zip, options)?;
+            if let Some(content) = append_config_file(&absolute_path, &rel_path, zip, options)? {
+                hashes.add(Path::new(&file.get_absolute_path()), &content);
+            }
         }
 
         FileType::Dir => {
@@ -198,32 +317,27 @@ fn match_file(
     Ok(())
 }
 
-fn append_config_file(
+fn append_config_file<W: Write + Seek>(
     absolute_path: &PathBuf,
     rel_path: &Path,
-    zip: &mut ZipWriter<File>,
+    zip: &mut ZipWriter<W>,
     option: FileOptions,
-) -> Result<(), UraniumError> {
+) -> Result<Option<Vec<u8>>, UraniumError> {
     // Read the file
-    let file = match File::open(absolute_path) {
-        Ok(f) => f,
+    let buffer = match std::fs::read(absolute_path) {
+        Ok(data) => data,
         Err(e) => {
             error!("Unable to open {:?}: {}", absolute_path, e);
             return Err(UraniumError::IOError(e));
         }
     };
 
-    let buffer = file
-        .bytes()
-        .flatten()
-        .collect::<Vec<u8>>();
-
     // Is a recoverable error reading 0 bytes from file ?
     // In this case Uranium will just send a warning about it
     // and don't add the file
     if buffer.is_empty() {
         warn!("No bytes read from the pack");
-        return Ok(());
+        return Ok(None);
     }
 
     // Add the file to the zip
@@ -235,21 +349,22 @@ fn append_config_file(
         option,
     );
     let _ = zip.write_all(&buffer);
-    Ok(())
+    Ok(Some(buffer))
 }
 
-fn add_raw_mods<P: AsRef<Path>>(
+fn add_raw_mods<P: AsRef<Path>, W: Write + Seek>(
     path: &Path,
-    zip: &mut ZipWriter<File>,
+    zip: &mut ZipWriter<W>,
     raw_mods: &[P],
     options: FileOptions,
+    hashes: &mut OverridesIndex,
 ) -> Result<(), UraniumError> {
     zip.add_directory("overrides/mods", options)?;
 
     for jar_file in raw_mods {
         let file_name = PathBuf::from("overrides/mods/").join(jar_file);
 
-        info!("Adding {:?}", &file_name);
+        info!("Adding {:?}", file_name);
 
         info!(
             "{}",
@@ -279,6 +394,137 @@ fn add_raw_mods<P: AsRef<Path>>(
             options,
         );
         let _ = zip.write_all(&buffer);
+        hashes.add(&Path::new("mods").join(jar_file), &buffer);
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Cursor};
+
+    use zip::ZipArchive;
+
+    use super::*;
+
+    #[test]
+    fn override_folders() {
+        let instance = tempfile::tempdir().unwrap();
+        let instance = instance.path();
+        for file in [
+            "config/sodium.json",
+            "config/old/sodium.bak",
+            "resourcepacks/faithful.zip",
+            "saves/world/level.dat",
+        ] {
+            let file = instance.join(file);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, b"data").unwrap();
+        }
+
+        let overrides = Overrides::default()
+            .exclude("config/**/*.bak")
+            .unwrap();
+        let raw_mods: [&str; 0] = [];
+        let zip = compress_curse_pack_into(
+            Cursor::new(Vec::new()),
+            instance,
+            &raw_mods,
+            &overrides,
+            b"{}",
+        )
+        .unwrap();
+
+        let zip = ZipArchive::new(Cursor::new(zip.into_inner())).unwrap();
+        let names: Vec<&str> = zip.file_names().collect();
+        assert!(names.contains(&"overrides/config/sodium.json"));
+        assert!(names.contains(&"overrides/resourcepacks/faithful.zip"));
+        assert!(!names.contains(&"overrides/config/old/sodium.bak"));
+        assert!(!names
+            .iter()
+            .any(|n| n.starts_with("overrides/saves")));
+    }
+
+    #[test]
+    fn default_exclusions() {
+        let instance = tempfile::tempdir().unwrap();
+        let instance = instance.path();
+        for file in [
+            "kubejs/server_scripts/main.js",
+            "kubejs/logs/server.log",
+            "saves/world/datapacks/pack.zip",
+            "logs/latest.log",
+        ] {
+            let file = instance.join(file);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, b"data").unwrap();
+        }
+
+        let raw_mods: [&str; 0] = [];
+        let compress = |overrides: &Overrides| {
+            let zip = compress_curse_pack_into(
+                Cursor::new(Vec::new()),
+                instance,
+                &raw_mods,
+                overrides,
+                b"{}",
+            )
+            .unwrap();
+            let zip = ZipArchive::new(Cursor::new(zip.into_inner())).unwrap();
+            zip.file_names()
+                .map(str::to_owned)
+                .collect::<Vec<String>>()
+        };
+
+        let overrides = Overrides::default()
+            .add_folder("saves")
+            .add_folder("logs");
+        let names = compress(&overrides);
+        assert!(names.contains(&"overrides/kubejs/server_scripts/main.js".to_owned()));
+        assert!(!names.contains(&"overrides/kubejs/logs/server.log".to_owned()));
+        assert!(!names
+            .iter()
+            .any(|n| n.starts_with("overrides/saves") || n.starts_with("overrides/logs")));
+
+        let names = compress(&overrides.remove_exclusion("saves"));
+        assert!(names.contains(&"overrides/saves/world/datapacks/pack.zip".to_owned()));
+        assert!(!names
+            .iter()
+            .any(|n| n.starts_with("overrides/logs")));
+    }
+
+    #[test]
+    fn nested_exclusions() {
+        let instance = tempfile::tempdir().unwrap();
+        for file in [
+            "config/sodium.json",
+            "saves/MyWorld/level.dat",
+            "crash-reports/old/crash.txt",
+        ] {
+            let file = instance.path().join(file);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, b"data").unwrap();
+        }
+
+        let overrides = Overrides::default()
+            .add_folder("saves/MyWorld")
+            .add_folder("crash-reports/old");
+        let raw_mods: [&str; 0] = [];
+        let zip = compress_curse_pack_into(
+            Cursor::new(Vec::new()),
+            instance.path(),
+            &raw_mods,
+            &overrides,
+            b"{}",
+        )
+        .unwrap();
+
+        let zip = ZipArchive::new(Cursor::new(zip.into_inner())).unwrap();
+        let names: Vec<&str> = zip.file_names().collect();
+        assert!(names.contains(&"overrides/config/sodium.json"));
+        assert!(!names
+            .iter()
+            .any(|n| n.starts_with("overrides/saves") || n.starts_with("overrides/crash-reports")));
+        assert!(!overrides.is_excluded("config/saves/sodium.json"));
+    }
+}